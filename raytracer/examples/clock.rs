@@ -0,0 +1,22 @@
+use std::{f64::consts::PI, path::Path};
+
+use anyhow::Result;
+use raytracer::{canvas::Canvas, color::Color, point::Point, transform::rotation_y};
+
+// chapter 4: the twelve hours of a clock face, found by rotating twelve o'clock
+// around the y axis
+fn main() -> Result<()> {
+    let size = 400;
+    let mut canvas = Canvas::new(size, size);
+    let radius = size as f64 * 3.0 / 8.0;
+    let center = size as f64 / 2.0;
+    let twelve = Point::new(0, 0, 1);
+
+    for hour in 0..12 {
+        let p = &rotation_y(hour as f64 * PI / 6.0) * twelve;
+        let (x, y) = (p.x * radius + center, p.z * radius + center);
+        canvas.set_pixel(x.round() as usize, y.round() as usize, Color::white());
+    }
+
+    canvas.save(Path::new("clock.png"))
+}
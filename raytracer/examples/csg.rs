@@ -0,0 +1,64 @@
+use std::{f64::consts::PI, path::Path};
+
+use anyhow::Result;
+use raytracer::{
+    camera::Camera,
+    color::Color,
+    geometry::{
+        shape::{Csg, Cube, Cylinder, Operation, Plane, Sphere},
+        Shape,
+    },
+    light::PointLight,
+    material::Material,
+    point::Point,
+    transform::{rotation_x, rotation_y, rotation_z, scaling, translation, view_transform},
+    vector::Vector,
+    world::World,
+};
+
+// chapter 16: a die shaped box, the intersection of a cube and a sphere, with
+// three cylinders drilled through it
+fn main() -> Result<()> {
+    let mut world = World::new();
+    world.add_light(PointLight::new(Point::new(-10, 10, -10), Color::white()));
+
+    let mut floor = Plane::default();
+    floor.set_transform(translation(0, -1, 0));
+    world.add_object(floor);
+
+    let mut red = Material::default();
+    red.color = Color::new(0.8, 0.1, 0.1);
+    let mut cube = Cube::default();
+    cube.set_material(red.clone());
+    let mut sphere = Sphere::default();
+    sphere.set_transform(scaling(1.35, 1.35, 1.35));
+    sphere.set_material(red);
+    let body = Csg::new(Operation::Intersection, cube, sphere);
+
+    let drill = |rotation| {
+        let mut hole = Cylinder::new(-2, 2, true);
+        hole.set_transform(&rotation * &scaling(0.5, 1.0, 0.5));
+        hole
+    };
+    let holes = Csg::new(
+        Operation::Union,
+        drill(rotation_x(PI / 2.0)),
+        Csg::new(
+            Operation::Union,
+            drill(rotation_z(PI / 2.0)),
+            drill(scaling(1, 1, 1)),
+        ),
+    );
+    let mut die = Csg::new(Operation::Difference, body, holes);
+    die.set_transform(rotation_y(PI / 6.0));
+    world.add_object(die);
+
+    let mut camera = Camera::new(300, 200, PI / 3.0);
+    camera.set_transform(view_transform(
+        Point::new(0.0, 2.5, -4.5),
+        Point::origin(),
+        Vector::new(0, 1, 0),
+    ));
+
+    camera.render_parallel(&world).save(Path::new("csg.png"))
+}
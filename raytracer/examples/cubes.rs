@@ -0,0 +1,76 @@
+use std::{f64::consts::PI, path::Path};
+
+use anyhow::Result;
+use raytracer::{
+    camera::Camera,
+    color::Color,
+    geometry::{shape::Cube, Shape},
+    light::PointLight,
+    material::Material,
+    pattern::{checkers_pattern, stripe_pattern},
+    point::Point,
+    transform::{rotation_y, scaling, translation, view_transform},
+    vector::Vector,
+    world::World,
+};
+
+// chapter 12: a table with a few boxes on it, in a room that is a cube seen
+// from the inside
+fn main() -> Result<()> {
+    let mut world = World::new();
+    world.add_light(PointLight::new(Point::new(0.0, 6.9, -5.0), Color::white()));
+
+    let mut room = Cube::default();
+    room.set_transform(&translation(0, 3, 0) * &scaling(10, 4, 10));
+    let mut walls = checkers_pattern(Color::new(0.45, 0.45, 0.5), Color::new(0.55, 0.55, 0.6));
+    walls.set_transform(scaling(0.05, 0.1, 0.05));
+    let mut plaster = Material::default();
+    plaster.set_pattern(walls);
+    plaster.ambient = 0.25;
+    plaster.specular = 0.0;
+    room.set_material(plaster);
+    world.add_object(room);
+
+    let mut wood = stripe_pattern(Color::new(0.55, 0.35, 0.2), Color::new(0.45, 0.28, 0.15));
+    wood.set_transform(&scaling(0.05, 0.05, 0.05) * &rotation_y(0.1));
+    let mut wood_material = Material::default();
+    wood_material.set_pattern(wood);
+    wood_material.specular = 0.2;
+    let mut top = Cube::default();
+    top.set_transform(&translation(0.0, 3.1, 0.0) * &scaling(3.0, 0.1, 2.0));
+    let mut polished = wood_material.clone();
+    polished.reflective = 0.2;
+    top.set_material(polished);
+    world.add_object(top);
+    for &(x, z) in &[(-2.7, -1.7), (2.7, -1.7), (-2.7, 1.7), (2.7, 1.7)] {
+        let mut leg = Cube::default();
+        leg.set_transform(&translation(x, 1.5, z) * &scaling(0.1, 1.5, 0.1));
+        leg.set_material(wood_material.clone());
+        world.add_object(leg);
+    }
+
+    for &(x, z, size, angle, color) in &[
+        (-1.5, 0.5, 0.4, 0.3, Color::new(0.8, 0.2, 0.1)),
+        (0.2, -0.5, 0.3, -0.6, Color::new(0.2, 0.6, 0.9)),
+        (1.6, 0.8, 0.25, 1.2, Color::new(0.9, 0.8, 0.2)),
+    ] {
+        let mut block = Cube::default();
+        block.set_transform(
+            &(&translation(x, 3.2 + size, z) * &rotation_y(angle)) * &scaling(size, size, size),
+        );
+        let mut paint = Material::default();
+        paint.color = color;
+        paint.reflective = 0.1;
+        block.set_material(paint);
+        world.add_object(block);
+    }
+
+    let mut camera = Camera::new(400, 200, PI / 3.0);
+    camera.set_transform(view_transform(
+        Point::new(0.0, 5.5, -8.0),
+        Point::new(0, 3, 0),
+        Vector::new(0, 1, 0),
+    ));
+
+    camera.render_parallel(&world).save(Path::new("cubes.png"))
+}
@@ -0,0 +1,56 @@
+use std::{f64::consts::PI, path::Path};
+
+use anyhow::Result;
+use raytracer::{
+    camera::Camera,
+    color::Color,
+    geometry::{
+        shape::{Cylinder, Group, Sphere},
+        Shape,
+    },
+    light::PointLight,
+    material::Material,
+    point::Point,
+    transform::{rotation_x, rotation_y, rotation_z, scaling, translation, view_transform},
+    vector::Vector,
+    world::World,
+};
+
+// chapter 14: a hexagon built from six groups of a sphere and a cylinder, with
+// one material set on the outer group for all of them
+fn main() -> Result<()> {
+    let mut world = World::new();
+    world.add_light(PointLight::new(Point::new(-10, 10, -10), Color::white()));
+
+    let mut hexagon = Group::default();
+    for n in 0..6 {
+        let mut corner = Sphere::default();
+        corner.set_transform(&translation(0, 0, -1) * &scaling(0.25, 0.25, 0.25));
+        let mut edge = Cylinder::new(0, 1, false);
+        edge.set_transform(
+            &(&(&translation(0, 0, -1) * &rotation_y(-PI / 6.0)) * &rotation_z(-PI / 2.0))
+                * &scaling(0.25, 1.0, 0.25),
+        );
+
+        let mut side = Group::default();
+        side.add_child(Box::new(corner));
+        side.add_child(Box::new(edge));
+        side.set_transform(rotation_y(n as f64 * PI / 3.0));
+        hexagon.add_child(Box::new(side));
+    }
+    let mut material = Material::default();
+    material.color = Color::new(0.9, 0.5, 0.1);
+    material.reflective = 0.2;
+    hexagon.set_material(material);
+    hexagon.set_transform(&translation(0.0, 1.0, 0.0) * &rotation_x(-PI / 6.0));
+    world.add_object(hexagon);
+
+    let mut camera = Camera::new(300, 200, PI / 3.0);
+    camera.set_transform(view_transform(
+        Point::new(0.0, 2.5, -4.0),
+        Point::new(0, 1, 0),
+        Vector::new(0, 1, 0),
+    ));
+
+    camera.render_parallel(&world).save(Path::new("groups.png"))
+}
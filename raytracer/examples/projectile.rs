@@ -0,0 +1,27 @@
+use std::path::Path;
+
+use anyhow::Result;
+use raytracer::{canvas::Canvas, color::Color, point::Point, vector::Vector};
+
+// chapter 2: the path of a projectile fired through gravity and wind, one red
+// pixel per tick
+fn main() -> Result<()> {
+    let gravity = Vector::new(0.0, -0.1, 0.0);
+    let wind = Vector::new(-0.01, 0.0, 0.0);
+    let mut position = Point::new(0, 1, 0);
+    let mut velocity = Vector::new(1.0, 1.8, 0.0).normalize() * 11.25;
+
+    let mut canvas = Canvas::new(900, 550);
+    let red = Color::new(1.0, 0.0, 0.0);
+    while position.y >= 0.0 {
+        let x = position.x.round() as usize;
+        let y = canvas.height() - 1 - (position.y.round() as usize).min(canvas.height() - 1);
+        if x < canvas.width() {
+            canvas.set_pixel(x, y, red);
+        }
+        position = position + velocity;
+        velocity = velocity + gravity + wind;
+    }
+
+    canvas.save(Path::new("projectile.png"))
+}
@@ -0,0 +1,68 @@
+use std::{f64::consts::PI, path::Path};
+
+use anyhow::Result;
+use raytracer::{
+    camera::Camera,
+    color::Color,
+    geometry::{
+        shape::{Plane, Sphere},
+        Shape,
+    },
+    light::PointLight,
+    material::Material,
+    pattern::checkers_pattern,
+    point::Point,
+    transform::{scaling, translation, view_transform},
+    vector::Vector,
+    world::World,
+};
+
+// chapter 11: a mirror and a glass ball on a checkered floor
+fn main() -> Result<()> {
+    let mut world = World::new();
+    world.add_light(PointLight::new(Point::new(-10, 10, -10), Color::white()));
+
+    let mut floor = Plane::default();
+    let mut checkered = Material::default();
+    checkered.set_pattern(checkers_pattern(
+        Color::new(0.35, 0.35, 0.35),
+        Color::new(0.65, 0.65, 0.65),
+    ));
+    checkered.specular = 0.0;
+    checkered.reflective = 0.4;
+    floor.set_material(checkered);
+    world.add_object(floor);
+
+    let mut mirror = Sphere::default();
+    mirror.set_transform(translation(-1, 1, 1));
+    let mut chrome = Material::default();
+    chrome.color = Color::new(0.1, 0.1, 0.1);
+    chrome.diffuse = 0.1;
+    chrome.reflective = 0.9;
+    mirror.set_material(chrome);
+    world.add_object(mirror);
+
+    let mut glass = Sphere::default();
+    glass.set_transform(&translation(1.0, 0.75, -0.5) * &scaling(0.75, 0.75, 0.75));
+    let mut clear = Material::default();
+    clear.color = Color::new(0.05, 0.05, 0.1);
+    clear.diffuse = 0.1;
+    clear.specular = 1.0;
+    clear.shininess = 300.0;
+    clear.reflective = 0.9;
+    clear.transparency = 0.9;
+    clear.refractive_index = 1.5;
+    glass.set_material(clear);
+    world.add_object(glass);
+
+    let mut camera = Camera::new(400, 200, PI / 3.0);
+    camera.set_transform(view_transform(
+        Point::new(0.0, 1.5, -5.0),
+        Point::new(0, 1, 0),
+        Vector::new(0, 1, 0),
+    ));
+
+    camera
+        .render_parallel(&world)
+        .save(Path::new("reflection_refraction.png"))
+}
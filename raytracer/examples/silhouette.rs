@@ -0,0 +1,39 @@
+use std::path::Path;
+
+use anyhow::Result;
+use raytracer::{
+    canvas::Canvas,
+    color::Color,
+    geometry::{intersection::hit, shape::Sphere, Shape},
+    point::Point,
+    ray::Ray,
+    transform::{rotation_z, scaling},
+};
+
+// chapter 5: casting rays from a point at a wall, coloring the wall wherever
+// the sphere in between casts its shadow
+fn main() -> Result<()> {
+    let ray_origin = Point::new(0, 0, -5);
+    let (wall_z, wall_size) = (10.0, 7.0);
+    let pixels = 200;
+    let pixel_size = wall_size / pixels as f64;
+    let half = wall_size / 2.0;
+
+    let mut sphere = Sphere::default();
+    sphere.set_transform(&rotation_z(std::f64::consts::PI / 4.0) * &scaling(0.5, 1.0, 1.0));
+
+    let mut canvas = Canvas::new(pixels, pixels);
+    for y in 0..pixels {
+        let world_y = half - pixel_size * y as f64;
+        for x in 0..pixels {
+            let world_x = -half + pixel_size * x as f64;
+            let target = Point::new(world_x, world_y, wall_z);
+            let ray = Ray::new(ray_origin, (target - ray_origin).normalize());
+            if hit(&sphere.intersect(&ray)).is_some() {
+                canvas.set_pixel(x, y, Color::new(1.0, 0.0, 0.0));
+            }
+        }
+    }
+
+    canvas.save(Path::new("silhouette.png"))
+}
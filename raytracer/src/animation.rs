@@ -0,0 +1,352 @@
+use anyhow::Result;
+
+use crate::{
+    camera::Camera, canvas::Canvas, color::Color, matrix::Matrix, transform::DecomposedTransform,
+    vector::Vector, world::World,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Easing {
+    Linear,
+    EaseIn,
+    EaseOut,
+    EaseInOut,
+    // the css timing function: a bezier curve from (0, 0) to (1, 1) with the
+    // control points (x1, y1) and (x2, y2), x1 and x2 kept within 0 and 1
+    CubicBezier(f64, f64, f64, f64),
+}
+
+impl Easing {
+    pub fn apply(&self, t: f64) -> f64 {
+        let t = t.clamp(0.0, 1.0);
+        match *self {
+            Self::Linear => t,
+            Self::EaseIn => t * t,
+            Self::EaseOut => t * (2.0 - t),
+            Self::EaseInOut => t * t * (3.0 - 2.0 * t),
+            Self::CubicBezier(x1, y1, x2, y2) => {
+                let (x1, x2) = (x1.clamp(0.0, 1.0), x2.clamp(0.0, 1.0));
+                bezier(y1, y2, bezier_parameter(x1, x2, t))
+            }
+        }
+    }
+}
+
+// one coordinate of the curve from 0 to 1 with control values p1 and p2
+fn bezier(p1: f64, p2: f64, s: f64) -> f64 {
+    let r = 1.0 - s;
+    3.0 * r * r * s * p1 + 3.0 * r * s * s * p2 + s * s * s
+}
+
+fn bezier_slope(p1: f64, p2: f64, s: f64) -> f64 {
+    let r = 1.0 - s;
+    3.0 * r * r * p1 + 6.0 * r * s * (p2 - p1) + 3.0 * s * s * (1.0 - p2)
+}
+
+// where along the curve x is reached. x only grows with the control points
+// within 0 and 1, so newton's method does when the slope allows it and
+// bisection finishes the job
+fn bezier_parameter(x1: f64, x2: f64, x: f64) -> f64 {
+    let mut s = x;
+    for _ in 0..8 {
+        let error = bezier(x1, x2, s) - x;
+        if error.abs() < 1e-9 {
+            return s;
+        }
+        let slope = bezier_slope(x1, x2, s);
+        if slope.abs() < 1e-6 {
+            break;
+        }
+        s = (s - error / slope).clamp(0.0, 1.0);
+    }
+
+    let (mut lo, mut hi) = (0.0, 1.0);
+    for _ in 0..50 {
+        s = (lo + hi) / 2.0;
+        if bezier(x1, x2, s) < x {
+            lo = s;
+        } else {
+            hi = s;
+        }
+    }
+    s
+}
+
+// what keyframes can hold: values that can be blended
+pub trait Interpolate: Copy {
+    // self at t = 0, other at t = 1
+    fn interpolate(&self, other: &Self, t: f64) -> Self;
+}
+
+impl Interpolate for f64 {
+    fn interpolate(&self, other: &Self, t: f64) -> Self {
+        self + (other - self) * t
+    }
+}
+
+impl Interpolate for Vector {
+    fn interpolate(&self, other: &Self, t: f64) -> Self {
+        self.lerp(*other, t)
+    }
+}
+
+impl Interpolate for Color {
+    fn interpolate(&self, other: &Self, t: f64) -> Self {
+        *self + (*other - *self) * t
+    }
+}
+
+// translation, rotation and scale are blended apart
+impl Interpolate for DecomposedTransform {
+    fn interpolate(&self, other: &Self, t: f64) -> Self {
+        DecomposedTransform {
+            translation: self.translation.interpolate(&other.translation, t),
+            rotation: self.rotation.interpolate(&other.rotation, t),
+            scale: self.scale.interpolate(&other.scale, t),
+        }
+    }
+}
+
+// the easing of a keyframe applies to the segment leading to the next one
+#[derive(Debug, Clone, PartialEq)]
+pub struct Keyframe<T> {
+    pub time: f64,
+    pub value: T,
+    pub easing: Easing,
+}
+
+// a value changing over time, e.g. a light's intensity or a material's
+// color, held before the first keyframe and after the last
+#[derive(Debug, Clone, PartialEq)]
+pub struct Keyframes<T> {
+    keyframes: Vec<Keyframe<T>>,
+}
+
+impl<T> Default for Keyframes<T> {
+    fn default() -> Self {
+        Self { keyframes: vec![] }
+    }
+}
+
+impl<T: Interpolate> Keyframes<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // keyframes at the same time keep the order they were added in
+    pub fn add(&mut self, time: f64, value: T, easing: Easing) {
+        let idx = self.keyframes.partition_point(|k| k.time <= time);
+        self.keyframes.insert(
+            idx,
+            Keyframe {
+                time,
+                value,
+                easing,
+            },
+        );
+    }
+
+    pub fn keyframes(&self) -> &[Keyframe<T>] {
+        &self.keyframes
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keyframes.is_empty()
+    }
+
+    pub fn sample(&self, time: f64) -> Option<T> {
+        let first = self.keyframes.first()?;
+        let last = self.keyframes.last()?;
+        if time <= first.time {
+            return Some(first.value);
+        }
+        if time >= last.time {
+            return Some(last.value);
+        }
+
+        let idx = self.keyframes.partition_point(|k| k.time <= time);
+        let (k0, k1) = (&self.keyframes[idx - 1], &self.keyframes[idx]);
+        let t = k0.easing.apply((time - k0.time) / (k1.time - k0.time));
+        Some(k0.value.interpolate(&k1.value, t))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct AnimationChannel {
+    keyframes: Keyframes<DecomposedTransform>,
+}
+
+impl AnimationChannel {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_keyframe(&mut self, time: f64, transform: DecomposedTransform, easing: Easing) {
+        self.keyframes.add(time, transform, easing);
+    }
+
+    pub fn keyframes(&self) -> &[Keyframe<DecomposedTransform>] {
+        self.keyframes.keyframes()
+    }
+
+    pub fn sample(&self, time: f64) -> Option<DecomposedTransform> {
+        self.keyframes.sample(time)
+    }
+
+    pub fn transform_at(&self, time: f64) -> Option<Matrix> {
+        self.sample(time).map(|d| d.to_matrix())
+    }
+}
+
+// takes the frames of an animation in order, to write them all to one file
+// (see image::gif::GifSink, and image::ffmpeg::FfmpegSink with the ffmpeg
+// feature) instead of one image per frame
+pub trait FrameSink {
+    fn add_frame(&mut self, frame: &Canvas) -> Result<()>;
+    // writes out the file, no frames can be added after
+    fn finish(&mut self) -> Result<()>;
+}
+
+// renders a frame for each time, with the world and camera advanced to it
+pub fn render_animation(
+    camera: &mut Camera,
+    world: &mut World,
+    times: impl IntoIterator<Item = f64>,
+    sink: &mut dyn FrameSink,
+) -> Result<()> {
+    for time in times {
+        world.advance_time(time);
+        camera.advance_time(time);
+        sink.add_frame(&camera.render_parallel(world))?;
+    }
+    sink.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::PI;
+
+    use crate::{equal, transform::translation};
+
+    use super::*;
+
+    fn at(x: f64) -> DecomposedTransform {
+        DecomposedTransform {
+            translation: Vector::new(x, 0.0, 0.0),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn easing_functions_hit_endpoints() {
+        for easing in [
+            Easing::Linear,
+            Easing::EaseIn,
+            Easing::EaseOut,
+            Easing::EaseInOut,
+        ]
+        .iter()
+        {
+            assert!(equal(easing.apply(0.0), 0.0));
+            assert!(equal(easing.apply(1.0), 1.0));
+        }
+        assert!(equal(Easing::EaseIn.apply(0.5), 0.25));
+        assert!(equal(Easing::EaseOut.apply(0.5), 0.75));
+        assert!(equal(Easing::EaseInOut.apply(0.5), 0.5));
+    }
+
+    #[test]
+    fn cubic_bezier_easing() {
+        let linear = Easing::CubicBezier(0.25, 0.25, 0.75, 0.75);
+        let ease = Easing::CubicBezier(0.25, 0.1, 0.25, 1.0);
+        for &t in [0.0, 0.1, 0.3, 0.5, 0.9, 1.0].iter() {
+            assert!(equal(linear.apply(t), t));
+        }
+        assert!(equal(ease.apply(0.0), 0.0));
+        assert!(equal(ease.apply(1.0), 1.0));
+        // the css ease curve is around 0.8 half way through
+        assert!((ease.apply(0.5) - 0.8024).abs() < 1e-3);
+        // steep curves that newton's method overshoots on still come out right
+        let steep = Easing::CubicBezier(1.0, 0.0, 1.0, 0.0);
+        assert!(steep.apply(0.5) < 0.1);
+        assert!(steep.apply(0.99) < steep.apply(1.0));
+        // y can leave 0 to 1 to overshoot, x can't
+        assert!(Easing::CubicBezier(0.3, 1.6, 0.6, 1.0).apply(0.6) > 1.0);
+        assert!(equal(
+            Easing::CubicBezier(-1.0, 0.0, 2.0, 1.0).apply(0.5),
+            Easing::CubicBezier(0.0, 0.0, 1.0, 1.0).apply(0.5)
+        ));
+    }
+
+    #[test]
+    fn keyframes_sample_colors_and_numbers() {
+        let mut fade = Keyframes::new();
+        assert!(fade.is_empty());
+        fade.add(0.0, Color::white(), Easing::Linear);
+        fade.add(2.0, Color::black(), Easing::Linear);
+        assert_eq!(fade.sample(1.0).unwrap(), Color::new(0.5, 0.5, 0.5));
+        assert_eq!(fade.sample(3.0).unwrap(), Color::black());
+
+        let mut dimmer = Keyframes::new();
+        dimmer.add(1.0, 0.5, Easing::EaseIn);
+        dimmer.add(0.0, 1.0, Easing::EaseOut);
+        dimmer.add(2.0, 0.0, Easing::Linear);
+        assert!(equal(dimmer.sample(0.5).unwrap(), 0.625));
+        assert!(equal(dimmer.sample(1.5).unwrap(), 0.375));
+        assert!(Keyframes::<f64>::new().sample(1.0).is_none());
+    }
+
+    #[test]
+    fn empty_channel_has_no_sample() {
+        let channel = AnimationChannel::new();
+        assert!(channel.sample(0.0).is_none());
+    }
+
+    #[test]
+    fn keyframes_are_kept_sorted() {
+        let mut channel = AnimationChannel::new();
+        channel.add_keyframe(2.0, at(2.0), Easing::Linear);
+        channel.add_keyframe(0.0, at(0.0), Easing::Linear);
+        channel.add_keyframe(1.0, at(1.0), Easing::Linear);
+        let times: Vec<_> = channel.keyframes().iter().map(|k| k.time).collect();
+        assert_eq!(times, vec![0.0, 1.0, 2.0]);
+    }
+
+    #[test]
+    fn sample_clamps_outside_keyframes() {
+        let mut channel = AnimationChannel::new();
+        channel.add_keyframe(1.0, at(1.0), Easing::Linear);
+        channel.add_keyframe(2.0, at(3.0), Easing::Linear);
+        assert_eq!(channel.sample(0.0).unwrap(), at(1.0));
+        assert_eq!(channel.sample(5.0).unwrap(), at(3.0));
+    }
+
+    #[test]
+    fn sample_interpolates_with_easing() {
+        let mut channel = AnimationChannel::new();
+        channel.add_keyframe(0.0, at(0.0), Easing::Linear);
+        channel.add_keyframe(1.0, at(4.0), Easing::EaseIn);
+        channel.add_keyframe(2.0, at(8.0), Easing::Linear);
+        assert_eq!(channel.sample(0.5).unwrap(), at(2.0));
+        assert_eq!(channel.sample(1.5).unwrap(), at(5.0));
+        assert_eq!(channel.transform_at(1.5).unwrap(), translation(5, 0, 0));
+    }
+
+    #[test]
+    fn sample_interpolates_rotation_and_scale() {
+        let mut channel = AnimationChannel::new();
+        channel.add_keyframe(0.0, DecomposedTransform::default(), Easing::Linear);
+        channel.add_keyframe(
+            1.0,
+            DecomposedTransform {
+                rotation: Vector::new(0.0, PI, 0.0),
+                scale: Vector::new(3, 3, 3),
+                ..Default::default()
+            },
+            Easing::Linear,
+        );
+        let d = channel.sample(0.5).unwrap();
+        assert_eq!(d.rotation, Vector::new(0.0, PI / 2.0, 0.0));
+        assert_eq!(d.scale, Vector::new(2, 2, 2));
+    }
+}
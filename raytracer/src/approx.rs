@@ -0,0 +1,92 @@
+use crate::EPSILON;
+
+// float comparisons. near zero only an absolute tolerance makes sense, further
+// out the tolerance grows with the numbers compared, so points a thousand units
+// away compare as reliably as points next to the origin
+
+// the comparison behind PartialEq for points, vectors, colors, matrices and
+// bounding boxes
+pub fn eq(a: f64, b: f64) -> bool {
+    relative_eq(a, b, EPSILON)
+}
+
+pub fn abs_eq(a: f64, b: f64, epsilon: f64) -> bool {
+    a == b || (a - b).abs() < epsilon
+}
+
+// within epsilon, or within epsilon times the larger magnitude. infinities of
+// the same sign are equal, nan equals nothing
+pub fn relative_eq(a: f64, b: f64, epsilon: f64) -> bool {
+    if a == b {
+        return true;
+    }
+    if !a.is_finite() || !b.is_finite() {
+        return false;
+    }
+    let diff = (a - b).abs();
+    diff < epsilon || diff <= epsilon * a.abs().max(b.abs())
+}
+
+// how many representable f64s lie between a and b, none if either is nan.
+// zero and negative zero are the same number
+pub fn ulps_between(a: f64, b: f64) -> Option<u64> {
+    if a.is_nan() || b.is_nan() {
+        return None;
+    }
+    let distance = (ordered_bits(a) as i128 - ordered_bits(b) as i128).unsigned_abs();
+    Some(distance.min(u64::MAX as u128) as u64)
+}
+
+pub fn ulps_eq(a: f64, b: f64, max_ulps: u64) -> bool {
+    ulps_between(a, b).is_some_and(|ulps| ulps <= max_ulps)
+}
+
+// maps the bits of a float onto integers that sort like the floats do
+fn ordered_bits(x: f64) -> i64 {
+    let bits = x.to_bits() as i64;
+    if bits < 0 {
+        i64::MIN.wrapping_sub(bits)
+    } else {
+        bits
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tolerance_grows_with_magnitude() {
+        assert!(eq(0.1 + 0.2, 0.3));
+        assert!(eq(1.0, 1.000001));
+        assert!(!eq(1.0, 1.0001));
+        // a fixed epsilon is below the spacing of floats this large
+        let far = 1.0e12;
+        assert!(eq(far, far + 1.0));
+        assert!(!eq(far, far * 1.001));
+        assert!(!abs_eq(far, far + 1.0, EPSILON));
+    }
+
+    #[test]
+    fn infinities_and_nan() {
+        assert!(eq(f64::INFINITY, f64::INFINITY));
+        assert!(eq(f64::NEG_INFINITY, f64::NEG_INFINITY));
+        assert!(!eq(f64::INFINITY, f64::NEG_INFINITY));
+        assert!(!eq(f64::INFINITY, f64::MAX));
+        assert!(!eq(f64::NAN, f64::NAN));
+        assert!(!relative_eq(f64::NAN, 1.0, 1.0));
+        assert_eq!(ulps_between(f64::NAN, 0.0), None);
+    }
+
+    #[test]
+    fn counting_ulps() {
+        assert_eq!(ulps_between(1.0, 1.0), Some(0));
+        assert_eq!(ulps_between(0.0, -0.0), Some(0));
+        assert_eq!(ulps_between(1.0, 1.0 + f64::EPSILON), Some(1));
+        let tiniest = f64::from_bits(1);
+        assert_eq!(ulps_between(tiniest, -tiniest), Some(2));
+        assert!(ulps_eq(0.1 + 0.2, 0.3, 1));
+        assert!(!ulps_eq(0.1 + 0.2, 0.3, 0));
+        assert_eq!(ulps_between(f64::MAX, f64::INFINITY), Some(1));
+    }
+}
@@ -0,0 +1,105 @@
+use std::{
+    f64::consts::{PI, TAU},
+    time::Instant,
+};
+
+use raytracer::{
+    camera::Camera,
+    color::Color,
+    geometry::{
+        shape::{Group, Sphere, Triangle},
+        Shape,
+    },
+    light::PointLight,
+    point::Point,
+    transform::{rotation_x, rotation_y, scaling, translation, view_transform},
+    vector::Vector,
+    world::World,
+};
+
+// renders a grid of groups with and without the bounding sphere check, once
+// with clusters of spheres and once with small triangle meshes like the obj
+// parser makes. the check rejects the rays through the corners of the spheres'
+// boxes and saves around a fifth of the time, for the thin meshes the sphere is
+// looser than the box and the difference gets lost in the noise
+fn main() {
+    for &meshes in [false, true].iter() {
+        for &check in [false, true].iter() {
+            let world = scene(meshes, check);
+            let mut camera = Camera::new(200, 200, PI / 3.0);
+            camera.set_transform(view_transform(
+                Point::new(0, 0, -12),
+                Point::origin(),
+                Vector::new(0, 1, 0),
+            ));
+
+            let start = Instant::now();
+            camera.render(&world);
+            println!(
+                "{}, bounding sphere check: {}, rendered in {} ms",
+                if meshes { "meshes" } else { "spheres" },
+                check,
+                start.elapsed().as_millis()
+            );
+        }
+    }
+}
+
+fn scene(meshes: bool, check: bool) -> World {
+    let mut world = World::new();
+    world.add_light(PointLight::new(Point::new(-10, 10, -10), Color::white()));
+
+    for gx in -4..=4 {
+        for gy in -4..=4 {
+            let mut group = if meshes { mesh() } else { cluster() };
+            let turn = &rotation_y((gx + gy) as f64) * &rotation_x(-0.5);
+            group.set_transform(
+                &(&translation(gx as f64, gy as f64, 0.0) * &turn) * &scaling(0.4, 0.4, 0.4),
+            );
+            group.set_bounding_sphere_check(check);
+            world.add_object(group);
+        }
+    }
+
+    world
+}
+
+// a ball of spheres around the origin
+fn cluster() -> Group {
+    let mut group = Group::default();
+    for i in 0..8 {
+        let angle = i as f64 * TAU / 8.0;
+        let mut sphere = Sphere::default();
+        sphere.set_transform(
+            &translation(
+                angle.cos() * 0.6,
+                (i as f64 * 0.7).sin() * 0.5,
+                angle.sin() * 0.6,
+            ) * &scaling(0.35, 0.35, 0.35),
+        );
+        group.add_child(Box::new(sphere));
+    }
+    group
+}
+
+// a gently rolling patch of ground, 2 by 2 and thin like most meshes' parts
+fn mesh() -> Group {
+    const CELLS: usize = 8;
+    let vertex = |i: usize, j: usize| {
+        let (x, z) = (
+            i as f64 * 2.0 / CELLS as f64 - 1.0,
+            j as f64 * 2.0 / CELLS as f64 - 1.0,
+        );
+        Point::new(x, (x * 3.0).sin() * (z * 2.0).cos() * 0.1, z)
+    };
+    let mut group = Group::default();
+    for i in 0..CELLS {
+        for j in 0..CELLS {
+            let (a, b) = (vertex(i, j), vertex(i + 1, j));
+            let (c, d) = (vertex(i, j + 1), vertex(i + 1, j + 1));
+            group.add_child(Box::new(Triangle::new(a, b, c)));
+            group.add_child(Box::new(Triangle::new(b, d, c)));
+        }
+    }
+    group
+}
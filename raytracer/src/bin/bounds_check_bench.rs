@@ -0,0 +1,63 @@
+use std::{f64::consts::PI, time::Instant};
+
+use raytracer::{
+    camera::Camera,
+    color::Color,
+    geometry::{
+        shape::{Group, Sphere},
+        Shape,
+    },
+    light::PointLight,
+    point::Point,
+    transform::{scaling, translation, view_transform},
+    vector::Vector,
+    world::World,
+};
+
+// renders a grid of small groups with different bounds check thresholds, used to
+// pick DEFAULT_BOUNDS_CHECK_THRESHOLD
+fn main() {
+    for &children in [1, 2, 3, 4, 8].iter() {
+        for &threshold in [0, 1, 2, 4, 8].iter() {
+            let world = scene(children, threshold);
+            let mut camera = Camera::new(200, 200, PI / 3.0);
+            camera.set_transform(view_transform(
+                Point::new(0, 0, -12),
+                Point::origin(),
+                Vector::new(0, 1, 0),
+            ));
+
+            let start = Instant::now();
+            camera.render(&world);
+            println!(
+                "children per group: {}, threshold: {}, rendered in {} ms",
+                children,
+                threshold,
+                start.elapsed().as_millis()
+            );
+        }
+    }
+}
+
+fn scene(children: usize, threshold: usize) -> World {
+    let mut world = World::new();
+    world.add_light(PointLight::new(Point::new(-10, 10, -10), Color::white()));
+
+    for gx in -4..=4 {
+        for gy in -4..=4 {
+            let mut group = Group::default();
+            group.set_bounds_check_threshold(threshold);
+            for i in 0..children {
+                let mut sphere = Sphere::default();
+                sphere.set_transform(
+                    &translation(gx as f64, gy as f64 + i as f64 * 0.1, 0.0)
+                        * &scaling(0.2, 0.2, 0.2),
+                );
+                group.add_child(Box::new(sphere));
+            }
+            world.add_object(group);
+        }
+    }
+
+    world
+}
@@ -21,7 +21,7 @@ fn main() -> Result<()> {
     println!("simple scene 1.0!");
 
     let mut floor = Plane::default();
-    floor.get_base_mut().material.set_pattern(checkers_pattern(
+    floor.material_mut().set_pattern(checkers_pattern(
         Color::new(0.0, 0.5, 0.5),
         Color::new(0.5, 0.0, 0.5),
     ));
@@ -35,10 +35,7 @@ fn main() -> Result<()> {
     left_wall.set_transform(left_wall_transform);
     let mut left_wall_pattern = ring_pattern(Color::new(0.0, 0.0, 1.0), Color::new(0.0, 1.0, 1.0));
     left_wall_pattern.set_transform(scaling(0.333, 0.333, 0.333));
-    left_wall
-        .get_base_mut()
-        .material
-        .set_pattern(left_wall_pattern);
+    left_wall.material_mut().set_pattern(left_wall_pattern);
 
     let mut right_wall = Plane::default();
 
@@ -48,33 +45,32 @@ fn main() -> Result<()> {
         .translate(0, 0, 5);
 
     right_wall.set_transform(right_wall_transform);
-    right_wall.get_base_mut().material = Material::default();
+    right_wall.set_material(Material::default());
     right_wall
-        .get_base_mut()
-        .material
+        .material_mut()
         .set_pattern(stripe_pattern(Color::white(), Color::black()));
 
     let mut middle = Sphere::default();
     middle.set_transform(translation(-0.5, 1.0, 0.5));
-    middle.get_base_mut().material.color = Color::new(0.1, 1.0, 0.5);
-    middle.get_base_mut().material.diffuse = 0.7;
-    middle.get_base_mut().material.specular = 0.3;
-    middle.get_base_mut().material.reflective = 0.9;
+    middle.material_mut().color = Color::new(0.1, 1.0, 0.5);
+    middle.material_mut().diffuse = 0.7;
+    middle.material_mut().specular = 0.3;
+    middle.material_mut().reflective = 0.9;
 
     let mut right = Sphere::default();
     right.set_transform(&translation(1.5, 0.5, -0.5) * &scaling(0.5, 0.5, 0.5));
-    right.get_base_mut().material.set_pattern(checkers_pattern(
+    right.material_mut().set_pattern(checkers_pattern(
         Color::new(1.0, 0.0, 0.0),
         Color::new(0.0, 1.0, 0.0),
     ));
 
     let mut left = Sphere::glass();
-    left.get_base_mut().material.color = Color::new(0.1, 0.0, 0.0);
-    left.get_base_mut().material.ambient = 0.1;
-    left.get_base_mut().material.diffuse = 0.05;
-    left.get_base_mut().material.reflective = 0.3;
-    left.get_base_mut().material.specular = 1.0;
-    left.get_base_mut().material.shininess = 300.0;
+    left.material_mut().color = Color::new(0.1, 0.0, 0.0);
+    left.material_mut().ambient = 0.1;
+    left.material_mut().diffuse = 0.05;
+    left.material_mut().reflective = 0.3;
+    left.material_mut().specular = 1.0;
+    left.material_mut().shininess = 300.0;
     left.set_transform(&translation(-1.5, 0.33, -0.75) * &scaling(0.33, 0.33, 0.33));
 
     let mut cube = Cube::default();
@@ -118,7 +114,7 @@ fn main() -> Result<()> {
     // save_ppm(&canvas, Path::new("renders/first_scene.ppm"))
     let canvas = camera::Camera::render_multithreaded(Arc::new(camera), Arc::new(world));
 
-    let exporter = PngExporter {};
+    let exporter = PngExporter::default();
     exporter.save(
         &canvas,
         Path::new("raytracer/renders/first_scene_multithread.png"),
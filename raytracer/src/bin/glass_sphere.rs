@@ -33,36 +33,36 @@ fn main() -> Result<()> {
 
     let mut wall = Plane::default();
     wall.set_transform(&translation(0, 0, 10) * &rotation_x(FRAC_PI_2));
-    wall.get_base_mut().material.set_pattern(checkers_pattern(
+    wall.material_mut().set_pattern(checkers_pattern(
         Color::new(0.15, 0.15, 0.15),
         Color::new(0.8, 0.8, 0.8),
     ));
-    wall.get_base_mut().material.ambient = 0.8;
-    wall.get_base_mut().material.diffuse = 0.2;
-    wall.get_base_mut().material.specular = 0.0;
+    wall.material_mut().ambient = 0.8;
+    wall.material_mut().diffuse = 0.2;
+    wall.material_mut().specular = 0.0;
     world.add_object(wall);
 
     let mut ball = Sphere::default();
-    ball.get_base_mut().material.color = Color::white();
-    ball.get_base_mut().material.ambient = 0.0;
-    ball.get_base_mut().material.diffuse = 0.0;
-    ball.get_base_mut().material.specular = 0.9;
-    ball.get_base_mut().material.shininess = 300.0;
-    ball.get_base_mut().material.reflective = 0.9;
-    ball.get_base_mut().material.transparency = 0.9;
-    ball.get_base_mut().material.refractive_index = 1.5;
+    ball.material_mut().color = Color::white();
+    ball.material_mut().ambient = 0.0;
+    ball.material_mut().diffuse = 0.0;
+    ball.material_mut().specular = 0.9;
+    ball.material_mut().shininess = 300.0;
+    ball.material_mut().reflective = 0.9;
+    ball.material_mut().transparency = 0.9;
+    ball.material_mut().refractive_index = 1.5;
     world.add_object(ball);
 
     let mut center = Sphere::default();
     center.set_transform(scaling(0.5, 0.5, 0.5));
-    center.get_base_mut().material.color = Color::white();
-    center.get_base_mut().material.ambient = 0.0;
-    center.get_base_mut().material.diffuse = 0.0;
-    center.get_base_mut().material.specular = 0.9;
-    center.get_base_mut().material.shininess = 300.0;
-    center.get_base_mut().material.reflective = 0.9;
-    center.get_base_mut().material.transparency = 0.9;
-    center.get_base_mut().material.refractive_index = 1.0000034;
+    center.material_mut().color = Color::white();
+    center.material_mut().ambient = 0.0;
+    center.material_mut().diffuse = 0.0;
+    center.material_mut().specular = 0.9;
+    center.material_mut().shininess = 300.0;
+    center.material_mut().reflective = 0.9;
+    center.material_mut().transparency = 0.9;
+    center.material_mut().refractive_index = 1.0000034;
     world.add_object(center);
 
     let canvas = camera::Camera::render_multithreaded(Arc::new(camera), Arc::new(world));
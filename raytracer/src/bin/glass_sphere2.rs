@@ -42,36 +42,36 @@ fn main() -> Result<()> {
 
     let mut wall = Plane::default();
     wall.set_transform(&translation(0, 0, 10) * &rotation_x(FRAC_PI_2));
-    wall.get_base_mut().material.set_pattern(checkers_pattern(
+    wall.material_mut().set_pattern(checkers_pattern(
         Color::new(0.15, 0.15, 0.15),
         Color::new(0.8, 0.8, 0.8),
     ));
-    wall.get_base_mut().material.ambient = 0.8;
-    wall.get_base_mut().material.diffuse = 0.2;
-    wall.get_base_mut().material.specular = 0.0;
+    wall.material_mut().ambient = 0.8;
+    wall.material_mut().diffuse = 0.2;
+    wall.material_mut().specular = 0.0;
     world.add_object(wall);
 
     let mut ball1 = Sphere::default();
-    ball1.get_base_mut().material.color = Color::white();
-    ball1.get_base_mut().material.ambient = 0.0;
-    ball1.get_base_mut().material.diffuse = 0.0;
-    ball1.get_base_mut().material.specular = 0.9;
-    ball1.get_base_mut().material.shininess = 300.0;
-    ball1.get_base_mut().material.reflective = 0.9;
-    ball1.get_base_mut().material.transparency = 0.9;
-    ball1.get_base_mut().material.refractive_index = 1.5;
+    ball1.material_mut().color = Color::white();
+    ball1.material_mut().ambient = 0.0;
+    ball1.material_mut().diffuse = 0.0;
+    ball1.material_mut().specular = 0.9;
+    ball1.material_mut().shininess = 300.0;
+    ball1.material_mut().reflective = 0.9;
+    ball1.material_mut().transparency = 0.9;
+    ball1.material_mut().refractive_index = 1.5;
 
     ball1.set_transform(translation(0.25, 0.0, 0.0));
 
     let mut ball2 = Sphere::default();
-    ball2.get_base_mut().material.color = Color::white();
-    ball2.get_base_mut().material.ambient = 0.0;
-    ball2.get_base_mut().material.diffuse = 0.0;
-    ball2.get_base_mut().material.specular = 0.9;
-    ball2.get_base_mut().material.shininess = 300.0;
-    ball2.get_base_mut().material.reflective = 0.9;
-    ball2.get_base_mut().material.transparency = 0.9;
-    ball2.get_base_mut().material.refractive_index = 1.5;
+    ball2.material_mut().color = Color::white();
+    ball2.material_mut().ambient = 0.0;
+    ball2.material_mut().diffuse = 0.0;
+    ball2.material_mut().specular = 0.9;
+    ball2.material_mut().shininess = 300.0;
+    ball2.material_mut().reflective = 0.9;
+    ball2.material_mut().transparency = 0.9;
+    ball2.material_mut().refractive_index = 1.5;
 
     ball2.set_transform(translation(-0.25, 0.0, 0.0));
 
@@ -83,19 +83,19 @@ fn main() -> Result<()> {
 
     // let mut center = Sphere::default();
     // center.set_transform(scaling(0.5, 0.5, 0.5));
-    // center.get_base_mut().material.color = Color::white();
-    // center.get_base_mut().material.ambient = 0.0;
-    // center.get_base_mut().material.diffuse = 0.0;
-    // center.get_base_mut().material.specular = 0.9;
-    // center.get_base_mut().material.shininess = 300.0;
-    // center.get_base_mut().material.reflective = 0.9;
-    // center.get_base_mut().material.transparency = 0.9;
-    // center.get_base_mut().material.refractive_index = 1.0000034;
+    // center.material_mut().color = Color::white();
+    // center.material_mut().ambient = 0.0;
+    // center.material_mut().diffuse = 0.0;
+    // center.material_mut().specular = 0.9;
+    // center.material_mut().shininess = 300.0;
+    // center.material_mut().reflective = 0.9;
+    // center.material_mut().transparency = 0.9;
+    // center.material_mut().refractive_index = 1.0000034;
     // world.add_object(center);
 
     let canvas = camera::Camera::render_multithreaded(Arc::new(camera), Arc::new(world));
 
-    let exporter = raytracer::image::png::PngExporter {};
+    let exporter = raytracer::image::png::PngExporter::default();
     exporter.save(
         &canvas,
         Path::new("raytracer/renders/glass_sphere2-difference-4k-aax16.png"),
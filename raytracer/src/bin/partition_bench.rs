@@ -0,0 +1,68 @@
+use std::{f64::consts::PI, time::Instant};
+
+use raytracer::{
+    camera::Camera,
+    color::Color,
+    geometry::{
+        shape::{Group, PartitionMode, Sphere},
+        Shape,
+    },
+    light::PointLight,
+    point::Point,
+    transform::{scaling, translation, view_transform},
+    vector::Vector,
+    world::World,
+};
+
+// renders a cloud of overlapping spheres divided with each partition mode. many
+// of the spheres straddle the split planes, which is where the modes differ.
+// on a scatter this even sah comes out about level with centroid
+fn main() {
+    for &mode in [
+        PartitionMode::Contained,
+        PartitionMode::Centroid,
+        PartitionMode::Sah,
+    ]
+    .iter()
+    {
+        for &threshold in [1, 4, 16].iter() {
+            let world = scene(mode, threshold);
+            let mut camera = Camera::new(200, 200, PI / 3.0);
+            camera.set_transform(view_transform(
+                Point::new(0, 0, -12),
+                Point::origin(),
+                Vector::new(0, 1, 0),
+            ));
+
+            let start = Instant::now();
+            camera.render(&world);
+            println!(
+                "mode: {:?}, threshold: {}, rendered in {} ms",
+                mode,
+                threshold,
+                start.elapsed().as_millis()
+            );
+        }
+    }
+}
+
+fn scene(mode: PartitionMode, threshold: usize) -> World {
+    let mut world = World::new();
+    world.add_light(PointLight::new(Point::new(-10, 10, -10), Color::white()));
+
+    let mut group = Group::default();
+    group.set_partition_mode(mode);
+    for i in 0..2000 {
+        // a deterministic scatter over [-4, 4]²
+        let x = (i as f64 * 0.618_034).fract() * 8.0 - 4.0;
+        let y = (i as f64 * 0.414_214).fract() * 8.0 - 4.0;
+        let z = (i as f64 * 0.732_051).fract() * 2.0;
+        let mut sphere = Sphere::default();
+        sphere.set_transform(&translation(x, y, z) * &scaling(0.3, 0.3, 0.3));
+        group.add_child(Box::new(sphere));
+    }
+    group.divide(threshold);
+    world.add_object(group);
+
+    world
+}
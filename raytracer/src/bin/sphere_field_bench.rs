@@ -0,0 +1,54 @@
+use std::{f64::consts::PI, time::Instant};
+
+use raytracer::{
+    camera::Camera,
+    color::Color,
+    geometry::{shape::PartitionMode, Shape},
+    light::PointLight,
+    point::Point,
+    testing::generate_sphere_field,
+    transform::view_transform,
+    vector::Vector,
+    world::World,
+};
+
+// renders ever bigger sphere fields, divided and not. with the groups divided
+// the work per ray should grow with log n rather than n, on top of the field
+// getting deeper as it grows
+fn main() {
+    for &n in [1000, 4000, 16000, 64000].iter() {
+        for &divided in [false, true].iter() {
+            // the undivided big fields take forever
+            if !divided && n > 4000 {
+                continue;
+            }
+            let mut field = generate_sphere_field(n, 1);
+            let start = Instant::now();
+            if divided {
+                field.set_partition_mode(PartitionMode::Centroid);
+                field.divide(4);
+            }
+            let build = start.elapsed().as_millis();
+
+            let mut world = World::new();
+            world.add_light(PointLight::new(Point::new(-100, 100, -100), Color::white()));
+            world.add_object(field);
+            let mut camera = Camera::new(100, 100, PI / 3.0);
+            camera.set_transform(view_transform(
+                Point::new(0.0, 0.0, -(n as f64).cbrt() * 4.0),
+                Point::origin(),
+                Vector::new(0, 1, 0),
+            ));
+
+            let start = Instant::now();
+            camera.render_parallel(&world);
+            println!(
+                "spheres: {}, divided: {}, divided in {} ms, rendered in {} ms",
+                n,
+                divided,
+                build,
+                start.elapsed().as_millis()
+            );
+        }
+    }
+}
@@ -11,7 +11,7 @@ use raytracer::{
         Shape,
     },
     image::ppm::save_ppm,
-    light::PointLight,
+    light::{Light, PointLight},
     material::Material,
     matrix::Matrix,
     point::Point,
@@ -37,7 +37,7 @@ fn main() -> Result<()> {
 
     let light_position = Point::new(-10, 10, -10);
     let light_color = Color::new(1.0, 1.0, 1.0);
-    let light = PointLight::new(light_position, light_color);
+    let light = Light::from(PointLight::new(light_position, light_color));
 
     shape.set_transform(
         Matrix::identity(4, 4)
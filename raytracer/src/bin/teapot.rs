@@ -95,6 +95,6 @@ fn main() -> Result<()> {
 
     // let canvas = camera.render(&world);
     let canvas = camera::Camera::render_multithreaded(Arc::new(camera), Arc::new(world));
-    let exporter = raytracer::image::png::PngExporter {};
+    let exporter = raytracer::image::png::PngExporter::default();
     exporter.save(&canvas, Path::new("raytracer/renders/teapot_4k_aax16.png"))
 }
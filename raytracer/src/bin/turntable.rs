@@ -0,0 +1,72 @@
+use std::{env, f64::consts::PI, path::Path};
+
+use anyhow::Result;
+
+use raytracer::{
+    animation::{render_animation, AnimationChannel, Easing},
+    camera::Camera,
+    camera_path::CameraPath,
+    color::Color,
+    geometry::{
+        shape::{Cube, Group, Plane, Sphere},
+        Shape,
+    },
+    image::gif::GifSink,
+    light::PointLight,
+    point::Point,
+    transform::{scaling, translation, view_transform, DecomposedTransform},
+    vector::Vector,
+    world::World,
+};
+
+const FRAMES: usize = 36;
+
+// a full turn of a cube and two spheres, as one looping gif. given a camera
+// path file, the camera follows it over the turn, with times from 0 to 1
+fn main() -> Result<()> {
+    let mut world = World::new();
+    world.add_light(PointLight::new(Point::new(-10, 10, -10), Color::white()));
+
+    let mut floor = Plane::default();
+    floor.set_transform(translation(0, -1, 0));
+    world.add_object(floor);
+
+    let mut group = Group::default();
+    let mut cube = Cube::default();
+    cube.material_mut().color = Color::new(0.8, 0.3, 0.2);
+    group.add_child(Box::new(cube));
+    for &x in &[-2.0, 2.0] {
+        let mut sphere = Sphere::default();
+        sphere.set_transform(&translation(x, -0.5, 0.0) * &scaling(0.5, 0.5, 0.5));
+        sphere.material_mut().color = Color::new(0.2, 0.4, 0.8);
+        sphere.material_mut().reflective = 0.3;
+        group.add_child(Box::new(sphere));
+    }
+    let mut turn = AnimationChannel::new();
+    turn.add_keyframe(0.0, DecomposedTransform::default(), Easing::Linear);
+    turn.add_keyframe(
+        1.0,
+        DecomposedTransform {
+            rotation: Vector::new(0.0, 2.0 * PI, 0.0),
+            ..Default::default()
+        },
+        Easing::Linear,
+    );
+    group.set_animation(turn);
+    world.add_object(group);
+
+    let mut camera = Camera::new(320, 200, PI / 3.0);
+    camera.set_transform(view_transform(
+        Point::new(0, 3, -6),
+        Point::origin(),
+        Vector::new(0, 1, 0),
+    ));
+    if let Some(file) = env::args().nth(1) {
+        camera.set_path(CameraPath::load(Path::new(&file))?);
+    }
+
+    // the last frame would repeat the first
+    let times = (0..FRAMES).map(|i| i as f64 / FRAMES as f64);
+    let mut sink = GifSink::create(Path::new("turntable.gif"), 12)?;
+    render_animation(&mut camera, &mut world, times, &mut sink)
+}
@@ -1,8 +1,6 @@
-use std::f64;
-
 use crate::{equal, matrix::Matrix, point::Point, ray::Ray, EPSILON};
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct BoundingBox {
     min: Point,
     max: Point,
@@ -17,12 +15,6 @@ impl Default for BoundingBox {
     }
 }
 
-impl PartialEq for BoundingBox {
-    fn eq(&self, other: &Self) -> bool {
-        Point::eq_ignore_inf(&self.min, &other.min) && Point::eq_ignore_inf(&self.max, &other.max)
-    }
-}
-
 impl BoundingBox {
     pub fn new(min: Point, max: Point) -> Self {
         Self { min, max }
@@ -36,6 +28,22 @@ impl BoundingBox {
         self.max
     }
 
+    pub fn center(&self) -> Point {
+        Point::new(
+            (self.min.x + self.max.x) / 2.0,
+            (self.min.y + self.max.y) / 2.0,
+            (self.min.z + self.max.z) / 2.0,
+        )
+    }
+
+    pub fn surface_area(&self) -> f64 {
+        if self.is_empty() {
+            return 0.0;
+        }
+        let d = self.max - self.min;
+        2.0 * (d.x * d.y + d.y * d.z + d.z * d.x)
+    }
+
     pub fn add_point(&mut self, point: Point) {
         if point.x > self.max.x {
             self.max.x = point.x;
@@ -74,6 +82,10 @@ impl BoundingBox {
     }
 
     pub fn transform(&self, matrix: &Matrix) -> BoundingBox {
+        if self.is_unbounded() {
+            return BoundingBox::infinite();
+        }
+
         let p1 = self.min;
         let p2 = Point::new(self.min.x, self.min.y, self.max.z);
         let p3 = Point::new(self.min.x, self.max.y, self.min.z);
@@ -92,6 +104,30 @@ impl BoundingBox {
         new_bb
     }
 
+    pub fn infinite() -> Self {
+        Self {
+            min: Point::new(f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY),
+            max: Point::new(f64::INFINITY, f64::INFINITY, f64::INFINITY),
+        }
+    }
+
+    // transforming the corners of an infinite box gives NaNs, so these are kept
+    // infinite under any transform instead
+    pub fn is_unbounded(&self) -> bool {
+        !self.is_empty() && !self.is_finite()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.min.x > self.max.x || self.min.y > self.max.y || self.min.z > self.max.z
+    }
+
+    // false for empty boxes and for unbounded shapes like planes
+    pub fn is_finite(&self) -> bool {
+        [self.min, self.max]
+            .iter()
+            .all(|p| p.x.is_finite() && p.y.is_finite() && p.z.is_finite())
+    }
+
     pub fn intersects(&self, ray: &Ray) -> bool {
         let (xtmin, xtmax) =
             self.check_axis(ray.origin().x, ray.direction().x, self.min.x, self.max.x);
@@ -175,7 +211,7 @@ mod tests {
 
     use crate::{
         geometry::{
-            shape::{Csg, Group, Operation, Sphere, TestShape},
+            shape::{Csg, Group, Operation, Plane, Sphere, TestShape},
             Shape,
         },
         transform::{rotation_x, rotation_y, scaling, translation},
@@ -373,6 +409,7 @@ mod tests {
     fn intersect_ray_with_group_does_not_test_children_if_box_is_missed() {
         let child = TestShape::default();
         let mut shape = Group::default();
+        shape.set_bounds_check_threshold(0);
         shape.add_child(Box::new(child));
 
         let r = Ray::new(Point::new(0, 0, -5), Vector::new(0, 1, 0));
@@ -390,6 +427,59 @@ mod tests {
         );
     }
 
+    #[test]
+    fn small_group_skips_its_bounding_box() {
+        let mut shape = Group::default();
+        shape.set_bounds_check_threshold(1);
+        shape.add_child(Box::new(TestShape::default()));
+
+        let r = Ray::new(Point::new(0, 0, -5), Vector::new(0, 1, 0));
+        let _ = shape.intersect(&r);
+
+        let child = &shape.children[0]
+            .as_any()
+            .downcast_ref::<TestShape>()
+            .unwrap();
+        assert_eq!(child.saved_ray.read().unwrap().origin(), r.origin());
+    }
+
+    #[test]
+    fn unbounded_children_do_not_disable_bounds_check() {
+        let mut shape = Group::default();
+        shape.add_child(Box::new(TestShape::default()));
+        shape.add_child(Box::new(Plane::default()));
+        assert!(!shape.get_bounds().is_finite());
+
+        let r = Ray::new(Point::new(0, 0, -5), Vector::new(0, 1, 0));
+        let _ = shape.intersect(&r);
+
+        let child = &shape.children[0]
+            .as_any()
+            .downcast_ref::<TestShape>()
+            .unwrap();
+        assert_eq!(child.saved_ray.read().unwrap().origin(), Point::origin());
+    }
+
+    #[test]
+    fn transformed_unbounded_box_stays_unbounded() {
+        let bb = Plane::default()
+            .get_bounds()
+            .transform(&translation(0, 1, 0));
+        assert_eq!(bb, BoundingBox::infinite());
+    }
+
+    #[test]
+    fn finite_bounding_boxes() {
+        assert!(BoundingBox::default().is_empty());
+        assert!(!BoundingBox::default().is_finite());
+        assert!(BoundingBox::new(Point::new(-1, -1, -1), Point::new(1, 1, 1)).is_finite());
+        assert!(!BoundingBox::new(
+            Point::new(f64::NEG_INFINITY, 0.0, -1.0),
+            Point::new(1, 1, 1)
+        )
+        .is_finite());
+    }
+
     #[test]
     fn intersect_ray_with_group_tests_children_if_box_is_hit() {
         let child = TestShape::default();
@@ -412,7 +502,8 @@ mod tests {
     fn intersect_ray_with_csg_does_not_test_children_if_box_is_missed() {
         let left = TestShape::default();
         let right = TestShape::default();
-        let shape = Csg::new(Operation::Difference, left, right);
+        let mut shape = Csg::new(Operation::Difference, left, right);
+        shape.set_bounds_check_threshold(0);
 
         let r = Ray::new(Point::new(0, 0, -5), Vector::new(0, 1, 0));
         let _ = shape.intersect(&r);
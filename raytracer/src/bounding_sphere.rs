@@ -0,0 +1,144 @@
+use crate::{
+    bounding_box::BoundingBox,
+    matrix::Matrix,
+    point::Point,
+    ray::Ray,
+    vector::{dot, Vector},
+};
+
+// a single distance test instead of three slabs, and a tighter fit than a box
+// around round shapes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoundingSphere {
+    center: Point,
+    radius: f64,
+}
+
+impl BoundingSphere {
+    pub fn new(center: Point, radius: f64) -> Self {
+        Self { center, radius }
+    }
+
+    // the sphere through the box's corners, none for empty or unbounded boxes
+    pub fn around_box(bounds: &BoundingBox) -> Option<Self> {
+        if bounds.is_empty() || !bounds.is_finite() {
+            return None;
+        }
+        let center = bounds.center();
+        Some(Self::new(center, (bounds.get_max() - center).magnitude()))
+    }
+
+    pub fn get_center(&self) -> Point {
+        self.center
+    }
+
+    pub fn get_radius(&self) -> f64 {
+        self.radius
+    }
+
+    // the radius grows by the most the matrix stretches any direction, so the
+    // sphere still contains whatever it contained before
+    pub fn transform(&self, matrix: &Matrix) -> Self {
+        Self::new(matrix * self.center, self.radius * max_stretch(matrix))
+    }
+
+    // the smallest sphere containing both
+    pub fn merge(&self, other: &Self) -> Self {
+        let offset = other.center - self.center;
+        let distance = offset.magnitude();
+        if distance + other.radius <= self.radius {
+            return *self;
+        }
+        if distance + self.radius <= other.radius {
+            return *other;
+        }
+        let radius = (distance + self.radius + other.radius) / 2.0;
+        let center = self.center + offset * ((radius - self.radius) / distance);
+        Self::new(center, radius)
+    }
+
+    // like the box test, the whole line through the ray counts
+    pub fn intersects(&self, ray: &Ray) -> bool {
+        let to_center = self.center - ray.origin();
+        let direction = ray.direction();
+        let t = dot(to_center, direction) / dot(direction, direction);
+        let closest: Vector = to_center - direction * t;
+        dot(closest, closest) <= self.radius * self.radius
+    }
+}
+
+// the largest singular value of the upper 3x3, i.e. the square root of the
+// largest eigenvalue of aᵀa, which no row of aᵀa sums to less than. exact for
+// rotations combined with scaling
+fn max_stretch(matrix: &Matrix) -> f64 {
+    let ata = |i: usize, j: usize| (0..3).map(|k| matrix[(k, i)] * matrix[(k, j)]).sum::<f64>();
+    (0..3)
+        .map(|i| (0..3).map(|j| ata(i, j).abs()).sum::<f64>())
+        .fold(0.0, f64::max)
+        .sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::PI;
+
+    use crate::transform::{rotation_z, scaling, shearing, translation};
+
+    use super::*;
+
+    #[test]
+    fn sphere_around_box() {
+        let bb = BoundingBox::new(Point::new(-1, -2, -2), Point::new(1, 2, 2));
+        let sphere = BoundingSphere::around_box(&bb).unwrap();
+        assert_eq!(sphere.get_center(), Point::origin());
+        assert!(crate::equal(sphere.get_radius(), 3.0));
+        assert!(BoundingSphere::around_box(&BoundingBox::default()).is_none());
+        assert!(BoundingSphere::around_box(&BoundingBox::infinite()).is_none());
+    }
+
+    #[test]
+    fn transformed_spheres_stay_around_their_contents() {
+        let unit = BoundingSphere::new(Point::origin(), 1.0);
+        let moved =
+            unit.transform(&(&(&translation(5, 0, 0) * &rotation_z(PI / 3.0)) * &scaling(2, 2, 2)));
+        assert_eq!(moved.get_center(), Point::new(5, 0, 0));
+        assert!(crate::equal(moved.get_radius(), 2.0));
+        assert!(crate::equal(
+            unit.transform(&scaling(1.0, 3.0, 0.5)).get_radius(),
+            3.0
+        ));
+
+        // a sheared unit sphere reaches no further than the radius says
+        let shear = shearing(1.0, 0.5, 0.0, 0.0, 0.0, 1.0);
+        let sheared = unit.transform(&shear);
+        for i in 0..100 {
+            let (a, b) = (i as f64 * 0.7, i as f64 * 1.3);
+            let on_unit = Point::new(a.cos() * b.sin(), a.sin() * b.sin(), b.cos());
+            let p = &shear * on_unit;
+            assert!((p - sheared.get_center()).magnitude() <= sheared.get_radius() + 1e-9);
+        }
+    }
+
+    #[test]
+    fn merging_spheres() {
+        let a = BoundingSphere::new(Point::new(-2, 0, 0), 1.0);
+        let b = BoundingSphere::new(Point::new(3, 0, 0), 2.0);
+        let merged = a.merge(&b);
+        assert_eq!(merged.get_center(), Point::new(1, 0, 0));
+        assert!(crate::equal(merged.get_radius(), 4.0));
+        let inside = BoundingSphere::new(Point::new(3.5, 0.0, 0.0), 0.5);
+        assert_eq!(b.merge(&inside), b);
+        assert_eq!(inside.merge(&b), b);
+    }
+
+    #[test]
+    fn ray_against_sphere() {
+        let sphere = BoundingSphere::new(Point::new(0, 0, 5), 1.0);
+        let hit = Ray::new(Point::origin(), Vector::new(0, 0, 1));
+        let grazing = Ray::new(Point::new(1, 0, 0), Vector::new(0, 0, 2));
+        let miss = Ray::new(Point::new(0.0, 1.5, 0.0), Vector::new(0, 0, 1));
+        assert!(sphere.intersects(&hit));
+        assert!(sphere.intersects(&grazing));
+        assert!(!sphere.intersects(&miss));
+    }
+}
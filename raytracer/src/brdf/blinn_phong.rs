@@ -0,0 +1,49 @@
+use crate::{color::Color, vector::dot};
+
+use super::{Brdf, BrdfInput, BrdfTerms};
+
+// lambert diffuse with a highlight from the half vector between light and eye
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct BlinnPhong;
+
+impl Brdf for BlinnPhong {
+    fn terms(&self, input: &BrdfInput) -> BrdfTerms {
+        let material = input.material;
+        let light_dot_normal = dot(input.lightv, input.normalv);
+        let diffuse = input.color * input.light_intensity * material.diffuse * light_dot_normal;
+
+        let halfv = (input.lightv + input.eyev).normalize();
+        let half_dot_normal = dot(halfv, input.normalv);
+        let specular = if half_dot_normal <= 0.0 {
+            Color::black()
+        } else {
+            let factor = half_dot_normal.powf(material.shininess);
+            input.light_intensity * material.specular * factor
+        };
+
+        BrdfTerms { diffuse, specular }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{material::Material, vector::Vector};
+
+    use super::*;
+
+    #[test]
+    fn blinn_phong_highlight_is_wider_than_phong() {
+        let m = Material::default();
+        let input = BrdfInput {
+            material: &m,
+            color: Color::black(),
+            light_intensity: Color::white(),
+            lightv: Vector::new(0, 0, -1),
+            eyev: Vector::new(0.0, 0.1, -1.0).normalize(),
+            normalv: Vector::new(0, 0, -1),
+        };
+        let blinn = BlinnPhong.shade(&input);
+        let phong = super::super::Phong.shade(&input);
+        assert!(blinn.red > phong.red);
+    }
+}
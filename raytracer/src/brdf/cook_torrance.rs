@@ -0,0 +1,84 @@
+use std::f64::consts::PI;
+
+use crate::{color::Color, vector::dot};
+
+use super::{Brdf, BrdfInput, BrdfTerms};
+
+// reflectance of dielectrics at normal incidence
+const DIELECTRIC_F0: f64 = 0.04;
+
+// metallic-roughness microfacet model as used by glTF: GGX distribution,
+// smith-schlick geometry and schlick fresnel. the lambert term elsewhere in the
+// renderer is not divided by pi, so the specular lobe is scaled up to match.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct CookTorrance;
+
+impl Brdf for CookTorrance {
+    fn terms(&self, input: &BrdfInput) -> BrdfTerms {
+        let material = input.material;
+        let metallic = material.metallic.clamp(0.0, 1.0);
+        let roughness = material.roughness.clamp(0.04, 1.0);
+        let alpha = roughness * roughness;
+
+        let halfv = (input.lightv + input.eyev).normalize();
+        let n_dot_l = dot(input.normalv, input.lightv).max(0.0);
+        let n_dot_v = dot(input.normalv, input.eyev).max(0.0001);
+        let n_dot_h = dot(input.normalv, halfv).max(0.0);
+        let v_dot_h = dot(input.eyev, halfv).max(0.0);
+
+        let f0 = Color::new(DIELECTRIC_F0, DIELECTRIC_F0, DIELECTRIC_F0) * (1.0 - metallic)
+            + input.color * metallic;
+        let fresnel = f0 + (Color::white() - f0) * (1.0 - v_dot_h).powi(5);
+
+        let alpha2 = alpha * alpha;
+        let denom = n_dot_h * n_dot_h * (alpha2 - 1.0) + 1.0;
+        let distribution = alpha2 / (PI * denom * denom);
+
+        let k = (roughness + 1.0) * (roughness + 1.0) / 8.0;
+        let geometry =
+            (n_dot_l / (n_dot_l * (1.0 - k) + k)) * (n_dot_v / (n_dot_v * (1.0 - k) + k));
+
+        let specular = fresnel * (PI * distribution * geometry / (4.0 * n_dot_v));
+        let diffuse =
+            (Color::white() - fresnel) * input.color * (material.diffuse * (1.0 - metallic));
+
+        BrdfTerms {
+            diffuse: diffuse * n_dot_l * input.light_intensity,
+            specular: specular * input.light_intensity,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{material::Material, vector::Vector};
+
+    use super::*;
+
+    fn shade_facing(m: &Material) -> Color {
+        let input = BrdfInput {
+            material: m,
+            color: m.color,
+            light_intensity: Color::white(),
+            lightv: Vector::new(0.0, 1.0, -1.0).normalize(),
+            eyev: Vector::new(0.0, -1.0, -1.0).normalize(),
+            normalv: Vector::new(0, 0, -1),
+        };
+        CookTorrance.shade(&input)
+    }
+
+    #[test]
+    fn metals_have_no_diffuse_and_tinted_highlights() {
+        let m = Material::from_metallic_roughness(Color::new(1.0, 0.5, 0.0), 1.0, 0.3);
+        let c = shade_facing(&m);
+        assert!(c.red > c.green);
+        assert!(c.green > c.blue);
+    }
+
+    #[test]
+    fn rough_surfaces_have_dimmer_mirror_highlights() {
+        let smooth = Material::from_metallic_roughness(Color::white(), 0.0, 0.1);
+        let rough = Material::from_metallic_roughness(Color::white(), 0.0, 0.9);
+        assert!(shade_facing(&smooth).red > shade_facing(&rough).red);
+    }
+}
@@ -0,0 +1,70 @@
+use crate::{color::Color, material::Material, vector::Vector};
+
+pub use blinn_phong::BlinnPhong;
+pub use cook_torrance::CookTorrance;
+pub use oren_nayar::OrenNayar;
+pub use phong::Phong;
+
+mod blinn_phong;
+mod cook_torrance;
+mod oren_nayar;
+mod phong;
+
+// everything a brdf needs to shade one light at a surface point.
+// all vectors are normalized and the light is known to be in front of the surface.
+#[derive(Debug, Clone, Copy)]
+pub struct BrdfInput<'a> {
+    pub material: &'a Material,
+    pub color: Color,
+    pub light_intensity: Color,
+    pub lightv: Vector,
+    pub eyev: Vector,
+    pub normalv: Vector,
+}
+
+// the diffuse and specular contribution of a single light, without ambient
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BrdfTerms {
+    pub diffuse: Color,
+    pub specular: Color,
+}
+
+impl BrdfTerms {
+    pub fn total(&self) -> Color {
+        self.diffuse + self.specular
+    }
+}
+
+pub trait Brdf {
+    fn terms(&self, input: &BrdfInput) -> BrdfTerms;
+
+    fn shade(&self, input: &BrdfInput) -> Color {
+        self.terms(input).total()
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum BrdfModel {
+    #[default]
+    Phong,
+    BlinnPhong,
+    OrenNayar,
+    CookTorrance,
+}
+
+impl BrdfModel {
+    fn brdf(&self) -> &dyn Brdf {
+        match self {
+            Self::Phong => &Phong,
+            Self::BlinnPhong => &BlinnPhong,
+            Self::OrenNayar => &OrenNayar,
+            Self::CookTorrance => &CookTorrance,
+        }
+    }
+}
+
+impl Brdf for BrdfModel {
+    fn terms(&self, input: &BrdfInput) -> BrdfTerms {
+        self.brdf().terms(input)
+    }
+}
@@ -0,0 +1,100 @@
+use crate::{
+    color::Color,
+    vector::{dot, Vector},
+    EPSILON,
+};
+
+use super::{Brdf, BrdfInput, BrdfTerms};
+
+// rough diffuse surfaces (clay, plaster), the material roughness is used as the
+// standard deviation of the facet angles in radians. the highlight is phong's.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct OrenNayar;
+
+impl Brdf for OrenNayar {
+    fn terms(&self, input: &BrdfInput) -> BrdfTerms {
+        let material = input.material;
+        let sigma2 = material.roughness * material.roughness;
+        let a = 1.0 - 0.5 * sigma2 / (sigma2 + 0.33);
+        let b = 0.45 * sigma2 / (sigma2 + 0.09);
+
+        let light_dot_normal = dot(input.lightv, input.normalv);
+        let eye_dot_normal = dot(input.eyev, input.normalv).max(0.0);
+        let theta_i = light_dot_normal.clamp(-1.0, 1.0).acos();
+        let theta_r = eye_dot_normal.clamp(-1.0, 1.0).acos();
+        let alpha = theta_i.max(theta_r);
+        let beta = theta_i.min(theta_r);
+
+        let cos_phi = cos_azimuth(input.lightv, input.eyev, input.normalv).max(0.0);
+        let factor = a + b * cos_phi * alpha.sin() * beta.tan();
+        let diffuse =
+            input.color * input.light_intensity * material.diffuse * light_dot_normal * factor;
+
+        let reflectv = (-input.lightv).reflect(input.normalv);
+        let reflect_dot_eye = dot(reflectv, input.eyev);
+        let specular = if reflect_dot_eye <= 0.0 {
+            Color::black()
+        } else {
+            input.light_intensity * material.specular * reflect_dot_eye.powf(material.shininess)
+        };
+
+        BrdfTerms { diffuse, specular }
+    }
+}
+
+// cosine of the angle between light and eye once projected on the surface
+fn cos_azimuth(lightv: Vector, eyev: Vector, normalv: Vector) -> f64 {
+    let light_tangent = lightv - normalv * dot(lightv, normalv);
+    let eye_tangent = eyev - normalv * dot(eyev, normalv);
+    if light_tangent.magnitude() < EPSILON || eye_tangent.magnitude() < EPSILON {
+        return 0.0;
+    }
+    dot(light_tangent.normalize(), eye_tangent.normalize())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::material::Material;
+
+    use super::*;
+
+    fn diffuse_only(roughness: f64) -> Material {
+        let mut m = Material::default();
+        m.specular = 0.0;
+        m.roughness = roughness;
+        m
+    }
+
+    #[test]
+    fn smooth_oren_nayar_is_lambert() {
+        let m = diffuse_only(0.0);
+        let input = BrdfInput {
+            material: &m,
+            color: Color::white(),
+            light_intensity: Color::white(),
+            lightv: Vector::new(0.0, 1.0, -1.0).normalize(),
+            eyev: Vector::new(0, 0, -1),
+            normalv: Vector::new(0, 0, -1),
+        };
+        let lambert = 0.9 * 2.0f64.sqrt() / 2.0;
+        assert_eq!(
+            OrenNayar.shade(&input),
+            Color::new(lambert, lambert, lambert)
+        );
+    }
+
+    #[test]
+    fn rough_oren_nayar_flattens_falloff() {
+        let m = diffuse_only(1.0);
+        let input = BrdfInput {
+            material: &m,
+            color: Color::white(),
+            light_intensity: Color::white(),
+            lightv: Vector::new(0, 0, -1),
+            eyev: Vector::new(0, 0, -1),
+            normalv: Vector::new(0, 0, -1),
+        };
+        // facing the light, rough surfaces look darker than lambert
+        assert!(OrenNayar.shade(&input).red < 0.9);
+    }
+}
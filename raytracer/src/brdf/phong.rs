@@ -0,0 +1,47 @@
+use crate::{color::Color, vector::dot};
+
+use super::{Brdf, BrdfInput, BrdfTerms};
+
+// lambert diffuse with a phong highlight
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Phong;
+
+impl Brdf for Phong {
+    fn terms(&self, input: &BrdfInput) -> BrdfTerms {
+        let material = input.material;
+        let light_dot_normal = dot(input.lightv, input.normalv);
+        let diffuse = input.color * input.light_intensity * material.diffuse * light_dot_normal;
+
+        let reflectv = (-input.lightv).reflect(input.normalv);
+        let reflect_dot_eye = dot(reflectv, input.eyev);
+        let specular = if reflect_dot_eye <= 0.0 {
+            Color::black()
+        } else {
+            let factor = reflect_dot_eye.powf(material.shininess);
+            input.light_intensity * material.specular * factor
+        };
+
+        BrdfTerms { diffuse, specular }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{material::Material, vector::Vector};
+
+    use super::*;
+
+    #[test]
+    fn phong_highlight_peaks_along_reflection() {
+        let m = Material::default();
+        let input = BrdfInput {
+            material: &m,
+            color: Color::white(),
+            light_intensity: Color::white(),
+            lightv: Vector::new(0, 0, -1),
+            eyev: Vector::new(0, 0, -1),
+            normalv: Vector::new(0, 0, -1),
+        };
+        assert_eq!(Phong.shade(&input), Color::new(1.8, 1.8, 1.8));
+    }
+}
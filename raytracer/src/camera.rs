@@ -1,36 +1,100 @@
 use std::{
+    f64::consts::PI,
+    fmt,
+    ops::Range,
     sync::{
-        mpsc::{self, Receiver, Sender},
-        Arc,
+        atomic::{AtomicUsize, Ordering},
+        mpsc::{self, Sender},
+        Arc, Mutex,
     },
     thread,
-    time::Instant,
+    time::{Duration, Instant},
 };
 
 use crate::{
-    canvas::Canvas,
+    animation::AnimationChannel,
+    bounding_box::BoundingBox,
+    camera_path::CameraPath,
+    canvas::{Canvas, PixelStorage},
     color::Color,
+    exposure::AutoExposure,
+    geometry::{intersection::hit_in_range, RayKind, Shape},
+    image::PixelFormat,
     matrix::Matrix,
     point::Point,
-    ray::Ray,
-    world::{World, MAX_RECURSION_DEPTH},
+    progress::RenderProgress,
+    ray::{Ray, RayDifferentials},
+    ray_cache::{RayCache, RayCacheStats},
+    ray_log::{RayLog, RayLogEntry},
+    sampler::Sampler,
+    tiles::{tiles, Tile, TileOrder},
+    transform::{view_transform, view_transform_to_fit},
+    vector::Vector,
+    world::{RenderMode, RussianRoulette, ShadowRayStats, TraceOptions, World},
 };
 
+// the width of 35mm film and full frame sensors, in millimeters
+pub const FULL_FRAME_SENSOR_WIDTH: f64 = 36.0;
+
+// the tiles of budgeted renders without a tile size
+const BUDGET_TILE_SIZE: usize = 16;
+
+// why a camera can't be set up, see ViewError for its view transform
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CameraError {
+    ZeroSize { hsize: usize, vsize: usize },
+    // the field of view must be strictly between 0 and pi
+    FieldOfView(f64),
+}
+
+impl fmt::Display for CameraError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ZeroSize { hsize, vsize } => {
+                write!(f, "camera size {}x{} has no pixels", hsize, vsize)
+            }
+            Self::FieldOfView(fov) => {
+                write!(f, "field of view {} is not between 0 and pi radians", fov)
+            }
+        }
+    }
+}
+
+impl std::error::Error for CameraError {}
+
 #[derive(Debug)]
 pub struct Camera {
     hsize: usize,
     vsize: usize,
-    _field_of_view: f64,
+    field_of_view: f64,
     transform: Matrix,
     transform_inverse: Matrix,
     pixel_size: f64,
     half_width: f64,
     half_height: f64,
+    lens_shift: (f64, f64),
+    animation: Option<AnimationChannel>,
+    path: Option<CameraPath>,
+    near: f64,
+    far: f64,
     pub render_opts: RenderOpts,
 }
 
 impl Camera {
+    // panics on sizes or fields of view that try_new turns down
     pub fn new(hsize: usize, vsize: usize, field_of_view: f64) -> Self {
+        Self::try_new(hsize, vsize, field_of_view).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    // for sizes and angles read from input, which may have no pixels or see
+    // nothing or everything
+    pub fn try_new(hsize: usize, vsize: usize, field_of_view: f64) -> Result<Self, CameraError> {
+        if hsize == 0 || vsize == 0 {
+            return Err(CameraError::ZeroSize { hsize, vsize });
+        }
+        if !(field_of_view > 0.0 && field_of_view < PI) {
+            return Err(CameraError::FieldOfView(field_of_view));
+        }
         let half_view = (field_of_view / 2.0).tan();
         let aspect = hsize as f64 / vsize as f64;
         let (half_width, half_height) = if aspect >= 1.0 {
@@ -41,52 +105,148 @@ impl Camera {
 
         let pixel_size = half_width * 2.0 / hsize as f64;
 
-        Self {
+        Ok(Self {
             hsize,
             vsize,
-            _field_of_view: field_of_view,
+            field_of_view,
             transform: Matrix::identity(4, 4),
             transform_inverse: Matrix::identity(4, 4),
             pixel_size,
             half_width,
             half_height,
+            lens_shift: (0.0, 0.0),
+            animation: None,
+            path: None,
+            near: 0.0,
+            far: f64::INFINITY,
             render_opts: RenderOpts::default(),
-        }
+        })
+    }
+
+    // a lens of focal_length on a sensor sensor_width wide, both in
+    // millimeters, like a photographer would set up. the sensor spans the
+    // image's width, see FULL_FRAME_SENSOR_WIDTH for 35mm equivalent lenses
+    pub fn with_lens(hsize: usize, vsize: usize, focal_length: f64, sensor_width: f64) -> Self {
+        Self::try_with_lens(hsize, vsize, focal_length, sensor_width)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    // lenses and sensors that aren't longer than 0 give fields of view try_new
+    // turns down
+    pub fn try_with_lens(
+        hsize: usize,
+        vsize: usize,
+        focal_length: f64,
+        sensor_width: f64,
+    ) -> Result<Self, CameraError> {
+        let half_width = sensor_width / (2.0 * focal_length);
+        let aspect = hsize as f64 / vsize as f64;
+        // field_of_view is across the longer side
+        let half_view = if aspect >= 1.0 {
+            half_width
+        } else {
+            half_width / aspect
+        };
+        Self::try_new(hsize, vsize, 2.0 * half_view.atan())
+    }
+
+    // the focal length in millimeters of the lens that gives this camera's
+    // view on a sensor sensor_width wide
+    pub fn focal_length(&self, sensor_width: f64) -> f64 {
+        sensor_width / (2.0 * self.half_width)
     }
 
+    // the ray through the center of pixel (px, py)
     pub fn ray_for_pixel(&self, px: usize, py: usize) -> Ray {
-        let xoffset = (px as f64 + 0.5) * self.pixel_size;
-        let yoffset = (py as f64 + 0.5) * self.pixel_size;
+        self.ray_through(px, py, (0.5, 0.5))
+    }
+
+    // one ray per antialiasing sample of pixel (px, py)
+    pub fn rays_for_pixel(&self, px: usize, py: usize) -> Vec<Ray> {
+        self.rays_for_pixel_with(&self.render_opts, px, py)
+    }
+
+    fn rays_for_pixel_with(&self, opts: &RenderOpts, px: usize, py: usize) -> Vec<Ray> {
+        self.samples_for_pixel(opts, px, py)
+            .into_iter()
+            .map(|(ray, _)| ray)
+            .collect()
+    }
+
+    // the rays of a pixel, with the point soft lights are sampled at when
+    // opts has a sampler
+    fn samples_for_pixel(
+        &self,
+        opts: &RenderOpts,
+        px: usize,
+        py: usize,
+    ) -> Vec<(Ray, Option<(f64, f64)>)> {
+        match &opts.sampler {
+            Some(sampler) => sampler
+                .samples(px, py)
+                .iter()
+                .map(|s| (self.ray_through(px, py, s.pixel), Some(s.light)))
+                .collect(),
+            None => Self::get_offsets(&opts.aa_samples)
+                .into_iter()
+                .map(|offset| (self.ray_through(px, py, offset), None))
+                .collect(),
+        }
+    }
+
+    // the antialiasing rays of a pixel with the weight of each sample, the
+    // weights add up to 1 so the pixel color is the weighted sum of the samples
+    pub fn weighted_rays_for_pixel(&self, px: usize, py: usize) -> Vec<(Ray, f64)> {
+        let rays = self.rays_for_pixel(px, py);
+        let weight = 1.0 / rays.len() as f64;
+        rays.into_iter().map(|ray| (ray, weight)).collect()
+    }
 
-        let world_x = self.half_width - xoffset;
-        let world_y = self.half_height - yoffset;
+    fn ray_through(&self, px: usize, py: usize, offset: (f64, f64)) -> Ray {
+        let xoffset = (px as f64 + offset.0) * self.pixel_size;
+        let yoffset = (py as f64 + offset.1) * self.pixel_size;
+
+        // camera space x points left, so shifting right moves the window to -x
+        let (shift_x, shift_y) = self.lens_shift;
+        let world_x = self.half_width * (1.0 - 2.0 * shift_x) - xoffset;
+        let world_y = self.half_height * (1.0 + 2.0 * shift_y) - yoffset;
 
-        let pixel = &self.transform_inverse * Point::new(world_x, world_y, -1.0);
         let origin = &self.transform_inverse * Point::origin();
-        let direction = (pixel - origin).normalize();
+        let direction_to = |x: f64, y: f64| {
+            let pixel = &self.transform_inverse * Point::new(x, y, -1.0);
+            (pixel - origin).normalize()
+        };
 
-        Ray::new(origin, direction)
+        Ray::new(origin, direction_to(world_x, world_y)).with_differentials(RayDifferentials {
+            rx_origin: origin,
+            rx_direction: direction_to(world_x - self.pixel_size, world_y),
+            ry_origin: origin,
+            ry_direction: direction_to(world_x, world_y - self.pixel_size),
+        })
     }
 
-    pub fn rays_for_pixel(&self, px: usize, py: usize) -> Vec<Ray> {
-        let mut rays = vec![];
-        let offsets = Self::get_offsets(&self.render_opts.aa_samples);
+    pub fn hsize(&self) -> usize {
+        self.hsize
+    }
 
-        for offset in offsets.iter() {
-            let xoffset = (px as f64 + offset.0) * self.pixel_size;
-            let yoffset = (py as f64 + offset.1) * self.pixel_size;
+    pub fn vsize(&self) -> usize {
+        self.vsize
+    }
 
-            let world_x = self.half_width - xoffset;
-            let world_y = self.half_height - yoffset;
+    pub fn resolution(&self) -> (usize, usize) {
+        (self.hsize, self.vsize)
+    }
 
-            let pixel = &self.transform_inverse * Point::new(world_x, world_y, -1.0);
-            let origin = &self.transform_inverse * Point::origin();
-            let direction = (pixel - origin).normalize();
+    pub fn field_of_view(&self) -> f64 {
+        self.field_of_view
+    }
 
-            rays.push(Ray::new(origin, direction));
-        }
+    pub fn pixel_size(&self) -> f64 {
+        self.pixel_size
+    }
 
-        rays
+    pub fn transform(&self) -> &Matrix {
+        &self.transform
     }
 
     fn get_offsets(samples: &AASamples) -> Vec<(f64, f64)> {
@@ -130,90 +290,543 @@ impl Camera {
         self.transform_inverse = self.transform.inverse();
     }
 
+    // keeps the camera looking the same way but moves it so the whole world fits
+    // in view, with margin as a fraction of the scene's size left around it.
+    // false, and the camera left alone, when the world has nothing bounded
+    pub fn frame_scene(&mut self, world: &World, margin: f64) -> bool {
+        let bounds = world.bounds();
+        if !bounds.is_finite() {
+            return false;
+        }
+        // growing the box grows its bounding sphere by the same fraction
+        let center = bounds.center();
+        let corner = (bounds.get_max() - center) * (1.0 + margin);
+        let bounds = BoundingBox::new(center - corner, center + corner);
+
+        let forward = &self.transform_inverse * Vector::new(0, 0, -1);
+        let aspect = self.hsize as f64 / self.vsize as f64;
+        match view_transform_to_fit(&bounds, self.field_of_view, aspect, forward) {
+            Some((from, to, _)) => {
+                // keeps the camera's own roll rather than the suggested up
+                let up = &self.transform_inverse * Vector::new(0, 1, 0);
+                self.set_transform(view_transform(from, to, up));
+                true
+            }
+            None => false,
+        }
+    }
+
+    // slides the image window across the image plane without turning the camera,
+    // by fractions of the image's width and height: positive shifts look right
+    // and up. looking level and shifting up keeps vertical lines parallel
+    pub fn set_lens_shift(&mut self, horizontal: f64, vertical: f64) {
+        self.lens_shift = (horizontal, vertical);
+    }
+
+    pub fn lens_shift(&self) -> (f64, f64) {
+        self.lens_shift
+    }
+
+    pub fn set_clipping(&mut self, near: f64, far: f64) {
+        self.near = near;
+        self.far = far;
+    }
+
+    pub fn near(&self) -> f64 {
+        self.near
+    }
+
+    pub fn far(&self) -> f64 {
+        self.far
+    }
+
+    pub fn set_animation(&mut self, animation: AnimationChannel) {
+        self.animation = Some(animation);
+    }
+
+    // advance_time follows the path instead of the animation when both are set
+    pub fn set_path(&mut self, path: CameraPath) {
+        self.path = Some(path);
+    }
+
+    pub fn path(&self) -> Option<&CameraPath> {
+        self.path.as_ref()
+    }
+
+    pub fn advance_time(&mut self, time: f64) {
+        let transform = match &self.path {
+            Some(path) => path.transform_at(time),
+            None => self.animation.as_ref().and_then(|a| a.transform_at(time)),
+        };
+        if let Some(transform) = transform {
+            self.set_transform(transform);
+        }
+    }
+
     pub fn render(&mut self, world: &World) -> Canvas {
-        let mut image = Canvas::new(self.hsize, self.vsize);
+        let mut image = Canvas::with_storage(self.hsize, self.vsize, self.render_opts.storage);
+        self.render_opts.start_progress();
 
         for y in 0..self.vsize {
-            if y % 10 == 0 {
+            // whoever listens to progress reports it instead
+            if y % 10 == 0 && self.render_opts.progress.is_none() {
                 println!("rendering row {}/{}", y, self.vsize);
             }
             for x in 0..self.hsize {
-                let ray = self.ray_for_pixel(x, y);
-                let color = world.color_at(&ray, MAX_RECURSION_DEPTH);
-                image.set_pixel(x, y, color);
+                image.set_pixel(x, y, self.pixel_color(world, x, y));
             }
+            self.render_opts
+                .notify_dirty_region(&image.rows(y..y + 1), y..y + 1);
+            self.render_opts.row_done(self.vsize);
         }
 
+        self.render_opts.expose(&mut image);
         image
     }
 
+    // renders the pixels in x0..x1, y0..y1 (clamped to the image) and returns
+    // them with the offset of the region's top left corner in the full image
+    pub fn render_region(
+        &self,
+        world: &World,
+        x0: usize,
+        y0: usize,
+        x1: usize,
+        y1: usize,
+    ) -> (Canvas, (usize, usize)) {
+        let (x1, y1) = (x1.min(self.hsize), y1.min(self.vsize));
+        let (x0, y0) = (x0.min(x1), y0.min(y1));
+        let mut image = Canvas::with_storage(x1 - x0, y1 - y0, self.render_opts.storage);
+        self.render_opts.start_progress();
+
+        for y in y0..y1 {
+            for x in x0..x1 {
+                image.set_pixel(x - x0, y - y0, self.pixel_color(world, x, y));
+            }
+            self.render_opts.row_done(y1 - y0);
+        }
+
+        (image, (x0, y0))
+    }
+
+    // updates only the given pixels of a previous render of this camera
+    pub fn rerender_pixels(&self, world: &World, pixels: &[(usize, usize)], canvas: &mut Canvas) {
+        for &(x, y) in pixels {
+            canvas.set_pixel(x, y, self.pixel_color(world, x, y));
+        }
+    }
+
+    // pixels whose primary ray first hits a shape matching the predicate, e.g. to
+    // find what needs rerendering after changing one object's material
+    pub fn pixels_hitting<F>(&self, world: &World, predicate: F) -> Vec<(usize, usize)>
+    where
+        F: Fn(&dyn Shape) -> bool,
+    {
+        let mut pixels = vec![];
+        for y in 0..self.vsize {
+            for x in 0..self.hsize {
+                let hits = self.rays_for_pixel(x, y).iter().any(|ray| {
+                    let xs = world.intersect_for(ray, RayKind::Camera);
+                    hit_in_range(&xs, self.near, self.far).is_some_and(|i| predicate(i.object()))
+                });
+                if hits {
+                    pixels.push((x, y));
+                }
+            }
+        }
+        pixels
+    }
+
+    fn pixel_color(&self, world: &World, x: usize, y: usize) -> Color {
+        let (color, cache, shadow_rays) = self.sample_pixel(world, x, y, &self.render_opts);
+        self.render_opts.record_ray_cache(cache.as_ref());
+        self.render_opts.record_shadow_rays(&shadow_rays);
+        color
+    }
+
+    // the average of every sample of the pixel, the pixel's ray cache and the
+    // shadow rays its samples cast
+    fn sample_pixel(
+        &self,
+        world: &World,
+        x: usize,
+        y: usize,
+        opts: &RenderOpts,
+    ) -> (Color, Option<RayCache>, ShadowRayStats) {
+        let mut cache = opts.ray_cache.map(RayCache::new);
+        let mut shadow_rays = ShadowRayStats::default();
+        let colors: Vec<Color> = self
+            .samples_for_pixel(opts, x, y)
+            .iter()
+            .map(|(ray, light_sample)| {
+                let options = TraceOptions {
+                    light_sample: *light_sample,
+                    cache: cache.as_mut(),
+                    ..Default::default()
+                };
+                self.trace_with(world, ray, opts, options, &mut shadow_rays)
+            })
+            .collect();
+        (Color::average(&colors), cache, shadow_rays)
+    }
+
+    // traces pixel (px, py) like a render would and records every ray of it,
+    // to find out why a pixel came out the way it did
+    pub fn log_pixel(&self, world: &World, px: usize, py: usize) -> RayLog {
+        self.debug_pixel(world, px, py).log
+    }
+
+    pub fn debug_pixel(&self, world: &World, px: usize, py: usize) -> PixelTrace {
+        let mut log = RayLog::new();
+        let mut shadow_rays = ShadowRayStats::default();
+        let colors: Vec<Color> = self
+            .samples_for_pixel(&self.render_opts, px, py)
+            .iter()
+            .map(|(ray, light_sample)| {
+                let options = TraceOptions {
+                    light_sample: *light_sample,
+                    log: Some(&mut log),
+                    ..Default::default()
+                };
+                self.trace_with(world, ray, &self.render_opts, options, &mut shadow_rays)
+            })
+            .collect();
+        PixelTrace {
+            x: px,
+            y: py,
+            color: Color::average(&colors),
+            log,
+        }
+    }
+
+    // options brings what is particular to the ray, the rest comes from opts
+    fn trace_with(
+        &self,
+        world: &World,
+        ray: &Ray,
+        opts: &RenderOpts,
+        options: TraceOptions,
+        shadow_rays: &mut ShadowRayStats,
+    ) -> Color {
+        let mut options = TraceOptions {
+            russian_roulette: opts.russian_roulette.as_ref(),
+            environment_reflections: opts.environment_reflections,
+            render_mode: opts.render_mode,
+            ..options
+        };
+        let color = world.color_at_with(ray, self.near, self.far, &mut options);
+        shadow_rays.add(&options.shadow_rays);
+        color
+    }
+
+    // the shadow pass, for compositing shadows over other images: how much
+    // light reaches the surface each pixel sees, white where every light does
+    // and black where none does. averaged over the pixel's samples
+    pub fn render_shadows(&self, world: &World) -> Canvas {
+        let mut factors = vec![0.0; self.hsize * self.vsize];
+        if !factors.is_empty() {
+            let block_size = self.vsize.div_ceil(self.render_opts.num_threads) * self.hsize;
+            thread::scope(|scope| {
+                for (i, block) in factors.chunks_mut(block_size).enumerate() {
+                    scope.spawn(move || {
+                        for (j, factor) in block.iter_mut().enumerate() {
+                            let p = i * block_size + j;
+                            let rays = self.rays_for_pixel(p % self.hsize, p / self.hsize);
+                            let total: f64 = rays
+                                .iter()
+                                .map(|ray| world.shadow_factor(ray, self.near, self.far))
+                                .sum();
+                            *factor = total / rays.len() as f64;
+                        }
+                    });
+                }
+            });
+        }
+
+        let mut image = Canvas::with_storage(self.hsize, self.vsize, self.render_opts.storage);
+        for (p, &factor) in factors.iter().enumerate() {
+            image.set_pixel(
+                p % self.hsize,
+                p / self.hsize,
+                Color::new(factor, factor, factor),
+            );
+        }
+        image
+    }
+
+    // for a given scene and render options the output doesn't depend on the
+    // number of threads, and is bit for bit the same as render()
     pub fn render_multithreaded(this: Arc<Self>, world: Arc<World>) -> Canvas {
-        let mut image = Canvas::new(this.hsize, this.vsize);
+        this.render_parallel(&world)
+    }
+
+    // renders on render_opts' threads, in tiles when a tile size is set
+    pub fn render_parallel(&self, world: &World) -> Canvas {
+        let mut image = Canvas::with_storage(self.hsize, self.vsize, self.render_opts.storage);
+        let start_time = Instant::now();
+        self.render_opts.start_progress();
+        match (self.render_opts.budget, self.render_opts.tile_size) {
+            (Some(budget), size) => {
+                self.render_budgeted(world, &mut image, budget, size.unwrap_or(BUDGET_TILE_SIZE))
+            }
+            (None, Some(size)) => self.render_tiles(world, &mut image, size),
+            (None, None) => self.render_rows(world, &mut image),
+        }
+        self.render_opts.expose(&mut image);
+
+        let elapsed_time = start_time.elapsed().as_millis();
+        println!("rendered in {} ms", elapsed_time);
+        if self.render_opts.ray_cache.is_some() {
+            let stats = self.render_opts.ray_cache_stats();
+            println!(
+                "ray cache: {} hits, {} misses ({:.1}% hit rate)",
+                stats.hits,
+                stats.misses,
+                stats.hit_rate() * 100.0
+            );
+        }
+        println!("all render threads done!");
+        image
+    }
 
-        let mut handles = vec![];
-        let (tx, rx): (Sender<RenderThreadResult>, Receiver<RenderThreadResult>) = mpsc::channel();
-        let rows = this.vsize;
-        let num_threads = this.render_opts.num_threads;
+    // renders on render_opts' threads straight into buffer, as 8 bit pixels in
+    // rows with no padding between them, for previews that would otherwise
+    // convert a canvas every refresh. auto exposure needs the whole image before
+    // any pixel is final, so with it on a canvas is rendered after all
+    pub fn render_into(&self, world: &World, buffer: &mut [u8], format: PixelFormat) {
+        let bpp = format.bytes_per_pixel();
+        assert_eq!(buffer.len(), self.hsize * self.vsize * bpp);
+        if self.render_opts.auto_exposure.is_some() {
+            let image = self.render_parallel(world);
+            for (i, pixel) in buffer.chunks_mut(bpp).enumerate() {
+                let color = image.get_pixel(i % self.hsize, i / self.hsize);
+                pixel.copy_from_slice(&format.encode(color));
+            }
+            return;
+        }
+        if buffer.is_empty() {
+            return;
+        }
+
+        self.render_opts.start_progress();
+        let row_bytes = self.hsize * bpp;
+        let rows_per_thread = self.vsize.div_ceil(self.render_opts.num_threads);
+        thread::scope(|scope| {
+            for (i, block) in buffer.chunks_mut(rows_per_thread * row_bytes).enumerate() {
+                scope.spawn(move || {
+                    for (dy, row) in block.chunks_mut(row_bytes).enumerate() {
+                        let y = i * rows_per_thread + dy;
+                        for (x, pixel) in row.chunks_mut(bpp).enumerate() {
+                            pixel.copy_from_slice(&format.encode(self.pixel_color(world, x, y)));
+                        }
+                        self.render_opts.row_done(self.vsize);
+                    }
+                });
+            }
+        });
+    }
+
+    fn render_rows(&self, world: &World, image: &mut Canvas) {
+        let rows = self.vsize;
+        let num_threads = self.render_opts.num_threads;
         let rows_per_thread = rows / num_threads;
 
         println!(
             "running with {} threads: assigning {} rows per thread",
             num_threads, rows_per_thread
         );
-        let start_time = Instant::now();
-        for i in 0..num_threads {
-            let camera_ref = this.clone();
-            let world_ref = world.clone();
-            let tx_ref = tx.clone();
-            let handle = thread::spawn(move || {
-                let (start, mut end) = (i * rows_per_thread, i * rows_per_thread + rows_per_thread);
-                if i == num_threads - 1 {
-                    end = rows;
-                }
-                let mut result = RenderThreadResult {
-                    start,
-                    end,
-                    colors: vec![],
+        thread::scope(|scope| {
+            // every thread writes straight into its own block of rows
+            let mut pixels = image.rows_mut(0..rows);
+            for i in 0..num_threads {
+                let start = i * rows_per_thread;
+                let end = if i == num_threads - 1 {
+                    rows
+                } else {
+                    start + rows_per_thread
                 };
-                for y in start..end {
-                    for x in 0..camera_ref.hsize {
-                        let rays = camera_ref.rays_for_pixel(x, y);
-                        let mut colors = vec![];
-                        for ray in rays.iter() {
-                            let color = world_ref.color_at(&ray, MAX_RECURSION_DEPTH);
-                            colors.push(color);
+                let (mut block, rest) = pixels.split_at_mut((end - start) * self.hsize);
+                pixels = rest;
+                scope.spawn(move || {
+                    for y in start..end {
+                        for x in 0..self.hsize {
+                            block.set((y - start) * self.hsize + x, self.pixel_color(world, x, y));
                         }
-                        let color = Color::average(&colors);
-                        result.colors.push(color);
+                        self.render_opts.row_done(rows);
+                    }
+                    self.render_opts
+                        .notify_dirty_region(&block.colors(), start..end);
+                });
+            }
+        });
+    }
+
+    // threads take the next tile in order as they finish one. progress counts
+    // tiles instead of rows
+    fn render_tiles(&self, world: &World, image: &mut Canvas, size: usize) {
+        let tiles = tiles(self.hsize, self.vsize, size, self.render_opts.tile_order);
+        let num_threads = self.render_opts.num_threads;
+        println!(
+            "running with {} threads: {} tiles of {}x{} in {:?} order",
+            num_threads,
+            tiles.len(),
+            size,
+            size,
+            self.render_opts.tile_order
+        );
+        let next_tile = AtomicUsize::new(0);
+        let (tx, rx) = mpsc::channel();
+        thread::scope(|scope| {
+            for _ in 0..num_threads {
+                let (tx, tiles, next_tile) = (tx.clone(), &tiles, &next_tile);
+                scope.spawn(move || {
+                    while let Some(tile) = tiles.get(next_tile.fetch_add(1, Ordering::Relaxed)) {
+                        let colors: Vec<Color> = tile
+                            .pixels()
+                            .map(|(x, y)| self.pixel_color(world, x, y))
+                            .collect();
+                        tx.send((*tile, colors))
+                            .expect("failed to send tile from render thread");
                     }
+                });
+            }
+            drop(tx);
+
+            for (tile, colors) in rx {
+                for ((x, y), color) in tile.pixels().zip(colors) {
+                    image.set_pixel(x, y, color);
                 }
-                tx_ref.send(result).unwrap();
-            });
-            handles.push(handle);
-        }
-
-        for _ in 0..num_threads {
-            let res = rx
-                .recv()
-                .expect("failed to receive render result from thread");
-            println!("received colors array from thread");
-            let mut i = 0;
-            for y in res.start..res.end {
-                for x in 0..this.hsize {
-                    image.set_pixel(x, y, res.colors[i]);
-                    i += 1;
+                // whole rows, with whatever is already rendered next to the tile
+                self.render_opts
+                    .notify_dirty_region(&image.rows(tile.y0..tile.y1), tile.y0..tile.y1);
+                self.render_opts.row_done(tiles.len());
+            }
+        });
+    }
+
+    // every pass gives every pixel one more of its samples, tile by tile, until
+    // all the samples are done or the budget runs out. pixels show the average
+    // of the samples their tile got. the first pass always finishes so no pixel
+    // is left empty, and progress counts its tiles
+    fn render_budgeted(
+        &self,
+        world: &World,
+        image: &mut Canvas,
+        budget: RenderBudget,
+        size: usize,
+    ) {
+        let opts = &self.render_opts;
+        let tiles = tiles(self.hsize, self.vsize, size, opts.tile_order);
+        let samples = self.rays_for_pixel_with(opts, 0, 0).len();
+        let start = Instant::now();
+        let rays_traced = AtomicUsize::new(0);
+        // claims the rays of a tile, false when the budget can't afford them
+        let spend = |rays: usize, forced: bool| {
+            if forced {
+                rays_traced.fetch_add(rays, Ordering::Relaxed);
+                return true;
+            }
+            match budget {
+                RenderBudget::Time(limit) => start.elapsed() < limit,
+                RenderBudget::Rays(limit) => rays_traced
+                    .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |used| {
+                        (used + rays <= limit).then_some(used + rays)
+                    })
+                    .is_ok(),
+            }
+        };
+
+        let mut sums = vec![Color::black(); self.hsize * self.vsize];
+        let mut tile_samples = vec![0; tiles.len()];
+        for pass in 0..samples {
+            let next_tile = AtomicUsize::new(0);
+            let (tx, rx) = mpsc::channel();
+            thread::scope(|scope| {
+                for _ in 0..opts.num_threads {
+                    let (tx, tiles, next_tile, spend) = (tx.clone(), &tiles, &next_tile, &spend);
+                    scope.spawn(move || {
+                        let mut shadow_rays = ShadowRayStats::default();
+                        loop {
+                            let idx = next_tile.fetch_add(1, Ordering::Relaxed);
+                            let tile = match tiles.get(idx) {
+                                Some(tile) => tile,
+                                None => break,
+                            };
+                            if !spend(tile.pixels().count(), pass == 0) {
+                                break;
+                            }
+                            let colors: Vec<Color> = tile
+                                .pixels()
+                                .map(|(x, y)| {
+                                    let (ray, light_sample) =
+                                        self.samples_for_pixel(opts, x, y)[pass];
+                                    let options = TraceOptions {
+                                        light_sample,
+                                        ..Default::default()
+                                    };
+                                    self.trace_with(world, &ray, opts, options, &mut shadow_rays)
+                                })
+                                .collect();
+                            tx.send((idx, colors))
+                                .expect("failed to send tile from render thread");
+                        }
+                        opts.record_shadow_rays(&shadow_rays);
+                    });
                 }
+                drop(tx);
+
+                for (idx, colors) in rx {
+                    let tile = tiles[idx];
+                    tile_samples[idx] += 1;
+                    let scale = 1.0 / tile_samples[idx] as f64;
+                    for ((x, y), color) in tile.pixels().zip(colors) {
+                        let sum = &mut sums[y * self.hsize + x];
+                        *sum = *sum + color;
+                        image.set_pixel(x, y, *sum * scale);
+                    }
+                    opts.notify_dirty_region(&image.rows(tile.y0..tile.y1), tile.y0..tile.y1);
+                    if pass == 0 {
+                        opts.row_done(tiles.len());
+                    }
+                }
+            });
+            // some tile was turned away, the budget is spent
+            if tile_samples.iter().any(|&n| n <= pass) {
+                break;
             }
         }
 
-        let elapsed_time = start_time.elapsed().as_millis();
-        println!("rendered in {} ms", elapsed_time);
+        *opts.tile_samples.lock().unwrap() = tiles
+            .iter()
+            .zip(tile_samples)
+            .map(|(&tile, samples)| TileSamples { tile, samples })
+            .collect();
+    }
+}
 
-        for handle in handles {
-            handle.join().expect("could not join thread handle");
+// the color a render with opts gives pixel (x, y) of camera: every sample
+// traced and averaged. unlike a render it leaves opts alone, no ray cache
+// stats, progress or dirty regions. auto exposure depends on every pixel, so
+// with it on the whole image is traced to find the scale
+pub fn render_pixel(
+    camera: &Camera,
+    world: &World,
+    x: usize,
+    y: usize,
+    opts: &RenderOpts,
+) -> Color {
+    let color = camera.sample_pixel(world, x, y, opts).0;
+    match &opts.auto_exposure {
+        Some(exposure) => {
+            let mut image = Canvas::new(camera.hsize, camera.vsize);
+            for py in 0..camera.vsize {
+                for px in 0..camera.hsize {
+                    image.set_pixel(px, py, camera.sample_pixel(world, px, py, opts).0);
+                }
+            }
+            color * exposure.exposure(&image)
         }
-        println!("all render threads done!");
-        image
+        None => color,
     }
 }
 
@@ -221,6 +834,40 @@ impl Camera {
 pub struct RenderOpts {
     num_threads: usize,
     aa_samples: AASamples,
+    sampler: Option<Sampler>,
+    russian_roulette: Option<RussianRoulette>,
+    environment_reflections: bool,
+    render_mode: RenderMode,
+    ray_cache: Option<usize>,
+    ray_cache_hits: AtomicUsize,
+    ray_cache_misses: AtomicUsize,
+    shadow_rays_cast: AtomicUsize,
+    shadow_rays_blocked: AtomicUsize,
+    dirty_regions: Option<Sender<DirtyRegion>>,
+    progress: Option<Sender<RenderProgress>>,
+    rows_done: AtomicUsize,
+    tile_size: Option<usize>,
+    tile_order: TileOrder,
+    auto_exposure: Option<AutoExposure>,
+    storage: PixelStorage,
+    budget: Option<RenderBudget>,
+    tile_samples: Mutex<Vec<TileSamples>>,
+}
+
+// caps the work render_parallel puts into an image
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RenderBudget {
+    // wall clock time from the start of the render
+    Time(Duration),
+    // camera rays traced
+    Rays(usize),
+}
+
+// how many samples each pixel of a tile got in a budgeted render
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TileSamples {
+    pub tile: Tile,
+    pub samples: usize,
 }
 
 #[derive(Debug)]
@@ -235,8 +882,26 @@ pub enum AASamples {
 impl Default for RenderOpts {
     fn default() -> Self {
         Self {
-            num_threads: 1,
+            num_threads: thread::available_parallelism().map_or(1, |n| n.get()),
             aa_samples: AASamples::X1,
+            sampler: None,
+            russian_roulette: None,
+            environment_reflections: false,
+            render_mode: RenderMode::default(),
+            ray_cache: None,
+            ray_cache_hits: AtomicUsize::new(0),
+            ray_cache_misses: AtomicUsize::new(0),
+            shadow_rays_cast: AtomicUsize::new(0),
+            shadow_rays_blocked: AtomicUsize::new(0),
+            dirty_regions: None,
+            progress: None,
+            rows_done: AtomicUsize::new(0),
+            tile_size: None,
+            tile_order: TileOrder::default(),
+            auto_exposure: None,
+            storage: PixelStorage::default(),
+            budget: None,
+            tile_samples: Mutex::new(vec![]),
         }
     }
 }
@@ -247,25 +912,216 @@ impl RenderOpts {
         self.num_threads = n;
     }
 
+    // render_parallel splits the image into size x size tiles instead of one
+    // block of rows per thread
+    pub fn tile_size(&mut self, size: usize) {
+        assert!(size > 0);
+        self.tile_size = Some(size);
+    }
+
+    // how rendered canvases store their pixels, F32 for big renders
+    pub fn pixel_storage(&mut self, storage: PixelStorage) {
+        self.storage = storage;
+    }
+
+    pub fn get_pixel_storage(&self) -> PixelStorage {
+        self.storage
+    }
+
+    pub fn tile_order(&mut self, order: TileOrder) {
+        self.tile_order = order;
+    }
+
+    // render and render_parallel scale the finished image with it. regions
+    // aren't exposed, a crop alone would pick a different exposure
+    pub fn auto_exposure(&mut self, exposure: AutoExposure) {
+        self.auto_exposure = Some(exposure);
+    }
+
+    pub fn get_auto_exposure(&self) -> Option<AutoExposure> {
+        self.auto_exposure
+    }
+
+    fn expose(&self, image: &mut Canvas) {
+        if let Some(exposure) = &self.auto_exposure {
+            println!("auto exposure: {:.3}", exposure.apply(image));
+        }
+    }
+
+    // render_parallel stops adding samples to tiles once the budget is spent
+    // and averages what each pixel got, the ray cache is left out
+    pub fn render_budget(&mut self, budget: RenderBudget) {
+        self.budget = Some(budget);
+    }
+
+    pub fn get_render_budget(&self) -> Option<RenderBudget> {
+        self.budget
+    }
+
+    // the samples every tile got in the last budgeted render
+    pub fn tile_samples(&self) -> Vec<TileSamples> {
+        self.tile_samples.lock().unwrap().clone()
+    }
+
+    pub fn threads(&self) -> usize {
+        self.num_threads
+    }
+
+    // tile size and order, None when rendering a block of rows per thread
+    pub fn tiles(&self) -> Option<(usize, TileOrder)> {
+        self.tile_size.map(|size| (size, self.tile_order))
+    }
+
     pub fn aa_samples(&mut self, samples: AASamples) {
         self.aa_samples = samples;
     }
+
+    // total stratified samples per pixel, shared by every sampled effect.
+    // replaces the fixed aa_samples grid.
+    pub fn samples_per_pixel(&mut self, n: usize) {
+        self.sampler = Some(Sampler::new(n));
+    }
+
+    // off by default, secondary rays then stop at MAX_RECURSION_DEPTH
+    pub fn russian_roulette(&mut self, roulette: RussianRoulette) {
+        self.russian_roulette = Some(roulette);
+    }
+
+    // looks every reflection up in the world's sky instead of tracing it, for
+    // quick previews. worlds without a sky still trace their reflections
+    pub fn environment_reflections(&mut self, on: bool) {
+        self.environment_reflections = on;
+    }
+
+    pub fn get_environment_reflections(&self) -> bool {
+        self.environment_reflections
+    }
+
+    // RenderMode::Clay renders every shape in matte gray with ambient occlusion
+    pub fn render_mode(&mut self, mode: RenderMode) {
+        self.render_mode = mode;
+    }
+
+    pub fn get_render_mode(&self) -> RenderMode {
+        self.render_mode
+    }
+
+    // gives every pixel a cache of this many traced rays, so rays that repeat
+    // exactly within the pixel are only traced once
+    pub fn ray_cache(&mut self, capacity: usize) {
+        self.ray_cache = Some(capacity);
+    }
+
+    // totals over every pixel rendered with the ray cache so far
+    pub fn ray_cache_stats(&self) -> RayCacheStats {
+        RayCacheStats {
+            hits: self.ray_cache_hits.load(Ordering::Relaxed),
+            misses: self.ray_cache_misses.load(Ordering::Relaxed),
+        }
+    }
+
+    fn record_ray_cache(&self, cache: Option<&RayCache>) {
+        if let Some(cache) = cache {
+            let stats = cache.stats();
+            self.ray_cache_hits.fetch_add(stats.hits, Ordering::Relaxed);
+            self.ray_cache_misses
+                .fetch_add(stats.misses, Ordering::Relaxed);
+        }
+    }
+
+    // totals over every pixel rendered so far
+    pub fn shadow_ray_stats(&self) -> ShadowRayStats {
+        ShadowRayStats {
+            cast: self.shadow_rays_cast.load(Ordering::Relaxed),
+            blocked: self.shadow_rays_blocked.load(Ordering::Relaxed),
+        }
+    }
+
+    fn record_shadow_rays(&self, stats: &ShadowRayStats) {
+        self.shadow_rays_cast
+            .fetch_add(stats.cast, Ordering::Relaxed);
+        self.shadow_rays_blocked
+            .fetch_add(stats.blocked, Ordering::Relaxed);
+    }
+
+    pub fn dirty_regions(&mut self, sender: Sender<DirtyRegion>) {
+        self.dirty_regions = Some(sender);
+    }
+
+    // gets a message every time a row of the image is finished
+    pub fn progress(&mut self, sender: Sender<RenderProgress>) {
+        self.progress = Some(sender);
+    }
+
+    fn start_progress(&self) {
+        self.rows_done.store(0, Ordering::Relaxed);
+    }
+
+    fn row_done(&self, total_rows: usize) {
+        let rows_done = self.rows_done.fetch_add(1, Ordering::Relaxed) + 1;
+        if let Some(sender) = &self.progress {
+            let _ = sender.send(RenderProgress {
+                rows_done,
+                total_rows,
+            });
+        }
+    }
+
+    fn notify_dirty_region(&self, pixels: &[Color], rows: Range<usize>) {
+        if let Some(sender) = &self.dirty_regions {
+            // the receiver going away shouldn't abort the render
+            let _ = sender.send(DirtyRegion {
+                pixels: pixels.to_vec(),
+                rows,
+            });
+        }
+    }
+}
+
+// a block of finished rows, with its pixels in row-major order
+#[derive(Debug)]
+pub struct DirtyRegion {
+    pub rows: Range<usize>,
+    pub pixels: Vec<Color>,
 }
 
-struct RenderThreadResult {
-    start: usize,
-    end: usize,
-    colors: Vec<Color>,
+// everything that went into one pixel: the rays traced for it, what they hit
+// and how each hit was shaded
+#[derive(Debug)]
+pub struct PixelTrace {
+    pub x: usize,
+    pub y: usize,
+    pub color: Color,
+    pub log: RayLog,
+}
+
+impl PixelTrace {
+    // the antialiasing samples, each the root of a bounce tree in the log
+    pub fn camera_rays(&self) -> impl Iterator<Item = &RayLogEntry> {
+        self.log.entries().iter().filter(|e| e.depth == 0)
+    }
+}
+
+impl fmt::Display for PixelTrace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "pixel ({}, {}) => {}", self.x, self.y, self.color)?;
+        write!(f, "{}", self.log)
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use std::f64::consts::PI;
+    use std::{f64::consts::PI, sync::mpsc};
 
     use crate::{
+        animation::Easing,
         color::Color,
         equal,
-        transform::{rotation_y, translation, view_transform},
+        geometry::shape::{Plane, Sphere},
+        light::PointLight,
+        pattern::{checkers_pattern, filtered_checkers_pattern, Pattern},
+        sky::Sky,
+        transform::{decompose, rotation_y, scaling, translation, view_transform},
         vector::Vector,
         world::World,
     };
@@ -280,10 +1136,64 @@ mod tests {
         let c = Camera::new(hsize, vsize, field_of_view);
         assert_eq!(c.hsize, hsize);
         assert_eq!(c.vsize, vsize);
-        assert!(equal(c._field_of_view, field_of_view));
+        assert!(equal(c.field_of_view, field_of_view));
         assert_eq!(c.transform, Matrix::identity(4, 4));
     }
 
+    #[test]
+    fn degenerate_cameras() {
+        assert_eq!(
+            Camera::try_new(0, 120, PI / 2.0).unwrap_err(),
+            CameraError::ZeroSize {
+                hsize: 0,
+                vsize: 120
+            }
+        );
+        assert!(matches!(
+            Camera::try_new(160, 0, PI / 2.0),
+            Err(CameraError::ZeroSize { .. })
+        ));
+        for &fov in &[0.0, -1.0, PI, 4.0, f64::NAN] {
+            assert!(
+                matches!(
+                    Camera::try_new(160, 120, fov),
+                    Err(CameraError::FieldOfView(_))
+                ),
+                "{}",
+                fov
+            );
+        }
+        for &(focal_length, sensor_width) in &[(0.0, 36.0), (-50.0, 36.0), (50.0, 0.0)] {
+            assert!(Camera::try_with_lens(300, 200, focal_length, sensor_width).is_err());
+        }
+        assert!(Camera::try_new(1, 1, 0.01).is_ok());
+    }
+
+    #[test]
+    #[should_panic(expected = "has no pixels")]
+    fn new_camera_without_pixels_panics() {
+        Camera::new(0, 0, PI / 2.0);
+    }
+
+    #[test]
+    fn camera_from_lens() {
+        // a 50mm lens on a full frame sensor sees about 39.6 degrees across
+        let c = Camera::with_lens(300, 200, 50.0, FULL_FRAME_SENSOR_WIDTH);
+        assert!(equal(c.field_of_view(), 2.0 * (18.0f64 / 50.0).atan()));
+        assert!(equal(c.focal_length(FULL_FRAME_SENSOR_WIDTH), 50.0));
+
+        // in portrait the sensor still spans the width, the field of view is
+        // across the height
+        let portrait = Camera::with_lens(200, 300, 50.0, FULL_FRAME_SENSOR_WIDTH);
+        assert!(equal(portrait.half_width, c.half_width));
+        assert!(equal(portrait.half_height, c.half_width * 1.5));
+        assert!(equal(portrait.focal_length(FULL_FRAME_SENSOR_WIDTH), 50.0));
+
+        // a smaller sensor crops what the same lens sees
+        let cropped = Camera::with_lens(300, 200, 50.0, 23.6);
+        assert!(cropped.field_of_view() < c.field_of_view());
+    }
+
     #[test]
     fn pixel_size_horizontal_canvas() {
         let c = Camera::new(200, 125, PI / 2.0);
@@ -324,6 +1234,92 @@ mod tests {
         );
     }
 
+    #[test]
+    fn camera_rays_carry_neighbouring_pixel_rays() {
+        let mut c = Camera::new(201, 101, PI / 2.0);
+        c.set_transform(&rotation_y(PI / 4.0) * &translation(0, -2, 5));
+        let d = *c.ray_for_pixel(30, 20).differentials().unwrap();
+        let (right, below) = (c.ray_for_pixel(31, 20), c.ray_for_pixel(30, 21));
+        assert_eq!(d.rx_origin, right.origin());
+        assert_eq!(d.rx_direction, right.direction());
+        assert_eq!(d.ry_origin, below.origin());
+        assert_eq!(d.ry_direction, below.direction());
+    }
+
+    #[test]
+    fn camera_resolution_getters() {
+        let c = Camera::new(160, 120, PI / 2.0);
+        assert_eq!(c.resolution(), (160, 120));
+        assert_eq!(c.hsize(), 160);
+        assert_eq!(c.vsize(), 120);
+        assert!(equal(c.field_of_view(), PI / 2.0));
+        assert!(equal(c.pixel_size(), 2.0 / 160.0));
+    }
+
+    #[test]
+    fn weighted_rays_cover_pixel() {
+        let mut c = Camera::new(201, 101, PI / 2.0);
+        let rays = c.weighted_rays_for_pixel(100, 50);
+        assert_eq!(rays.len(), 1);
+        assert_eq!(rays[0].0.direction(), c.ray_for_pixel(100, 50).direction());
+        assert!(equal(rays[0].1, 1.0));
+
+        c.render_opts.aa_samples(AASamples::X4);
+        let rays = c.weighted_rays_for_pixel(100, 50);
+        assert_eq!(rays.len(), 4);
+        assert!(equal(rays.iter().map(|(_, w)| w).sum::<f64>(), 1.0));
+    }
+
+    #[test]
+    fn samples_per_pixel_replaces_aa_grid() {
+        let mut c = Camera::new(201, 101, PI / 2.0);
+        c.render_opts.aa_samples(AASamples::X4);
+        c.render_opts.samples_per_pixel(9);
+        let rays = c.rays_for_pixel(100, 50);
+        assert_eq!(rays.len(), 9);
+        assert_eq!(
+            rays.iter().map(|r| r.direction()).collect::<Vec<_>>(),
+            c.rays_for_pixel(100, 50)
+                .iter()
+                .map(|r| r.direction())
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn ray_cache_keeps_render_identical() {
+        let w = World::default();
+        let mut c = test_camera();
+        c.render_opts.aa_samples(AASamples::X4);
+        let plain = c.render(&w);
+
+        c.render_opts.ray_cache(16);
+        let cached = c.render(&w);
+        assert_identical(&plain, &cached);
+        assert!(c.render_opts.ray_cache_stats().misses > 0);
+    }
+
+    #[test]
+    fn advance_time_moves_animated_camera() {
+        let mut c = Camera::new(201, 101, PI / 2.0);
+        let mut channel = AnimationChannel::new();
+        channel.add_keyframe(0.0, decompose(&translation(0, 0, 0)), Easing::Linear);
+        channel.add_keyframe(2.0, decompose(&translation(0, 0, 4)), Easing::Linear);
+        c.set_animation(channel);
+
+        c.advance_time(1.0);
+        let r = c.ray_for_pixel(100, 50);
+        assert_eq!(r.origin(), Point::new(0, 0, -2));
+
+        let mut path = CameraPath::new();
+        let (to, up) = (Point::origin(), Vector::new(0, 1, 0));
+        path.add_look_at(0.0, Point::new(0, 0, -5), to, up, Easing::Linear);
+        path.add_look_at(2.0, Point::new(0, 0, -1), to, up, Easing::Linear);
+        c.set_path(path);
+        c.advance_time(1.0);
+        assert_eq!(c.ray_for_pixel(100, 50).origin(), Point::new(0, 0, -3));
+    }
+
     #[test]
     fn render_world_with_camera() {
         let w = World::default();
@@ -335,4 +1331,596 @@ mod tests {
         let image = c.render(&w);
         assert_eq!(image.get_pixel(5, 5), Color::new(0.38066, 0.47583, 0.2855));
     }
+
+    #[test]
+    fn log_pixel_matches_render() {
+        let w = World::default();
+        let mut c = Camera::new(11, 11, PI / 2.0);
+        c.set_transform(view_transform(
+            Point::new(0, 0, -5),
+            Point::origin(),
+            Vector::new(0, 1, 0),
+        ));
+        let log = c.log_pixel(&w, 5, 5);
+        let camera_rays: Vec<_> = log.entries().iter().filter(|e| e.depth == 0).collect();
+        assert_eq!(camera_rays.len(), 1);
+        assert_eq!(camera_rays[0].color, Color::new(0.38066, 0.47583, 0.2855));
+        assert!(camera_rays[0].hit.is_some());
+        assert!(log.to_string().starts_with("camera ray"));
+    }
+
+    #[test]
+    fn debug_pixel_breaks_down_shading() {
+        let w = World::default();
+        let mut c = Camera::new(11, 11, PI / 2.0);
+        c.set_transform(view_transform(
+            Point::new(0, 0, -5),
+            Point::origin(),
+            Vector::new(0, 1, 0),
+        ));
+        c.render_opts.aa_samples(AASamples::X4);
+        let trace = c.debug_pixel(&w, 5, 5);
+        assert_eq!(trace.camera_rays().count(), 4);
+        for ray in trace.camera_rays() {
+            let shading = ray.shading.as_ref().unwrap();
+            assert!(shading.lights[0].lighting.diffuse.max_component() > 0.0);
+            assert_eq!(shading.surface(), ray.color);
+        }
+        assert_eq!(trace.color, c.pixel_color(&w, 5, 5));
+        assert!(trace.to_string().starts_with("pixel (5, 5)"));
+    }
+
+    #[test]
+    fn render_with_far_clipping() {
+        let w = World::default();
+        let mut c = Camera::new(11, 11, PI / 2.0);
+        c.set_transform(view_transform(
+            Point::new(0, 0, -5),
+            Point::origin(),
+            Vector::new(0, 1, 0),
+        ));
+        c.set_clipping(0.0, 3.0);
+        let image = c.render(&w);
+        assert_eq!(image.get_pixel(5, 5), Color::black());
+    }
+
+    fn assert_identical(a: &Canvas, b: &Canvas) {
+        let (a, b) = (a.rows(0..a.height()), b.rows(0..b.height()));
+        assert_eq!(a.len(), b.len());
+        for (ca, cb) in a.iter().zip(b.iter()) {
+            assert_eq!(ca.red.to_bits(), cb.red.to_bits());
+            assert_eq!(ca.green.to_bits(), cb.green.to_bits());
+            assert_eq!(ca.blue.to_bits(), cb.blue.to_bits());
+        }
+    }
+
+    fn test_camera() -> Camera {
+        let mut c = Camera::new(21, 13, PI / 2.0);
+        c.set_transform(view_transform(
+            Point::new(0, 0, -5),
+            Point::origin(),
+            Vector::new(0, 1, 0),
+        ));
+        c
+    }
+
+    #[test]
+    fn default_thread_count_is_detected() {
+        let opts = RenderOpts::default();
+        assert!(opts.num_threads >= 1);
+    }
+
+    #[test]
+    fn multithreaded_render_matches_single_threaded() {
+        let w = Arc::new(World::default());
+        let single = test_camera().render(&w);
+
+        for &n in [1, 2, 3, 4, 7, 13, 32].iter() {
+            let mut c = test_camera();
+            c.render_opts.num_threads(n);
+            let multi = Camera::render_multithreaded(Arc::new(c), w.clone());
+            assert_identical(&single, &multi);
+        }
+    }
+
+    #[test]
+    fn multithreaded_render_with_aa_is_deterministic() {
+        let w = Arc::new(World::default());
+        let render = |n| {
+            let mut c = test_camera();
+            c.render_opts.aa_samples(AASamples::X4);
+            c.render_opts.num_threads(n);
+            Camera::render_multithreaded(Arc::new(c), w.clone())
+        };
+        let reference = render(1);
+        for &n in [2, 5, 16].iter() {
+            assert_identical(&reference, &render(n));
+        }
+    }
+
+    #[test]
+    fn single_threaded_render_uses_aa_samples() {
+        let w = World::default();
+        let mut c = test_camera();
+        c.render_opts.aa_samples(AASamples::X4);
+        let single = c.render(&w);
+
+        assert_identical(&single, &c.render_parallel(&w));
+        let plain = test_camera().render(&w);
+        let smoothed =
+            (0..13).any(|y| (0..21).any(|x| single.get_pixel(x, y) != plain.get_pixel(x, y)));
+        assert!(smoothed);
+    }
+
+    #[test]
+    fn tiled_render_matches_single_threaded() {
+        use crate::tiles::TileOrder;
+
+        let w = World::default();
+        let single = test_camera().render(&w);
+        for &order in &[TileOrder::Scanline, TileOrder::Hilbert, TileOrder::Spiral] {
+            for &(size, n) in &[(1, 3), (4, 2), (64, 4)] {
+                let mut c = test_camera();
+                c.render_opts.num_threads(n);
+                c.render_opts.tile_size(size);
+                c.render_opts.tile_order(order);
+                assert_identical(&single, &c.render_parallel(&w));
+            }
+        }
+    }
+
+    #[test]
+    fn budgeted_renders_stop_adding_samples() {
+        let w = World::default();
+        let mut c = test_camera();
+        c.render_opts.aa_samples(AASamples::X4);
+        c.render_opts.num_threads(3);
+        let full = c.render_parallel(&w);
+
+        // enough for every sample
+        c.render_opts.render_budget(RenderBudget::Rays(usize::MAX));
+        assert_identical(&full, &c.render_parallel(&w));
+        assert!(c.render_opts.tile_samples().iter().all(|t| t.samples == 4));
+
+        // the first pass and half of the second
+        let (width, height) = c.resolution();
+        let pixels = width * height;
+        c.render_opts.tile_size(1);
+        c.render_opts
+            .render_budget(RenderBudget::Rays(pixels + pixels / 2));
+        let partial = c.render_parallel(&w);
+        let tile_samples = c.render_opts.tile_samples();
+        assert_eq!(tile_samples.len(), pixels);
+        assert_eq!(
+            tile_samples.iter().map(|t| t.samples).sum::<usize>(),
+            pixels + pixels / 2
+        );
+        for t in &tile_samples {
+            assert!(t.samples == 1 || t.samples == 2);
+            // each pixel is the average of the samples it got
+            let (x, y) = (t.tile.x0, t.tile.y0);
+            let rays = c.rays_for_pixel(x, y);
+            let colors: Vec<Color> = rays[..t.samples].iter().map(|r| w.color_at(r, 5)).collect();
+            assert_eq!(partial.get_pixel(x, y), Color::average(&colors));
+        }
+
+        // a spent budget still gets the first pass
+        c.render_opts
+            .render_budget(RenderBudget::Time(Duration::ZERO));
+        c.render_parallel(&w);
+        assert!(c.render_opts.tile_samples().iter().all(|t| t.samples == 1));
+    }
+
+    #[test]
+    fn compact_renders_match_full_precision() {
+        let w = World::default();
+        let full = test_camera().render(&w);
+        let mut c = test_camera();
+        c.render_opts.pixel_storage(PixelStorage::F32);
+        let mut tiled = test_camera();
+        tiled.render_opts.pixel_storage(PixelStorage::F32);
+        tiled.render_opts.tile_size(4);
+        for image in [
+            c.render(&w),
+            c.render_parallel(&w),
+            tiled.render_parallel(&w),
+        ] {
+            assert_eq!(image.storage(), PixelStorage::F32);
+            for (a, b) in full
+                .rows(0..full.height())
+                .iter()
+                .zip(image.rows(0..image.height()).iter())
+            {
+                assert!(a.approx_eq(b, 1e-6));
+            }
+        }
+    }
+
+    #[test]
+    fn render_into_matches_encoded_render() {
+        let w = World::default();
+        let image = test_camera().render(&w);
+        for &format in &[PixelFormat::Rgba8, PixelFormat::Bgra8] {
+            for &n in &[1, 3] {
+                let mut c = test_camera();
+                c.render_opts.num_threads(n);
+                let mut buffer = vec![0; 21 * 13 * 4];
+                c.render_into(&w, &mut buffer, format);
+                for y in 0..13 {
+                    for x in 0..21 {
+                        let idx = (y * 21 + x) * 4;
+                        assert_eq!(buffer[idx..idx + 4], format.encode(image.get_pixel(x, y)));
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn render_into_needs_a_buffer_of_the_right_size() {
+        let mut buffer = vec![0; 21 * 13 * 3];
+        test_camera().render_into(&World::default(), &mut buffer, PixelFormat::Rgba8);
+    }
+
+    #[test]
+    fn render_pixel_matches_render() {
+        let w = World::default();
+        let mut c = test_camera();
+        c.render_opts.aa_samples(AASamples::X4);
+        c.render_opts.ray_cache(16);
+        c.render_opts.num_threads(2);
+        let image = c.render_parallel(&w);
+        let stats = c.render_opts.ray_cache_stats();
+
+        for (x, y) in [(0, 0), (5, 5), (10, 3)] {
+            assert_eq!(
+                render_pixel(&c, &w, x, y, &c.render_opts),
+                image.get_pixel(x, y)
+            );
+        }
+        assert_eq!(c.render_opts.ray_cache_stats(), stats);
+
+        // opts other than the camera's own
+        let single = test_camera().render(&w);
+        let opts = RenderOpts::default();
+        assert_eq!(render_pixel(&c, &w, 5, 5, &opts), single.get_pixel(5, 5));
+    }
+
+    #[test]
+    fn render_applies_auto_exposure() {
+        let w = World::default();
+        let mut plain = test_camera().render(&w);
+        let exposure = AutoExposure::new(1.0, 0.5).apply(&mut plain);
+        assert!(exposure != 1.0);
+
+        let mut c = test_camera();
+        c.render_opts.auto_exposure(AutoExposure::new(1.0, 0.5));
+        assert_identical(&plain, &c.render(&w));
+        assert_identical(&plain, &c.render_parallel(&w));
+        for (x, y) in [(0, 0), (10, 6)] {
+            assert_eq!(render_pixel(&c, &w, x, y, &c.render_opts), plain.get_pixel(x, y));
+        }
+    }
+
+    #[test]
+    fn frame_scene_fits_world_in_view() {
+        let mut w = World::default();
+        let mut far_sphere = Sphere::default();
+        far_sphere.set_transform(translation(6, 1, 2));
+        w.add_object(far_sphere);
+        let bounds = w.bounds();
+
+        let mut c = Camera::new(40, 20, PI / 3.0);
+        c.set_transform(view_transform(
+            Point::new(1, 2, -3),
+            Point::new(0, 0, 1),
+            Vector::new(0, 1, 0),
+        ));
+        let forward = (&c.transform_inverse * Vector::new(0, 0, -1)).normalize();
+        assert!(c.frame_scene(&w, 0.1));
+
+        let center_ray = c.ray_through(20, 10, (0.0, 0.0));
+        assert_eq!(center_ray.direction(), forward);
+        let (min, max) = (bounds.get_min(), bounds.get_max());
+        for &x in &[min.x, max.x] {
+            for &y in &[min.y, max.y] {
+                for &z in &[min.z, max.z] {
+                    let p = c.transform() * Point::new(x, y, z);
+                    assert!(p.z < 0.0);
+                    assert!((p.x / -p.z).abs() <= c.half_width);
+                    assert!((p.y / -p.z).abs() <= c.half_height);
+                }
+            }
+        }
+
+        assert!(!c.frame_scene(&World::new(), 0.1));
+    }
+
+    #[test]
+    fn lens_shift_moves_the_image_window() {
+        let mut c = Camera::new(200, 100, PI / 2.0);
+        c.set_lens_shift(0.0, 0.5);
+        // the bottom edge now looks straight ahead
+        let r = c.ray_through(100, 100, (0.0, 0.0));
+        assert_eq!(r.direction(), Vector::new(0, 0, -1));
+        let top = c.ray_through(100, 0, (0.0, 0.0)).direction();
+        assert!(top.y > 0.0 && equal(top.y / -top.z, 1.0));
+
+        c.set_lens_shift(0.5, 0.0);
+        // and with a shift right, the left edge
+        let r = c.ray_through(0, 50, (0.0, 0.0));
+        assert_eq!(r.direction(), Vector::new(0, 0, -1));
+        let right = c.ray_through(200, 50, (0.0, 0.0)).direction();
+        assert!(right.x < 0.0 && equal(right.x / right.z, 2.0));
+    }
+
+    #[test]
+    fn filtered_checkers_fade_to_gray_in_the_distance() {
+        let floor = |pattern: Pattern| {
+            let mut w = World::new();
+            w.add_light(PointLight::new(Point::new(0, 10, 0), Color::white()));
+            let mut plane = Plane::default();
+            let m = plane.material_mut();
+            m.ambient = 1.0;
+            m.diffuse = 0.0;
+            m.specular = 0.0;
+            m.set_pattern(pattern);
+            w.add_object(plane);
+            w
+        };
+        let mut c = Camera::new(11, 11, PI / 3.0);
+        c.set_transform(view_transform(
+            Point::new(0, 1, 0),
+            Point::new(0, 0, -100),
+            Vector::new(0, 1, 0),
+        ));
+        let ray = c.ray_for_pixel(5, 5);
+
+        let (white, black) = (Color::white(), Color::black());
+        let plain = c.trace_with(
+            &floor(checkers_pattern(white, black)),
+            &ray,
+            &c.render_opts,
+            TraceOptions::default(),
+            &mut ShadowRayStats::default(),
+        );
+        assert!(plain == white || plain == black);
+        let filtered = c.trace_with(
+            &floor(filtered_checkers_pattern(white, black)),
+            &ray,
+            &c.render_opts,
+            TraceOptions::default(),
+            &mut ShadowRayStats::default(),
+        );
+        assert!((filtered.red - 0.5).abs() < 0.05);
+    }
+
+    // a small sphere over a floor, lit from straight above and from the side
+    fn sphere_over_floor() -> World {
+        let mut w = World::new();
+        w.add_light(PointLight::new(Point::new(0, 10, 0), Color::white()));
+        w.add_light(PointLight::new(Point::new(10, 10, 0), Color::white()));
+        let mut floor = Plane::default();
+        floor.set_transform(translation(0, -1, 0));
+        w.add_object(floor);
+        let mut s = Sphere::default();
+        s.set_transform(&translation(0, 1, 0) * &scaling(0.5, 0.5, 0.5));
+        w.add_object(s);
+        w
+    }
+
+    #[test]
+    fn debug_pixel_matches_sampled_soft_shadows() {
+        let mut w = sphere_over_floor();
+        for light in w.lights_mut() {
+            light.source_mut().set_shadow_softness(1.0);
+        }
+        let mut c = test_camera();
+        c.set_transform(view_transform(
+            Point::new(0, 3, -4),
+            Point::new(0, -1, 0),
+            Vector::new(0, 1, 0),
+        ));
+        c.render_opts.samples_per_pixel(8);
+        for (x, y) in [(10, 6), (8, 9), (12, 9), (3, 11)] {
+            assert_eq!(
+                c.debug_pixel(&w, x, y).color,
+                render_pixel(&c, &w, x, y, &c.render_opts)
+            );
+        }
+    }
+
+    #[test]
+    fn environment_reflections_toggle_for_the_whole_render() {
+        let world = |environment_reflections: bool| {
+            let mut w = World::new();
+            w.add_light(PointLight::new(Point::new(0, 10, 0), Color::white()));
+            w.set_sky(Sky::new(0.5, 0.0, 2.0));
+            let mut floor = Plane::default();
+            floor.set_transform(translation(0, -1, 0));
+            floor.material_mut().reflective = 0.8;
+            floor.material_mut().environment_reflections = environment_reflections;
+            w.add_object(floor);
+            w.add_object(Sphere::default());
+            w
+        };
+        let mut c = test_camera();
+        c.set_transform(view_transform(
+            Point::new(0, 1, -5),
+            Point::new(0, -1, 0),
+            Vector::new(0, 1, 0),
+        ));
+        let pixels = |image: Canvas| image.rows(0..image.height()).into_owned();
+        let traced = pixels(c.render(&world(false)));
+
+        c.render_opts.environment_reflections(true);
+        let looked_up = pixels(c.render(&world(false)));
+        // the sphere no longer shows up in the floor
+        assert_ne!(traced, looked_up);
+
+        c.render_opts.environment_reflections(false);
+        assert_eq!(pixels(c.render(&world(true))), looked_up);
+    }
+
+    #[test]
+    fn renders_count_shadow_rays() {
+        let w = sphere_over_floor();
+        let mut c = test_camera();
+        c.set_transform(view_transform(
+            Point::new(0, 5, -5),
+            Point::origin(),
+            Vector::new(0, 1, 0),
+        ));
+        c.render_opts.num_threads(3);
+        c.render_parallel(&w);
+        let stats = c.render_opts.shadow_ray_stats();
+        // two lights for every pixel, all of which see the floor or the sphere
+        assert_eq!(stats.cast, 2 * 21 * 13);
+        assert!(stats.blocked > 0 && stats.blocked < stats.cast);
+
+        c.render(&w);
+        let twice = c.render_opts.shadow_ray_stats();
+        assert_eq!(twice.cast, 2 * stats.cast);
+        assert_eq!(twice.blocked, 2 * stats.blocked);
+    }
+
+    #[test]
+    fn shadow_pass_is_gray() {
+        let w = sphere_over_floor();
+        let mut c = Camera::new(21, 21, PI / 2.0);
+        c.set_transform(view_transform(
+            Point::new(0, 5, 0),
+            Point::origin(),
+            Vector::new(0, 0, 1),
+        ));
+        c.render_opts.num_threads(4);
+        let image = c.render_shadows(&w);
+
+        let mut values = vec![];
+        for y in 0..21 {
+            for x in 0..21 {
+                let p = image.get_pixel(x, y);
+                assert!(p.red == p.green && p.green == p.blue);
+                values.push(p.red);
+            }
+        }
+        // the top of the sphere and the far floor see both lights, the floor
+        // around the sphere only the one from the side
+        assert_eq!(image.get_pixel(10, 10).red, 1.0);
+        assert_eq!(image.get_pixel(0, 0).red, 1.0);
+        assert!(values.contains(&0.5));
+        assert_eq!(c.render_opts.shadow_ray_stats().cast, 0);
+    }
+
+    #[test]
+    fn render_region_matches_full_render() {
+        let w = World::default();
+        let c = test_camera();
+        let full = test_camera().render(&w);
+        let (region, offset) = c.render_region(&w, 5, 3, 12, 9);
+        assert_eq!(offset, (5, 3));
+        assert_eq!((region.width(), region.height()), (7, 6));
+        for y in 0..6 {
+            for x in 0..7 {
+                assert_eq!(region.get_pixel(x, y), full.get_pixel(x + 5, y + 3));
+            }
+        }
+    }
+
+    #[test]
+    fn render_region_is_clamped_to_image() {
+        let w = World::default();
+        let c = test_camera();
+        let (region, offset) = c.render_region(&w, 15, 10, 100, 100);
+        assert_eq!(offset, (15, 10));
+        assert_eq!((region.width(), region.height()), (6, 3));
+
+        let (region, offset) = c.render_region(&w, 50, 50, 100, 100);
+        assert_eq!(offset, (21, 13));
+        assert_eq!((region.width(), region.height()), (0, 0));
+    }
+
+    #[test]
+    fn rerender_pixels_after_material_change() {
+        let w = World::default();
+        let mut c = test_camera();
+        let mut image = c.render(&w);
+
+        let is_outer = |s: &dyn Shape| s.material().color == Color::new(0.8, 1.0, 0.6);
+        let pixels = c.pixels_hitting(&w, is_outer);
+        assert!(pixels.contains(&(10, 6)));
+        assert!(!pixels.contains(&(0, 0)));
+
+        let mut outer = Sphere::default();
+        outer.material_mut().color = Color::new(1.0, 0.0, 0.0);
+        outer.material_mut().diffuse = 0.7;
+        outer.material_mut().specular = 0.2;
+        let mut changed = World::new();
+        changed.add_light(PointLight::new(Point::new(-10, 10, -10), Color::white()));
+        changed.add_object(outer);
+        let mut inner = Sphere::default();
+        inner.set_transform(scaling(0.5, 0.5, 0.5));
+        changed.add_object(inner);
+
+        c.rerender_pixels(&changed, &pixels, &mut image);
+        let expected = test_camera().render(&changed);
+        for y in 0..13 {
+            for x in 0..21 {
+                assert_eq!(image.get_pixel(x, y), expected.get_pixel(x, y));
+            }
+        }
+    }
+
+    #[test]
+    fn render_notifies_dirty_regions() {
+        let w = World::default();
+        let mut c = Camera::new(11, 11, PI / 2.0);
+        c.set_transform(view_transform(
+            Point::new(0, 0, -5),
+            Point::origin(),
+            Vector::new(0, 1, 0),
+        ));
+        let (tx, rx) = mpsc::channel();
+        c.render_opts.dirty_regions(tx);
+        c.render_opts.num_threads(3);
+
+        let image = Camera::render_multithreaded(Arc::new(c), Arc::new(w));
+
+        let mut covered = [false; 11];
+        for region in rx.try_iter() {
+            assert_eq!(region.pixels.len(), region.rows.len() * 11);
+            assert_eq!(region.pixels.as_slice(), &*image.rows(region.rows.clone()));
+            for y in region.rows {
+                covered[y] = true;
+            }
+        }
+        assert!(covered.iter().all(|&c| c));
+    }
+
+    #[test]
+    fn render_reports_progress() {
+        let w = World::default();
+        let mut c = Camera::new(6, 5, PI / 2.0);
+        let (tx, rx) = mpsc::channel();
+        c.render_opts.progress(tx);
+        c.render_opts.num_threads(2);
+        let c = Arc::new(c);
+
+        Camera::render_multithreaded(c.clone(), Arc::new(w));
+        let mut rows: Vec<usize> = rx.try_iter().map(|p| p.rows_done).collect();
+        rows.sort_unstable();
+        assert_eq!(rows, vec![1, 2, 3, 4, 5]);
+
+        c.render_region(&World::default(), 0, 1, 6, 3);
+        let last = rx.try_iter().last().unwrap();
+        assert_eq!(
+            last,
+            RenderProgress {
+                rows_done: 2,
+                total_rows: 2
+            }
+        );
+        assert!(last.is_done());
+    }
 }
@@ -0,0 +1,354 @@
+use std::{fmt, fs, path::Path};
+
+use anyhow::{anyhow, bail, Result};
+
+use crate::{
+    animation::Easing,
+    camera::Camera,
+    matrix::Matrix,
+    point::Point,
+    transform::{decompose, view_transform, DecomposedTransform},
+    vector::Vector,
+};
+
+// where the camera is at a key, either as a look-at or as the translation,
+// rotation and scale of its view transform
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CameraPose {
+    LookAt { from: Point, to: Point, up: Vector },
+    Trs(DecomposedTransform),
+}
+
+impl CameraPose {
+    pub fn view_transform(&self) -> Matrix {
+        match self {
+            CameraPose::LookAt { from, to, up } => view_transform(*from, *to, *up),
+            CameraPose::Trs(trs) => trs.to_matrix(),
+        }
+    }
+
+    fn trs(&self) -> DecomposedTransform {
+        match self {
+            CameraPose::LookAt { .. } => decompose(&self.view_transform()),
+            CameraPose::Trs(trs) => *trs,
+        }
+    }
+}
+
+// like an animation keyframe, the easing applies to the segment leading to
+// the next key
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CameraKey {
+    pub time: f64,
+    pub pose: CameraPose,
+    pub easing: Easing,
+}
+
+// camera moves that can be written to a file and replayed exactly. one key
+// per line:
+//
+//   <time> look-at <from x y z> <to x y z> <up x y z> [easing]
+//   <time> trs <translation x y z> <rotation x y z> <scale x y z> [easing]
+//
+// with # starting a comment, and easing one of linear (the default), ease-in,
+// ease-out and ease-in-out
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct CameraPath {
+    keys: Vec<CameraKey>,
+}
+
+impl CameraPath {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_key(&mut self, time: f64, pose: CameraPose, easing: Easing) {
+        let idx = self.keys.partition_point(|k| k.time <= time);
+        self.keys.insert(idx, CameraKey { time, pose, easing });
+    }
+
+    pub fn add_look_at(&mut self, time: f64, from: Point, to: Point, up: Vector, easing: Easing) {
+        self.add_key(time, CameraPose::LookAt { from, to, up }, easing);
+    }
+
+    // keeps wherever the camera is now, e.g. to record moves made in a preview
+    pub fn record(&mut self, time: f64, camera: &Camera, easing: Easing) {
+        self.add_key(time, CameraPose::Trs(decompose(camera.transform())), easing);
+    }
+
+    pub fn keys(&self) -> &[CameraKey] {
+        &self.keys
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    // clamped to the first and last keys. look-at keys blend their from, to and
+    // up, a segment with a trs key at either end blends translation, rotation
+    // and scale
+    pub fn transform_at(&self, time: f64) -> Option<Matrix> {
+        let first = self.keys.first()?;
+        let last = self.keys.last()?;
+        if time <= first.time {
+            return Some(first.pose.view_transform());
+        }
+        if time >= last.time {
+            return Some(last.pose.view_transform());
+        }
+
+        let idx = self.keys.partition_point(|k| k.time <= time);
+        let (k0, k1) = (&self.keys[idx - 1], &self.keys[idx]);
+        let t = k0.easing.apply((time - k0.time) / (k1.time - k0.time));
+        let transform = match (k0.pose, k1.pose) {
+            (
+                CameraPose::LookAt { from, to, up },
+                CameraPose::LookAt {
+                    from: from1,
+                    to: to1,
+                    up: up1,
+                },
+            ) => view_transform(
+                from + (from1 - from) * t,
+                to + (to1 - to) * t,
+                up.lerp(up1, t),
+            ),
+            (pose0, pose1) => {
+                let (a, b) = (pose0.trs(), pose1.trs());
+                DecomposedTransform {
+                    translation: a.translation.lerp(b.translation, t),
+                    rotation: a.rotation.lerp(b.rotation, t),
+                    scale: a.scale.lerp(b.scale, t),
+                }
+                .to_matrix()
+            }
+        };
+        Some(transform)
+    }
+
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut path = Self::new();
+        for (idx, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (time, pose, easing) =
+                parse_key(line).map_err(|e| anyhow!("camera path line {}: {}", idx + 1, e))?;
+            path.add_key(time, pose, easing);
+        }
+        Ok(path)
+    }
+
+    pub fn load(file: &Path) -> Result<Self> {
+        Self::parse(&fs::read_to_string(file)?)
+    }
+
+    pub fn save(&self, file: &Path) -> Result<()> {
+        fs::write(file, self.to_string())?;
+        Ok(())
+    }
+}
+
+// f64's display is the shortest text that reads back as the same number, so
+// a saved path replays exactly
+impl fmt::Display for CameraPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let triple = |v: (f64, f64, f64)| format!("{} {} {}", v.0, v.1, v.2);
+        let vector = |v: Vector| triple((v.x, v.y, v.z));
+        for key in &self.keys {
+            let pose = match key.pose {
+                CameraPose::LookAt { from, to, up } => format!(
+                    "look-at {} {} {}",
+                    triple((from.x, from.y, from.z)),
+                    triple((to.x, to.y, to.z)),
+                    vector(up)
+                ),
+                CameraPose::Trs(trs) => format!(
+                    "trs {} {} {}",
+                    vector(trs.translation),
+                    vector(trs.rotation),
+                    vector(trs.scale)
+                ),
+            };
+            writeln!(f, "{} {} {}", key.time, pose, easing_name(key.easing))?;
+        }
+        Ok(())
+    }
+}
+
+fn parse_key(line: &str) -> Result<(f64, CameraPose, Easing)> {
+    let items: Vec<&str> = line.split_ascii_whitespace().collect();
+    let (time, kind) = match items.as_slice() {
+        [time, kind, ..] => (time.parse::<f64>()?, *kind),
+        _ => bail!("expected a time and a kind of key"),
+    };
+    let numbers = items[2..]
+        .iter()
+        .take(9)
+        .map(|item| item.parse::<f64>())
+        .collect::<Result<Vec<_>, _>>()?;
+    if numbers.len() != 9 {
+        bail!("expected 9 numbers after {}", kind);
+    }
+    let vector = |i: usize| Vector::new(numbers[i], numbers[i + 1], numbers[i + 2]);
+    let point = |i: usize| Point::new(numbers[i], numbers[i + 1], numbers[i + 2]);
+    let pose = match kind {
+        "look-at" => CameraPose::LookAt {
+            from: point(0),
+            to: point(3),
+            up: vector(6),
+        },
+        "trs" => CameraPose::Trs(DecomposedTransform {
+            translation: vector(0),
+            rotation: vector(3),
+            scale: vector(6),
+        }),
+        _ => bail!("unknown kind of key {}", kind),
+    };
+    let easing = match items[11..] {
+        [] => Easing::Linear,
+        [name] => parse_easing(name)?,
+        _ => bail!("unexpected {}", items[12..].join(" ")),
+    };
+    Ok((time, pose, easing))
+}
+
+fn easing_name(easing: Easing) -> String {
+    match easing {
+        Easing::Linear => "linear".to_string(),
+        Easing::EaseIn => "ease-in".to_string(),
+        Easing::EaseOut => "ease-out".to_string(),
+        Easing::EaseInOut => "ease-in-out".to_string(),
+        Easing::CubicBezier(x1, y1, x2, y2) => {
+            format!("cubic-bezier({},{},{},{})", x1, y1, x2, y2)
+        }
+    }
+}
+
+// cubic-bezier takes its control points without spaces, like
+// cubic-bezier(0.25,0.1,0.25,1)
+fn parse_easing(name: &str) -> Result<Easing> {
+    if let Some(args) = name
+        .strip_prefix("cubic-bezier(")
+        .and_then(|rest| rest.strip_suffix(')'))
+    {
+        let numbers = args
+            .split(',')
+            .map(str::parse)
+            .collect::<Result<Vec<f64>, _>>()?;
+        return match numbers[..] {
+            [x1, y1, x2, y2] => Ok(Easing::CubicBezier(x1, y1, x2, y2)),
+            _ => bail!("cubic-bezier needs 4 numbers, not {}", args),
+        };
+    }
+    Ok(match name {
+        "linear" => Easing::Linear,
+        "ease-in" => Easing::EaseIn,
+        "ease-out" => Easing::EaseOut,
+        "ease-in-out" => Easing::EaseInOut,
+        _ => bail!("unknown easing {}", name),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::PI;
+
+    use crate::transform::{rotation_y, translation};
+
+    use super::*;
+
+    fn orbit() -> CameraPath {
+        let mut path = CameraPath::new();
+        let up = Vector::new(0, 1, 0);
+        path.add_look_at(
+            2.0,
+            Point::new(5, 1, 0),
+            Point::origin(),
+            up,
+            Easing::Linear,
+        );
+        path.add_look_at(
+            0.0,
+            Point::new(0, 1, -5),
+            Point::origin(),
+            up,
+            Easing::EaseIn,
+        );
+        path
+    }
+
+    #[test]
+    fn look_at_keys_blend_from_to_and_up() {
+        let path = orbit();
+        assert_eq!(path.keys()[0].time, 0.0);
+        let up = Vector::new(0, 1, 0);
+        assert_eq!(
+            path.transform_at(-1.0).unwrap(),
+            view_transform(Point::new(0, 1, -5), Point::origin(), up)
+        );
+        assert_eq!(
+            path.transform_at(3.0).unwrap(),
+            view_transform(Point::new(5, 1, 0), Point::origin(), up)
+        );
+        // ease-in is a quarter of the way along halfway through
+        assert_eq!(
+            path.transform_at(1.0).unwrap(),
+            view_transform(Point::new(1.25, 1.0, -3.75), Point::origin(), up)
+        );
+        assert!(CameraPath::new().transform_at(0.0).is_none());
+    }
+
+    #[test]
+    fn trs_keys_blend_translation_rotation_and_scale() {
+        let mut camera = Camera::new(10, 10, PI / 3.0);
+        let mut path = CameraPath::new();
+        camera.set_transform(translation(0, 0, 5));
+        path.record(0.0, &camera, Easing::Linear);
+        camera.set_transform(&translation(4, 0, 0) * &rotation_y(PI / 2.0));
+        path.record(1.0, &camera, Easing::Linear);
+
+        let halfway = &translation(2.0, 0.0, 2.5) * &rotation_y(PI / 4.0);
+        assert_eq!(path.transform_at(0.5).unwrap(), halfway);
+        assert_eq!(path.transform_at(1.0).unwrap(), *camera.transform());
+    }
+
+    #[test]
+    fn saved_paths_replay_exactly() {
+        let mut path = orbit();
+        path.add_key(
+            3.0,
+            CameraPose::Trs(DecomposedTransform {
+                translation: Vector::new(0.1, -0.2, 1.0 / 3.0),
+                ..Default::default()
+            }),
+            Easing::EaseInOut,
+        );
+        path.add_key(
+            4.0,
+            CameraPose::Trs(DecomposedTransform::default()),
+            Easing::CubicBezier(0.25, 0.1, 0.25, 1.0),
+        );
+        let text = path.to_string();
+        assert!(text.starts_with("0 look-at 0 1 -5 0 0 0 0 1 0 ease-in\n"));
+        let loaded = CameraPath::parse(&format!("# orbit\n\n{}", text)).unwrap();
+        assert_eq!(loaded, path);
+        for time in [0.3, 1.7, 2.5, 3.5] {
+            assert_eq!(loaded.transform_at(time), path.transform_at(time));
+        }
+    }
+
+    #[test]
+    fn malformed_keys_name_their_line() {
+        let err = CameraPath::parse("0 look-at 0 1 -5 0 0 0 0 1 0\n1 look-at 0 1\n")
+            .unwrap_err()
+            .to_string();
+        assert!(err.starts_with("camera path line 2"));
+        assert!(CameraPath::parse("0 dolly 0 0 0 0 0 0 0 0 0").is_err());
+        assert!(CameraPath::parse("0 trs 0 0 0 0 0 0 1 1 1 bounce").is_err());
+        assert!(CameraPath::parse("0 trs 0 0 0 0 0 0 1 1 1 linear extra").is_err());
+        assert!(CameraPath::parse("0 trs 0 0 0 0 0 0 1 1 1 cubic-bezier(0,1)").is_err());
+        assert!(CameraPath::parse("0 trs 0 0 0 0 0 0 1 1 1 cubic-bezier(a,b,c,d)").is_err());
+    }
+}
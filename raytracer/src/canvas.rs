@@ -1,26 +1,55 @@
-use std::path::Path;
+use std::{borrow::Cow, ops::Range, path::Path};
 
 use anyhow::Result;
 
-use crate::{color::Color, image::png::PngExporter, image::ExportCanvas};
+use crate::{color::Color, exposure::luminance, image::png::PngExporter, image::ExportCanvas};
+
+// how a canvas keeps its pixels. F32 takes half the memory of F64, and still
+// has far more precision than an 8 or 16 bit image can show
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PixelStorage {
+    #[default]
+    F64,
+    F32,
+}
+
+#[derive(Debug)]
+enum Pixels {
+    F64(Vec<Color>),
+    F32(Vec<[f32; 3]>),
+}
 
 #[derive(Debug)]
 pub struct Canvas {
     width: usize,
     height: usize,
-    pixels: Vec<Color>,
+    pixels: Pixels,
     exporter: Box<dyn ExportCanvas>,
 }
 
 impl Canvas {
     pub fn new(width: usize, height: usize) -> Self {
-        let pixels = vec![Color::new(0.0, 0.0, 0.0); width * height];
+        Self::with_storage(width, height, PixelStorage::default())
+    }
+
+    pub fn with_storage(width: usize, height: usize, storage: PixelStorage) -> Self {
+        let pixels = match storage {
+            PixelStorage::F64 => Pixels::F64(vec![Color::new(0.0, 0.0, 0.0); width * height]),
+            PixelStorage::F32 => Pixels::F32(vec![[0.0; 3]; width * height]),
+        };
 
         Self {
             width,
             height,
             pixels,
-            exporter: Box::new(PngExporter {}),
+            exporter: Box::new(PngExporter::default()),
+        }
+    }
+
+    pub fn storage(&self) -> PixelStorage {
+        match self.pixels {
+            Pixels::F64(_) => PixelStorage::F64,
+            Pixels::F32(_) => PixelStorage::F32,
         }
     }
 
@@ -33,12 +62,51 @@ impl Canvas {
     }
 
     pub fn get_pixel(&self, x: usize, y: usize) -> Color {
-        self.pixels[self.pixel_idx(x, y)]
+        let idx = self.pixel_idx(x, y);
+        match &self.pixels {
+            Pixels::F64(pixels) => pixels[idx],
+            Pixels::F32(pixels) => from_f32(pixels[idx]),
+        }
     }
 
     pub fn set_pixel(&mut self, x: usize, y: usize, color: Color) {
         let idx = self.pixel_idx(x, y);
-        self.pixels[idx] = color;
+        match &mut self.pixels {
+            Pixels::F64(pixels) => pixels[idx] = color,
+            Pixels::F32(pixels) => pixels[idx] = to_f32(color),
+        }
+    }
+
+    // borrowed from an F64 canvas, converted from an F32 one
+    pub fn rows(&self, rows: Range<usize>) -> Cow<'_, [Color]> {
+        let range = self.row_range(rows);
+        match &self.pixels {
+            Pixels::F64(pixels) => Cow::Borrowed(&pixels[range]),
+            Pixels::F32(pixels) => {
+                Cow::Owned(pixels[range].iter().copied().map(from_f32).collect())
+            }
+        }
+    }
+
+    pub fn rows_mut(&mut self, rows: Range<usize>) -> PixelsMut<'_> {
+        let range = self.row_range(rows);
+        match &mut self.pixels {
+            Pixels::F64(pixels) => PixelsMut::F64(&mut pixels[range]),
+            Pixels::F32(pixels) => PixelsMut::F32(&mut pixels[range]),
+        }
+    }
+
+    pub fn map_pixels(&mut self, f: impl Fn(Color) -> Color) {
+        match &mut self.pixels {
+            Pixels::F64(pixels) => pixels.iter_mut().for_each(|p| *p = f(*p)),
+            Pixels::F32(pixels) => pixels.iter_mut().for_each(|p| *p = to_f32(f(from_f32(*p)))),
+        }
+    }
+
+    fn row_range(&self, rows: Range<usize>) -> Range<usize> {
+        assert!(rows.start <= rows.end);
+        assert!(rows.end <= self.height);
+        rows.start * self.width..rows.end * self.width
     }
 
     fn pixel_idx(&self, x: usize, y: usize) -> usize {
@@ -48,10 +116,198 @@ impl Canvas {
     }
 
     pub fn save(&self, path: &Path) -> Result<()> {
-        self.exporter.save(&self, path)
+        self.exporter.save(self, path)
+    }
+}
+
+// a mutable run of a canvas' pixels, whatever its storage
+#[derive(Debug)]
+pub enum PixelsMut<'a> {
+    F64(&'a mut [Color]),
+    F32(&'a mut [[f32; 3]]),
+}
+
+impl<'a> PixelsMut<'a> {
+    pub fn len(&self) -> usize {
+        match self {
+            PixelsMut::F64(pixels) => pixels.len(),
+            PixelsMut::F32(pixels) => pixels.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get(&self, idx: usize) -> Color {
+        match self {
+            PixelsMut::F64(pixels) => pixels[idx],
+            PixelsMut::F32(pixels) => from_f32(pixels[idx]),
+        }
+    }
+
+    pub fn set(&mut self, idx: usize, color: Color) {
+        match self {
+            PixelsMut::F64(pixels) => pixels[idx] = color,
+            PixelsMut::F32(pixels) => pixels[idx] = to_f32(color),
+        }
+    }
+
+    pub fn split_at_mut(self, mid: usize) -> (PixelsMut<'a>, PixelsMut<'a>) {
+        match self {
+            PixelsMut::F64(pixels) => {
+                let (a, b) = pixels.split_at_mut(mid);
+                (PixelsMut::F64(a), PixelsMut::F64(b))
+            }
+            PixelsMut::F32(pixels) => {
+                let (a, b) = pixels.split_at_mut(mid);
+                (PixelsMut::F32(a), PixelsMut::F32(b))
+            }
+        }
+    }
+
+    pub fn colors(&self) -> Cow<'_, [Color]> {
+        match self {
+            PixelsMut::F64(pixels) => Cow::Borrowed(pixels),
+            PixelsMut::F32(pixels) => Cow::Owned(pixels.iter().copied().map(from_f32).collect()),
+        }
+    }
+}
+
+// how far two images are apart, e.g. a render and a reference one
+#[derive(Debug)]
+pub struct DiffReport {
+    // the largest and the average difference of a color channel
+    pub max_error: f64,
+    pub mean_error: f64,
+    // in decibels, with colors clamped to 0..1 like they are saved. infinite
+    // for identical images
+    pub psnr: f64,
+    // structural similarity of the luminance, 1 for identical images
+    pub ssim: f64,
+    // each pixel's largest channel difference, scaled so that the largest one
+    // in the image is white
+    pub diff: Canvas,
+}
+
+impl DiffReport {
+    pub fn is_identical(&self) -> bool {
+        self.max_error == 0.0
+    }
+}
+
+// ssim compares windows this wide and high, half a window apart
+const SSIM_WINDOW: usize = 8;
+
+pub fn compare(a: &Canvas, b: &Canvas) -> DiffReport {
+    assert_eq!(
+        (a.width, a.height),
+        (b.width, b.height),
+        "only canvases of the same size can be compared"
+    );
+    let (a_pixels, b_pixels) = (a.rows(0..a.height), b.rows(0..b.height));
+
+    let mut errors = Vec::with_capacity(a_pixels.len());
+    let (mut error_sum, mut squared_sum) = (0.0, 0.0);
+    for (ca, cb) in a_pixels.iter().zip(b_pixels.iter()) {
+        let (da, db) = (clamped(*ca), clamped(*cb));
+        let channels = [
+            (ca.red - cb.red, da.red - db.red),
+            (ca.green - cb.green, da.green - db.green),
+            (ca.blue - cb.blue, da.blue - db.blue),
+        ];
+        let mut error = 0.0f64;
+        for (raw, displayed) in channels {
+            error = error.max(raw.abs());
+            error_sum += raw.abs();
+            squared_sum += displayed * displayed;
+        }
+        errors.push(error);
+    }
+    let max_error = errors.iter().copied().fold(0.0, f64::max);
+    let samples = (errors.len() * 3).max(1) as f64;
+    let mse = squared_sum / samples;
+    let psnr = if mse == 0.0 {
+        f64::INFINITY
+    } else {
+        10.0 * (1.0 / mse).log10()
+    };
+
+    let mut diff = Canvas::new(a.width, a.height);
+    if max_error > 0.0 {
+        for (idx, error) in errors.iter().enumerate() {
+            let shade = error / max_error;
+            diff.set_pixel(
+                idx % a.width,
+                idx / a.width,
+                Color::new(shade, shade, shade),
+            );
+        }
+    }
+
+    DiffReport {
+        max_error,
+        mean_error: error_sum / samples,
+        psnr,
+        ssim: ssim(&a_pixels, &b_pixels, a.width, a.height),
+        diff,
     }
 }
 
+fn ssim(a: &[Color], b: &[Color], width: usize, height: usize) -> f64 {
+    // the usual constants for values between 0 and 1
+    const C1: f64 = 0.01 * 0.01;
+    const C2: f64 = 0.03 * 0.03;
+
+    if width == 0 || height == 0 {
+        return 1.0;
+    }
+    let luma =
+        |pixels: &[Color]| -> Vec<f64> { pixels.iter().map(|c| luminance(&clamped(*c))).collect() };
+    let (la, lb) = (luma(a), luma(b));
+    let (window_w, window_h) = (SSIM_WINDOW.min(width), SSIM_WINDOW.min(height));
+
+    let (mut total, mut windows) = (0.0, 0);
+    for y0 in (0..=height - window_h).step_by((window_h / 2).max(1)) {
+        for x0 in (0..=width - window_w).step_by((window_w / 2).max(1)) {
+            let idx =
+                (y0..y0 + window_h).flat_map(|y| (x0..x0 + window_w).map(move |x| y * width + x));
+            let n = (window_w * window_h) as f64;
+            let (mean_a, mean_b) = (
+                idx.clone().map(|i| la[i]).sum::<f64>() / n,
+                idx.clone().map(|i| lb[i]).sum::<f64>() / n,
+            );
+            let (mut var_a, mut var_b, mut covar) = (0.0, 0.0, 0.0);
+            for i in idx {
+                let (da, db) = (la[i] - mean_a, lb[i] - mean_b);
+                var_a += da * da / n;
+                var_b += db * db / n;
+                covar += da * db / n;
+            }
+            total += ((2.0 * mean_a * mean_b + C1) * (2.0 * covar + C2))
+                / ((mean_a * mean_a + mean_b * mean_b + C1) * (var_a + var_b + C2));
+            windows += 1;
+        }
+    }
+    total / windows as f64
+}
+
+fn clamped(color: Color) -> Color {
+    Color::new(
+        color.red.clamp(0.0, 1.0),
+        color.green.clamp(0.0, 1.0),
+        color.blue.clamp(0.0, 1.0),
+    )
+}
+
+fn to_f32(color: Color) -> [f32; 3] {
+    [color.red as f32, color.green as f32, color.blue as f32]
+}
+
+fn from_f32([red, green, blue]: [f32; 3]) -> Color {
+    Color::new(red as f64, green as f64, blue as f64)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -61,8 +317,39 @@ mod tests {
         let c = Canvas::new(10, 20);
         assert_eq!(c.width, 10);
         assert_eq!(c.height, 20);
-        for pixel in c.pixels {
-            assert_eq!(pixel, Color::new(0.0, 0.0, 0.0));
+        assert_eq!(c.storage(), PixelStorage::F64);
+        for pixel in c.rows(0..20).iter() {
+            assert_eq!(*pixel, Color::new(0.0, 0.0, 0.0));
+        }
+    }
+
+    #[test]
+    fn compact_canvas_keeps_pixels_as_f32() {
+        let mut c = Canvas::with_storage(4, 3, PixelStorage::F32);
+        assert_eq!(c.storage(), PixelStorage::F32);
+        let color = Color::new(0.1, 0.5, 2.0);
+        c.set_pixel(1, 2, color);
+        let stored = c.get_pixel(1, 2);
+        assert_eq!(stored.red, 0.1f32 as f64);
+        assert!(stored.approx_eq(&color, 1e-6));
+        assert_eq!(c.rows(2..3)[1], stored);
+
+        c.map_pixels(|p| p * 2.0);
+        assert_eq!(c.get_pixel(1, 2), stored * 2.0);
+        assert_eq!(c.get_pixel(0, 0), Color::black());
+    }
+
+    #[test]
+    fn split_pixels_mut() {
+        for storage in [PixelStorage::F64, PixelStorage::F32] {
+            let mut c = Canvas::with_storage(2, 3, storage);
+            let (mut top, mut rest) = c.rows_mut(0..3).split_at_mut(2);
+            assert_eq!((top.len(), rest.len()), (2, 4));
+            top.set(1, Color::white());
+            rest.set(3, Color::new(0.5, 0.5, 0.5));
+            assert_eq!(rest.colors()[3], rest.get(3));
+            assert_eq!(c.get_pixel(1, 0), Color::white());
+            assert_eq!(c.get_pixel(1, 2), Color::new(0.5, 0.5, 0.5));
         }
     }
 
@@ -96,4 +383,81 @@ mod tests {
         c.set_pixel(2, 3, red);
         assert_eq!(c.get_pixel(2, 3), red);
     }
+
+    #[test]
+    fn get_rows_as_slice() {
+        let mut c = Canvas::new(4, 3);
+        let red = Color::new(1.0, 0.0, 0.0);
+        c.set_pixel(0, 1, red);
+        c.set_pixel(3, 2, red);
+
+        let rows = c.rows(1..3);
+        assert_eq!(rows.len(), 8);
+        assert_eq!(rows[0], red);
+        assert_eq!(rows[7], red);
+        assert_eq!(rows[1], Color::black());
+
+        assert!(c.rows(2..2).is_empty());
+    }
+
+    #[test]
+    #[should_panic]
+    fn bad_rows_range() {
+        let c = Canvas::new(4, 3);
+        c.rows(1..4);
+    }
+
+    fn gradient(width: usize, height: usize) -> Canvas {
+        let mut c = Canvas::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                let v = (x + y) as f64 / (width + height) as f64;
+                c.set_pixel(x, y, Color::new(v, 1.0 - v, 0.5));
+            }
+        }
+        c
+    }
+
+    #[test]
+    fn compare_identical_canvases() {
+        let a = gradient(20, 10);
+        let report = compare(&a, &gradient(20, 10));
+        assert!(report.is_identical());
+        assert_eq!(report.mean_error, 0.0);
+        assert_eq!(report.psnr, f64::INFINITY);
+        assert!(crate::equal(report.ssim, 1.0));
+        assert!(report.diff.rows(0..10).iter().all(|&p| p == Color::black()));
+    }
+
+    #[test]
+    fn compare_different_canvases() {
+        let (a, mut b) = (Canvas::new(16, 16), Canvas::new(16, 16));
+        b.map_pixels(|_| Color::new(0.1, 0.1, 0.1));
+        let report = compare(&a, &b);
+        assert!(crate::equal(report.max_error, 0.1));
+        assert!(crate::equal(report.mean_error, 0.1));
+        assert!(crate::equal(report.psnr, 20.0));
+
+        // a few pixels off hurt less than a shifted image
+        let a = gradient(32, 32);
+        let mut touched = gradient(32, 32);
+        touched.set_pixel(3, 4, Color::new(0.5, 0.0, 0.5));
+        touched.set_pixel(20, 9, Color::new(1.0, 2.0, 0.5));
+        let mut shifted = gradient(32, 32);
+        shifted.map_pixels(|p| Color::new(p.green, p.red, p.blue));
+        let (small, big) = (compare(&a, &touched), compare(&a, &shifted));
+        assert!(crate::equal(small.max_error, 1.0 + 29.0 / 64.0));
+        assert!(small.psnr > big.psnr);
+        assert!(small.ssim > big.ssim && small.ssim < 1.0);
+
+        assert_eq!(small.diff.get_pixel(20, 9), Color::white());
+        assert!(small.diff.get_pixel(3, 4).red > 0.0);
+        assert_eq!(small.diff.get_pixel(4, 4), Color::black());
+    }
+
+    #[test]
+    #[should_panic]
+    fn compare_canvases_of_different_sizes() {
+        compare(&Canvas::new(4, 3), &Canvas::new(3, 4));
+    }
 }
@@ -1,8 +1,11 @@
 use std::{
+    fmt,
     iter::Sum,
     ops::{Add, Mul, Sub},
 };
 
+use crate::{approx::relative_eq, DISPLAY_PRECISION, EPSILON};
+
 #[derive(Debug, Clone, Copy)]
 pub struct Color {
     pub red: f64,
@@ -23,6 +26,40 @@ impl Color {
         Self::new(1.0, 1.0, 1.0)
     }
 
+    pub fn approx_eq(&self, other: &Self, epsilon: f64) -> bool {
+        relative_eq(self.red, other.red, epsilon)
+            && relative_eq(self.green, other.green, epsilon)
+            && relative_eq(self.blue, other.blue, epsilon)
+    }
+
+    pub fn is_finite(&self) -> bool {
+        self.red.is_finite() && self.green.is_finite() && self.blue.is_finite()
+    }
+
+    pub fn is_nan(&self) -> bool {
+        self.red.is_nan() || self.green.is_nan() || self.blue.is_nan()
+    }
+
+    // panics in debug builds when a NaN or infinity sneaks into a color
+    pub fn debug_assert_finite(self) -> Self {
+        debug_assert!(self.is_finite(), "non-finite color: {:?}", self);
+        self
+    }
+
+    pub fn max_component(&self) -> f64 {
+        self.red.max(self.green).max(self.blue)
+    }
+
+    pub fn abs(&self) -> Self {
+        Self::new(self.red.abs(), self.green.abs(), self.blue.abs())
+    }
+
+    // clamps each component to [0, 1], NaN becomes 0
+    pub fn saturate(&self) -> Self {
+        let clamp = |c: f64| if c.is_nan() { 0.0 } else { c.clamp(0.0, 1.0) };
+        Self::new(clamp(self.red), clamp(self.green), clamp(self.blue))
+    }
+
     pub fn average(colors: &[Color]) -> Color {
         let mut avg_color = Color::black();
         for &color in colors {
@@ -35,9 +72,18 @@ impl Color {
 
 impl PartialEq for Color {
     fn eq(&self, other: &Self) -> bool {
-        crate::equal(self.red, other.red)
-            && crate::equal(self.green, other.green)
-            && crate::equal(self.blue, other.blue)
+        self.approx_eq(other, EPSILON)
+    }
+}
+
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let p = f.precision().unwrap_or(DISPLAY_PRECISION);
+        write!(
+            f,
+            "color({:.*}, {:.*}, {:.*})",
+            p, self.red, p, self.green, p, self.blue
+        )
     }
 }
 
@@ -98,7 +144,7 @@ impl Sum<Self> for Color {
     where
         I: Iterator<Item = Self>,
     {
-        iter.fold(Self::new(0.0, 0.0, 0.0), |a, b| a + b)
+        iter.fold(Self::new(0.0, 0.0, 0.0), |a, b| a + b.debug_assert_finite())
     }
 }
 
@@ -147,4 +193,45 @@ mod tests {
         assert_eq!(c1 * c2, expected);
         assert_eq!(c2 * c1, expected);
     }
+
+    #[test]
+    fn display_color() {
+        let c = Color::new(0.25, 1.0, 0.0);
+        assert_eq!(c.to_string(), "color(0.2500, 1.0000, 0.0000)");
+        assert!(c.approx_eq(&Color::new(0.3, 1.0, 0.0), 0.1));
+        assert!(!c.approx_eq(&Color::new(0.3, 1.0, 0.0), 0.01));
+    }
+
+    #[test]
+    fn color_is_finite() {
+        assert!(Color::new(0.1, 2.0, -3.0).is_finite());
+        assert!(!Color::new(f64::NAN, 0.0, 0.0).is_finite());
+        assert!(!Color::new(0.0, f64::INFINITY, 0.0).is_finite());
+        assert!(Color::new(0.0, 0.0, f64::NAN).is_nan());
+        assert!(!Color::new(0.0, f64::INFINITY, 0.0).is_nan());
+    }
+
+    #[test]
+    fn color_max_component_and_abs() {
+        let c = Color::new(-2.0, 0.5, 1.5);
+        assert!(crate::equal(c.max_component(), 1.5));
+        assert_eq!(c.abs(), Color::new(2.0, 0.5, 1.5));
+    }
+
+    #[test]
+    fn saturate_color() {
+        let c = Color::new(-0.5, 0.4, 1.7).saturate();
+        assert_eq!(c, Color::new(0.0, 0.4, 1.0));
+        let c = Color::new(f64::NAN, 0.0, f64::INFINITY).saturate();
+        assert_eq!(c, Color::new(0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic]
+    fn summing_nan_colors_panics_in_debug() {
+        let _: Color = vec![Color::white(), Color::new(f64::NAN, 0.0, 0.0)]
+            .into_iter()
+            .sum();
+    }
 }
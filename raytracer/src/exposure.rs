@@ -0,0 +1,105 @@
+use crate::{canvas::Canvas, color::Color};
+
+// scales a finished render so the luminance at the given percentile of its
+// pixels ends up at the target, middle gray by default
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AutoExposure {
+    percentile: f64,
+    target: f64,
+}
+
+impl Default for AutoExposure {
+    fn default() -> Self {
+        Self::new(0.5, 0.18)
+    }
+}
+
+impl AutoExposure {
+    pub fn new(percentile: f64, target: f64) -> Self {
+        assert!((0.0..=1.0).contains(&percentile));
+        assert!(target > 0.0);
+        Self { percentile, target }
+    }
+
+    pub fn percentile(&self) -> f64 {
+        self.percentile
+    }
+
+    pub fn target(&self) -> f64 {
+        self.target
+    }
+
+    // the factor to multiply every pixel by, 1 for an image that is black at
+    // the percentile
+    pub fn exposure(&self, canvas: &Canvas) -> f64 {
+        let mut luminances: Vec<f64> = canvas
+            .rows(0..canvas.height())
+            .iter()
+            .map(luminance)
+            .filter(|l| l.is_finite())
+            .collect();
+        if luminances.is_empty() {
+            return 1.0;
+        }
+        luminances.sort_by(f64::total_cmp);
+        let idx = ((luminances.len() - 1) as f64 * self.percentile).round() as usize;
+        let at_percentile = luminances[idx];
+        if at_percentile > 0.0 {
+            self.target / at_percentile
+        } else {
+            1.0
+        }
+    }
+
+    // returns the exposure it applied
+    pub fn apply(&self, canvas: &mut Canvas) -> f64 {
+        let exposure = self.exposure(canvas);
+        canvas.map_pixels(|pixel| pixel * exposure);
+        exposure
+    }
+}
+
+// relative luminance with the rec. 709 weights
+pub fn luminance(color: &Color) -> f64 {
+    0.2126 * color.red + 0.7152 * color.green + 0.0722 * color.blue
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gradient() -> Canvas {
+        // luminances 0.1, 0.2, .., 1.0
+        let mut c = Canvas::new(5, 2);
+        for i in 0..10 {
+            let l = (i + 1) as f64 / 10.0;
+            c.set_pixel(i % 5, i / 5, Color::new(l, l, l));
+        }
+        c
+    }
+
+    #[test]
+    fn exposure_puts_percentile_at_target() {
+        let c = gradient();
+        // 4.5 rounds to index 5 of the sorted luminances, 0.6
+        assert!((AutoExposure::new(0.5, 0.18).exposure(&c) - 0.3).abs() < 1e-9);
+        assert!((AutoExposure::new(1.0, 0.5).exposure(&c) - 0.5).abs() < 1e-9);
+        assert!((AutoExposure::new(0.0, 0.2).exposure(&c) - 2.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn apply_scales_every_pixel() {
+        let mut c = gradient();
+        let exposure = AutoExposure::new(1.0, 0.5).apply(&mut c);
+        assert!((exposure - 0.5).abs() < 1e-9);
+        assert_eq!(c.get_pixel(4, 1), Color::new(0.5, 0.5, 0.5));
+        assert_eq!(c.get_pixel(0, 0), Color::new(0.05, 0.05, 0.05));
+    }
+
+    #[test]
+    fn black_image_is_left_alone() {
+        let mut c = Canvas::new(3, 3);
+        assert_eq!(AutoExposure::default().apply(&mut c), 1.0);
+        assert_eq!(c.get_pixel(1, 1), Color::black());
+    }
+}
@@ -0,0 +1,81 @@
+use crate::color::Color;
+
+// exponential fog between start and end distances along the ray.
+// past the end distance the fog stops getting thicker.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fog {
+    color: Color,
+    density: f64,
+    start: f64,
+    end: f64,
+}
+
+impl Fog {
+    pub fn new(color: Color, density: f64) -> Self {
+        Self {
+            color,
+            density,
+            start: 0.0,
+            end: f64::INFINITY,
+        }
+    }
+
+    pub fn range(&mut self, start: f64, end: f64) {
+        self.start = start;
+        self.end = end;
+    }
+
+    pub fn color(&self) -> Color {
+        self.color
+    }
+
+    pub fn density(&self) -> f64 {
+        self.density
+    }
+
+    // how much of the fog color replaces radiance travelling the given distance
+    pub fn factor(&self, distance: f64) -> f64 {
+        let distance = distance.min(self.end) - self.start;
+        if distance <= 0.0 {
+            return 0.0;
+        }
+        1.0 - (-self.density * distance).exp()
+    }
+
+    pub fn apply(&self, color: Color, distance: f64) -> Color {
+        let f = self.factor(distance);
+        color * (1.0 - f) + self.color * f
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::equal;
+
+    use super::*;
+
+    #[test]
+    fn no_fog_before_start() {
+        let mut fog = Fog::new(Color::white(), 0.5);
+        fog.range(10.0, 20.0);
+        assert!(equal(fog.factor(5.0), 0.0));
+        assert_eq!(fog.apply(Color::black(), 10.0), Color::black());
+    }
+
+    #[test]
+    fn fog_thickens_with_distance() {
+        let fog = Fog::new(Color::white(), 0.1);
+        assert!(equal(fog.factor(10.0), 1.0 - (-1.0f64).exp()));
+        assert!(fog.factor(20.0) > fog.factor(10.0));
+        assert!(equal(fog.factor(f64::INFINITY), 1.0));
+    }
+
+    #[test]
+    fn fog_stops_at_end_distance() {
+        let mut fog = Fog::new(Color::white(), 0.1);
+        fog.range(0.0, 10.0);
+        assert!(equal(fog.factor(50.0), fog.factor(10.0)));
+        let c = fog.apply(Color::black(), 50.0);
+        assert_eq!(c, Color::white() * fog.factor(10.0));
+    }
+}
@@ -0,0 +1,98 @@
+use crate::{
+    geometry::intersection::{blocks_light, Intersection},
+    point::Point,
+    ray::Ray,
+};
+
+// shadow rays leaving one point for several targets, traced through the world
+// together so that every group is visited once for all of them instead of once
+// per ray, and rays stop being tested as soon as something blocks them
+#[derive(Debug, Clone)]
+pub struct RayBundle {
+    origin: Point,
+    rays: Vec<Ray>,
+    // how far each ray's target is
+    distances: Vec<f64>,
+    blocked: Vec<bool>,
+}
+
+impl RayBundle {
+    pub fn towards(origin: Point, targets: &[Point]) -> Self {
+        let (rays, distances) = targets
+            .iter()
+            .map(|&target| {
+                let v = target - origin;
+                (Ray::new(origin, v.normalize()), v.magnitude())
+            })
+            .unzip();
+        Self {
+            origin,
+            rays,
+            distances,
+            blocked: vec![false; targets.len()],
+        }
+    }
+
+    pub fn origin(&self) -> Point {
+        self.origin
+    }
+
+    pub fn len(&self) -> usize {
+        self.rays.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rays.is_empty()
+    }
+
+    pub fn ray(&self, idx: usize) -> &Ray {
+        &self.rays[idx]
+    }
+
+    pub fn is_blocked(&self, idx: usize) -> bool {
+        self.blocked[idx]
+    }
+
+    // the rays nothing has blocked yet
+    pub fn unblocked(&self) -> Vec<usize> {
+        (0..self.len()).filter(|&i| !self.blocked[i]).collect()
+    }
+
+    // blocks the ray if one of xs casts a shadow before its target
+    pub fn block_with(&mut self, idx: usize, xs: &[Intersection]) {
+        if blocks_light(xs, &self.rays[idx], self.distances[idx]) {
+            self.blocked[idx] = true;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        geometry::{shape::Sphere, Shape},
+        transform::translation,
+        vector::Vector,
+    };
+
+    use super::*;
+
+    #[test]
+    fn bundle_rays_stop_at_their_targets() {
+        let mut bundle = RayBundle::towards(
+            Point::origin(),
+            &[Point::new(0, 0, 10), Point::new(0, 0, 2)],
+        );
+        assert_eq!(bundle.len(), 2);
+        assert_eq!(bundle.ray(1).direction(), Vector::new(0, 0, 1));
+
+        let mut s = Sphere::default();
+        s.set_transform(translation(0, 0, 5));
+        for idx in bundle.unblocked() {
+            let xs = s.intersect(bundle.ray(idx));
+            bundle.block_with(idx, &xs);
+        }
+        assert!(bundle.is_blocked(0));
+        assert!(!bundle.is_blocked(1));
+        assert_eq!(bundle.unblocked(), vec![1]);
+    }
+}
@@ -1,11 +1,13 @@
 use crate::{
     point::Point,
-    ray::Ray,
+    ray::{Footprint, Ray, RayDifferentials},
     vector::{dot, Vector},
     EPSILON,
 };
 
-use super::Shape;
+use std::ptr;
+
+use super::{shape::Instance, RayKind, Shape};
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Intersection<'a> {
@@ -13,15 +15,34 @@ pub struct Intersection<'a> {
     object: &'a dyn Shape,
     u: Option<f64>,
     v: Option<f64>,
+    // the instance object was hit through, whose transform takes object's
+    // space to the world's
+    frame: Option<&'a dyn Shape>,
 }
 
 impl<'a> Intersection<'a> {
+    fn is_preferred_to(&self, other: &Intersection) -> bool {
+        let t = self.biased_t();
+        let other_t = other.biased_t();
+        if (t - other_t).abs() < EPSILON {
+            // within epsilon t can't be trusted, so the order only depends on
+            // the shapes, not on which of them the rounding favoured
+            match self.object.priority().cmp(&other.object.priority()) {
+                std::cmp::Ordering::Equal => self.object.id() < other.object.id(),
+                ordering => ordering.is_gt(),
+            }
+        } else {
+            t < other_t
+        }
+    }
+
     pub fn new(t: f64, object: &'a dyn Shape) -> Self {
         Self {
             t,
             object,
             u: None,
             v: None,
+            frame: None,
         }
     }
 
@@ -31,6 +52,7 @@ impl<'a> Intersection<'a> {
             object,
             u: Some(u),
             v: Some(v),
+            frame: None,
         }
     }
 
@@ -38,10 +60,76 @@ impl<'a> Intersection<'a> {
         self.t
     }
 
+    // t once the shape's ray bias is added, what hits are ranked by
+    pub fn biased_t(&self) -> f64 {
+        self.t + self.object.ray_bias()
+    }
+
     pub fn object(&self) -> &dyn Shape {
         self.object
     }
 
+    pub fn frame(&self) -> Option<&dyn Shape> {
+        self.frame
+    }
+
+    // the same hit, made on a shape inside frame
+    pub fn through(mut self, frame: &'a dyn Shape) -> Self {
+        self.frame = Some(frame);
+        self
+    }
+
+    // the shape whose material shades the hit: the instance it was made
+    // through when that has a material of its own, the object otherwise
+    pub fn material_shape(&self) -> &'a dyn Shape {
+        match self.frame {
+            Some(frame)
+                if frame
+                    .as_any()
+                    .downcast_ref::<Instance>()
+                    .is_some_and(Instance::has_material) =>
+            {
+                frame
+            }
+            _ => self.object,
+        }
+    }
+
+    // rays see the hit when they see its object and the instance it was made
+    // through
+    pub fn is_visible_to(&self, kind: RayKind) -> bool {
+        self.object.visibility().is_visible_to(kind)
+            && self
+                .frame
+                .is_none_or(|frame| frame.visibility().is_visible_to(kind))
+    }
+
+    // whether the hit is on shape or one of the shapes inside it. hits through
+    // an instance are on the instance, not on the shape it shares
+    pub fn is_within(&self, shape: &dyn Shape) -> bool {
+        shape.includes(self.frame.unwrap_or(self.object))
+    }
+
+    fn is_on_same_object(&self, other: &Intersection) -> bool {
+        ptr::eq(self.object.get_base(), other.object.get_base()) && self.frame == other.frame
+    }
+
+    // a world point in the space the object's transform starts from
+    fn in_object_space(&self, point: Point) -> Point {
+        match self.frame {
+            Some(frame) => &frame.get_base().transform_inverse * point,
+            None => point,
+        }
+    }
+
+    // a normal at a point of to_object_space back in the world
+    fn normal_to_world(&self, normal: Vector) -> Vector {
+        match self.frame {
+            Some(frame) => (&frame.get_base().transform_inverse_transpose * normal).normalize(),
+            None => normal,
+        }
+    }
+
     pub fn u(&self) -> Option<f64> {
         self.u
     }
@@ -50,17 +138,64 @@ impl<'a> Intersection<'a> {
         self.v
     }
 
-    pub fn prepare_computations(&self, ray: &Ray, xs: &[Intersection]) -> Computations {
+    // the intersection where the ray leaves this hit's object again, if this
+    // hit is where the ray enters it. xs must be sorted.
+    pub fn exit<'b>(&self, xs: &'b [Intersection<'a>]) -> Option<&'b Intersection<'a>> {
+        let is_same_object = |i: &Intersection| i.is_on_same_object(self);
+        let idx = xs.iter().position(|i| i == self)?;
+        let crossings_before = xs[..idx].iter().filter(|&i| is_same_object(i)).count();
+        if crossings_before % 2 == 1 {
+            return None;
+        }
+        xs[idx + 1..].iter().find(|&i| is_same_object(i))
+    }
+
+    // whether the material's cutout removes the surface where ray hits it
+    pub fn is_cut_out(&self, ray: &Ray) -> bool {
+        match &self.material_shape().material().cutout {
+            Some(cutout) => {
+                let point = self.in_object_space(ray.position(self.t));
+                cutout.removes(self.object, point, self.object.uv_at(point, self))
+            }
+            None => false,
+        }
+    }
+
+    // whether the surface can stop a shadow ray
+    pub fn casts_shadow(&self, ray: &Ray) -> bool {
+        self.t >= 0.0 && self.is_visible_to(RayKind::Shadow) && !self.is_cut_out(ray)
+    }
+
+    // distance the ray travels through the object between this hit and its exit
+    pub fn thickness(&self, ray: &Ray, xs: &[Intersection<'a>]) -> Option<f64> {
+        self.exit(xs)
+            .map(|exit| (exit.t - self.t) * ray.direction().magnitude())
+    }
+
+    pub fn prepare_computations(&self, ray: &Ray, xs: &[Intersection]) -> Computations<'_> {
         let point = ray.position(self.t);
+        let object_point = self.in_object_space(point);
         let eyev = -ray.direction();
-        let mut normalv = self.object.normal_at(point, self);
+        let normal = self.object.normal_at(object_point, self);
+        let pixel_width = ray.pixel_width_at(self.t);
+        // shading uses the displaced normal, offsetting points the geometric one
+        let shading_normal = self.material_shape().material().shading_normal(
+            self.object,
+            &object_point,
+            normal,
+            pixel_width,
+        );
+        let mut normalv = self.normal_to_world(normal);
+        let mut shading_normalv = self.normal_to_world(shading_normal);
         let mut inside = false;
         if dot(normalv, eyev) < 0.0 {
             inside = true;
             normalv = -normalv;
+            shading_normalv = -shading_normalv;
         }
 
-        let mut containers: Vec<&dyn Shape> = vec![];
+        let epsilon = self.object.epsilon();
+        let mut containers: Vec<&Intersection> = vec![];
         let mut n1 = -1.0;
         let mut n2 = -1.0;
         for i in xs {
@@ -68,39 +203,47 @@ impl<'a> Intersection<'a> {
                 if containers.is_empty() {
                     n1 = 1.0;
                 } else {
-                    n1 = containers.last().unwrap().material().refractive_index;
+                    n1 = containers.last().unwrap().material_shape().material().refractive_index;
                 }
             }
 
-            if containers.contains(&i.object) {
-                let idx = containers.iter().position(|&el| el == i.object).unwrap();
-                containers.remove(idx);
-            } else {
-                containers.push(i.object);
+            match containers.iter().position(|el| el.is_on_same_object(i)) {
+                Some(idx) => {
+                    containers.remove(idx);
+                }
+                None => containers.push(i),
             }
 
             if i == self {
                 if containers.is_empty() {
                     n2 = 1.0;
                 } else {
-                    n2 = containers.last().unwrap().material().refractive_index;
+                    n2 = containers.last().unwrap().material_shape().material().refractive_index;
                 }
                 break;
             }
         }
 
+        let over_point = point + normalv * epsilon;
         Computations {
             object: self.object,
+            material_shape: self.material_shape(),
             t: self.t,
             point,
-            over_point: point + normalv * EPSILON,
-            under_point: point - normalv * EPSILON,
+            over_point,
+            surface_point: self.in_object_space(over_point),
+            under_point: point - normalv * epsilon,
             eyev,
-            normalv,
+            normalv: shading_normalv,
             inside,
-            reflectv: ray.direction().reflect(normalv),
+            reflectv: ray.direction().reflect(shading_normalv),
             n1,
             n2,
+            thickness: self.thickness(ray, xs),
+            footprint: ray.footprint(point, normalv),
+            pixel_width,
+            differentials: ray.differentials().copied(),
+            uv: self.object.uv_at(object_point, self),
         }
     }
 }
@@ -110,18 +253,62 @@ pub fn intersections<'a>(xs: &[Intersection<'a>]) -> Vec<Intersection<'a>> {
 
     v.extend_from_slice(xs);
 
-    v.sort_unstable_by(|a, b| a.t.partial_cmp(&b.t).unwrap());
+    sort_intersections(&mut v);
 
     v
 }
 
+// sorts xs by t in place. degenerate transforms can give NaN distances, those
+// sort after everything else and get dropped so one broken shape can't take
+// the whole render down
+pub fn sort_intersections(xs: &mut Vec<Intersection>) {
+    xs.sort_by(|a, b| match (a.t.is_nan(), b.t.is_nan()) {
+        (false, false) => a.t.total_cmp(&b.t),
+        (a_nan, b_nan) => a_nan.cmp(&b_nan),
+    });
+    let len = xs.iter().position(|i| i.t.is_nan()).unwrap_or(xs.len());
+    xs.truncate(len);
+}
+
 pub fn hit<'a>(xs: &'a [Intersection<'a>]) -> Option<&'a Intersection<'a>> {
-    xs.iter().find(|&&i| i.t() >= 0.0)
+    hit_in_range(xs, 0.0, f64::INFINITY)
+}
+
+// the closest intersection once each shape's ray bias is added. coincident
+// surfaces go to the higher priority shape, then to whichever was created first.
+// hits on the same shape through different instances keep the smaller t.
+pub fn hit_in_range<'a>(
+    xs: &'a [Intersection<'a>],
+    min: f64,
+    max: f64,
+) -> Option<&'a Intersection<'a>> {
+    preferred_hit(xs, min, max, |_| true)
+}
+
+// like hit, but only shapes that cast shadows can block the light
+pub fn shadow_hit<'a>(xs: &'a [Intersection<'a>], ray: &Ray) -> Option<&'a Intersection<'a>> {
+    preferred_hit(xs, 0.0, f64::INFINITY, |i| i.casts_shadow(ray))
 }
 
-pub fn shadow_hit<'a>(xs: &'a [Intersection<'a>]) -> Option<&'a Intersection<'a>> {
+// whether xs keep ray from reaching a light distance away
+pub fn blocks_light(xs: &[Intersection], ray: &Ray, distance: f64) -> bool {
+    shadow_hit(xs, ray).is_some_and(|hit| hit.biased_t() < distance)
+}
+
+// xs is sorted, so everything after the first t past max can be skipped
+fn preferred_hit<'a>(
+    xs: &'a [Intersection<'a>],
+    min: f64,
+    max: f64,
+    accept: impl Fn(&Intersection) -> bool,
+) -> Option<&'a Intersection<'a>> {
     xs.iter()
-        .find(|&&i| i.t() >= 0.0 && i.object().has_shadow())
+        .take_while(|i| i.t() <= max)
+        .filter(|&i| i.t() >= min && accept(i))
+        .fold(None, |best, i| match best {
+            Some(best) if !i.is_preferred_to(best) => Some(best),
+            _ => Some(i),
+        })
 }
 
 // TODO: figure out how to make this work
@@ -131,9 +318,14 @@ pub fn shadow_hit<'a>(xs: &'a [Intersection<'a>]) -> Option<&'a Intersection<'a>
 
 pub struct Computations<'a> {
     pub object: &'a dyn Shape,
+    // what the material is looked up on, see Intersection::material_shape
+    pub material_shape: &'a dyn Shape,
     pub t: f64,
     pub point: Point,
     pub over_point: Point,
+    // over_point where patterns look it up, in the space the object's transform
+    // starts from
+    pub surface_point: Point,
     pub under_point: Point,
     pub eyev: Vector,
     pub normalv: Vector,
@@ -141,6 +333,17 @@ pub struct Computations<'a> {
     pub reflectv: Vector,
     pub n1: f64,
     pub n2: f64,
+    // distance to where the ray leaves the object, if it enters it here
+    pub thickness: Option<f64>,
+    // how much of the surface the pixel covers, for rays with differentials
+    pub footprint: Option<Footprint>,
+    // how wide a pixel is at the hit, for picking blur widths and dropping
+    // detail too small to see. cheaper than the footprint but ignores the slope
+    pub pixel_width: Option<f64>,
+    // the differentials of the ray that hit, reflected ray differentials start from them
+    pub differentials: Option<RayDifferentials>,
+    // texture coordinates of the hit, for shapes that have a mapping
+    pub uv: Option<(f64, f64)>,
 }
 
 impl<'a> Computations<'a> {
@@ -160,9 +363,26 @@ impl<'a> Computations<'a> {
         let r0 = ((self.n1 - self.n2) / (self.n1 + self.n2)).powi(2);
         r0 + (1.0 - r0) * (1.0 - cos).powi(5)
     }
+
+    // the reflection of the ray, with differentials that leave from the corners
+    // of the footprint and bounce off the same normal. curvature is ignored, so
+    // footprints on curved mirrors come out too small
+    pub fn reflected_ray(&self) -> Ray {
+        let ray = Ray::new(self.over_point, self.reflectv);
+        match (self.differentials, self.footprint) {
+            (Some(d), Some(f)) => ray.with_differentials(RayDifferentials {
+                rx_origin: self.over_point + f.dpdx,
+                rx_direction: d.rx_direction.reflect(self.normalv),
+                ry_origin: self.over_point + f.dpdy,
+                ry_direction: d.ry_direction.reflect(self.normalv),
+            }),
+            _ => ray,
+        }
+    }
 }
 
 #[cfg(test)]
+#[allow(clippy::bool_assert_comparison)]
 mod tests {
     use crate::{
         equal,
@@ -191,6 +411,24 @@ mod tests {
         assert!(crate::equal(xs[1].t(), 2.0));
     }
 
+    #[test]
+    fn aggregating_intersections_drops_nan() {
+        let s = Sphere::default();
+        let xs = intersections(&[
+            Intersection::new(f64::NAN, &s),
+            Intersection::new(2.0, &s),
+            Intersection::new(-f64::NAN, &s),
+            Intersection::new(-1.0, &s),
+            Intersection::new(f64::INFINITY, &s),
+        ]);
+        let ts: Vec<f64> = xs.iter().map(|i| i.t()).collect();
+        assert_eq!(ts, vec![-1.0, 2.0, f64::INFINITY]);
+
+        let mut xs = vec![Intersection::new(f64::NAN, &s)];
+        sort_intersections(&mut xs);
+        assert!(xs.is_empty());
+    }
+
     #[test]
     fn hit_all_intersections_positive_t() {
         let s = Sphere::default();
@@ -201,6 +439,49 @@ mod tests {
         assert_eq!(*i.unwrap(), i1);
     }
 
+    #[test]
+    fn hit_prefers_higher_priority_on_coincident_surfaces() {
+        let s1 = Sphere::default();
+        let mut s2 = Sphere::default();
+        let i1 = Intersection::new(1.0, &s1);
+        let i2 = Intersection::new(1.0 + EPSILON / 2.0, &s2);
+        let xs = intersections(&[i2, i1]);
+        assert!(ptr::eq(hit(&xs).unwrap().object(), &s1 as &dyn Shape));
+
+        s2.set_priority(1);
+        let i2 = Intersection::new(1.0 + EPSILON / 2.0, &s2);
+        let xs = intersections(&[i1, i2]);
+        assert!(ptr::eq(hit(&xs).unwrap().object(), &s2 as &dyn Shape));
+    }
+
+    #[test]
+    fn hit_prefers_the_older_shape_on_coincident_surfaces() {
+        let s1 = Sphere::default();
+        let s2 = Sphere::default();
+        let i1 = Intersection::new(1.0 + EPSILON / 2.0, &s1);
+        let i2 = Intersection::new(1.0, &s2);
+        for xs in [intersections(&[i1, i2]), intersections(&[i2, i1])] {
+            assert!(ptr::eq(hit(&xs).unwrap().object(), &s1 as &dyn Shape));
+            assert!(ptr::eq(
+                shadow_hit(&xs, &Ray::new(Point::new(0, 0, -5), Vector::new(0, 0, 1)))
+                    .unwrap()
+                    .object(),
+                &s1 as &dyn Shape
+            ));
+        }
+    }
+
+    #[test]
+    fn hit_applies_ray_bias() {
+        let mut s1 = Sphere::default();
+        s1.set_ray_bias(0.5);
+        let s2 = Sphere::default();
+        let i1 = Intersection::new(1.0, &s1);
+        let i2 = Intersection::new(1.2, &s2);
+        let xs = intersections(&[i1, i2]);
+        assert_eq!(hit(&xs).unwrap().t(), 1.2);
+    }
+
     #[test]
     fn hit_some_intersections_negative_t() {
         let s = Sphere::default();
@@ -279,6 +560,70 @@ mod tests {
         assert!(comps.point.z > comps.over_point.z);
     }
 
+    #[test]
+    fn displaced_normal_is_only_used_for_shading() {
+        use crate::material::{Displacement, Material};
+
+        let r = Ray::new(Point::new(0.1, 0.2, -5.0), Vector::new(0, 0, 1));
+        let mut shape = Sphere::default();
+        let mut material = Material::default();
+        material.displacement = Some(Displacement {
+            amplitude: 0.3,
+            frequency: 5.0,
+        });
+        shape.set_material(material);
+        let xs = shape.intersect(&r);
+        let comps = xs[0].prepare_computations(&r, &xs);
+
+        let geometric = shape.normal_at(comps.point, &xs[0]);
+        assert!(comps.normalv != geometric);
+        assert_eq!(comps.over_point, comps.point + geometric * EPSILON);
+        assert_eq!(comps.reflectv, r.direction().reflect(comps.normalv));
+    }
+
+    #[test]
+    fn precompute_footprint_and_reflected_differentials() {
+        let shape = Plane::default();
+        let origin = Point::new(0, 1, 0);
+        let r = Ray::new(origin, Vector::new(0, -1, 0)).with_differentials(RayDifferentials {
+            rx_origin: origin,
+            rx_direction: Vector::new(0.1, -1.0, 0.0),
+            ry_origin: origin,
+            ry_direction: Vector::new(0.0, -1.0, 0.2),
+        });
+        let i = Intersection::new(1.0, &shape);
+        let comps = i.prepare_computations(&r, &[i]);
+        let footprint = comps.footprint.unwrap();
+        assert_eq!(footprint.dpdx, Vector::new(0.1, 0.0, 0.0));
+        assert_eq!(footprint.dpdy, Vector::new(0.0, 0.0, 0.2));
+        assert!(equal(comps.pixel_width.unwrap(), 0.2));
+
+        let reflected = comps.reflected_ray();
+        assert_eq!(reflected.direction(), Vector::new(0, 1, 0));
+        let d = reflected.differentials().unwrap();
+        assert_eq!(d.rx_origin, comps.over_point + Vector::new(0.1, 0.0, 0.0));
+        assert_eq!(d.rx_direction, Vector::new(0.1, 1.0, 0.0));
+        assert_eq!(d.ry_direction, Vector::new(0.0, 1.0, 0.2));
+
+        let plain = Ray::new(origin, Vector::new(0, -1, 0));
+        let comps = i.prepare_computations(&plain, &[i]);
+        assert_eq!(comps.footprint, None);
+        assert_eq!(comps.pixel_width, None);
+        assert_eq!(comps.reflected_ray().differentials(), None);
+    }
+
+    #[test]
+    fn hit_offsets_scale_with_shape() {
+        let mut shape = Sphere::default();
+        shape.set_transform(scaling(1000, 1000, 1000));
+        let r = Ray::new(Point::new(0, 0, -5000), Vector::new(0, 0, 1));
+        let i = Intersection::new(4000.0, &shape);
+        let comps = i.prepare_computations(&r, &[i]);
+        let offset = comps.point.z - comps.over_point.z;
+        assert!(equal(offset, shape.epsilon()));
+        assert!(offset > 10.0 * EPSILON);
+    }
+
     #[test]
     fn precompute_reflection_vector() {
         let shape = Plane::default();
@@ -298,15 +643,15 @@ mod tests {
     fn finding_n1_n2_at_various_intersections() {
         let mut a = Sphere::glass();
         a.set_transform(scaling(2, 2, 2));
-        a.get_base_mut().material.refractive_index = 1.5;
+        a.material_mut().refractive_index = 1.5;
 
         let mut b = Sphere::glass();
         b.set_transform(translation(0.0, 0.0, -0.25));
-        b.get_base_mut().material.refractive_index = 2.0;
+        b.material_mut().refractive_index = 2.0;
 
         let mut c = Sphere::glass();
         c.set_transform(translation(0.0, 0.0, 0.25));
-        c.get_base_mut().material.refractive_index = 2.5;
+        c.material_mut().refractive_index = 2.5;
 
         let r = Ray::new(Point::new(0, 0, -4), Vector::new(0, 0, 1));
         let xs = intersections(&[
@@ -401,6 +746,34 @@ mod tests {
         assert!(equal(i.v.unwrap(), 0.4));
     }
 
+    #[test]
+    fn exit_of_entering_hit() {
+        let mut a = Sphere::default();
+        a.set_transform(scaling(2, 2, 2));
+        let b = Sphere::default();
+        let r = Ray::new(Point::new(0, 0, -4), Vector::new(0, 0, 1));
+        let xs = intersections(&[
+            Intersection::new(2.0, &a),
+            Intersection::new(3.0, &b),
+            Intersection::new(5.0, &b),
+            Intersection::new(6.0, &a),
+        ]);
+
+        assert_eq!(*xs[0].exit(&xs).unwrap(), xs[3]);
+        assert_eq!(*xs[1].exit(&xs).unwrap(), xs[2]);
+        assert!(xs[2].exit(&xs).is_none());
+        assert!(xs[3].exit(&xs).is_none());
+        assert!(equal(xs[0].thickness(&r, &xs).unwrap(), 4.0));
+    }
+
+    #[test]
+    fn exit_is_none_without_a_pair() {
+        let s = Sphere::default();
+        let i = Intersection::new(4.0, &s);
+        let xs = intersections(&[i]);
+        assert!(i.exit(&xs).is_none());
+    }
+
     #[test]
     fn skip_hits_with_no_shadow() {
         let mut s1 = Sphere::default();
@@ -413,7 +786,74 @@ mod tests {
         let i4 = Intersection::new(2.0, &s2);
 
         let xs = intersections(&[i1, i2, i3, i4]);
-        let i = shadow_hit(&xs);
+        let r = Ray::new(Point::new(0, 0, -5), Vector::new(0, 0, 1));
+        let i = shadow_hit(&xs, &r);
         assert_eq!(*i.unwrap(), i3);
     }
+
+    #[test]
+    fn shadow_hit_applies_bias_and_priority() {
+        let r = Ray::new(Point::new(0, 0, -5), Vector::new(0, 0, 1));
+        let mut s1 = Sphere::default();
+        s1.set_ray_bias(2.0);
+        let s2 = Sphere::default();
+        let xs = intersections(&[Intersection::new(1.0, &s1), Intersection::new(2.0, &s2)]);
+        assert_eq!(shadow_hit(&xs, &r), hit(&xs));
+        assert_eq!(shadow_hit(&xs, &r).unwrap().t(), 2.0);
+
+        let s3 = Sphere::default();
+        let mut s4 = Sphere::default();
+        s4.set_priority(1);
+        let xs = intersections(&[Intersection::new(1.0, &s3), Intersection::new(1.0, &s4)]);
+        assert!(ptr::eq(
+            shadow_hit(&xs, &r).unwrap().object().get_base(),
+            s4.get_base()
+        ));
+        assert!(blocks_light(&xs, &r, 1.5));
+        assert!(!blocks_light(&xs, &r, 1.0));
+    }
+
+    #[test]
+    fn computations_carry_texture_coordinates() {
+        use crate::geometry::shape::{Cube, Cylinder};
+
+        let assert_uv = |i: Intersection, r: &Ray, u: f64, v: f64| {
+            let (hit_u, hit_v) = i.prepare_computations(r, &[]).uv.unwrap();
+            assert!(equal(hit_u, u) && equal(hit_v, v));
+        };
+
+        let mut s = Sphere::default();
+        s.set_transform(&translation(0, 0, 5) * &scaling(2, 2, 2));
+        let r = Ray::new(Point::origin(), Vector::new(0, 0, 1));
+        // the back of the sphere faces the ray
+        assert_uv(Intersection::new(3.0, &s), &r, 0.0, 0.5);
+
+        let p = Plane::default();
+        let r = Ray::new(Point::new(1.25, 1.0, -0.5), Vector::new(0, -1, 0));
+        assert_uv(Intersection::new(1.0, &p), &r, 0.25, 0.5);
+
+        let c = Cube::default();
+        let r = Ray::new(Point::new(-0.5, 0.5, 5.0), Vector::new(0, 0, -1));
+        assert_uv(Intersection::new(4.0, &c), &r, 0.25, 0.75);
+
+        let mut cyl = Cylinder::new(0, 1, true);
+        cyl.set_transform(scaling(2, 1, 2));
+        let r = Ray::new(Point::new(1, 5, 0), Vector::new(0, -1, 0));
+        assert_uv(Intersection::new(4.0, &cyl), &r, 0.75, 0.5);
+
+        let t = Triangle::new(
+            Point::new(0, 1, 0),
+            Point::new(-1, 0, 0),
+            Point::new(1, 0, 0),
+        );
+        let r = Ray::new(Point::new(0.0, 0.5, -2.0), Vector::new(0, 0, 1));
+        assert_eq!(
+            Intersection::new(2.0, &t).prepare_computations(&r, &[]).uv,
+            None
+        );
+        let uv = Intersection::new_with_uv(2.0, &t, 0.25, 0.5)
+            .prepare_computations(&r, &[])
+            .uv;
+        assert_eq!(uv, Some((0.25, 0.5)));
+    }
 }
@@ -1,22 +1,127 @@
+pub mod bundle;
 pub mod intersection;
 pub mod shape;
+pub mod uv;
+pub mod visitor;
 
 use crate::{
-    bounding_box::BoundingBox, material::Material, matrix::Matrix, point::Point, ray::Ray,
+    animation::AnimationChannel,
+    bounding_box::BoundingBox,
+    bounding_sphere::BoundingSphere,
+    material::Material,
+    matrix::Matrix,
+    point::Point,
+    ray::Ray,
+    transform::{decompose, DecomposedTransform},
     vector::Vector,
+    EPSILON,
 };
-use std::{any::Any, fmt::Debug, ptr};
+use std::{
+    any::Any,
+    fmt::Debug,
+    hash::{Hash, Hasher},
+    ptr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+use self::{bundle::RayBundle, intersection::Intersection};
+
+// groups and csgs with at most this many children skip their own bounding box
+// test. the bounds_check_bench binary shows the box test paying for itself even
+// with a single child, since every child intersection transforms the ray first.
+pub const DEFAULT_BOUNDS_CHECK_THRESHOLD: usize = 0;
+
+// shapes between these sizes across use EPSILON as is, bigger and smaller
+// ones scale it in proportion
+const MIN_UNSCALED_EPSILON_SIZE: f64 = 0.01;
+const MAX_UNSCALED_EPSILON_SIZE: f64 = 100.0;
+
+pub(crate) fn epsilon_scale(size: f64) -> f64 {
+    if !size.is_finite() || size <= 0.0 {
+        return 1.0;
+    }
+    (size / MAX_UNSCALED_EPSILON_SIZE).max(1.0) * (size / MIN_UNSCALED_EPSILON_SIZE).min(1.0)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RayKind {
+    Camera,
+    Shadow,
+    Secondary,
+}
+
+// which kinds of rays can see a shape, secondary covers reflection and refraction
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Visibility {
+    pub camera: bool,
+    pub shadow: bool,
+    pub secondary: bool,
+}
+
+impl Default for Visibility {
+    fn default() -> Self {
+        Self {
+            camera: true,
+            shadow: true,
+            secondary: true,
+        }
+    }
+}
 
-use self::intersection::Intersection;
+impl Visibility {
+    pub fn is_visible_to(&self, kind: RayKind) -> bool {
+        match kind {
+            RayKind::Camera => self.camera,
+            RayKind::Shadow => self.shadow,
+            RayKind::Secondary => self.secondary,
+        }
+    }
 
-#[derive(Debug, PartialEq)]
+    pub fn set(&mut self, kind: RayKind, visible: bool) {
+        match kind {
+            RayKind::Camera => self.camera = visible,
+            RayKind::Shadow => self.shadow = visible,
+            RayKind::Secondary => self.secondary = visible,
+        }
+    }
+}
+
+// tells shapes apart for as long as the program runs, every new shape gets the
+// next one
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ShapeId(u64);
+
+impl ShapeId {
+    fn next() -> Self {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+        Self(NEXT_ID.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+#[derive(Debug)]
 pub struct BaseShape {
+    id: ShapeId,
     transform: Matrix,
     pub transform_inverse: Matrix,
     transform_inverse_transpose: Matrix,
-    pub material: Material,
+    // shared between shapes until one of them changes it
+    material: Arc<Material>,
     bounding_box: BoundingBox,
-    shadow: bool,
+    visibility: Visibility,
+    priority: i32,
+    ray_bias: f64,
+    epsilon: Option<f64>,
+    animation: Option<AnimationChannel>,
+    // set when the transform changes, cleared by refit
+    bounds_dirty: bool,
+    tags: Vec<String>,
+    // tested before anything else when the check is on, in the parent's
+    // space like the bounding box
+    bounding_sphere_check: bool,
+    bounding_sphere: Option<BoundingSphere>,
 }
 
 impl Default for BaseShape {
@@ -25,29 +130,89 @@ impl Default for BaseShape {
         let transform_inverse = Matrix::identity(4, 4);
         let transform_inverse_transpose = Matrix::identity(4, 4);
         Self {
+            id: ShapeId::next(),
             transform,
             transform_inverse,
             transform_inverse_transpose,
-            material: Material::default(),
+            material: Arc::new(Material::default()),
             bounding_box: BoundingBox::default(),
-            shadow: true,
+            visibility: Visibility::default(),
+            priority: 0,
+            ray_bias: 0.0,
+            epsilon: None,
+            animation: None,
+            bounds_dirty: false,
+            tags: vec![],
+            bounding_sphere_check: false,
+            bounding_sphere: None,
         }
     }
 }
 
+// compares everything but the id, dirty flag and tags, so equals finds shapes
+// built the same way
+impl PartialEq for BaseShape {
+    fn eq(&self, other: &Self) -> bool {
+        self.transform == other.transform
+            && self.material == other.material
+            && self.bounding_box == other.bounding_box
+            && self.visibility == other.visibility
+            && self.priority == other.priority
+            && self.ray_bias == other.ray_bias
+            && self.epsilon == other.epsilon
+            && self.animation == other.animation
+            && self.bounding_sphere == other.bounding_sphere
+    }
+}
+
 pub trait Shape: Debug + Send + Sync {
     fn get_base(&self) -> &BaseShape;
     fn get_base_mut(&mut self) -> &mut BaseShape;
-    fn local_intersect(&self, ray: &Ray) -> Vec<Intersection>;
+    fn local_intersect(&self, ray: &Ray) -> Vec<Intersection<'_>>;
     fn local_normal_at(&self, point: Point, intersection: &Intersection) -> Vector;
     fn as_any(&self) -> &dyn Any;
+    // same kind of shape with the same geometry, transform and material
     fn equals(&self, other: &dyn Shape) -> bool;
 
-    fn intersect(&self, ray: &Ray) -> Vec<Intersection> {
+    fn id(&self) -> ShapeId {
+        self.get_base().id
+    }
+
+    // the shape's type without its module path, e.g. "Sphere"
+    fn type_name(&self) -> &'static str {
+        let name = std::any::type_name::<Self>();
+        name.rsplit("::").next().unwrap_or(name)
+    }
+
+    fn intersect(&self, ray: &Ray) -> Vec<Intersection<'_>> {
+        if !self.may_hit(ray) {
+            return vec![];
+        }
         let local_ray = ray.transform(&self.get_base().transform_inverse);
         self.local_intersect(&local_ray)
     }
 
+    // marks the rays of the bundle listed in active that this shape blocks
+    fn intersect_bundle(&self, bundle: &mut RayBundle, active: &[usize]) {
+        for &idx in active {
+            if !bundle.is_blocked(idx) {
+                let xs = self.intersect(bundle.ray(idx));
+                bundle.block_with(idx, &xs);
+            }
+        }
+    }
+
+    // texture coordinates of a hit, by default the ones the intersection
+    // carries, e.g. the barycentric ones of smooth triangles
+    fn local_uv_at(&self, _point: Point, intersection: &Intersection) -> Option<(f64, f64)> {
+        intersection.u().zip(intersection.v())
+    }
+
+    fn uv_at(&self, point: Point, intersection: &Intersection) -> Option<(f64, f64)> {
+        let local_point = &self.get_base().transform_inverse * point;
+        self.local_uv_at(local_point, intersection)
+    }
+
     fn normal_at(&self, point: Point, intersection: &Intersection) -> Vector {
         let local_point = &self.get_base().transform_inverse * point;
         let local_normal = self.local_normal_at(local_point, intersection);
@@ -59,11 +224,27 @@ pub trait Shape: Debug + Send + Sync {
         &self.get_base().material
     }
 
+    fn shared_material(&self) -> &Arc<Material> {
+        &self.get_base().material
+    }
+
+    // copies the material first if other shapes share it
     fn material_mut(&mut self) -> &mut Material {
-        &mut self.get_base_mut().material
+        Arc::make_mut(&mut self.get_base_mut().material)
     }
 
+    // debug builds warn about materials that are likely to shade to NaN
     fn set_material(&mut self, material: Material) {
+        if cfg!(debug_assertions) {
+            for problem in material.problems() {
+                eprintln!("warning: material of shape {:?}: {}", self.id(), problem);
+            }
+        }
+        self.set_shared_material(Arc::new(material));
+    }
+
+    // lets many shapes, e.g. the triangles of a mesh, use one material
+    fn set_shared_material(&mut self, material: Arc<Material>) {
         self.get_base_mut().material = material;
     }
 
@@ -71,6 +252,10 @@ pub trait Shape: Debug + Send + Sync {
         &self.get_base().transform
     }
 
+    fn transform_decomposed(&self) -> DecomposedTransform {
+        decompose(self.transform())
+    }
+
     fn set_transform(&mut self, transform: Matrix) {
         self.get_base_mut().bounding_box = self
             .get_bounds()
@@ -82,6 +267,8 @@ pub trait Shape: Debug + Send + Sync {
         self.get_base_mut().transform_inverse_transpose = inverse_transpose;
 
         self.get_base_mut().bounding_box = self.get_bounds().transform(self.transform());
+        self.get_base_mut().bounds_dirty = true;
+        self.update_bounding_sphere();
     }
 
     fn includes(&self, other: &dyn Shape) -> bool {
@@ -96,20 +283,163 @@ pub trait Shape: Debug + Send + Sync {
         self.get_bounds().transform(&Matrix::identity(4, 4))
     }
 
+    // the sphere the bounding sphere check uses, by default the one around
+    // the parent space bounds
+    fn parent_space_bounding_sphere(&self) -> Option<BoundingSphere> {
+        BoundingSphere::around_box(&self.parent_space_bounds())
+    }
+
+    fn bounding_sphere(&self) -> Option<&BoundingSphere> {
+        self.get_base().bounding_sphere.as_ref()
+    }
+
+    // tests rays against a sphere around the shape before anything else. off
+    // by default: the bounding_sphere_bench binary shows it paying off for
+    // groups of round shapes but not for meshes
+    fn set_bounding_sphere_check(&mut self, check: bool) {
+        self.get_base_mut().bounding_sphere_check = check;
+        self.update_bounding_sphere();
+    }
+
+    fn update_bounding_sphere(&mut self) {
+        let sphere = if self.get_base().bounding_sphere_check {
+            self.parent_space_bounding_sphere()
+        } else {
+            None
+        };
+        self.get_base_mut().bounding_sphere = sphere;
+    }
+
+    // false only when the bounding sphere check is on and the ray misses it
+    fn may_hit(&self, ray: &Ray) -> bool {
+        self.bounding_sphere().is_none_or(|s| s.intersects(ray))
+    }
+
     fn divide(&mut self, _threshold: usize) {}
 
+    // whether the transform changed since the last refit
+    fn bounds_dirty(&self) -> bool {
+        self.get_base().bounds_dirty
+    }
+
+    // updates the bounds of the groups and csgs above shapes whose transform
+    // changed, keeping the tree divide built. returns whether the bounds of
+    // this shape changed
+    fn refit(&mut self) -> bool {
+        std::mem::take(&mut self.get_base_mut().bounds_dirty)
+    }
+
+    // a group made by divide hands over its children so that they can be
+    // divided again, other shapes keep theirs
+    fn take_split_children(&mut self) -> Option<Vec<Box<dyn Shape>>> {
+        None
+    }
+
+    // group children and csg operands, see visitor::walk for whole trees
+    fn for_each_child(&self, _f: &mut dyn FnMut(&dyn Shape)) {}
+
+    fn for_each_child_mut(&mut self, _f: &mut dyn FnMut(&mut dyn Shape)) {}
+
+    // whether the children's transforms are relative to this shape
+    fn children_in_object_space(&self) -> bool {
+        true
+    }
+
     fn has_shadow(&self) -> bool {
-        self.get_base().shadow
+        self.get_base().visibility.shadow
     }
 
     fn no_shadow(&mut self) {
-        self.get_base_mut().shadow = false;
+        self.get_base_mut().visibility.shadow = false;
+    }
+
+    fn visibility(&self) -> Visibility {
+        self.get_base().visibility
+    }
+
+    fn set_visibility(&mut self, visibility: Visibility) {
+        self.get_base_mut().visibility = visibility;
+    }
+
+    // whether one kind of ray sees the shape, the others are left as they are
+    fn set_visible_to(&mut self, kind: RayKind, visible: bool) {
+        self.get_base_mut().visibility.set(kind, visible);
+    }
+
+    fn priority(&self) -> i32 {
+        self.get_base().priority
+    }
+
+    // wins ties against coincident surfaces of lower priority
+    fn set_priority(&mut self, priority: i32) {
+        self.get_base_mut().priority = priority;
+    }
+
+    fn ray_bias(&self) -> f64 {
+        self.get_base().ray_bias
+    }
+
+    // pushes the shape's hits further along the ray when picking the closest hit
+    fn set_ray_bias(&mut self, ray_bias: f64) {
+        self.get_base_mut().ray_bias = ray_bias;
+    }
+
+    // how far hits are offset from the surface to keep them from hitting it
+    // again: the one set, or EPSILON scaled to the shape's size in world space
+    fn epsilon(&self) -> f64 {
+        let base = self.get_base();
+        if let Some(epsilon) = base.epsilon {
+            return epsilon;
+        }
+        // the bounding box is already in the parent's space
+        let bounds = &base.bounding_box;
+        EPSILON * epsilon_scale((bounds.get_max() - bounds.get_min()).magnitude())
+    }
+
+    // for scenes modelled at a scale, e.g. millimeters, the automatic one gets wrong
+    fn set_epsilon(&mut self, epsilon: f64) {
+        self.get_base_mut().epsilon = Some(epsilon);
+    }
+
+    fn tags(&self) -> &[String] {
+        &self.get_base().tags
+    }
+
+    fn has_tag(&self, tag: &str) -> bool {
+        self.tags().iter().any(|t| t == tag)
+    }
+
+    // tags the shape and everything in it, World::override_material picks
+    // shapes by tag
+    fn add_tag(&mut self, tag: &str) {
+        if !self.has_tag(tag) {
+            self.get_base_mut().tags.push(tag.to_string());
+        }
+        self.for_each_child_mut(&mut |child| child.add_tag(tag));
+    }
+
+    fn animation(&self) -> Option<&AnimationChannel> {
+        self.get_base().animation.as_ref()
+    }
+
+    fn set_animation(&mut self, animation: AnimationChannel) {
+        self.get_base_mut().animation = Some(animation);
     }
 }
 
+// == on shapes is identity: a shape only equals itself, use equals to compare
+// shapes by value. together with Hash this lets &dyn Shape key a HashMap
 impl<'a, 'b> PartialEq<dyn Shape + 'b> for dyn Shape + 'a {
     fn eq(&self, other: &dyn Shape) -> bool {
-        self.equals(other)
+        self.id() == other.id()
+    }
+}
+
+impl Eq for dyn Shape + '_ {}
+
+impl Hash for dyn Shape + '_ {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id().hash(state);
     }
 }
 
@@ -151,4 +481,106 @@ mod tests {
         );
         assert_eq!(n, Vector::new(0.2857, 0.42854, -0.85716));
     }
+
+    #[test]
+    fn decomposed_shape_transform() {
+        let mut s = Sphere::default();
+        s.set_transform(&translation(1, 2, 3) * &scaling(2, 2, 2));
+        let d = s.transform_decomposed();
+        assert_eq!(d.translation, Vector::new(1, 2, 3));
+        assert_eq!(d.rotation, Vector::new(0, 0, 0));
+        assert_eq!(d.scale, Vector::new(2, 2, 2));
+    }
+
+    #[test]
+    fn shapes_are_equal_only_to_themselves() {
+        let (a, b) = (Sphere::default(), Sphere::default());
+        let (a, b): (&dyn Shape, &dyn Shape) = (&a, &b);
+        assert_ne!(a.id(), b.id());
+        assert!(a == a);
+        assert!(a != b);
+        assert!(a.equals(b));
+    }
+
+    #[test]
+    fn equals_compares_children_by_value() {
+        use shape::{Csg, Cube, Operation};
+
+        let group = || {
+            let mut g = Group::default();
+            g.add_child(Box::new(Sphere::default()));
+            g
+        };
+        assert!(group().equals(&group()));
+        let mut other = group();
+        other.add_child(Box::new(Cube::default()));
+        assert!(!group().equals(&other));
+
+        let csg = || Csg::new(Operation::Difference, Cube::default(), Sphere::default());
+        assert!(csg().equals(&csg()));
+        assert!(!csg().equals(&Csg::new(
+            Operation::Difference,
+            Sphere::default(),
+            Cube::default()
+        )));
+    }
+
+    #[test]
+    fn shared_material_is_copied_on_write() {
+        let mut material = Material::default();
+        material.ambient = 0.5;
+        let mut g = Group::default();
+        g.add_child(Box::new(Sphere::default()));
+        g.add_child(Box::new(Sphere::default()));
+        g.set_material(material);
+
+        let (a, b) = (&g.children[0], &g.children[1]);
+        assert!(Arc::ptr_eq(a.shared_material(), b.shared_material()));
+        assert!(Arc::ptr_eq(a.shared_material(), g.shared_material()));
+
+        let mut s = Sphere::default();
+        s.set_shared_material(g.shared_material().clone());
+        s.material_mut().ambient = 1.0;
+        assert_eq!(g.material().ambient, 0.5);
+        assert_eq!(g.children[0].material().ambient, 0.5);
+        assert!(!Arc::ptr_eq(s.shared_material(), g.shared_material()));
+    }
+
+    #[test]
+    fn epsilon_follows_shape_size() {
+        assert_eq!(Sphere::default().epsilon(), EPSILON);
+        assert_eq!(shape::Plane::default().epsilon(), EPSILON);
+
+        // 2 * sqrt(3) * 1000 across, about 35 times the largest unscaled size
+        let mut big = Sphere::default();
+        big.set_transform(&translation(5, 0, 0) * &scaling(1000, 1000, 1000));
+        let across = 2000.0 * 3f64.sqrt();
+        assert!(crate::equal(big.epsilon() / EPSILON, across / 100.0));
+
+        let mut tiny = shape::Ellipsoid::new(0.001, 0.001, 0.001);
+        let across = 0.002 * 3f64.sqrt();
+        assert!(crate::equal(tiny.epsilon() / EPSILON, across / 0.01));
+
+        tiny.set_epsilon(1e-9);
+        assert_eq!(tiny.epsilon(), 1e-9);
+
+        let mut g = Group::default();
+        g.add_child(Box::new(Sphere::default()));
+        g.set_epsilon(0.1);
+        assert_eq!(g.children[0].epsilon(), 0.1);
+    }
+
+    #[test]
+    fn shapes_as_hash_map_keys() {
+        use std::collections::HashMap;
+
+        let (a, b) = (Sphere::default(), Sphere::default());
+        let mut names: HashMap<&dyn Shape, &str> = HashMap::new();
+        names.insert(&a, "a");
+        names.insert(&b, "b");
+        names.insert(&a, "a again");
+        assert_eq!(names.len(), 2);
+        assert_eq!(names[&(&b as &dyn Shape)], "b");
+        assert_eq!(names[&(&a as &dyn Shape)], "a again");
+    }
 }
@@ -2,7 +2,11 @@ use std::any::Any;
 
 use crate::{
     bounding_box::BoundingBox,
-    geometry::{intersection::Intersection, BaseShape, Shape},
+    geometry::{
+        intersection::Intersection,
+        uv::{cylindrical_map, disc_map},
+        BaseShape, Shape,
+    },
     point::Point,
     ray::Ray,
     vector::Vector,
@@ -15,6 +19,9 @@ pub struct Cone {
     minimum: f64,
     maximum: f64,
     closed: bool,
+    // radius along x and z at y = ±1, the cross-section is an ellipse when they differ
+    radius_x: f64,
+    radius_z: f64,
 }
 
 impl Default for Cone {
@@ -25,27 +32,44 @@ impl Default for Cone {
 
 impl Cone {
     pub fn new<T: Into<f64> + Copy>(minimum: T, maximum: T, closed: bool) -> Self {
-        let min = minimum.into();
-        let max = maximum.into();
-        let a = min.abs();
-        let b = max.abs();
-        let limit = a.max(b);
-
-        Self {
-            base: BaseShape {
-                bounding_box: BoundingBox::new(
-                    Point::new(-limit, min, -limit),
-                    Point::new(limit, max, limit),
-                ),
-                ..BaseShape::default()
-            },
-            minimum: min,
-            maximum: max,
+        let mut cone = Self {
+            base: BaseShape::default(),
+            minimum: minimum.into(),
+            maximum: maximum.into(),
             closed,
-        }
+            radius_x: 1.0,
+            radius_z: 1.0,
+        };
+        cone.base.bounding_box = cone.local_bounds();
+        cone
+    }
+
+    pub fn radii(&self) -> (f64, f64) {
+        (self.radius_x, self.radius_z)
+    }
+
+    // squashes the cone without scaling it, so normals and patterns aren't stretched
+    pub fn set_radii(&mut self, radius_x: f64, radius_z: f64) {
+        self.radius_x = radius_x;
+        self.radius_z = radius_z;
+        self.base.bounding_box = self.local_bounds().transform(&self.base.transform);
+        self.update_bounding_sphere();
+    }
+
+    fn local_bounds(&self) -> BoundingBox {
+        let limit = self.minimum.abs().max(self.maximum.abs());
+        BoundingBox::new(
+            Point::new(-limit * self.radius_x, self.minimum, -limit * self.radius_z),
+            Point::new(limit * self.radius_x, self.maximum, limit * self.radius_z),
+        )
+    }
+
+    // distance from the axis squared, in units of the radius
+    fn radial_dist(&self, x: f64, z: f64) -> f64 {
+        (x / self.radius_x).powi(2) + (z / self.radius_z).powi(2)
     }
 
-    fn intersect_caps(&self, ray: &Ray) -> Vec<Intersection> {
+    fn intersect_caps(&self, ray: &Ray) -> Vec<Intersection<'_>> {
         let mut xs = vec![];
         if !self.closed {
             return xs;
@@ -67,7 +91,7 @@ impl Cone {
     fn check_cap(&self, ray: &Ray, t: f64, radius: f64) -> bool {
         let x = ray.origin().x + t * ray.direction().x;
         let z = ray.origin().z + t * ray.direction().z;
-        (x * x + z * z) <= radius * radius
+        self.radial_dist(x, z) <= radius * radius
     }
 }
 
@@ -85,17 +109,23 @@ impl Shape for Cone {
     }
 
     fn equals(&self, other: &dyn Shape) -> bool {
-        other
-            .as_any()
-            .downcast_ref::<Cone>()
-            .map_or(false, |a| self == a)
+        other.as_any().downcast_ref::<Cone>() == Some(self)
     }
 
-    fn local_intersect(&self, ray: &Ray) -> Vec<Intersection> {
-        let a = ray.direction().x.powi(2) - ray.direction().y.powi(2) + ray.direction().z.powi(2);
-        let b = 2.0 * ray.origin().x * ray.direction().x - 2.0 * ray.origin().y * ray.direction().y
-            + 2.0 * ray.origin().z * ray.direction().z;
-        let c = ray.origin().x.powi(2) - ray.origin().y.powi(2) + ray.origin().z.powi(2);
+    fn local_intersect(&self, ray: &Ray) -> Vec<Intersection<'_>> {
+        let (ox, oy, oz) = (
+            ray.origin().x / self.radius_x,
+            ray.origin().y,
+            ray.origin().z / self.radius_z,
+        );
+        let (dx, dy, dz) = (
+            ray.direction().x / self.radius_x,
+            ray.direction().y,
+            ray.direction().z / self.radius_z,
+        );
+        let a = dx.powi(2) - dy.powi(2) + dz.powi(2);
+        let b = 2.0 * ox * dx - 2.0 * oy * dy + 2.0 * oz * dz;
+        let c = ox.powi(2) - oy.powi(2) + oz.powi(2);
 
         let mut xs = vec![];
 
@@ -134,18 +164,41 @@ impl Shape for Cone {
     }
 
     fn local_normal_at(&self, point: Point, _intersection: &Intersection) -> Vector {
-        let dist = point.x * point.x + point.z * point.z;
+        let dist = self.radial_dist(point.x, point.z);
         if dist < 1.0 && point.y >= self.maximum - EPSILON {
             Vector::new(0, 1, 0)
         } else if dist < 1.0 && point.y <= self.minimum + EPSILON {
             Vector::new(0, -1, 0)
         } else {
-            let mut y = (point.x * point.x + point.z * point.z).sqrt();
+            let mut y = dist.sqrt();
             if point.y > 0.0 {
                 y = -y;
             }
-            Vector::new(point.x, y, point.z)
+            Vector::new(
+                point.x / self.radius_x.powi(2),
+                y,
+                point.z / self.radius_z.powi(2),
+            )
+        }
+    }
+
+    // like a cylinder's, with the caps scaled to their radius
+    fn local_uv_at(&self, point: Point, _intersection: &Intersection) -> Option<(f64, f64)> {
+        let on_cap =
+            self.closed && (point.y >= self.maximum - EPSILON || point.y <= self.minimum + EPSILON);
+        if !on_cap {
+            let unit = Point::new(point.x / self.radius_x, point.y, point.z / self.radius_z);
+            return Some(cylindrical_map(unit));
         }
+        let radius = point.y.abs();
+        if radius < EPSILON {
+            return Some((0.5, 0.5));
+        }
+        Some(disc_map(Point::new(
+            point.x / (self.radius_x * radius),
+            point.y,
+            point.z / (self.radius_z * radius),
+        )))
     }
 }
 
@@ -269,4 +322,25 @@ mod tests {
         assert_eq!(bb.get_min(), Point::new(-5, -5, -5));
         assert_eq!(bb.get_max(), Point::new(5, 3, 5));
     }
+
+    #[test]
+    fn elliptical_cone() {
+        let mut shape = Cone::new(-1, 1, true);
+        shape.set_radii(2.0, 1.0);
+
+        let r = Ray::new(Point::new(-5.0, 0.5, 0.0), Vector::new(1, 0, 0));
+        let xs = shape.local_intersect(&r);
+        assert_eq!(xs.len(), 2);
+        assert!(equal(xs[0].t(), 4.0));
+        assert!(equal(xs[1].t(), 6.0));
+
+        let n = shape
+            .local_normal_at(Point::new(1.0, -0.5, 0.0), &Intersection::new(0.0, &shape))
+            .normalize();
+        assert_eq!(n, Vector::new(0.25, 0.5, 0.0).normalize());
+
+        let bb = shape.get_bounds();
+        assert_eq!(bb.get_min(), Point::new(-2, -1, -1));
+        assert_eq!(bb.get_max(), Point::new(2, 1, 1));
+    }
 }
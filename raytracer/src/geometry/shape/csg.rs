@@ -4,7 +4,7 @@ use crate::{
     bounding_box::BoundingBox,
     geometry::{
         intersection::{intersections, Intersection},
-        BaseShape, Shape,
+        BaseShape, RayKind, Shape, Visibility, DEFAULT_BOUNDS_CHECK_THRESHOLD,
     },
     point::Point,
     ray::Ray,
@@ -46,6 +46,7 @@ pub struct Csg {
     operation: Operation,
     pub left: Box<dyn Shape>,
     pub right: Box<dyn Shape>,
+    bounds_check_threshold: usize,
 }
 
 impl Csg {
@@ -53,6 +54,15 @@ impl Csg {
         operation: Operation,
         left: L,
         right: R,
+    ) -> Self {
+        Self::with_operands(operation, Box::new(left), Box::new(right))
+    }
+
+    // for operands whose type is only known at runtime, like parsed ones
+    pub fn with_operands(
+        operation: Operation,
+        left: Box<dyn Shape>,
+        right: Box<dyn Shape>,
     ) -> Self {
         let mut bb = BoundingBox::default();
         bb.add_bounding_box(&left.parent_space_bounds());
@@ -63,11 +73,24 @@ impl Csg {
                 ..Default::default()
             },
             operation,
-            left: Box::new(left),
-            right: Box::new(right),
+            left,
+            right,
+            bounds_check_threshold: DEFAULT_BOUNDS_CHECK_THRESHOLD,
         }
     }
 
+    pub fn operation(&self) -> &Operation {
+        &self.operation
+    }
+
+    pub fn set_bounds_check_threshold(&mut self, threshold: usize) {
+        self.bounds_check_threshold = threshold;
+    }
+
+    fn needs_bounds_check(&self) -> bool {
+        2 > self.bounds_check_threshold && self.get_bounds().is_finite()
+    }
+
     pub fn filter_intersections<'a>(&self, xs: Vec<Intersection<'a>>) -> Vec<Intersection<'a>> {
         let mut inl = false;
         let mut inr = false;
@@ -75,7 +98,7 @@ impl Csg {
         let mut result = vec![];
 
         for intersection in xs {
-            let lhit = self.left.includes(intersection.object());
+            let lhit = intersection.is_within(self.left.as_ref());
             if self.operation.intersection_allowed(lhit, inl, inr) {
                 result.push(intersection);
             }
@@ -91,6 +114,12 @@ impl Csg {
     }
 }
 
+// unbounded operands always count as hit
+fn may_hit(operand: &dyn Shape, ray: &Ray) -> bool {
+    let bounds = operand.parent_space_bounds();
+    !bounds.is_finite() || bounds.intersects(ray)
+}
+
 impl Shape for Csg {
     fn get_base(&self) -> &BaseShape {
         &self.base
@@ -105,20 +134,40 @@ impl Shape for Csg {
     }
 
     fn equals(&self, other: &dyn Shape) -> bool {
-        other.as_any().downcast_ref::<Csg>().map_or(false, |a| {
+        other.as_any().downcast_ref::<Csg>().is_some_and(|a| {
             self.get_base() == other.get_base()
-                && self.left.as_ref() == a.left.as_ref()
-                && self.right.as_ref() == a.right.as_ref()
+                && self.left.equals(a.left.as_ref())
+                && self.right.equals(a.right.as_ref())
         })
     }
 
-    fn local_intersect(&self, ray: &Ray) -> Vec<Intersection> {
-        if !self.get_bounds().intersects(ray) {
+    fn local_intersect(&self, ray: &Ray) -> Vec<Intersection<'_>> {
+        if self.needs_bounds_check() && !self.get_bounds().intersects(ray) {
             return vec![];
         }
 
-        let mut leftxs = self.left.intersect(ray);
-        let rightxs = self.right.intersect(ray);
+        // an operand whose box the ray misses adds no intersections, and for
+        // some operations makes the other operand's irrelevant too
+        let (left_hit, right_hit) = (
+            may_hit(self.left.as_ref(), ray),
+            may_hit(self.right.as_ref(), ray),
+        );
+        match self.operation {
+            Operation::Difference if !left_hit => return vec![],
+            Operation::Intersection if !left_hit || !right_hit => return vec![],
+            _ => {}
+        }
+
+        let mut leftxs = if left_hit {
+            self.left.intersect(ray)
+        } else {
+            vec![]
+        };
+        let rightxs = if right_hit {
+            self.right.intersect(ray)
+        } else {
+            vec![]
+        };
 
         leftxs.extend(rightxs);
         let xs = intersections(&leftxs);
@@ -133,6 +182,45 @@ impl Shape for Csg {
         self.left.includes(other) || self.right.includes(other)
     }
 
+    fn for_each_child(&self, f: &mut dyn FnMut(&dyn Shape)) {
+        f(self.left.as_ref());
+        f(self.right.as_ref());
+    }
+
+    fn for_each_child_mut(&mut self, f: &mut dyn FnMut(&mut dyn Shape)) {
+        f(self.left.as_mut());
+        f(self.right.as_mut());
+    }
+
+    // hits are on the operands, so they need the flags too
+    fn set_visibility(&mut self, visibility: Visibility) {
+        self.get_base_mut().visibility = visibility;
+        self.left.set_visibility(visibility);
+        self.right.set_visibility(visibility);
+    }
+
+    fn set_visible_to(&mut self, kind: RayKind, visible: bool) {
+        self.get_base_mut().visibility.set(kind, visible);
+        self.left.set_visible_to(kind, visible);
+        self.right.set_visible_to(kind, visible);
+    }
+
+    // the operands are in the csg's space, so their box is transformed like
+    // set_transform does
+    fn refit(&mut self) -> bool {
+        let mut changed = std::mem::take(&mut self.base.bounds_dirty);
+        changed |= self.left.refit();
+        changed |= self.right.refit();
+        if changed {
+            let mut bb = BoundingBox::default();
+            bb.add_bounding_box(&self.left.parent_space_bounds());
+            bb.add_bounding_box(&self.right.parent_space_bounds());
+            self.base.bounding_box = bb.transform(self.transform());
+            self.update_bounding_sphere();
+        }
+        changed
+    }
+
     fn divide(&mut self, threshold: usize) {
         self.left.divide(threshold);
         self.right.divide(threshold);
@@ -288,6 +376,38 @@ mod tests {
         assert!(equal(xs[1].t(), 6.5));
     }
 
+    #[test]
+    fn missed_operands_are_not_intersected() {
+        use crate::geometry::shape::TestShape;
+
+        let r = Ray::new(Point::new(0, 0, -5), Vector::new(0, 0, 1));
+        let traced = |operation: Operation| {
+            let mut left = Sphere::default();
+            left.set_transform(translation(5, 0, 0));
+            let c = Csg::new(operation, left, TestShape::default());
+            assert!(c.local_intersect(&r).is_empty());
+            let right = c.right.as_any().downcast_ref::<TestShape>().unwrap();
+            let saved = *right.saved_ray.read().unwrap();
+            saved.direction() != Vector::new(0, 0, 0)
+        };
+        // nothing is left of the right operand once the left one is missed
+        assert!(!traced(Operation::Difference));
+        assert!(!traced(Operation::Intersection));
+        assert!(traced(Operation::Union));
+    }
+
+    #[test]
+    fn union_with_a_missed_operand() {
+        let mut right = Sphere::default();
+        right.set_transform(translation(5, 0, 0));
+        let c = Csg::new(Operation::Union, Sphere::default(), right);
+        let r = Ray::new(Point::new(0, 0, -5), Vector::new(0, 0, 1));
+        let xs = c.local_intersect(&r);
+        assert_eq!(xs.len(), 2);
+        assert!(equal(xs[0].t(), 4.0));
+        assert!(equal(xs[1].t(), 6.0));
+    }
+
     #[test]
     fn csg_bounding_box_contains_its_children() {
         let left = Sphere::default();
@@ -310,8 +430,7 @@ mod tests {
         s2.set_transform(translation(1.5, 0.0, 0.0));
 
         let mut left = Group::default();
-        left.add_child(Box::new(s1));
-        left.add_child(Box::new(s2));
+        left.extend([s1, s2]);
 
         let mut s3 = Sphere::default();
         s3.set_transform(translation(0.0, 0.0, -1.5));
@@ -320,8 +439,7 @@ mod tests {
         s4.set_transform(translation(0.0, 0.0, 1.5));
 
         let mut right = Group::default();
-        right.add_child(Box::new(s3));
-        right.add_child(Box::new(s4));
+        right.extend([s3, s4]);
 
         let mut shape = Csg::new(Operation::Difference, left, right);
         shape.divide(1);
@@ -3,7 +3,7 @@ use std::any::Any;
 use crate::{
     bounding_box::BoundingBox,
     equal,
-    geometry::{intersection::Intersection, BaseShape, Shape},
+    geometry::{intersection::Intersection, uv::cube_map, BaseShape, Shape},
     point::Point,
     ray::Ray,
     vector::Vector,
@@ -62,13 +62,10 @@ impl Shape for Cube {
     }
 
     fn equals(&self, other: &dyn Shape) -> bool {
-        other
-            .as_any()
-            .downcast_ref::<Cube>()
-            .map_or(false, |a| self == a)
+        other.as_any().downcast_ref::<Cube>() == Some(self)
     }
 
-    fn local_intersect(&self, ray: &Ray) -> Vec<Intersection> {
+    fn local_intersect(&self, ray: &Ray) -> Vec<Intersection<'_>> {
         let (xtmin, xtmax) = self.check_axis(ray.origin().x, ray.direction().x);
         let (ytmin, ytmax) = self.check_axis(ray.origin().y, ray.direction().y);
         let (ztmin, ztmax) = self.check_axis(ray.origin().z, ray.direction().z);
@@ -103,6 +100,12 @@ impl Shape for Cube {
             Vector::new(0.0, 0.0, point.z)
         }
     }
+
+    // within the face that was hit, see uv::cube_face
+    fn local_uv_at(&self, point: Point, _intersection: &Intersection) -> Option<(f64, f64)> {
+        let (_, u, v) = cube_map(point);
+        Some((u, v))
+    }
 }
 
 #[cfg(test)]
@@ -2,7 +2,11 @@ use std::any::Any;
 
 use crate::{
     bounding_box::BoundingBox,
-    geometry::{intersection::Intersection, BaseShape, Shape},
+    geometry::{
+        intersection::Intersection,
+        uv::{cylindrical_map, disc_map},
+        BaseShape, Shape,
+    },
     point::Point,
     ray::Ray,
     vector::Vector,
@@ -15,6 +19,9 @@ pub struct Cylinder {
     minimum: f64,
     maximum: f64,
     closed: bool,
+    // radius along x and z, the cross-section is an ellipse when they differ
+    radius_x: f64,
+    radius_z: f64,
 }
 
 impl Default for Cylinder {
@@ -25,27 +32,49 @@ impl Default for Cylinder {
 
 impl Cylinder {
     pub fn new<T: Into<f64> + Copy>(minimum: T, maximum: T, closed: bool) -> Self {
-        Self {
-            base: BaseShape {
-                bounding_box: BoundingBox::new(
-                    Point::new(-1.0, minimum.into(), -1.0),
-                    Point::new(1.0, maximum.into(), 1.0),
-                ),
-                ..Default::default()
-            },
+        let mut cylinder = Self {
+            base: BaseShape::default(),
             minimum: minimum.into(),
             maximum: maximum.into(),
             closed,
-        }
+            radius_x: 1.0,
+            radius_z: 1.0,
+        };
+        cylinder.base.bounding_box = cylinder.local_bounds();
+        cylinder
+    }
+
+    pub fn radii(&self) -> (f64, f64) {
+        (self.radius_x, self.radius_z)
+    }
+
+    // squashes the tube without scaling it, so normals and patterns aren't stretched
+    pub fn set_radii(&mut self, radius_x: f64, radius_z: f64) {
+        self.radius_x = radius_x;
+        self.radius_z = radius_z;
+        self.base.bounding_box = self.local_bounds().transform(&self.base.transform);
+        self.update_bounding_sphere();
+    }
+
+    fn local_bounds(&self) -> BoundingBox {
+        BoundingBox::new(
+            Point::new(-self.radius_x, self.minimum, -self.radius_z),
+            Point::new(self.radius_x, self.maximum, self.radius_z),
+        )
+    }
+
+    // distance from the axis squared, in units of the radius
+    fn radial_dist(&self, x: f64, z: f64) -> f64 {
+        (x / self.radius_x).powi(2) + (z / self.radius_z).powi(2)
     }
 
     fn check_cap(&self, ray: &Ray, t: f64) -> bool {
         let x = ray.origin().x + t * ray.direction().x;
         let z = ray.origin().z + t * ray.direction().z;
-        (x * x + z * z) <= 1.0
+        self.radial_dist(x, z) <= 1.0
     }
 
-    fn intersect_caps(&self, ray: &Ray) -> Vec<Intersection> {
+    fn intersect_caps(&self, ray: &Ray) -> Vec<Intersection<'_>> {
         let mut xs = vec![];
         if !self.closed {
             return xs;
@@ -79,20 +108,25 @@ impl Shape for Cylinder {
     }
 
     fn equals(&self, other: &dyn Shape) -> bool {
-        other
-            .as_any()
-            .downcast_ref::<Cylinder>()
-            .map_or(false, |a| self == a)
+        other.as_any().downcast_ref::<Cylinder>() == Some(self)
     }
 
-    fn local_intersect(&self, ray: &Ray) -> Vec<Intersection> {
-        let a = ray.direction().x.powi(2) + ray.direction().z.powi(2);
+    fn local_intersect(&self, ray: &Ray) -> Vec<Intersection<'_>> {
+        let (ox, oz) = (
+            ray.origin().x / self.radius_x,
+            ray.origin().z / self.radius_z,
+        );
+        let (dx, dz) = (
+            ray.direction().x / self.radius_x,
+            ray.direction().z / self.radius_z,
+        );
+        let a = dx.powi(2) + dz.powi(2);
         if a.abs() < EPSILON {
             return self.intersect_caps(ray);
         }
 
-        let b = 2.0 * ray.origin().x * ray.direction().x + 2.0 * ray.origin().z * ray.direction().z;
-        let c = ray.origin().x.powi(2) + ray.origin().z.powi(2) - 1.0;
+        let b = 2.0 * ox * dx + 2.0 * oz * dz;
+        let c = ox.powi(2) + oz.powi(2) - 1.0;
 
         let disc = b.powi(2) - 4.0 * a * c;
         if disc < 0.0 {
@@ -119,18 +153,35 @@ impl Shape for Cylinder {
     }
 
     fn local_normal_at(&self, point: Point, _intersection: &Intersection) -> Vector {
-        let dist = point.x * point.x + point.z * point.z;
+        let dist = self.radial_dist(point.x, point.z);
         if dist < 1.0 && point.y >= self.maximum - EPSILON {
             Vector::new(0, 1, 0)
         } else if dist < 1.0 && point.y <= self.minimum + EPSILON {
             Vector::new(0, -1, 0)
         } else {
-            Vector::new(point.x, 0.0, point.z)
+            Vector::new(
+                point.x / self.radius_x.powi(2),
+                0.0,
+                point.z / self.radius_z.powi(2),
+            )
         }
     }
+
+    // the side wraps around like a label, the caps are discs
+    fn local_uv_at(&self, point: Point, _intersection: &Intersection) -> Option<(f64, f64)> {
+        let unit = Point::new(point.x / self.radius_x, point.y, point.z / self.radius_z);
+        let on_cap =
+            self.closed && (point.y >= self.maximum - EPSILON || point.y <= self.minimum + EPSILON);
+        Some(if on_cap {
+            disc_map(unit)
+        } else {
+            cylindrical_map(unit)
+        })
+    }
 }
 
 #[cfg(test)]
+#[allow(clippy::bool_assert_comparison)]
 mod tests {
     use super::*;
     use crate::{equal, point::Point, vector::Vector};
@@ -305,4 +356,41 @@ mod tests {
         assert_eq!(bb.get_min(), Point::new(-1, -5, -1));
         assert_eq!(bb.get_max(), Point::new(1, 3, 1));
     }
+
+    #[test]
+    fn elliptical_cylinder() {
+        let mut cyl = Cylinder::new(-1, 1, true);
+        cyl.set_radii(2.0, 1.0);
+
+        let xs = cyl.local_intersect(&Ray::new(Point::new(-5, 0, 0), Vector::new(1, 0, 0)));
+        assert_eq!(xs.len(), 2);
+        assert!(equal(xs[0].t(), 3.0));
+        assert!(equal(xs[1].t(), 7.0));
+        let xs = cyl.local_intersect(&Ray::new(Point::new(0, 0, -5), Vector::new(0, 0, 1)));
+        assert!(equal(xs[0].t(), 4.0));
+        // the cap is an ellipse too
+        let xs = cyl.local_intersect(&Ray::new(Point::new(1.5, 5.0, 0.0), Vector::new(0, -1, 0)));
+        assert_eq!(xs.len(), 2);
+
+        let normal = |p: Point| {
+            cyl.local_normal_at(p, &Intersection::new(0.0, &cyl))
+                .normalize()
+        };
+        assert_eq!(normal(Point::new(2, 0, 0)), Vector::new(1, 0, 0));
+        let (x, z) = (2.0_f64.sqrt(), 0.5_f64.sqrt());
+        assert_eq!(
+            normal(Point::new(x, 0.0, z)),
+            Vector::new(x / 4.0, 0.0, z).normalize()
+        );
+    }
+
+    #[test]
+    fn set_radii_updates_bounds() {
+        let mut cyl = Cylinder::new(0, 1, false);
+        cyl.set_transform(crate::transform::translation(0, 0, 10));
+        cyl.set_radii(3.0, 0.5);
+        let bb = cyl.get_bounds();
+        assert_eq!(bb.get_min(), Point::new(-3.0, 0.0, 9.5));
+        assert_eq!(bb.get_max(), Point::new(3.0, 1.0, 10.5));
+    }
 }
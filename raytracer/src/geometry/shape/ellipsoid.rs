@@ -0,0 +1,134 @@
+use std::any::Any;
+
+use crate::{
+    bounding_box::BoundingBox,
+    geometry::{intersection::Intersection, BaseShape, Shape},
+    point::Point,
+    ray::Ray,
+    vector::{dot, Vector},
+};
+
+// a sphere stretched to radii rx, ry, rz without a scaling transform
+#[derive(Debug, PartialEq)]
+pub struct Ellipsoid {
+    base: BaseShape,
+    radii: Vector,
+}
+
+impl Default for Ellipsoid {
+    fn default() -> Self {
+        Self::new(1.0, 1.0, 1.0)
+    }
+}
+
+impl Ellipsoid {
+    pub fn new(rx: f64, ry: f64, rz: f64) -> Self {
+        Self {
+            base: BaseShape {
+                bounding_box: BoundingBox::new(Point::new(-rx, -ry, -rz), Point::new(rx, ry, rz)),
+                ..Default::default()
+            },
+            radii: Vector::new(rx, ry, rz),
+        }
+    }
+
+    pub fn radii(&self) -> Vector {
+        self.radii
+    }
+
+    // divides each component by the matching radius, turning the ellipsoid into a unit sphere
+    fn unit(&self, v: Vector) -> Vector {
+        Vector::new(v.x / self.radii.x, v.y / self.radii.y, v.z / self.radii.z)
+    }
+}
+
+impl Shape for Ellipsoid {
+    fn get_base(&self) -> &BaseShape {
+        &self.base
+    }
+
+    fn get_base_mut(&mut self) -> &mut BaseShape {
+        &mut self.base
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn equals(&self, other: &dyn Shape) -> bool {
+        other.as_any().downcast_ref::<Ellipsoid>() == Some(self)
+    }
+
+    fn local_intersect(&self, ray: &Ray) -> Vec<Intersection<'_>> {
+        let origin = self.unit(ray.origin() - Point::origin());
+        let direction = self.unit(ray.direction());
+        let a = dot(direction, direction);
+        let b = 2.0 * dot(direction, origin);
+        let c = dot(origin, origin) - 1.0;
+        let discriminant = b * b - 4.0 * a * c;
+
+        if discriminant < 0.0 {
+            vec![]
+        } else {
+            let t1 = (-b - discriminant.sqrt()) / (2.0 * a);
+            let t2 = (-b + discriminant.sqrt()) / (2.0 * a);
+
+            vec![Intersection::new(t1, self), Intersection::new(t2, self)]
+        }
+    }
+
+    // the gradient of (x/rx)² + (y/ry)² + (z/rz)²
+    fn local_normal_at(&self, point: Point, _intersection: &Intersection) -> Vector {
+        self.unit(self.unit(point - Point::origin()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{equal, geometry::shape::Sphere, transform::scaling};
+
+    use super::*;
+
+    #[test]
+    fn ray_intersects_ellipsoid() {
+        let e = Ellipsoid::new(2.0, 1.0, 3.0);
+        let xs = e.intersect(&Ray::new(Point::new(-5, 0, 0), Vector::new(1, 0, 0)));
+        assert_eq!(xs.len(), 2);
+        assert!(equal(xs[0].t(), 3.0));
+        assert!(equal(xs[1].t(), 7.0));
+
+        let xs = e.intersect(&Ray::new(Point::new(0, 0, -5), Vector::new(0, 0, 1)));
+        assert!(equal(xs[0].t(), 2.0));
+        assert!(equal(xs[1].t(), 8.0));
+
+        let xs = e.intersect(&Ray::new(Point::new(0.0, 1.5, -5.0), Vector::new(0, 0, 1)));
+        assert!(xs.is_empty());
+    }
+
+    #[test]
+    fn ellipsoid_matches_scaled_sphere() {
+        let e = Ellipsoid::new(2.0, 0.5, 1.5);
+        let mut s = Sphere::default();
+        s.set_transform(scaling(2.0, 0.5, 1.5));
+
+        let r = Ray::new(
+            Point::new(0.3, -2.0, -4.0),
+            Vector::new(0.1, 0.4, 1.0).normalize(),
+        );
+        let exs = e.intersect(&r);
+        let sxs = s.intersect(&r);
+        assert_eq!(exs.len(), sxs.len());
+        for (a, b) in exs.iter().zip(sxs.iter()) {
+            assert!(equal(a.t(), b.t()));
+            let p = r.position(a.t());
+            assert_eq!(e.normal_at(p, a), s.normal_at(p, b));
+        }
+    }
+
+    #[test]
+    fn ellipsoid_bounding_box() {
+        let e = Ellipsoid::new(2.0, 1.0, 3.0);
+        assert_eq!(e.get_bounds().get_min(), Point::new(-2, -1, -3));
+        assert_eq!(e.get_bounds().get_max(), Point::new(2, 1, 3));
+    }
+}
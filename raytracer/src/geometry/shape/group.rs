@@ -1,8 +1,12 @@
-use std::{any::Any, vec};
+use std::{any::Any, sync::Arc, vec};
 
 use crate::{
     bounding_box::BoundingBox,
-    geometry::{intersection::Intersection, BaseShape, Shape},
+    bounding_sphere::BoundingSphere,
+    geometry::{
+        bundle::RayBundle, intersection::Intersection, BaseShape, RayKind, Shape, Visibility,
+        DEFAULT_BOUNDS_CHECK_THRESHOLD,
+    },
     material::Material,
     matrix::Matrix,
     point::Point,
@@ -10,11 +14,51 @@ use crate::{
     vector::Vector,
 };
 
-#[derive(Debug, PartialEq)]
+// how divide sorts children into subgroups
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PartitionMode {
+    // children go to the half of the group's box that fully contains them,
+    // children straddling the split stay in the group itself
+    #[default]
+    Contained,
+    // children go to the side of the split their box center lies on, so that
+    // every child descends. splits the centers' box along its widest axis,
+    // falling back to halving the sorted children when the centers coincide
+    Centroid,
+    // like centroid, but at the split of the centers' box that the surface
+    // area heuristic expects rays to test the fewest children for, found
+    // among SAH_BINS planes along each axis. keeps long thin meshes from
+    // ending up in boxes that are mostly empty. see divide_sah
+    Sah,
+}
+
+const SAH_BINS: usize = 12;
+
+// past this many splits sah partitions split like centroid ones, so that a
+// run of lopsided splits can't make the tree as deep as the group is big
+const MAX_SAH_DEPTH: usize = 32;
+
+// refit rebuilds a divided group once its tree got this much more expensive
+// to trace than when divide built it
+const REBUILD_COST_RATIO: f64 = 1.5;
+
+#[derive(Debug)]
 pub struct Group {
     base: BaseShape,
     // TODO: make it private?
     pub children: Vec<Box<dyn Shape>>,
+    // children with infinite bounds (planes...), tested by every ray so that they
+    // don't spoil the bounding box of the finite children
+    unbounded: Vec<Box<dyn Shape>>,
+    bounded_box: BoundingBox,
+    bounds_check_threshold: usize,
+    partition_mode: PartitionMode,
+    // made by divide, to be taken apart again when the group is rebuilt
+    split: bool,
+    // how many splits below the group divide was called on
+    depth: usize,
+    // the threshold and cost of the last divide, for refit to rebuild with
+    divided: Option<(usize, f64)>,
 }
 
 impl Default for Group {
@@ -22,10 +66,32 @@ impl Default for Group {
         Self {
             base: BaseShape::default(),
             children: vec![],
+            unbounded: vec![],
+            bounded_box: BoundingBox::default(),
+            bounds_check_threshold: DEFAULT_BOUNDS_CHECK_THRESHOLD,
+            partition_mode: PartitionMode::default(),
+            split: false,
+            depth: 0,
+            divided: None,
         }
     }
 }
 
+// children are compared by value, == on shapes only matches the same shape
+impl PartialEq for Group {
+    fn eq(&self, other: &Self) -> bool {
+        let same_shapes = |a: &[Box<dyn Shape>], b: &[Box<dyn Shape>]| {
+            a.len() == b.len() && a.iter().zip(b).all(|(a, b)| a.equals(b.as_ref()))
+        };
+        self.base == other.base
+            && same_shapes(&self.children, &other.children)
+            && same_shapes(&self.unbounded, &other.unbounded)
+            && self.bounded_box == other.bounded_box
+            && self.bounds_check_threshold == other.bounds_check_threshold
+            && self.partition_mode == other.partition_mode
+    }
+}
+
 impl Shape for Group {
     fn get_base(&self) -> &BaseShape {
         &self.base
@@ -40,28 +106,52 @@ impl Shape for Group {
     }
 
     fn equals(&self, other: &dyn Shape) -> bool {
-        other
-            .as_any()
-            .downcast_ref::<Group>()
-            .map_or(false, |a| self == a)
+        other.as_any().downcast_ref::<Group>() == Some(self)
     }
 
-    fn intersect(&self, ray: &Ray) -> Vec<Intersection> {
-        if !self.get_bounds().intersects(ray) {
-            return vec![];
-        }
-
-        self.children
+    fn intersect(&self, ray: &Ray) -> Vec<Intersection<'_>> {
+        let mut xs: Vec<Intersection> = self
+            .unbounded
             .iter()
             .flat_map(|c| c.intersect(ray))
-            .collect()
+            .collect();
+
+        // the bounding sphere is around the bounded children only
+        if self.may_hit(ray) && (!self.needs_bounds_check() || self.bounded_box.intersects(ray)) {
+            xs.extend(self.children.iter().flat_map(|c| c.intersect(ray)));
+        }
+
+        xs
     }
 
-    fn local_intersect(&self, ray: &Ray) -> Vec<Intersection> {
-        self.children
+    // the rays still unblocked go through the bounds tests together and only
+    // the ones that pass visit the children. when the bundle starts inside the
+    // box every ray's line crosses it, and the box isn't tested at all
+    fn intersect_bundle(&self, bundle: &mut RayBundle, active: &[usize]) {
+        for child in &self.unbounded {
+            child.intersect_bundle(bundle, active);
+        }
+        let inside = !self.needs_bounds_check() || self.bounded_box.contains_point(bundle.origin());
+        let passing: Vec<usize> = active
             .iter()
-            .flat_map(|c| c.intersect(ray))
-            .collect()
+            .copied()
+            .filter(|&idx| {
+                let ray = bundle.ray(idx);
+                !bundle.is_blocked(idx)
+                    && self.may_hit(ray)
+                    && (inside || self.bounded_box.intersects(ray))
+            })
+            .collect();
+        if passing.is_empty() {
+            return;
+        }
+        for child in &self.children {
+            child.intersect_bundle(bundle, &passing);
+        }
+    }
+
+    fn local_intersect(&self, ray: &Ray) -> Vec<Intersection<'_>> {
+        self.all_children().flat_map(|c| c.intersect(ray)).collect()
     }
 
     fn local_normal_at(&self, _point: Point, _intersection: &Intersection) -> Vector {
@@ -71,7 +161,7 @@ impl Shape for Group {
     fn set_transform(&mut self, transform: Matrix) {
         // remove current transform from children
         let inverse = &self.get_base().transform_inverse.clone();
-        for child in &mut self.children {
+        for child in self.all_children_mut() {
             child.set_transform(inverse * &child.get_base().transform);
         }
 
@@ -84,59 +174,303 @@ impl Shape for Group {
 
         let transform = &self.get_base().transform.clone();
         let mut new_bb = BoundingBox::default();
+        let mut new_bounded_bb = BoundingBox::default();
 
         // apply new transform to children
         for child in &mut self.children {
             child.set_transform(transform * &child.get_base().transform);
             new_bb.add_bounding_box(child.get_bounds());
+            new_bounded_bb.add_bounding_box(child.get_bounds());
+        }
+        for child in &mut self.unbounded {
+            child.set_transform(transform * &child.get_base().transform);
+            new_bb = BoundingBox::infinite();
         }
         self.get_base_mut().bounding_box = new_bb;
+        self.bounded_box = new_bounded_bb;
+        self.get_base_mut().bounds_dirty = true;
+        self.update_bounding_sphere();
+    }
+
+    // around the bounded children, merging theirs. none when any of them has
+    // no sphere
+    fn parent_space_bounding_sphere(&self) -> Option<BoundingSphere> {
+        let mut spheres = self
+            .children
+            .iter()
+            .map(|c| c.parent_space_bounding_sphere());
+        let first = spheres.next()??;
+        spheres.try_fold(first, |merged, sphere| Some(merged.merge(&sphere?)))
     }
 
-    fn set_material(&mut self, material: Material) {
+    fn set_shared_material(&mut self, material: Arc<Material>) {
         self.get_base_mut().material = material.clone();
 
-        for child in &mut self.children {
-            child.set_material(material.clone());
+        for child in self.all_children_mut() {
+            child.set_shared_material(material.clone());
+        }
+    }
+
+    fn set_visibility(&mut self, visibility: Visibility) {
+        self.get_base_mut().visibility = visibility;
+
+        for child in self.all_children_mut() {
+            child.set_visibility(visibility);
+        }
+    }
+
+    fn set_visible_to(&mut self, kind: RayKind, visible: bool) {
+        self.get_base_mut().visibility.set(kind, visible);
+
+        for child in self.all_children_mut() {
+            child.set_visible_to(kind, visible);
+        }
+    }
+
+    fn set_epsilon(&mut self, epsilon: f64) {
+        self.get_base_mut().epsilon = Some(epsilon);
+
+        for child in self.all_children_mut() {
+            child.set_epsilon(epsilon);
         }
     }
 
     fn includes(&self, other: &dyn Shape) -> bool {
-        self.children.iter().any(|c| c.includes(other))
+        self.all_children().any(|c| c.includes(other))
+    }
+
+    fn for_each_child(&self, f: &mut dyn FnMut(&dyn Shape)) {
+        self.all_children().for_each(|c| f(c.as_ref()));
+    }
+
+    fn for_each_child_mut(&mut self, f: &mut dyn FnMut(&mut dyn Shape)) {
+        self.all_children_mut().for_each(|c| f(c.as_mut()));
+    }
+
+    // set_transform and add_child bake the group's transform into the children
+    fn children_in_object_space(&self) -> bool {
+        false
     }
 
     fn divide(&mut self, threshold: usize) {
-        if threshold <= self.children.len() {
-            let (left, right) = self.partition_children();
-            if !left.is_empty() {
-                self.make_subgroup(left);
-            }
-            if !right.is_empty() {
-                self.make_subgroup(right);
+        for child in self.all_children_mut() {
+            child.divide(threshold);
+        }
+        self.split_children(threshold);
+        self.divided = Some((threshold, self.cost()));
+    }
+
+    fn refit(&mut self) -> bool {
+        let mut changed = std::mem::take(&mut self.base.bounds_dirty);
+        for child in self.all_children_mut() {
+            changed |= child.refit();
+        }
+        if !changed {
+            return false;
+        }
+
+        self.update_bounds();
+        // subgroups leave the decision to the group divide was called on
+        if let (false, Some((threshold, cost))) = (self.split, self.divided) {
+            if self.cost() > cost * REBUILD_COST_RATIO {
+                self.rebuild(threshold);
             }
         }
+        true
+    }
 
-        for child in self.children.iter_mut() {
-            child.divide(threshold);
+    fn take_split_children(&mut self) -> Option<Vec<Box<dyn Shape>>> {
+        if !self.split {
+            return None;
         }
+        let mut children = std::mem::take(&mut self.children);
+        children.append(&mut self.unbounded);
+        Some(children)
     }
 }
 
 type ShapesSplit = (Vec<Box<dyn Shape>>, Vec<Box<dyn Shape>>);
 
 impl Group {
+    pub fn set_bounds_check_threshold(&mut self, threshold: usize) {
+        self.bounds_check_threshold = threshold;
+    }
+
+    fn needs_bounds_check(&self) -> bool {
+        self.children.len() > self.bounds_check_threshold && self.bounded_box.is_finite()
+    }
+
+    // subgroups created by divide inherit the mode
+    pub fn set_partition_mode(&mut self, mode: PartitionMode) {
+        self.partition_mode = mode;
+    }
+
+    pub fn partition_mode(&self) -> PartitionMode {
+        self.partition_mode
+    }
+
+    // changes the child at idx and refits the group's bounds to it
+    pub fn update_child(&mut self, idx: usize, f: impl FnOnce(&mut dyn Shape)) {
+        f(self.children[idx].as_mut());
+        self.refit();
+    }
+
+    // divides with PartitionMode::Sah until no subgroup has more than max_leaf
+    // children. groups among the children divide with their own mode
+    pub fn divide_sah(&mut self, max_leaf: usize) {
+        self.set_partition_mode(PartitionMode::Sah);
+        self.divide(max_leaf.max(1) + 1);
+    }
+
     pub fn add_child(&mut self, mut shape: Box<dyn Shape>) {
         shape.set_transform(&self.get_base().transform * &shape.get_base().transform);
-        let cbox = shape.parent_space_bounds();
-        self.get_base_mut().bounding_box.add_bounding_box(&cbox);
-        self.children.push(shape);
+        if shape.get_bounds().is_unbounded() {
+            self.get_base_mut().bounding_box = BoundingBox::infinite();
+            self.unbounded.push(shape);
+        } else {
+            let cbox = shape.parent_space_bounds();
+            self.get_base_mut().bounding_box.add_bounding_box(&cbox);
+            self.bounded_box.add_bounding_box(&cbox);
+            self.children.push(shape);
+        }
+        self.update_bounding_sphere();
+    }
+
+    pub fn add<T: 'static + Shape>(&mut self, shape: T) {
+        self.add_child(Box::new(shape));
+    }
+
+    pub fn extend<T: 'static + Shape>(&mut self, shapes: impl IntoIterator<Item = T>) {
+        for shape in shapes {
+            self.add(shape);
+        }
+    }
+
+    pub fn unbounded_children(&self) -> &[Box<dyn Shape>] {
+        &self.unbounded
+    }
+
+    fn all_children(&self) -> impl Iterator<Item = &Box<dyn Shape>> {
+        self.children.iter().chain(self.unbounded.iter())
+    }
+
+    fn all_children_mut(&mut self) -> impl Iterator<Item = &mut Box<dyn Shape>> {
+        self.children.iter_mut().chain(self.unbounded.iter_mut())
     }
 
     fn partition_children(&mut self) -> ShapesSplit {
+        match self.partition_mode {
+            PartitionMode::Contained => self.partition_contained(),
+            PartitionMode::Centroid => self.partition_centroid(),
+            PartitionMode::Sah => self.partition_sah(),
+        }
+    }
+
+    fn partition_sah(&mut self) -> ShapesSplit {
+        if self.children.len() < 2 || self.depth >= MAX_SAH_DEPTH {
+            return self.partition_centroid();
+        }
+
+        let boxes: Vec<BoundingBox> = self
+            .children
+            .iter()
+            .map(|c| c.parent_space_bounds())
+            .collect();
+        let mut centers = BoundingBox::default();
+        for b in &boxes {
+            centers.add_point(b.center());
+        }
+        let (min, max) = (centers.get_min(), centers.get_max());
+        let (min, max) = ([min.x, min.y, min.z], [max.x, max.y, max.z]);
+        let bin = |b: &BoundingBox, axis: usize| {
+            let c = b.center();
+            let offset = ([c.x, c.y, c.z][axis] - min[axis]) / (max[axis] - min[axis]);
+            ((offset * SAH_BINS as f64) as usize).min(SAH_BINS - 1)
+        };
+
+        // the cost, axis and first bin on the right of the best split
+        let mut best: Option<(f64, usize, usize)> = None;
+        for axis in (0..3).filter(|&axis| max[axis] > min[axis]) {
+            let mut bins = vec![(0usize, BoundingBox::default()); SAH_BINS];
+            for b in &boxes {
+                let (count, bounds) = &mut bins[bin(b, axis)];
+                *count += 1;
+                bounds.add_bounding_box(b);
+            }
+            // the count and area of everything left of each plane, then right
+            let sweep = |bins: &mut dyn Iterator<Item = &(usize, BoundingBox)>| {
+                let mut total = (0, BoundingBox::default());
+                bins.map(|(count, bounds)| {
+                    if *count > 0 {
+                        total.0 += count;
+                        total.1.add_bounding_box(bounds);
+                    }
+                    (total.0, total.1.surface_area())
+                })
+                .collect::<Vec<_>>()
+            };
+            let left = sweep(&mut bins.iter());
+            let mut right = sweep(&mut bins.iter().rev());
+            right.reverse();
+            for split in 1..SAH_BINS {
+                let ((lcount, larea), (rcount, rarea)) = (left[split - 1], right[split]);
+                if lcount == 0 || rcount == 0 {
+                    continue;
+                }
+                let cost = lcount as f64 * larea + rcount as f64 * rarea;
+                if best.is_none_or(|(best_cost, _, _)| cost < best_cost) {
+                    best = Some((cost, axis, split));
+                }
+            }
+        }
+
+        match best {
+            Some((_, axis, split)) => std::mem::take(&mut self.children)
+                .into_iter()
+                .partition(|c| bin(&c.parent_space_bounds(), axis) < split),
+            None => self.partition_centroid(),
+        }
+    }
+
+    fn partition_centroid(&mut self) -> ShapesSplit {
+        // a single child would end up alone in a subgroup, dividing forever
+        if self.children.len() < 2 {
+            return (vec![], vec![]);
+        }
+
+        let mut centers = BoundingBox::default();
+        for child in &self.children {
+            centers.add_point(child.parent_space_bounds().center());
+        }
+        let (min, max) = (centers.get_min(), centers.get_max());
+        let extents = [max.x - min.x, max.y - min.y, max.z - min.z];
+        let axis = (0..3)
+            .max_by(|&a, &b| extents[a].total_cmp(&extents[b]))
+            .unwrap();
+        let coord = |shape: &dyn Shape| {
+            let c = shape.parent_space_bounds().center();
+            [c.x, c.y, c.z][axis]
+        };
+
+        let children = std::mem::take(&mut self.children);
+        let mid = [min.x + max.x, min.y + max.y, min.z + max.z][axis] / 2.0;
+        let (left, right): ShapesSplit =
+            children.into_iter().partition(|c| coord(c.as_ref()) < mid);
+        if !left.is_empty() && !right.is_empty() {
+            return (left, right);
+        }
+
+        let mut children = if left.is_empty() { right } else { left };
+        children.sort_by(|a, b| coord(a.as_ref()).total_cmp(&coord(b.as_ref())));
+        let right = children.split_off(children.len() / 2);
+        (children, right)
+    }
+
+    fn partition_contained(&mut self) -> ShapesSplit {
         let mut left = vec![];
         let mut right = vec![];
 
-        let (left_bb, right_bb) = self.get_bounds().split();
+        let (left_bb, right_bb) = self.bounded_box.split();
 
         let mut i = 0;
         while i != self.children.len() {
@@ -188,22 +522,86 @@ impl Group {
         (left, right)
     }
 
-    fn make_subgroup(&mut self, shapes: Vec<Box<dyn Shape>>) {
+    // partitions the children into subgroups and those in turn, the children
+    // themselves are already divided
+    fn split_children(&mut self, threshold: usize) {
+        if threshold <= self.children.len() {
+            let (left, right) = self.partition_children();
+            if !left.is_empty() {
+                self.make_subgroup(left, threshold);
+            }
+            if !right.is_empty() {
+                self.make_subgroup(right, threshold);
+            }
+        }
+    }
+
+    fn make_subgroup(&mut self, shapes: Vec<Box<dyn Shape>>, threshold: usize) {
         let mut g = Group::default();
+        g.set_partition_mode(self.partition_mode);
+        g.split = true;
+        g.depth = self.depth + 1;
         for shape in shapes {
             g.add_child(shape);
         }
+        g.set_bounding_sphere_check(self.base.bounding_sphere_check);
+        g.split_children(threshold);
         self.children.push(Box::new(g));
     }
+
+    // the surface area heuristic without its constants: how many boxes and
+    // shapes a ray through the group's box is expected to be tested against
+    fn cost(&self) -> f64 {
+        let area = self.bounded_box.surface_area();
+        self.children
+            .iter()
+            .map(|child| match child.as_any().downcast_ref::<Group>() {
+                Some(g) if g.split && area > 0.0 && area.is_finite() => {
+                    1.0 + g.bounded_box.surface_area() / area * g.cost()
+                }
+                _ => 1.0,
+            })
+            .sum()
+    }
+
+    fn update_bounds(&mut self) {
+        let mut bb = BoundingBox::default();
+        let mut bounded_bb = BoundingBox::default();
+        for child in &self.children {
+            bb.add_bounding_box(child.get_bounds());
+            bounded_bb.add_bounding_box(child.get_bounds());
+        }
+        if !self.unbounded.is_empty() {
+            bb = BoundingBox::infinite();
+        }
+        self.base.bounding_box = bb;
+        self.bounded_box = bounded_bb;
+        self.update_bounding_sphere();
+    }
+
+    // undoes the subgroups divide made and divides the children again
+    fn rebuild(&mut self, threshold: usize) {
+        let mut pending = std::mem::take(&mut self.children);
+        while let Some(mut child) = pending.pop() {
+            match child.take_split_children() {
+                Some(children) => pending.extend(children),
+                None => self.children.push(child),
+            }
+        }
+        self.split_children(threshold);
+        self.divided = Some((threshold, self.cost()));
+    }
 }
 
 #[cfg(test)]
 mod tests {
 
     use crate::{
+        equal,
         geometry::{
             intersection::intersections,
-            shape::{Cylinder, Sphere},
+            shape::{Cylinder, Plane, Sphere, Triangle},
+            visitor::for_each_leaf,
             Shape,
         },
         matrix::Matrix,
@@ -228,6 +626,23 @@ mod tests {
         assert!(!g.children.is_empty());
     }
 
+    #[test]
+    fn add_shapes_without_boxing_them() {
+        let mut g = Group::default();
+        g.set_transform(translation(1, 0, 0));
+        g.add(Sphere::default());
+        g.extend((0..3).map(|i| {
+            let mut s = Sphere::default();
+            s.set_transform(translation(0, 0, 3 * i));
+            s
+        }));
+        g.extend(vec![Plane::default()]);
+
+        assert_eq!(g.children.len(), 4);
+        assert_eq!(g.unbounded_children().len(), 1);
+        assert_eq!(g.children[3].transform(), &translation(1, 0, 6));
+    }
+
     #[test]
     fn intersect_ray_with_empty_group() {
         let g = Group::default();
@@ -332,7 +747,7 @@ mod tests {
         let s2 = Sphere::default();
 
         let mut g = Group::default();
-        g.make_subgroup(vec![Box::new(s1), Box::new(s2)]);
+        g.make_subgroup(vec![Box::new(s1), Box::new(s2)], usize::MAX);
 
         assert_eq!(g.children.len(), 1);
         let g0 = g.children[0].as_any().downcast_ref::<Group>().unwrap();
@@ -389,4 +804,341 @@ mod tests {
             .unwrap();
         assert_eq!(s2.transform(), &translation(-2, 2, 0));
     }
+
+    #[test]
+    fn tags_reach_every_child() {
+        let mut inner = Group::default();
+        inner.add_child(Box::new(Sphere::default()));
+        let mut g = Group::default();
+        g.add_child(Box::new(inner));
+        g.add_child(Box::new(Plane::default()));
+        g.add_tag("glass");
+        g.add_tag("glass");
+
+        assert_eq!(g.tags(), ["glass"]);
+        let mut tagged = 0;
+        for_each_leaf(&g, |leaf, _| {
+            assert!(leaf.has_tag("glass") && !leaf.has_tag("hero"));
+            tagged += 1;
+        });
+        assert_eq!(tagged, 2);
+        // tags don't make shapes different
+        assert!(g.children[0].equals(g.children[0].as_ref()));
+        let mut untagged = Sphere::default();
+        assert!(!untagged.has_tag("glass"));
+        untagged.add_tag("glass");
+        assert!(untagged.equals(&Sphere::default()));
+    }
+
+    #[test]
+    fn unbounded_children_are_kept_apart() {
+        let mut g = Group::default();
+        g.add_child(Box::new(Sphere::default()));
+        g.add_child(Box::new(Plane::default()));
+
+        assert_eq!(g.children.len(), 1);
+        assert_eq!(g.unbounded_children().len(), 1);
+        assert!(!g.get_bounds().is_finite());
+        assert_eq!(
+            g.bounded_box,
+            BoundingBox::new(Point::new(-1, -1, -1), Point::new(1, 1, 1))
+        );
+    }
+
+    #[test]
+    fn unbounded_children_are_always_intersected() {
+        let mut g = Group::default();
+        g.add_child(Box::new(Sphere::default()));
+        let mut plane = Plane::default();
+        plane.set_transform(translation(0, -5, 0));
+        g.add_child(Box::new(plane));
+
+        // misses the sphere's box but hits the plane
+        let r = Ray::new(Point::new(10, 0, 0), Vector::new(0, -1, 0));
+        let xs = g.intersect(&r);
+        assert_eq!(xs.len(), 1);
+        assert!(equal(xs[0].t(), 5.0));
+    }
+
+    #[test]
+    fn dividing_group_ignores_unbounded_children() {
+        let mut s1 = Sphere::default();
+        s1.set_transform(translation(-2, 0, 0));
+        let mut s2 = Sphere::default();
+        s2.set_transform(translation(2, 0, 0));
+
+        let mut g = Group::default();
+        g.add_child(Box::new(s1));
+        g.add_child(Box::new(s2));
+        g.add_child(Box::new(Plane::default()));
+        g.divide(1);
+
+        assert_eq!(g.children.len(), 2);
+        assert!(g.children.iter().all(|c| c.as_any().is::<Group>()));
+        assert_eq!(g.unbounded_children().len(), 1);
+    }
+
+    fn row_of_spheres() -> Group {
+        let mut g = Group::default();
+        for x in [-1.5, -0.5, 0.5, 1.5] {
+            let mut s = Sphere::default();
+            s.set_transform(translation(x, 0.0, 0.0));
+            g.add_child(Box::new(s));
+        }
+        g
+    }
+
+    #[test]
+    fn centroid_partition_moves_straddling_children() {
+        let mut contained = row_of_spheres();
+        contained.divide(2);
+        // the spheres at -0.5 and 0.5 straddle x = 0 and stay behind
+        assert_eq!(contained.children.len(), 4);
+        assert_eq!(
+            contained
+                .children
+                .iter()
+                .filter(|c| c.as_any().is::<Sphere>())
+                .count(),
+            2
+        );
+
+        let mut centroid = row_of_spheres();
+        centroid.set_partition_mode(PartitionMode::Centroid);
+        centroid.divide(2);
+        assert_eq!(centroid.children.len(), 2);
+        for child in &centroid.children {
+            let subgroup = child.as_any().downcast_ref::<Group>().unwrap();
+            assert_eq!(subgroup.partition_mode(), PartitionMode::Centroid);
+            assert_eq!(subgroup.children.len(), 2);
+        }
+    }
+
+    #[test]
+    fn centroid_partition_splits_coincident_centers_in_half() {
+        let mut g = Group::default();
+        g.set_partition_mode(PartitionMode::Centroid);
+        for i in 1..=3 {
+            g.add_child(Box::new({
+                let mut s = Sphere::default();
+                s.set_transform(scaling(i, i, i));
+                s
+            }));
+        }
+        g.divide(3);
+        assert_eq!(g.children.len(), 2);
+        assert!(g.children.iter().all(|c| c.as_any().is::<Group>()));
+    }
+
+    #[test]
+    fn partition_modes_intersect_alike() {
+        let undivided = row_of_spheres();
+        let mut contained = row_of_spheres();
+        contained.divide(1);
+        let mut centroid = row_of_spheres();
+        centroid.set_partition_mode(PartitionMode::Centroid);
+        centroid.divide(1);
+        let mut sah = row_of_spheres();
+        sah.divide_sah(1);
+
+        let t = |g: &Group, r: &Ray| {
+            let xs = g.intersect(r);
+            intersections(&xs).iter().map(|i| i.t()).collect::<Vec<_>>()
+        };
+        for r in [
+            Ray::new(Point::new(-5, 0, 0), Vector::new(1, 0, 0)),
+            Ray::new(Point::new(0.2, 0.5, -5.0), Vector::new(0, 0, 1)),
+            Ray::new(
+                Point::new(-1, 5, 0),
+                Vector::new(0.3, -1.0, 0.0).normalize(),
+            ),
+        ] {
+            assert_eq!(t(&centroid, &r), t(&undivided, &r));
+            assert_eq!(t(&sah, &r), t(&undivided, &r));
+            assert_eq!(t(&contained, &r), t(&undivided, &r));
+        }
+    }
+
+    // a long diagonal strip of thin triangles, like the meshes the middle
+    // splits do badly on
+    fn strip(g: &mut Group) {
+        for i in 0..64 {
+            let (x, y) = (i as f64, i as f64 * 0.5);
+            g.add_child(Box::new(Triangle::new(
+                Point::new(x, y, 0.0),
+                Point::new(x + 1.0, y + 0.5, 0.0),
+                Point::new(x, y, 0.2),
+            )));
+        }
+        g.add_child(Box::new(Sphere::default()));
+    }
+
+    #[test]
+    fn sah_partition_keeps_leaves_small_and_cheap() {
+        let mut sah = Group::default();
+        strip(&mut sah);
+        sah.divide_sah(4);
+        let mut leaves = 0;
+        for_each_group(&sah, &mut |g| {
+            assert_eq!(g.partition_mode(), PartitionMode::Sah);
+            let shapes = g
+                .children
+                .iter()
+                .filter(|c| !c.as_any().is::<Group>())
+                .count();
+            assert!(shapes <= 4);
+            leaves += shapes;
+        });
+        assert_eq!(leaves, 65);
+
+        let mut centroid = Group::default();
+        centroid.set_partition_mode(PartitionMode::Centroid);
+        strip(&mut centroid);
+        centroid.divide(5);
+        assert!(sah.cost() <= centroid.cost());
+
+        let mut unsplittable = Group::default();
+        for _ in 0..3 {
+            unsplittable.add_child(Box::new(Sphere::default()));
+        }
+        unsplittable.divide_sah(1);
+        for_each_group(&unsplittable, &mut |g| assert!(g.children.len() <= 2));
+    }
+
+    fn for_each_group(g: &Group, f: &mut dyn FnMut(&Group)) {
+        f(g);
+        for child in &g.children {
+            if let Some(sub) = child.as_any().downcast_ref::<Group>() {
+                for_each_group(sub, f);
+            }
+        }
+    }
+
+    fn for_each_leaf_mut(shape: &mut dyn Shape, f: &mut dyn FnMut(&mut dyn Shape)) {
+        if shape.as_any().is::<Group>() {
+            shape.for_each_child_mut(&mut |child| for_each_leaf_mut(child, f));
+        } else {
+            f(shape);
+        }
+    }
+
+    fn long_row_of_spheres() -> Group {
+        let mut g = Group::default();
+        g.set_partition_mode(PartitionMode::Centroid);
+        for i in 0..16 {
+            let mut s = Sphere::default();
+            s.set_transform(translation(i as f64 * 3.0, 0.0, 0.0));
+            g.add_child(Box::new(s));
+        }
+        g
+    }
+
+    #[test]
+    fn refit_follows_moved_children() {
+        let mut g = long_row_of_spheres();
+        g.divide(1);
+        let divided = g.divided;
+        assert!(g.refit());
+        assert!(!g.refit());
+
+        for_each_leaf_mut(&mut g, &mut |s| {
+            if s.transform() == &translation(0, 0, 0) {
+                s.set_transform(translation(0, 10, 0));
+            }
+        });
+        let r = Ray::new(Point::new(0, 10, -5), Vector::new(0, 0, 1));
+        assert!(g.intersect(&r).is_empty());
+        assert!(g.refit());
+        assert_eq!(g.intersect(&r).len(), 2);
+        assert_eq!(g.get_bounds().get_max().y, 11.0);
+        // one sphere moving doesn't need a new tree
+        assert_eq!(g.divided, divided);
+    }
+
+    #[test]
+    fn updated_children_are_hit_at_once() {
+        let mut g = long_row_of_spheres();
+        let r = Ray::new(Point::new(0, 10, -5), Vector::new(0, 0, 1));
+        g.update_child(0, |s| s.set_transform(translation(0, 10, 0)));
+        assert_eq!(g.intersect(&r).len(), 2);
+        assert_eq!(g.get_bounds().get_max().y, 11.0);
+    }
+
+    // swaps every other sphere of the left half with its mirror image in the
+    // right half, so that both halves of the tree span the whole row
+    fn interleave_halves(g: &mut Group) {
+        for_each_leaf_mut(g, &mut |s| {
+            let x = (s.transform() * Point::origin()).x;
+            let i = (x / 3.0).round() as usize;
+            if (i < 8) == i.is_multiple_of(2) {
+                s.set_transform(translation(45.0 - x, 0.0, 0.0));
+            }
+        });
+    }
+
+    #[test]
+    fn refit_rebuilds_degraded_trees() {
+        let mut g = long_row_of_spheres();
+        g.divide(1);
+        let (_, cost) = g.divided.unwrap();
+
+        interleave_halves(&mut g);
+        g.refit();
+        assert!(g.divided.unwrap().1 < cost * REBUILD_COST_RATIO);
+        assert!(equal(g.cost(), cost));
+
+        let mut undivided = long_row_of_spheres();
+        interleave_halves(&mut undivided);
+        for i in 0..16 {
+            let r = Ray::new(Point::new(i as f64 * 3.0, 0.0, -5.0), Vector::new(0, 0, 1));
+            let t = |g: &Group| {
+                let xs = g.intersect(&r);
+                intersections(&xs).iter().map(|i| i.t()).collect::<Vec<_>>()
+            };
+            assert_eq!(t(&g), vec![4.0, 6.0]);
+            assert_eq!(t(&g), t(&undivided));
+        }
+    }
+
+    #[test]
+    fn bounding_sphere_check_rejects_box_corners() {
+        let mut g = Group::default();
+        g.set_bounding_sphere_check(true);
+        let mut s = Sphere::default();
+        s.set_transform(scaling(2, 2, 2));
+        g.add_child(Box::new(s));
+        let sphere = *g.bounding_sphere().unwrap();
+        assert_eq!(sphere, BoundingSphere::new(Point::origin(), 2.0));
+
+        let corner = Ray::new(Point::new(1.9, 1.9, -5.0), Vector::new(0, 0, 1));
+        assert!(g.get_bounds().intersects(&corner));
+        assert!(!g.may_hit(&corner));
+        assert!(g.intersect(&corner).is_empty());
+
+        g.set_transform(translation(10, 0, 0));
+        assert_eq!(
+            g.bounding_sphere().unwrap().get_center(),
+            Point::new(10, 0, 0)
+        );
+        g.set_bounding_sphere_check(false);
+        assert!(g.bounding_sphere().is_none());
+    }
+
+    #[test]
+    fn divided_groups_keep_the_bounding_sphere_check() {
+        let mut g = long_row_of_spheres();
+        g.set_bounding_sphere_check(true);
+        let sphere = g.bounding_sphere().unwrap();
+        assert_eq!(sphere.get_center(), Point::new(22.5, 0.0, 0.0));
+        assert!(equal(sphere.get_radius(), 23.5));
+
+        g.divide(1);
+        for_each_leaf(&g, |s, _| assert!(s.bounding_sphere().is_none()));
+        g.for_each_child(&mut |c| assert!(c.bounding_sphere().is_some()));
+        let undivided = long_row_of_spheres();
+        for i in 0..32 {
+            let r = Ray::new(Point::new(i as f64 * 1.5, 0.5, -5.0), Vector::new(0, 0, 1));
+            assert_eq!(g.intersect(&r).len(), undivided.intersect(&r).len());
+        }
+    }
 }
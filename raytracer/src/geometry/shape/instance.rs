@@ -0,0 +1,212 @@
+use std::{any::Any, sync::Arc};
+
+use crate::{
+    geometry::{intersection::Intersection, visitor::walk, BaseShape, Shape},
+    material::Material,
+    point::Point,
+    ray::Ray,
+    vector::Vector,
+};
+
+// places a shape that other instances share, with a transform of its own.
+// once it is given a material that shades every shape in it, and its
+// visibility hides them from the rays it hides itself from. the shared shape
+// can't be changed through one of its instances, and instances can't hold
+// other instances
+#[derive(Debug)]
+pub struct Instance {
+    base: BaseShape,
+    shape: Arc<dyn Shape>,
+    // whether the instance's material is used instead of the shared shape's
+    has_material: bool,
+}
+
+impl Instance {
+    pub fn new(shape: Arc<dyn Shape>) -> Self {
+        let mut nested = false;
+        walk(shape.as_ref(), &mut |s: &dyn Shape, _: &_, _| {
+            nested |= s.as_any().is::<Instance>();
+            !nested
+        });
+        assert!(!nested, "instances can't hold other instances");
+        Self {
+            base: BaseShape {
+                bounding_box: shape.parent_space_bounds(),
+                ..Default::default()
+            },
+            shape,
+            has_material: false,
+        }
+    }
+
+    pub fn shape(&self) -> &Arc<dyn Shape> {
+        &self.shape
+    }
+
+    pub fn has_material(&self) -> bool {
+        self.has_material
+    }
+}
+
+impl Shape for Instance {
+    fn get_base(&self) -> &BaseShape {
+        &self.base
+    }
+
+    fn get_base_mut(&mut self) -> &mut BaseShape {
+        &mut self.base
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn equals(&self, other: &dyn Shape) -> bool {
+        other.as_any().downcast_ref::<Instance>().is_some_and(|a| {
+            self.get_base() == other.get_base()
+                && self.has_material == a.has_material
+                && self.shape.equals(a.shape.as_ref())
+        })
+    }
+
+    fn material_mut(&mut self) -> &mut Material {
+        self.has_material = true;
+        Arc::make_mut(&mut self.base.material)
+    }
+
+    fn set_shared_material(&mut self, material: Arc<Material>) {
+        self.has_material = true;
+        self.base.material = material;
+    }
+
+    // hits on the shared shape remember the instance, which turns them into
+    // the world's space when they are shaded and gives them its material and
+    // visibility
+    fn local_intersect(&self, ray: &Ray) -> Vec<Intersection<'_>> {
+        self.shape
+            .intersect(ray)
+            .into_iter()
+            .map(|i| i.through(self))
+            .collect()
+    }
+
+    fn local_normal_at(&self, _point: Point, _intersection: &Intersection) -> Vector {
+        unreachable!()
+    }
+
+    fn for_each_child(&self, f: &mut dyn FnMut(&dyn Shape)) {
+        f(self.shape.as_ref());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        color::Color,
+        geometry::{
+            intersection::hit,
+            shape::{Csg, Operation, Sphere},
+            RayKind, Visibility,
+        },
+        light::PointLight,
+        transform::{scaling, translation},
+        world::World,
+    };
+
+    use super::*;
+
+    #[test]
+    fn instances_share_their_shape() {
+        let shape: Arc<dyn Shape> = Arc::new(Sphere::default());
+        let mut a = Instance::new(shape.clone());
+        a.set_transform(translation(5, 0, 0));
+        let mut b = Instance::new(shape.clone());
+        b.set_transform(scaling(2, 2, 2));
+        assert_eq!(Arc::strong_count(&shape), 3);
+        assert_eq!(a.get_bounds().get_min(), Point::new(4, -1, -1));
+        assert_eq!(b.get_bounds().get_max(), Point::new(2, 2, 2));
+
+        let mut w = World::new();
+        w.add_object(a);
+        w.add_object(b);
+        let r = Ray::new(Point::new(5, 0, -5), Vector::new(0, 0, 1));
+        let xs = w.intersect(&r);
+        let i = hit(&xs).unwrap();
+        assert_eq!(i.t(), 4.0);
+        assert!(i.object().includes(shape.as_ref()));
+        let comps = i.prepare_computations(&r, &xs);
+        assert_eq!(comps.normalv, Vector::new(0, 0, -1));
+        assert_eq!(comps.point, Point::new(5, 0, -1));
+        // patterns on the shared shape see it where it is in its own space
+        assert!(crate::equal(comps.surface_point.x, 0.0));
+
+        let r = Ray::new(Point::new(0, 0, -5), Vector::new(0, 0, 1));
+        let xs = w.intersect(&r);
+        let i = hit(&xs).unwrap();
+        assert_eq!(i.t(), 3.0);
+        assert_eq!(
+            i.prepare_computations(&r, &xs).normalv,
+            Vector::new(0, 0, -1)
+        );
+    }
+
+    #[test]
+    fn instances_shade_with_their_material_and_visibility() {
+        let mut sphere = Sphere::default();
+        sphere.material_mut().color = Color::new(1.0, 0.0, 0.0);
+        let shape: Arc<dyn Shape> = Arc::new(sphere);
+        let mut plain = Instance::new(shape.clone());
+        plain.set_transform(translation(5, 0, 0));
+        let mut blue = Instance::new(shape.clone());
+        blue.set_transform(translation(-5, 0, 0));
+        blue.material_mut().color = Color::new(0.0, 0.0, 1.0);
+        blue.set_visibility(Visibility {
+            shadow: false,
+            ..Default::default()
+        });
+        assert!(!plain.has_material() && blue.has_material());
+
+        let mut w = World::new();
+        w.add_object(plain);
+        w.add_object(blue);
+        let color_at = |x: f64| {
+            let r = Ray::new(Point::new(x, 0.0, -5.0), Vector::new(0, 0, 1));
+            let xs = w.intersect(&r);
+            let comps = hit(&xs).unwrap().prepare_computations(&r, &xs);
+            w.material_of(comps.material_shape).color
+        };
+        assert_eq!(color_at(5.0), Color::new(1.0, 0.0, 0.0));
+        assert_eq!(color_at(-5.0), Color::new(0.0, 0.0, 1.0));
+
+        // the shared sphere casts shadows, the blue instance of it doesn't
+        let shadowed = |x: f64| {
+            let light = PointLight::new(Point::new(x, 10.0, 0.0), Color::white()).into();
+            w.is_shadowed(Point::new(x, -5.0, 0.0), &light)
+        };
+        assert!(shadowed(5.0));
+        assert!(!shadowed(-5.0));
+        let down = Ray::new(Point::new(-5, 5, 0), Vector::new(0, -1, 0));
+        assert_eq!(w.intersect_for(&down, RayKind::Shadow).len(), 0);
+        assert_eq!(w.intersect_for(&down, RayKind::Camera).len(), 2);
+    }
+
+    #[test]
+    fn instances_of_one_shape_tell_csg_operands_apart() {
+        let shape: Arc<dyn Shape> = Arc::new(Sphere::default());
+        let left = Instance::new(shape.clone());
+        let mut right = Instance::new(shape.clone());
+        right.set_transform(translation(0.0, 0.0, 0.5));
+        let csg = Csg::new(Operation::Difference, left, right);
+
+        let r = Ray::new(Point::new(0, 0, -5), Vector::new(0, 0, 1));
+        let ts: Vec<f64> = csg.intersect(&r).iter().map(|i| i.t()).collect();
+        assert_eq!(ts, vec![4.0, 4.5]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn instances_do_not_nest() {
+        let inner: Arc<dyn Shape> = Arc::new(Instance::new(Arc::new(Sphere::default())));
+        Instance::new(inner);
+    }
+}
@@ -2,7 +2,9 @@ mod cone;
 mod csg;
 mod cube;
 mod cylinder;
+mod ellipsoid;
 mod group;
+mod instance;
 mod plane;
 mod smooth_triangle;
 mod sphere;
@@ -14,7 +16,10 @@ pub use self::csg::Csg;
 pub use self::csg::Operation;
 pub use self::cube::Cube;
 pub use self::cylinder::Cylinder;
+pub use self::ellipsoid::Ellipsoid;
 pub use self::group::Group;
+pub use self::group::PartitionMode;
+pub use self::instance::Instance;
 pub use self::plane::Plane;
 pub use self::smooth_triangle::SmoothTriangle;
 pub use self::sphere::Sphere;
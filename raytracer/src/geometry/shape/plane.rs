@@ -1,10 +1,8 @@
 use std::any::Any;
 
-use std::f64::{INFINITY, NEG_INFINITY};
-
 use crate::{
     bounding_box::BoundingBox,
-    geometry::{intersection::Intersection, BaseShape, Shape},
+    geometry::{intersection::Intersection, uv::planar_map, BaseShape, Shape},
     point::Point,
     ray::Ray,
     vector::Vector,
@@ -21,8 +19,8 @@ impl Default for Plane {
         Self {
             base: BaseShape {
                 bounding_box: BoundingBox::new(
-                    Point::new(NEG_INFINITY, 0.0, NEG_INFINITY),
-                    Point::new(INFINITY, 0.0, INFINITY),
+                    Point::new(f64::NEG_INFINITY, 0.0, f64::NEG_INFINITY),
+                    Point::new(f64::INFINITY, 0.0, f64::INFINITY),
                 ),
                 ..Default::default()
             },
@@ -44,13 +42,10 @@ impl Shape for Plane {
     }
 
     fn equals(&self, other: &dyn Shape) -> bool {
-        other
-            .as_any()
-            .downcast_ref::<Plane>()
-            .map_or(false, |a| self == a)
+        other.as_any().downcast_ref::<Plane>() == Some(self)
     }
 
-    fn local_intersect(&self, ray: &Ray) -> Vec<Intersection> {
+    fn local_intersect(&self, ray: &Ray) -> Vec<Intersection<'_>> {
         if ray.direction().y.abs() < EPSILON {
             vec![]
         } else {
@@ -62,6 +57,10 @@ impl Shape for Plane {
     fn local_normal_at(&self, _point: Point, _intersection: &Intersection) -> Vector {
         Vector::new(0, 1, 0)
     }
+
+    fn local_uv_at(&self, point: Point, _intersection: &Intersection) -> Option<(f64, f64)> {
+        Some(planar_map(point))
+    }
 }
 
 #[cfg(test)]
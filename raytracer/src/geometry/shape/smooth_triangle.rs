@@ -64,17 +64,16 @@ impl Shape for SmoothTriangle {
     }
 
     fn equals(&self, other: &dyn Shape) -> bool {
-        other
-            .as_any()
-            .downcast_ref::<SmoothTriangle>()
-            .map_or(false, |a| self == a)
+        other.as_any().downcast_ref::<SmoothTriangle>() == Some(self)
     }
 
-    fn local_intersect(&self, ray: &Ray) -> Vec<Intersection> {
+    fn local_intersect(&self, ray: &Ray) -> Vec<Intersection<'_>> {
         let dir_cross_e2 = cross(ray.direction(), self.e2);
         let det = dot(self.e1, dir_cross_e2);
 
-        if det.abs() < EPSILON {
+        // det goes with the square of the triangle's size, so tiny triangles
+        // get a smaller threshold. only worked out when det is small already
+        if det.abs() < EPSILON && det.abs() < EPSILON * (self.epsilon() / EPSILON).powi(2) {
             return vec![];
         }
 
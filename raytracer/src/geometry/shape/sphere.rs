@@ -2,7 +2,8 @@ use std::any::Any;
 
 use crate::{
     bounding_box::BoundingBox,
-    geometry::{intersection::Intersection, BaseShape, Shape},
+    bounding_sphere::BoundingSphere,
+    geometry::{intersection::Intersection, uv::spherical_map, BaseShape, Shape},
     point::Point,
     ray::Ray,
     vector::{dot, Vector},
@@ -38,13 +39,19 @@ impl Shape for Sphere {
     }
 
     fn equals(&self, other: &dyn Shape) -> bool {
-        other
-            .as_any()
-            .downcast_ref::<Sphere>()
-            .map_or(false, |a| self == a)
+        other.as_any().downcast_ref::<Sphere>() == Some(self)
     }
 
-    fn local_intersect(&self, ray: &Ray) -> Vec<Intersection> {
+    fn local_uv_at(&self, point: Point, _intersection: &Intersection) -> Option<(f64, f64)> {
+        Some(spherical_map(point))
+    }
+
+    // exact rather than around the box
+    fn parent_space_bounding_sphere(&self) -> Option<BoundingSphere> {
+        Some(BoundingSphere::new(Point::origin(), 1.0).transform(self.transform()))
+    }
+
+    fn local_intersect(&self, ray: &Ray) -> Vec<Intersection<'_>> {
         let sphere_to_ray = ray.origin() - Point::origin();
         let a = dot(ray.direction(), ray.direction());
         let b = 2.0 * dot(ray.direction(), sphere_to_ray);
@@ -69,8 +76,8 @@ impl Shape for Sphere {
 impl Sphere {
     pub fn glass() -> Sphere {
         let mut sphere = Sphere::default();
-        sphere.get_base_mut().material.transparency = 1.0;
-        sphere.get_base_mut().material.refractive_index = 1.5;
+        sphere.material_mut().transparency = 1.0;
+        sphere.material_mut().refractive_index = 1.5;
 
         sphere
     }
@@ -240,8 +247,8 @@ mod tests {
     fn create_glass_sphere() {
         let s = Sphere::glass();
         assert_eq!(s.transform(), &Matrix::identity(4, 4));
-        assert!(equal(s.get_base().material.transparency, 1.0));
-        assert!(equal(s.get_base().material.refractive_index, 1.5));
+        assert!(equal(s.material().transparency, 1.0));
+        assert!(equal(s.material().refractive_index, 1.5));
     }
 
     #[test]
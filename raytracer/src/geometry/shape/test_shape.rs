@@ -43,7 +43,7 @@ impl Shape for TestShape {
         self.get_base() == other.get_base()
     }
 
-    fn local_intersect(&self, ray: &Ray) -> Vec<Intersection> {
+    fn local_intersect(&self, ray: &Ray) -> Vec<Intersection<'_>> {
         *self.saved_ray.write().unwrap() = Ray::new(ray.origin(), ray.direction());
         vec![]
     }
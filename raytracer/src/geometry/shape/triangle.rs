@@ -58,17 +58,16 @@ impl Shape for Triangle {
     }
 
     fn equals(&self, other: &dyn Shape) -> bool {
-        other
-            .as_any()
-            .downcast_ref::<Triangle>()
-            .map_or(false, |a| self == a)
+        other.as_any().downcast_ref::<Triangle>() == Some(self)
     }
 
-    fn local_intersect(&self, ray: &Ray) -> Vec<Intersection> {
+    fn local_intersect(&self, ray: &Ray) -> Vec<Intersection<'_>> {
         let dir_cross_e2 = cross(ray.direction(), self.e2);
         let det = dot(self.e1, dir_cross_e2);
 
-        if det.abs() < EPSILON {
+        // det goes with the square of the triangle's size, so tiny triangles
+        // get a smaller threshold. only worked out when det is small already
+        if det.abs() < EPSILON && det.abs() < EPSILON * (self.epsilon() / EPSILON).powi(2) {
             return vec![];
         }
 
@@ -195,6 +194,19 @@ mod tests {
         assert!(equal(xs[0].t(), 2.0));
     }
 
+    #[test]
+    fn ray_strikes_millimeter_triangle() {
+        let t = Triangle::new(
+            Point::new(0.0, 0.001, 0.0),
+            Point::new(-0.001, 0.0, 0.0),
+            Point::new(0.001, 0.0, 0.0),
+        );
+        let r = Ray::new(Point::new(0.0, 0.0005, -2.0), Vector::new(0, 0, 1));
+        let xs = t.local_intersect(&r);
+        assert_eq!(xs.len(), 1);
+        assert!(equal(xs[0].t(), 2.0));
+    }
+
     #[test]
     fn triangle_bounding_box() {
         let p1 = Point::new(-3, 7, 2);
@@ -0,0 +1,182 @@
+use std::f64::consts::{PI, TAU};
+
+use crate::point::Point;
+
+// mappings from points in a shape's object space to texture coordinates, u
+// and v both running from 0 to 1
+
+// u goes around the y axis, starting and ending behind the sphere, v goes
+// from the south pole to the north pole
+pub fn spherical_map(point: Point) -> (f64, f64) {
+    let theta = point.x.atan2(point.z);
+    let radius = (point.x * point.x + point.y * point.y + point.z * point.z).sqrt();
+    let phi = (point.y / radius).clamp(-1.0, 1.0).acos();
+    let raw_u = theta / TAU;
+    (1.0 - (raw_u + 0.5), 1.0 - phi / PI)
+}
+
+// repeats every unit along x and z
+pub fn planar_map(point: Point) -> (f64, f64) {
+    (point.x.rem_euclid(1.0), point.z.rem_euclid(1.0))
+}
+
+// u goes around the y axis like the spherical map, v repeats every unit of y
+pub fn cylindrical_map(point: Point) -> (f64, f64) {
+    let theta = point.x.atan2(point.z);
+    let raw_u = theta / TAU;
+    (1.0 - (raw_u + 0.5), point.y.rem_euclid(1.0))
+}
+
+// a flat disc of radius 1 around the y axis, like the caps of cylinders
+// and cones, with its center at (0.5, 0.5)
+pub fn disc_map(point: Point) -> (f64, f64) {
+    ((point.x + 1.0) / 2.0, (1.0 - point.z) / 2.0)
+}
+
+// how a texture wraps around a shape
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum UvMapping {
+    // the shape's own texture coordinates, see Shape::uv_at
+    #[default]
+    Shape,
+    Spherical,
+    Planar,
+    Cylindrical,
+    // every face of the cube shows the whole texture
+    Cubic,
+}
+
+impl UvMapping {
+    // the texture coordinates of a point, none when they're up to the shape
+    pub fn map(&self, point: Point) -> Option<(f64, f64)> {
+        match self {
+            Self::Shape => None,
+            Self::Spherical => Some(spherical_map(point)),
+            Self::Planar => Some(planar_map(point)),
+            Self::Cylindrical => Some(cylindrical_map(point)),
+            Self::Cubic => {
+                let (_, u, v) = cube_map(point);
+                Some((u, v))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CubeFace {
+    Left,
+    Right,
+    Front,
+    Back,
+    Up,
+    Down,
+}
+
+pub fn cube_face(point: Point) -> CubeFace {
+    let (x, y, z) = (point.x.abs(), point.y.abs(), point.z.abs());
+    let coord = x.max(y).max(z);
+    if coord == point.x {
+        CubeFace::Right
+    } else if coord == -point.x {
+        CubeFace::Left
+    } else if coord == point.y {
+        CubeFace::Up
+    } else if coord == -point.y {
+        CubeFace::Down
+    } else if coord == point.z {
+        CubeFace::Front
+    } else {
+        CubeFace::Back
+    }
+}
+
+// each face of the unit cube maps to the whole texture, seen from outside
+// the cube with up, down and the sides lining up with the front
+pub fn cube_map(point: Point) -> (CubeFace, f64, f64) {
+    let half = |a: f64| a.rem_euclid(2.0) / 2.0;
+    let (x, y, z) = (point.x, point.y, point.z);
+    let face = cube_face(point);
+    let (u, v) = match face {
+        CubeFace::Front => (half(x + 1.0), half(y + 1.0)),
+        CubeFace::Back => (half(1.0 - x), half(y + 1.0)),
+        CubeFace::Left => (half(z + 1.0), half(y + 1.0)),
+        CubeFace::Right => (half(1.0 - z), half(y + 1.0)),
+        CubeFace::Up => (half(x + 1.0), half(1.0 - z)),
+        CubeFace::Down => (half(x + 1.0), half(z + 1.0)),
+    };
+    (face, u, v)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::equal;
+
+    use super::*;
+
+    fn assert_uv(uv: (f64, f64), u: f64, v: f64) {
+        assert!(
+            equal(uv.0, u) && equal(uv.1, v),
+            "{:?} != ({}, {})",
+            uv,
+            u,
+            v
+        );
+    }
+
+    #[test]
+    fn spherical_mapping() {
+        let s2 = 2.0f64.sqrt() / 2.0;
+        let cases = [
+            (Point::new(0, 0, -1), 0.0, 0.5),
+            (Point::new(1, 0, 0), 0.25, 0.5),
+            (Point::new(0, 0, 1), 0.5, 0.5),
+            (Point::new(-1, 0, 0), 0.75, 0.5),
+            (Point::new(0, 1, 0), 0.5, 1.0),
+            (Point::new(0, -1, 0), 0.5, 0.0),
+            (Point::new(s2, s2, 0.0), 0.25, 0.75),
+        ];
+        for (point, u, v) in cases {
+            assert_uv(spherical_map(point), u, v);
+        }
+    }
+
+    #[test]
+    fn planar_and_cylindrical_mapping() {
+        assert_uv(planar_map(Point::new(0.25, 0.0, 0.5)), 0.25, 0.5);
+        assert_uv(planar_map(Point::new(0.25, 0.0, -0.25)), 0.25, 0.75);
+        assert_uv(planar_map(Point::new(1.25, 0.5, -1.75)), 0.25, 0.25);
+
+        let s2 = 2.0f64.sqrt() / 2.0;
+        assert_uv(cylindrical_map(Point::new(0, 0, -1)), 0.0, 0.0);
+        assert_uv(cylindrical_map(Point::new(0.0, 0.5, -1.0)), 0.0, 0.5);
+        assert_uv(cylindrical_map(Point::new(s2, 0.5, s2)), 0.375, 0.5);
+        assert_uv(cylindrical_map(Point::new(-1.0, -0.25, 0.0)), 0.75, 0.75);
+
+        assert_uv(disc_map(Point::new(0, 1, 0)), 0.5, 0.5);
+        assert_uv(disc_map(Point::new(1, 1, 1)), 1.0, 0.0);
+    }
+
+    #[test]
+    fn cube_mapping() {
+        assert_eq!(cube_face(Point::new(-1.0, 0.5, -0.25)), CubeFace::Left);
+        assert_eq!(cube_face(Point::new(1.1, -0.75, 0.8)), CubeFace::Right);
+        assert_eq!(cube_face(Point::new(0.1, 0.6, 0.9)), CubeFace::Front);
+        assert_eq!(cube_face(Point::new(-0.7, 0.0, -2.0)), CubeFace::Back);
+        assert_eq!(cube_face(Point::new(0.5, 1.0, 0.9)), CubeFace::Up);
+        assert_eq!(cube_face(Point::new(-0.2, -1.3, 1.1)), CubeFace::Down);
+
+        let cases = [
+            (Point::new(-0.5, 0.5, 1.0), CubeFace::Front, 0.25, 0.75),
+            (Point::new(0.5, -0.5, -1.0), CubeFace::Back, 0.25, 0.25),
+            (Point::new(-1.0, 0.5, -0.5), CubeFace::Left, 0.25, 0.75),
+            (Point::new(1.0, -0.5, 0.5), CubeFace::Right, 0.25, 0.25),
+            (Point::new(-0.5, 1.0, -0.5), CubeFace::Up, 0.25, 0.75),
+            (Point::new(0.5, -1.0, 0.5), CubeFace::Down, 0.75, 0.75),
+        ];
+        for (point, face, u, v) in cases {
+            let (f, mu, mv) = cube_map(point);
+            assert_eq!(f, face);
+            assert_uv((mu, mv), u, v);
+        }
+    }
+}
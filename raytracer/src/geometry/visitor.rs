@@ -0,0 +1,109 @@
+use crate::{geometry::Shape, matrix::Matrix};
+
+// walks a shape tree: groups, csg operands and leaf shapes. transform is the
+// shape's accumulated object to world transform and depth is 0 for the shape
+// the walk starts at.
+pub trait Visitor {
+    // return false to skip the children of shape
+    fn visit(&mut self, shape: &dyn Shape, transform: &Matrix, depth: usize) -> bool;
+}
+
+impl<F: FnMut(&dyn Shape, &Matrix, usize) -> bool> Visitor for F {
+    fn visit(&mut self, shape: &dyn Shape, transform: &Matrix, depth: usize) -> bool {
+        self(shape, transform, depth)
+    }
+}
+
+pub fn walk(shape: &dyn Shape, visitor: &mut dyn Visitor) {
+    walk_at(shape, visitor, &Matrix::identity(4, 4), 0);
+}
+
+fn walk_at(shape: &dyn Shape, visitor: &mut dyn Visitor, parent: &Matrix, depth: usize) {
+    let transform = parent * shape.transform();
+    if visitor.visit(shape, &transform, depth) {
+        // groups bake their transform into their children, csgs don't
+        let children_parent = if shape.children_in_object_space() {
+            &transform
+        } else {
+            parent
+        };
+        shape.for_each_child(&mut |child| walk_at(child, visitor, children_parent, depth + 1));
+    }
+}
+
+// calls f for every shape in the tree without children
+pub fn for_each_leaf(shape: &dyn Shape, mut f: impl FnMut(&dyn Shape, &Matrix)) {
+    let mut visitor = |shape: &dyn Shape, transform: &Matrix, _: usize| {
+        let mut leaf = true;
+        shape.for_each_child(&mut |_| leaf = false);
+        if leaf {
+            f(shape, transform);
+        }
+        true
+    };
+    walk(shape, &mut visitor);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        geometry::shape::{Csg, Cube, Group, Operation, Plane, Sphere},
+        point::Point,
+        transform::{scaling, translation},
+    };
+
+    use super::*;
+
+    fn scene() -> Group {
+        let mut csg = Csg::new(Operation::Difference, Cube::default(), Sphere::default());
+        csg.set_transform(translation(0, 1, 0));
+        let mut inner = Group::default();
+        inner.set_transform(scaling(2, 2, 2));
+        inner.add_child(Box::new(csg));
+
+        let mut outer = Group::default();
+        outer.set_transform(translation(5, 0, 0));
+        outer.add_child(Box::new(inner));
+        outer.add_child(Box::new(Plane::default()));
+        outer
+    }
+
+    #[test]
+    fn walk_visits_every_shape_with_depth() {
+        let g = scene();
+        let mut depths = vec![];
+        walk(&g, &mut |_: &dyn Shape, _: &Matrix, depth: usize| {
+            depths.push(depth);
+            true
+        });
+        // outer, inner group, csg, cube, sphere, plane
+        assert_eq!(depths, vec![0, 1, 2, 3, 3, 1]);
+    }
+
+    #[test]
+    fn walk_skips_children_when_asked() {
+        let g = scene();
+        let mut count = 0;
+        walk(&g, &mut |_: &dyn Shape, _: &Matrix, depth: usize| {
+            count += 1;
+            depth < 1
+        });
+        assert_eq!(count, 3);
+    }
+
+    #[test]
+    fn leaves_carry_accumulated_transforms() {
+        let g = scene();
+        let mut origins = vec![];
+        for_each_leaf(&g, |_, transform| origins.push(transform * Point::origin()));
+        // cube and sphere: translate(5, 0, 0) * scale(2) * translate(0, 1, 0)
+        assert_eq!(
+            origins,
+            vec![
+                Point::new(5, 2, 0),
+                Point::new(5, 2, 0),
+                Point::new(5, 0, 0)
+            ]
+        );
+    }
+}
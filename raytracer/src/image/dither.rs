@@ -0,0 +1,232 @@
+use std::sync::OnceLock;
+
+use super::scale_color_component;
+use crate::{color::Color, sampler::Rng};
+
+// the blue noise map tiles the image in squares this size
+const BLUE_NOISE_SIZE: usize = 64;
+// width of the gaussian void and cluster measures clustering with
+const BLUE_NOISE_SIGMA: f64 = 1.5;
+const BLUE_NOISE_SEED: u64 = 0x6c75_6520_6e6f_6973;
+
+// how 8 bit export rounds colors. the dithered modes move the rounding
+// threshold from pixel to pixel, so a slow gradient turns into fine grain
+// instead of bands
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Dither {
+    #[default]
+    None,
+    // an 8x8 bayer matrix, cheap but leaves a regular cross hatch
+    Ordered,
+    // a void and cluster threshold map, with no visible pattern
+    BlueNoise,
+}
+
+impl Dither {
+    // what gets added to pixel (x, y) before rounding, in 8 bit steps and [-0.5, 0.5)
+    pub fn offset(&self, x: usize, y: usize) -> f64 {
+        let (rank, levels) = match self {
+            Dither::None => return 0.0,
+            Dither::Ordered => (bayer(x % 8, y % 8), 64),
+            Dither::BlueNoise => {
+                let size = BLUE_NOISE_SIZE;
+                (blue_noise()[(y % size) * size + x % size], size * size)
+            }
+        };
+        (rank as f64 + 0.5) / levels as f64 - 0.5
+    }
+
+    // clamps color and scales it to 8 bits
+    pub fn quantize(&self, color: Color, x: usize, y: usize) -> [u8; 3] {
+        let color = color.saturate();
+        let offset = self.offset(x, y) / 255.0;
+        [
+            scale_color_component(color.red + offset),
+            scale_color_component(color.green + offset),
+            scale_color_component(color.blue + offset),
+        ]
+    }
+}
+
+// the low bits of x and y pick the high bits of the rank
+fn bayer(x: usize, y: usize) -> usize {
+    (0..3).fold(0, |rank, bit| {
+        let (x, y) = ((x >> bit) & 1, (y >> bit) & 1);
+        rank << 2 | (x ^ y) << 1 | y
+    })
+}
+
+fn blue_noise() -> &'static [usize] {
+    static MAP: OnceLock<Vec<usize>> = OnceLock::new();
+    MAP.get_or_init(void_and_cluster)
+}
+
+// ulichney's void and cluster method: every pixel gets the rank at which it
+// joins a pattern that stays evenly spread. distances wrap around the edges so
+// the map tiles without seams
+fn void_and_cluster() -> Vec<usize> {
+    let n = BLUE_NOISE_SIZE * BLUE_NOISE_SIZE;
+    let mut pattern = Pattern::new();
+    let mut rng = Rng::new(BLUE_NOISE_SEED);
+    let initial = n / 10;
+    while pattern.count < initial {
+        let p = rng.next_u64() as usize % n;
+        if !pattern.points[p] {
+            pattern.toggle(p);
+        }
+    }
+    // move points from the tightest cluster to the largest void until that
+    // doesn't change anything
+    for _ in 0..n {
+        let cluster = pattern.tightest_cluster();
+        pattern.toggle(cluster);
+        let void = pattern.largest_void();
+        pattern.toggle(void);
+        if void == cluster {
+            break;
+        }
+    }
+
+    let mut ranks = vec![0; n];
+    let mut removing = pattern.clone();
+    for rank in (0..initial).rev() {
+        let p = removing.tightest_cluster();
+        removing.toggle(p);
+        ranks[p] = rank;
+    }
+    // the original fills the second half by clustering the minority zeros,
+    // filling voids all the way up spreads them just as well
+    for rank in initial..n {
+        let p = pattern.largest_void();
+        pattern.toggle(p);
+        ranks[p] = rank;
+    }
+    ranks
+}
+
+#[derive(Clone)]
+struct Pattern {
+    points: Vec<bool>,
+    count: usize,
+    // the sum of the gaussians around every point, at every pixel
+    energy: Vec<f64>,
+    kernel: Vec<f64>,
+}
+
+impl Pattern {
+    fn new() -> Self {
+        let size = BLUE_NOISE_SIZE;
+        let n = size * size;
+        let wrap = |d: usize| d.min(size - d) as f64;
+        let kernel = (0..n)
+            .map(|i| {
+                let (dx, dy) = (wrap(i % size), wrap(i / size));
+                (-(dx * dx + dy * dy) / (2.0 * BLUE_NOISE_SIGMA * BLUE_NOISE_SIGMA)).exp()
+            })
+            .collect();
+        Self {
+            points: vec![false; n],
+            count: 0,
+            energy: vec![0.0; n],
+            kernel,
+        }
+    }
+
+    fn toggle(&mut self, p: usize) {
+        let size = BLUE_NOISE_SIZE;
+        let sign = if self.points[p] { -1.0 } else { 1.0 };
+        self.points[p] = !self.points[p];
+        if self.points[p] {
+            self.count += 1;
+        } else {
+            self.count -= 1;
+        }
+        let (px, py) = (p % size, p / size);
+        for (q, energy) in self.energy.iter_mut().enumerate() {
+            let dx = (q % size + size - px) % size;
+            let dy = (q / size + size - py) % size;
+            *energy += sign * self.kernel[dy * size + dx];
+        }
+    }
+
+    // the point with the most points around it
+    fn tightest_cluster(&self) -> usize {
+        self.extreme(true, |a, b| a > b)
+    }
+
+    // the empty pixel with the fewest points around it
+    fn largest_void(&self) -> usize {
+        self.extreme(false, |a, b| a < b)
+    }
+
+    fn extreme(&self, set: bool, better: impl Fn(f64, f64) -> bool) -> usize {
+        let mut best: Option<usize> = None;
+        for (p, &energy) in self.energy.iter().enumerate() {
+            if self.points[p] == set && best.is_none_or(|b| better(energy, self.energy[b])) {
+                best = Some(p);
+            }
+        }
+        best.expect("pattern is neither empty nor full")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_permutation(ranks: impl Iterator<Item = usize>, n: usize) -> bool {
+        let mut ranks: Vec<usize> = ranks.collect();
+        ranks.sort_unstable();
+        ranks == (0..n).collect::<Vec<_>>()
+    }
+
+    #[test]
+    fn threshold_maps_use_every_rank_once() {
+        assert!(is_permutation((0..64).map(|i| bayer(i % 8, i / 8)), 64));
+        assert_eq!(
+            [bayer(0, 0), bayer(1, 0), bayer(0, 1), bayer(1, 1)],
+            [0, 32, 48, 16]
+        );
+        let n = BLUE_NOISE_SIZE * BLUE_NOISE_SIZE;
+        assert!(is_permutation(blue_noise().iter().copied(), n));
+    }
+
+    #[test]
+    fn dithering_keeps_the_average_level() {
+        // a quarter of the way from 100 to 101
+        let color = Color::new(100.25, 100.25, 100.25) * (1.0 / 255.0);
+        let size = BLUE_NOISE_SIZE;
+        for dither in [Dither::None, Dither::Ordered, Dither::BlueNoise] {
+            let mut levels = vec![];
+            for y in 0..size {
+                for x in 0..size {
+                    levels.push(dither.quantize(color, x, y)[0]);
+                }
+            }
+            let ones = levels.iter().filter(|&&level| level == 101).count();
+            assert!(levels.iter().all(|&level| level == 100 || level == 101));
+            match dither {
+                Dither::None => assert_eq!(ones, 0),
+                _ => assert_eq!(ones, size * size / 4),
+            }
+        }
+    }
+
+    #[test]
+    fn blue_noise_spreads_the_lowest_ranks() {
+        // the pixels that turn on first in a dark gradient never touch
+        let size = BLUE_NOISE_SIZE;
+        let map = blue_noise();
+        let dark: Vec<usize> = (0..size * size)
+            .filter(|&p| map[p] < size * size / 16)
+            .collect();
+        for &p in &dark {
+            for &q in &dark {
+                let wrap = |d: usize| d.min(size - d);
+                let dx = wrap((p % size + size - q % size) % size);
+                let dy = wrap((p / size + size - q / size) % size);
+                assert!(p == q || dx > 1 || dy > 1);
+            }
+        }
+    }
+}
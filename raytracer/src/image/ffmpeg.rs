@@ -0,0 +1,103 @@
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+    process::{Child, Command, Stdio},
+};
+
+use anyhow::{anyhow, Context, Result};
+
+use super::{encode_canvas, PixelFormat};
+use crate::{animation::FrameSink, canvas::Canvas};
+
+// pipes the frames to ffmpeg, which picks the format from the path's
+// extension, e.g. an h264 mp4. ffmpeg is started with the first frame, whose
+// size every other frame has to have
+pub struct FfmpegSink {
+    path: PathBuf,
+    fps: u32,
+    ffmpeg: Option<Child>,
+    size: Option<(usize, usize)>,
+}
+
+impl FfmpegSink {
+    pub fn new(path: &Path, fps: u32) -> Self {
+        assert!(fps > 0);
+        Self {
+            path: path.to_path_buf(),
+            fps,
+            ffmpeg: None,
+            size: None,
+        }
+    }
+
+    fn spawn(&self, (width, height): (usize, usize)) -> Result<Child> {
+        Command::new("ffmpeg")
+            .args([
+                "-y",
+                "-loglevel",
+                "error",
+                "-f",
+                "rawvideo",
+                "-pixel_format",
+                "rgba",
+            ])
+            .args(["-video_size", &format!("{}x{}", width, height)])
+            .args(["-framerate", &self.fps.to_string(), "-i", "-"])
+            // most players only take yuv420p, which needs even sizes
+            .args([
+                "-pix_fmt",
+                "yuv420p",
+                "-vf",
+                "pad=ceil(iw/2)*2:ceil(ih/2)*2",
+            ])
+            .arg(&self.path)
+            .stdin(Stdio::piped())
+            .spawn()
+            .context("couldn't start ffmpeg, is it on the PATH?")
+    }
+}
+
+impl FrameSink for FfmpegSink {
+    fn add_frame(&mut self, frame: &Canvas) -> Result<()> {
+        let size = (frame.width(), frame.height());
+        match self.size {
+            Some(first) if first != size => {
+                return Err(anyhow!(
+                    "frame is {:?}, earlier frames were {:?}",
+                    size,
+                    first
+                ));
+            }
+            Some(_) if self.ffmpeg.is_none() => return Err(anyhow!("video is already finished")),
+            Some(_) => {}
+            None => {
+                self.ffmpeg = Some(self.spawn(size)?);
+                self.size = Some(size);
+            }
+        }
+
+        let stdin = self
+            .ffmpeg
+            .as_mut()
+            .and_then(|ffmpeg| ffmpeg.stdin.as_mut())
+            .expect("ffmpeg was started with a piped stdin");
+        stdin
+            .write_all(&encode_canvas(frame, PixelFormat::Rgba8))
+            .context("ffmpeg stopped reading frames")
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        let mut ffmpeg = match (self.ffmpeg.take(), self.size) {
+            (Some(ffmpeg), _) => ffmpeg,
+            (None, Some(_)) => return Err(anyhow!("video is already finished")),
+            (None, None) => return Err(anyhow!("no frames were added")),
+        };
+        // closing stdin tells ffmpeg there are no more frames
+        drop(ffmpeg.stdin.take());
+        let status = ffmpeg.wait()?;
+        if !status.success() {
+            return Err(anyhow!("ffmpeg failed with {}", status));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,96 @@
+use std::{fs::File, io::BufWriter, path::Path};
+
+use anyhow::{anyhow, Result};
+use image::{
+    codecs::gif::{GifEncoder, Repeat},
+    Delay, Frame, RgbaImage,
+};
+
+use super::{encode_canvas, PixelFormat};
+use crate::{animation::FrameSink, canvas::Canvas};
+
+// writes the frames to a looping animated gif. gifs have 256 colors per
+// frame, fine for previews and turntables
+pub struct GifSink {
+    encoder: Option<GifEncoder<BufWriter<File>>>,
+    delay: Delay,
+    size: Option<(usize, usize)>,
+}
+
+impl GifSink {
+    pub fn create(path: &Path, fps: u32) -> Result<Self> {
+        assert!(fps > 0);
+        let mut encoder = GifEncoder::new(BufWriter::new(File::create(path)?));
+        encoder.set_repeat(Repeat::Infinite)?;
+        Ok(Self {
+            encoder: Some(encoder),
+            delay: Delay::from_numer_denom_ms(1000, fps),
+            size: None,
+        })
+    }
+}
+
+impl FrameSink for GifSink {
+    fn add_frame(&mut self, frame: &Canvas) -> Result<()> {
+        let encoder = self
+            .encoder
+            .as_mut()
+            .ok_or_else(|| anyhow!("gif is already finished"))?;
+        let size = (frame.width(), frame.height());
+        if *self.size.get_or_insert(size) != size {
+            return Err(anyhow!(
+                "frame is {:?}, earlier frames were {:?}",
+                size,
+                self.size.unwrap()
+            ));
+        }
+
+        let pixels = encode_canvas(frame, PixelFormat::Rgba8);
+        let buffer = RgbaImage::from_raw(size.0 as u32, size.1 as u32, pixels)
+            .expect("buffer has the frame's size");
+        encoder.encode_frame(Frame::from_parts(buffer, 0, 0, self.delay))?;
+        Ok(())
+    }
+
+    // the encoder writes the end of the gif when dropped
+    fn finish(&mut self) -> Result<()> {
+        self.encoder.take();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use image::{codecs::gif::GifDecoder, AnimationDecoder};
+
+    use super::*;
+    use crate::color::Color;
+
+    #[test]
+    fn gif_has_every_frame() {
+        let path = env::temp_dir().join("raytracer_gif_has_every_frame.gif");
+        let mut sink = GifSink::create(&path, 10).unwrap();
+        for shade in [0.0, 0.5, 1.0] {
+            let mut frame = Canvas::new(4, 3);
+            frame.set_pixel(1, 1, Color::new(shade, shade, shade));
+            sink.add_frame(&frame).unwrap();
+        }
+        assert!(sink.add_frame(&Canvas::new(3, 4)).is_err());
+        sink.finish().unwrap();
+        assert!(sink.add_frame(&Canvas::new(4, 3)).is_err());
+
+        let decoder = GifDecoder::new(File::open(&path).unwrap()).unwrap();
+        let frames = decoder.into_frames().collect_frames().unwrap();
+        assert_eq!(frames.len(), 3);
+        for (frame, expected) in frames.iter().zip([0, 128, 255]) {
+            assert_eq!(frame.buffer().dimensions(), (4, 3));
+            assert_eq!(
+                frame.buffer().get_pixel(1, 1).0,
+                [expected, expected, expected, 255]
+            );
+            assert_eq!(frame.delay().numer_denom_ms(), (100, 1));
+        }
+    }
+}
@@ -2,11 +2,110 @@ use anyhow::Result;
 use std::fmt::Debug;
 use std::path::Path;
 
-use crate::canvas::Canvas;
+use crate::{canvas::Canvas, color::Color};
 
+pub mod dither;
+#[cfg(feature = "ffmpeg")]
+pub mod ffmpeg;
+pub mod gif;
 pub mod png;
 pub mod ppm;
 
 pub trait ExportCanvas: Debug + Send + Sync {
     fn save(&self, canvas: &Canvas, path: &Path) -> Result<()>;
 }
+
+// 8 bit layouts for Camera::render_into, alpha is always opaque
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PixelFormat {
+    Rgba8,
+    Bgra8,
+}
+
+impl PixelFormat {
+    pub fn bytes_per_pixel(&self) -> usize {
+        4
+    }
+
+    // clamped and scaled the way png export does it
+    pub fn encode(&self, color: Color) -> [u8; 4] {
+        let color = color.saturate();
+        let (r, g, b) = (
+            scale_color_component(color.red),
+            scale_color_component(color.green),
+            scale_color_component(color.blue),
+        );
+        match self {
+            PixelFormat::Rgba8 => [r, g, b, 255],
+            PixelFormat::Bgra8 => [b, g, r, 255],
+        }
+    }
+}
+
+// reads any image the image crate knows, png and ppm among them, with every
+// channel scaled to 0..1
+pub fn load_canvas(path: &Path) -> Result<Canvas> {
+    let image = ::image::open(path)?.to_rgb8();
+    let mut canvas = Canvas::new(image.width() as usize, image.height() as usize);
+    for (x, y, pixel) in image.enumerate_pixels() {
+        let [r, g, b] = pixel.0;
+        let channel = |value: u8| value as f64 / 255.0;
+        canvas.set_pixel(
+            x as usize,
+            y as usize,
+            Color::new(channel(r), channel(g), channel(b)),
+        );
+    }
+    Ok(canvas)
+}
+
+// the canvas as 8 bit pixels, row by row
+fn encode_canvas(canvas: &Canvas, format: PixelFormat) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(canvas.width() * canvas.height() * format.bytes_per_pixel());
+    for y in 0..canvas.height() {
+        for x in 0..canvas.width() {
+            bytes.extend_from_slice(&format.encode(canvas.get_pixel(x, y)));
+        }
+    }
+    bytes
+}
+
+fn scale_color_component(value: f64) -> u8 {
+    (value * 255.0).round() as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_pixel_formats() {
+        let color = Color::new(1.5, 0.5, -0.5);
+        assert_eq!(PixelFormat::Rgba8.encode(color), [255, 128, 0, 255]);
+        assert_eq!(PixelFormat::Bgra8.encode(color), [0, 128, 255, 255]);
+    }
+
+    #[test]
+    fn load_saved_images() {
+        let mut canvas = Canvas::new(3, 2);
+        canvas.set_pixel(0, 0, Color::new(1.0, 0.0, 0.0));
+        canvas.set_pixel(2, 1, Color::new(0.0, 1.0, 1.0));
+        for name in &["load_saved_images.png", "load_saved_images.ppm"] {
+            let path = std::env::temp_dir().join(name);
+            if name.ends_with("ppm") {
+                ppm::save_ppm(&canvas, &path).unwrap();
+            } else {
+                canvas.save(&path).unwrap();
+            }
+            let loaded = load_canvas(&path).unwrap();
+            std::fs::remove_file(&path).unwrap();
+            assert_eq!((loaded.width(), loaded.height()), (3, 2));
+            assert!(
+                crate::canvas::compare(&canvas, &loaded).is_identical(),
+                "{}",
+                name
+            );
+        }
+        assert!(load_canvas(Path::new("no/such/image.png")).is_err());
+    }
+}
@@ -3,34 +3,46 @@ use std::{fs::File, io::Write, path::Path};
 
 use crate::{canvas::Canvas, color::Color};
 
-use super::ExportCanvas;
+use super::{dither::Dither, ExportCanvas};
 
-#[derive(Debug)]
-pub struct PpmExporter {}
+#[derive(Debug, Default)]
+pub struct PpmExporter {
+    dither: Dither,
+}
+
+impl PpmExporter {
+    pub fn with_dither(dither: Dither) -> Self {
+        Self { dither }
+    }
+}
 
 impl ExportCanvas for PpmExporter {
     fn save(&self, canvas: &Canvas, path: &Path) -> Result<()> {
-        save_ppm(canvas, path)
+        let ppm = dithered_ppm(canvas, self.dither);
+        let mut file = File::create(path)?;
+        file.write_all(ppm.as_bytes())?;
+        Ok(())
     }
 }
 
 pub fn save_ppm(canvas: &Canvas, path: &Path) -> Result<()> {
-    let ppm = canvas_to_ppm(&canvas);
-    let mut file = File::create(path)?;
-    file.write_all(ppm.as_bytes())?;
-    Ok(())
+    PpmExporter::default().save(canvas, path)
 }
 
 pub fn canvas_to_ppm(canvas: &Canvas) -> String {
+    dithered_ppm(canvas, Dither::None)
+}
+
+pub fn dithered_ppm(canvas: &Canvas, dither: Dither) -> String {
     let mut ppm = ppm_header(canvas);
 
     for j in 0..canvas.height() {
         let mut line = String::new();
         for i in 0..canvas.width() {
-            let pixel = encode_pixel(&canvas.get_pixel(i, j));
+            let pixel = encode_pixel(&canvas.get_pixel(i, j), i, j, dither);
             for (idx, val) in pixel.iter().enumerate() {
                 if line.len() + val.len() > 70 {
-                    ppm += &line.trim_end();
+                    ppm += line.trim_end();
                     ppm += "\n";
                     line = String::new();
                 }
@@ -62,20 +74,14 @@ fn ppm_header(canvas: &Canvas) -> String {
     )
 }
 
-fn encode_pixel(color: &Color) -> [String; 3] {
-    [
-        scale_color_component(color.red).to_string(),
-        scale_color_component(color.green).to_string(),
-        scale_color_component(color.blue).to_string(),
-    ]
-}
-
-fn scale_color_component(value: f64) -> u8 {
-    (value * 255.0).round() as u8
+fn encode_pixel(color: &Color, x: usize, y: usize, dither: Dither) -> [String; 3] {
+    dither.quantize(*color, x, y).map(|value| value.to_string())
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::image::scale_color_component;
+
     use super::*;
 
     #[test]
@@ -121,7 +127,7 @@ mod tests {
     fn encode_single_pixel() {
         let c = Color::new(0.0, 0.5, 0.0);
         let expected = ["0", "128", "0"];
-        assert_eq!(encode_pixel(&c), expected);
+        assert_eq!(encode_pixel(&c, 0, 0, Dither::None), expected);
     }
 
     #[test]
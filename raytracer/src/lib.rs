@@ -1,41 +1,42 @@
+pub mod animation;
+pub mod approx;
 pub mod bounding_box;
+pub mod bounding_sphere;
+pub mod brdf;
 pub mod camera;
+pub mod camera_path;
 pub mod canvas;
 pub mod color;
+pub mod exposure;
+pub mod fog;
 pub mod geometry;
 pub mod image;
 pub mod light;
 pub mod material;
 pub mod matrix;
+pub mod noise;
 pub mod obj_parser;
 pub mod pattern;
 pub mod point;
+pub mod progress;
 pub mod ray;
+pub mod ray_cache;
+pub mod ray_log;
+pub mod renderer;
+pub mod sampler;
+pub mod sky;
+pub mod testing;
+pub mod tiles;
 pub mod transform;
 pub mod vector;
 pub mod world;
 
 const EPSILON: f64 = 0.00001;
 
-fn equal(a: f64, b: f64) -> bool {
-    (a - b).abs() < EPSILON
-}
+// decimals shown when points, vectors, colors and matrices are displayed
+// without an explicit precision
+const DISPLAY_PRECISION: usize = 4;
 
-fn equal_ignore_inf(a: f64, b: f64) -> bool {
-    // TODO: WORKAROUND TO MAKE EQUALITY WORK WHEN BOUNDING BOXES HAVE -INF OR +INF COORDS
-    if a.is_infinite() && b.is_infinite() {
-        return (a.is_sign_positive() && a.is_sign_positive())
-            || (b.is_sign_negative() && b.is_sign_negative());
-    }
-    equal(a, b)
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-    #[test]
-    fn infiny_is_equal() {
-        assert!(!equal(f64::INFINITY, f64::INFINITY));
-        assert!(equal_ignore_inf(f64::INFINITY, f64::INFINITY));
-    }
+fn equal(a: f64, b: f64) -> bool {
+    approx::eq(a, b)
 }
@@ -1,9 +1,180 @@
-use crate::{color::Color, point::Point};
+use std::f64::consts::PI;
+
+use crate::{
+    color::Color,
+    point::Point,
+    vector::{dot, Vector},
+};
+
+// how the world scales light contributions when summing several lights
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum LightNormalization {
+    #[default]
+    None,
+    // all the lights together are as bright as a single white light
+    Sum,
+    // every light is divided by the number of lights
+    Average,
+    // intensities are radiant power, falling off with the square of the distance
+    InverseSquare,
+}
+
+impl LightNormalization {
+    pub fn scale(&self, lights: &[Light], light: &Light, point: Point) -> f64 {
+        match self {
+            Self::None => 1.0,
+            Self::Sum => {
+                let total: f64 = lights.iter().map(|l| l.intensity().max_component()).sum();
+                if total > 0.0 {
+                    1.0 / total
+                } else {
+                    1.0
+                }
+            }
+            Self::Average => 1.0 / lights.len().max(1) as f64,
+            Self::InverseSquare => {
+                let distance2 = (light.position() - point).magnitude().powi(2);
+                1.0 / (4.0 * PI * distance2.max(f64::EPSILON))
+            }
+        }
+    }
+}
+
+// every kind of light a world holds. they all have a position, an intensity
+// and the shadow settings of a point light
+#[derive(Debug, PartialEq)]
+pub enum Light {
+    Point(PointLight),
+    Spot(SpotLight),
+}
+
+impl Light {
+    // the point light a spotlight shines from, or the light itself
+    pub fn source(&self) -> &PointLight {
+        match self {
+            Self::Point(light) => light,
+            Self::Spot(spot) => &spot.source,
+        }
+    }
+
+    pub fn source_mut(&mut self) -> &mut PointLight {
+        match self {
+            Self::Point(light) => light,
+            Self::Spot(spot) => &mut spot.source,
+        }
+    }
+
+    // the intensity reaching point, less than intensity outside a spotlight's
+    // inner cone
+    pub fn intensity_at(&self, point: Point) -> Color {
+        match self {
+            Self::Point(light) => light.intensity(),
+            Self::Spot(spot) => spot.intensity_at(point),
+        }
+    }
+
+    pub fn intensity(&self) -> Color {
+        self.source().intensity()
+    }
+
+    pub fn position(&self) -> Point {
+        self.source().position()
+    }
+
+    pub fn shadow_fill(&self) -> Option<f64> {
+        self.source().shadow_fill()
+    }
+
+    pub fn shadow_softness(&self) -> f64 {
+        self.source().shadow_softness()
+    }
+
+    pub fn shadow_color(&self) -> Option<Color> {
+        self.source().shadow_color()
+    }
+}
+
+impl From<PointLight> for Light {
+    fn from(light: PointLight) -> Self {
+        Self::Point(light)
+    }
+}
+
+impl From<SpotLight> for Light {
+    fn from(spot: SpotLight) -> Self {
+        Self::Spot(spot)
+    }
+}
+
+// a point light shining along direction only. points within inner radians of
+// the direction get the whole light, it fades smoothly to nothing at outer
+// radians
+#[derive(Debug, PartialEq)]
+pub struct SpotLight {
+    source: PointLight,
+    direction: Vector,
+    inner: f64,
+    outer: f64,
+}
+
+impl SpotLight {
+    pub fn new(source: PointLight, direction: Vector, inner: f64, outer: f64) -> Self {
+        let inner = inner.clamp(0.0, PI);
+        Self {
+            source,
+            direction: direction.normalize(),
+            inner,
+            outer: outer.clamp(inner, PI),
+        }
+    }
+
+    pub fn source(&self) -> &PointLight {
+        &self.source
+    }
+
+    pub fn direction(&self) -> Vector {
+        self.direction
+    }
+
+    pub fn inner(&self) -> f64 {
+        self.inner
+    }
+
+    pub fn outer(&self) -> f64 {
+        self.outer
+    }
+
+    // how much of the light goes along v, from the light outwards
+    pub fn falloff(&self, v: Vector) -> f64 {
+        let cos = dot(self.direction, v.normalize());
+        let (cos_inner, cos_outer) = (self.inner.cos(), self.outer.cos());
+        if cos >= cos_inner {
+            return 1.0;
+        }
+        if cos <= cos_outer {
+            return 0.0;
+        }
+        let x = (cos - cos_outer) / (cos_inner - cos_outer);
+        x * x * (3.0 - 2.0 * x)
+    }
+
+    pub fn intensity_at(&self, point: Point) -> Color {
+        self.source.intensity() * self.falloff(point - self.source.position())
+    }
+}
 
 #[derive(Debug, PartialEq)]
 pub struct PointLight {
     intensity: Color,
     position: Point,
+    // overrides the world's shadow fill for this light
+    shadow_fill: Option<f64>,
+    // the radius of the sphere around the position that shadow rays are
+    // jittered over, 0 for hard shadows
+    shadow_softness: f64,
+    // what the light is multiplied by where it's blocked, instead of being
+    // left out, like a translucent occluder or bounced fill would
+    shadow_color: Option<Color>,
 }
 
 impl PointLight {
@@ -11,9 +182,36 @@ impl PointLight {
         Self {
             intensity,
             position,
+            shadow_fill: None,
+            shadow_softness: 0.0,
+            shadow_color: None,
         }
     }
 
+    pub fn shadow_fill(&self) -> Option<f64> {
+        self.shadow_fill
+    }
+
+    pub fn set_shadow_fill(&mut self, fill: f64) {
+        self.shadow_fill = Some(fill);
+    }
+
+    pub fn shadow_softness(&self) -> f64 {
+        self.shadow_softness
+    }
+
+    pub fn set_shadow_softness(&mut self, radius: f64) {
+        self.shadow_softness = radius.max(0.0);
+    }
+
+    pub fn shadow_color(&self) -> Option<Color> {
+        self.shadow_color
+    }
+
+    pub fn set_shadow_color(&mut self, color: Color) {
+        self.shadow_color = Some(color);
+    }
+
     pub fn intensity(&self) -> Color {
         self.intensity
     }
@@ -25,6 +223,8 @@ impl PointLight {
 
 #[cfg(test)]
 mod tests {
+    use crate::equal;
+
     use super::*;
 
     #[test]
@@ -35,4 +235,51 @@ mod tests {
         assert_eq!(light.position, position);
         assert_eq!(light.intensity, intensity);
     }
+
+    #[test]
+    fn spotlights_fade_between_their_cones() {
+        let source = PointLight::new(Point::new(0, 1, 0), Color::white());
+        let spot = SpotLight::new(source, Vector::new(0, -2, 0), PI / 8.0, PI / 4.0);
+        assert_eq!(spot.direction(), Vector::new(0, -1, 0));
+        let light = Light::from(spot);
+        let at_angle = |angle: f64| Point::new(angle.tan(), 0.0, 0.0);
+
+        assert_eq!(light.intensity_at(Point::origin()), Color::white());
+        assert_eq!(light.intensity_at(at_angle(PI / 10.0)), Color::white());
+        let middle = light.intensity_at(at_angle(3.0 * PI / 16.0)).red;
+        assert!(middle > 0.0 && middle < 1.0);
+        assert!(light.intensity_at(at_angle(PI / 5.0)).red < middle);
+        assert_eq!(light.intensity_at(at_angle(PI / 3.0)), Color::black());
+        assert_eq!(light.intensity_at(Point::new(0, 2, 0)), Color::black());
+
+        // an outer cone inside the inner one is a hard edge
+        let source = PointLight::new(Point::origin(), Color::white());
+        let hard = SpotLight::new(source, Vector::new(0, -1, 0), 0.5, 0.2);
+        assert_eq!(hard.outer(), 0.5);
+    }
+
+    #[test]
+    fn light_normalization_scales() {
+        let lights: Vec<Light> = vec![
+            PointLight::new(Point::new(0, 10, 0), Color::new(1.0, 0.5, 0.5)).into(),
+            PointLight::new(Point::new(0, -10, 0), Color::new(0.5, 0.5, 3.0)).into(),
+        ];
+        let p = Point::origin();
+        assert!(equal(
+            LightNormalization::None.scale(&lights, &lights[0], p),
+            1.0
+        ));
+        assert!(equal(
+            LightNormalization::Sum.scale(&lights, &lights[0], p),
+            0.25
+        ));
+        assert!(equal(
+            LightNormalization::Average.scale(&lights, &lights[1], p),
+            0.5
+        ));
+        assert!(equal(
+            LightNormalization::InverseSquare.scale(&lights, &lights[0], p),
+            1.0 / (400.0 * PI)
+        ));
+    }
 }
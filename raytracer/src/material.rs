@@ -1,12 +1,106 @@
 use crate::{
+    brdf::{Brdf, BrdfInput, BrdfModel, BrdfTerms},
     color::Color,
+    exposure::luminance,
     geometry::Shape,
-    light::PointLight,
+    light::Light,
+    noise::{octave_weight, perlin_vector},
     pattern::Pattern,
     point::Point,
+    ray::Footprint,
     vector::{dot, Vector},
 };
 
+// surfaces facing away from the eye more than this are drawn as toon outlines
+const TOON_EDGE_THRESHOLD: f64 = 0.2;
+
+// what lighting adds up for a single light
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LightingTerms {
+    pub ambient: Color,
+    pub diffuse: Color,
+    pub specular: Color,
+}
+
+impl LightingTerms {
+    pub fn total(&self) -> Color {
+        self.ambient + (self.diffuse + self.specular)
+    }
+
+    // the terms of an unshadowed light for a point in its shadow: a fill
+    // fraction of the diffuse light is kept so shadows aren't just ambient
+    pub fn in_shadow(&self, fill: f64) -> Self {
+        Self {
+            ambient: self.ambient,
+            diffuse: self.diffuse * fill,
+            specular: Color::black(),
+        }
+    }
+
+    // the terms of an unshadowed light for a point behind something that lets
+    // color through
+    pub fn tinted(&self, color: Color) -> Self {
+        Self {
+            ambient: self.ambient,
+            diffuse: self.diffuse * color,
+            specular: self.specular * color,
+        }
+    }
+
+    // self where all of the light gets through, shadowed where none does
+    pub fn blend(&self, shadowed: &Self, visibility: f64) -> Self {
+        let mix = |lit: Color, dark: Color| lit * visibility + dark * (1.0 - visibility);
+        Self {
+            ambient: mix(self.ambient, shadowed.ambient),
+            diffuse: mix(self.diffuse, shadowed.diffuse),
+            specular: mix(self.specular, shadowed.specular),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum ShadingModel {
+    #[default]
+    Phong,
+    Toon {
+        levels: usize,
+    },
+}
+
+// bumps the shading normal with 3d noise, the geometry stays as it is. the noise
+// is sampled in the pattern's space, so it follows the pattern transform
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Displacement {
+    pub amplitude: f64,
+    pub frequency: f64,
+}
+
+// alpha testing: the surface isn't there where the pattern is darker than the
+// threshold, so leaves and fences can be cut out of a few quads. rays go on
+// through the holes, shadow rays included
+#[derive(Debug, PartialEq, Clone)]
+pub struct Cutout {
+    pub pattern: Pattern,
+    pub threshold: f64,
+}
+
+impl Cutout {
+    pub fn new(pattern: Pattern, threshold: f64) -> Self {
+        Self { pattern, threshold }
+    }
+
+    // whether the surface is cut away at world_point. there's nothing to cut
+    // outside the pattern's extent
+    pub fn removes(&self, object: &dyn Shape, world_point: Point, uv: Option<(f64, f64)>) -> bool {
+        self.pattern.covers(object, world_point)
+            && luminance(
+                &self
+                    .pattern
+                    .color_at_footprint(object, world_point, None, uv),
+            ) < self.threshold
+    }
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub struct Material {
     pub color: Color,
@@ -17,11 +111,26 @@ pub struct Material {
     pub reflective: f64,
     pub transparency: f64,
     pub refractive_index: f64,
+    // weight reflections by fresnel even when the material is opaque
+    pub fresnel: bool,
+    // look reflections up in the world's sky instead of tracing them. cheap
+    // enough for previews and distant mirrors, but nothing else shows up in them
+    pub environment_reflections: bool,
+    // microfacet roughness in [0, 1], oren-nayar reads it as a slope deviation in radians.
+    // transparent materials also blur what is seen through them by it
+    pub roughness: f64,
+    pub metallic: f64,
+    // how much light from behind bleeds through thin parts of the object
+    pub translucency: f64,
+    pub brdf: BrdfModel,
+    pub shading: ShadingModel,
+    pub displacement: Option<Displacement>,
+    pub cutout: Option<Cutout>,
     pattern: Option<Pattern>,
 }
 
-impl Material {
-    pub fn default() -> Self {
+impl Default for Material {
+    fn default() -> Self {
         Self {
             color: Color::new(1.0, 1.0, 1.0),
             ambient: 0.1,
@@ -31,54 +140,285 @@ impl Material {
             reflective: 0.0,
             transparency: 0.0,
             refractive_index: 1.0,
+            fresnel: false,
+            environment_reflections: false,
+            roughness: 0.0,
+            metallic: 0.0,
+            translucency: 0.0,
+            brdf: BrdfModel::default(),
+            shading: ShadingModel::default(),
+            displacement: None,
+            cutout: None,
             pattern: None,
         }
     }
+}
+
+impl Material {
+    // glTF style base color / metallic / roughness inputs
+    pub fn from_metallic_roughness(base_color: Color, metallic: f64, roughness: f64) -> Self {
+        Self {
+            color: base_color,
+            diffuse: 1.0,
+            metallic,
+            roughness,
+            brdf: BrdfModel::CookTorrance,
+            ..Default::default()
+        }
+    }
+
+    // the fields outside the range the lighting model expects, which can shade
+    // to NaN or to more light than there is. empty for a sound material
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = vec![];
+        if !self.color.is_finite() {
+            problems.push(format!("color {:?} is not finite", self.color));
+        }
+        for (name, value) in [
+            ("ambient", self.ambient),
+            ("diffuse", self.diffuse),
+            ("specular", self.specular),
+            ("translucency", self.translucency),
+        ] {
+            if value.is_nan() || value < 0.0 {
+                problems.push(format!("{} is {}, should be >= 0", name, value));
+            }
+        }
+        for (name, value) in [
+            ("reflective", self.reflective),
+            ("transparency", self.transparency),
+            ("roughness", self.roughness),
+            ("metallic", self.metallic),
+        ] {
+            if !(0.0..=1.0).contains(&value) {
+                problems.push(format!("{} is {}, should be in [0, 1]", name, value));
+            }
+        }
+        for (name, value) in [
+            ("shininess", self.shininess),
+            ("refractive index", self.refractive_index),
+        ] {
+            if value.is_nan() || value <= 0.0 {
+                problems.push(format!("{} is {}, should be > 0", name, value));
+            }
+        }
+        problems
+    }
 
     pub fn lighting(
         &self,
         object: &dyn Shape,
-        light: &PointLight,
+        light: &Light,
         point: &Point,
         eyev: &Vector,
         normalv: &Vector,
         in_shadow: bool,
     ) -> Color {
-        let color = if let Some(pattern) = &self.pattern {
-            pattern.color_at_shape(object, *point)
-        } else {
-            self.color
-        };
+        self.lighting_terms(object, light, point, eyev, normalv, in_shadow)
+            .total()
+    }
 
-        let effective_color = color * light.intensity();
+    // lighting split into its ambient, diffuse and specular parts
+    pub fn lighting_terms(
+        &self,
+        object: &dyn Shape,
+        light: &Light,
+        point: &Point,
+        eyev: &Vector,
+        normalv: &Vector,
+        in_shadow: bool,
+    ) -> LightingTerms {
+        let color = self.color_at(object, point);
+        self.lighting_terms_with(color, light, point, eyev, normalv, in_shadow)
+    }
+
+    // lighting_terms with the surface color already looked up
+    pub(crate) fn lighting_terms_with(
+        &self,
+        color: Color,
+        light: &Light,
+        point: &Point,
+        eyev: &Vector,
+        normalv: &Vector,
+        in_shadow: bool,
+    ) -> LightingTerms {
         let lightv = (light.position() - *point).normalize();
-        let ambient = effective_color * self.ambient;
+        let ambient = color * light.intensity() * self.ambient;
+        // spotlights only dim the direct light, ambient is everywhere
+        let intensity = light.intensity_at(*point);
+        let effective_color = color * intensity;
+        let only = |ambient| LightingTerms {
+            ambient,
+            diffuse: Color::black(),
+            specular: Color::black(),
+        };
+
+        if let ShadingModel::Toon { .. } = self.shading {
+            if dot(*eyev, *normalv) < TOON_EDGE_THRESHOLD {
+                return only(Color::black());
+            }
+        }
 
         if in_shadow {
-            return ambient;
+            return only(ambient);
         }
 
-        let light_dot_normal = dot(lightv, *normalv);
+        if dot(lightv, *normalv) < 0.0 || intensity == Color::black() {
+            return only(ambient);
+        }
 
-        let diffuse: Color;
-        let specular: Color;
+        let terms = match self.shading {
+            ShadingModel::Phong => {
+                let input = BrdfInput {
+                    material: self,
+                    color,
+                    light_intensity: intensity,
+                    lightv,
+                    eyev: *eyev,
+                    normalv: *normalv,
+                };
+                self.brdf.terms(&input)
+            }
+            ShadingModel::Toon { levels } => {
+                self.toon(effective_color, intensity, lightv, eyev, normalv, levels)
+            }
+        };
+        LightingTerms {
+            ambient,
+            diffuse: terms.diffuse,
+            specular: terms.specular,
+        }
+    }
 
-        if light_dot_normal < 0.0 {
-            diffuse = Color::black();
-            specular = Color::black();
-        } else {
-            diffuse = effective_color * self.diffuse * light_dot_normal;
-            let reflectv = (-lightv).reflect(*normalv);
-            let reflect_dot_eye = dot(reflectv, *eyev);
-            specular = if reflect_dot_eye <= 0.0 {
-                Color::black()
-            } else {
-                let factor = reflect_dot_eye.powf(self.shininess);
-                light.intensity() * self.specular * factor
+    // wrap lighting for light shining through the object from behind, fading
+    // with the distance the view ray travels inside it
+    pub fn translucent_lighting(
+        &self,
+        object: &dyn Shape,
+        light: &Light,
+        point: &Point,
+        eyev: &Vector,
+        normalv: &Vector,
+        thickness: f64,
+    ) -> Color {
+        let color = self.color_at(object, point);
+        self.translucent_lighting_with(color, light, point, eyev, normalv, thickness)
+    }
+
+    pub(crate) fn translucent_lighting_with(
+        &self,
+        color: Color,
+        light: &Light,
+        point: &Point,
+        eyev: &Vector,
+        normalv: &Vector,
+        thickness: f64,
+    ) -> Color {
+        if self.translucency <= 0.0 {
+            return Color::black();
+        }
+
+        let lightv = (light.position() - *point).normalize();
+        let back_lit = (-dot(lightv, *normalv)).max(0.0);
+        let towards_light = (-dot(lightv, *eyev)).max(0.0);
+        let scatter = (back_lit + towards_light) / 2.0;
+
+        color
+            * light.intensity_at(*point)
+            * (self.translucency * scatter * (-thickness.max(0.0)).exp())
+    }
+
+    // normalv bumped by the displacement, if there is one. bumps that are
+    // smaller than a pixel of pixel_width fade out
+    pub fn shading_normal(
+        &self,
+        object: &dyn Shape,
+        point: &Point,
+        normalv: Vector,
+        pixel_width: Option<f64>,
+    ) -> Vector {
+        let displacement = match &self.displacement {
+            Some(displacement) => displacement,
+            None => return normalv,
+        };
+        let to_pattern_space = |point: Point| match &self.pattern {
+            Some(pattern) => pattern.to_pattern_space(object, point),
+            None => &object.get_base().transform_inverse * point,
+        };
+        let pattern_point = to_pattern_space(*point);
+        // the pixel measured in pattern space, averaged over the axes
+        let pattern_width = pixel_width.map(|w| {
+            [
+                Vector::new(w, 0.0, 0.0),
+                Vector::new(0.0, w, 0.0),
+                Vector::new(0.0, 0.0, w),
+            ]
+            .iter()
+            .map(|&v| (to_pattern_space(*point + v) - pattern_point).magnitude())
+            .sum::<f64>()
+                / 3.0
+        });
+        let f = displacement.frequency;
+        let weight = octave_weight(1.0 / f, pattern_width);
+        if weight <= 0.0 {
+            return normalv;
+        }
+        let mut bump = perlin_vector(Point::new(
+            pattern_point.x * f,
+            pattern_point.y * f,
+            pattern_point.z * f,
+        ));
+        if let Some(pattern) = &self.pattern {
+            bump = pattern.transform() * bump;
+        }
+        bump = object.transform() * bump;
+        (normalv + bump * (displacement.amplitude * weight)).normalize()
+    }
+
+    fn color_at(&self, object: &dyn Shape, point: &Point) -> Color {
+        self.surface_color(object, point, None, None)
+    }
+
+    // the pattern or plain color at point, filtered over footprint when the
+    // pattern supports it and textured by the hit's uv. outside the pattern's
+    // extent the plain color shows
+    pub fn surface_color(
+        &self,
+        object: &dyn Shape,
+        point: &Point,
+        footprint: Option<&Footprint>,
+        uv: Option<(f64, f64)>,
+    ) -> Color {
+        match &self.pattern {
+            Some(pattern) if pattern.covers(object, *point) => {
+                pattern.color_at_footprint(object, *point, footprint, uv)
             }
+            _ => self.color,
         }
+    }
+
+    fn toon(
+        &self,
+        effective_color: Color,
+        light_intensity: Color,
+        lightv: Vector,
+        eyev: &Vector,
+        normalv: &Vector,
+        levels: usize,
+    ) -> BrdfTerms {
+        let levels = levels.max(1) as f64;
+        let light_dot_normal = (dot(lightv, *normalv) * levels).ceil() / levels;
+        let diffuse = effective_color * self.diffuse * light_dot_normal;
 
-        ambient + diffuse + specular
+        let reflectv = (-lightv).reflect(*normalv);
+        let reflect_dot_eye = dot(reflectv, *eyev);
+        let specular = if reflect_dot_eye > 0.0 && reflect_dot_eye.powf(self.shininess) > 0.5 {
+            light_intensity * self.specular
+        } else {
+            Color::black()
+        };
+
+        BrdfTerms { diffuse, specular }
     }
 
     pub fn set_pattern(&mut self, pattern: Pattern) {
@@ -88,7 +428,7 @@ impl Material {
 
 #[cfg(test)]
 mod tests {
-    use crate::{equal, geometry::shape::Sphere, pattern::stripe_pattern};
+    use crate::{equal, geometry::shape::Sphere, light::PointLight, pattern::stripe_pattern};
 
     use super::*;
 
@@ -102,13 +442,38 @@ mod tests {
         assert!(equal(m.shininess, 200.0))
     }
 
+    #[test]
+    fn default_material_has_no_problems() {
+        assert!(Material::default().problems().is_empty());
+        let m = Material::from_metallic_roughness(Color::white(), 1.0, 0.5);
+        assert!(m.problems().is_empty());
+    }
+
+    #[test]
+    fn out_of_range_fields_are_problems() {
+        let m = Material {
+            shininess: 0.0,
+            diffuse: -0.5,
+            reflective: f64::NAN,
+            ..Default::default()
+        };
+        assert_eq!(
+            m.problems(),
+            vec![
+                "diffuse is -0.5, should be >= 0",
+                "reflective is NaN, should be in [0, 1]",
+                "shininess is 0, should be > 0",
+            ]
+        );
+    }
+
     #[test]
     fn lighting_eye_between_eye_surface() {
         let m = Material::default();
         let position = Point::origin();
         let eyev = Vector::new(0, 0, -1);
         let normalv = Vector::new(0, 0, -1);
-        let light = PointLight::new(Point::new(0, 0, -10), Color::new(1.0, 1.0, 1.0));
+        let light = Light::from(PointLight::new(Point::new(0, 0, -10), Color::new(1.0, 1.0, 1.0)));
         let result = m.lighting(
             &Sphere::default(),
             &light,
@@ -120,13 +485,42 @@ mod tests {
         assert_eq!(result, Color::new(1.9, 1.9, 1.9));
     }
 
+    #[test]
+    fn lighting_with_spotlights() {
+        use crate::light::SpotLight;
+
+        let m = Material::default();
+        let (position, eyev, normalv) = (
+            Point::origin(),
+            Vector::new(0, 0, -1),
+            Vector::new(0, 0, -1),
+        );
+        let lighting = |direction: Vector| {
+            let source = PointLight::new(Point::new(0, 0, -10), Color::white());
+            let light = Light::from(SpotLight::new(source, direction, 0.1, 0.2));
+            m.lighting(
+                &Sphere::default(),
+                &light,
+                &position,
+                &eyev,
+                &normalv,
+                false,
+            )
+        };
+        assert_eq!(lighting(Vector::new(0, 0, 1)), Color::new(1.9, 1.9, 1.9));
+        // pointing away leaves only the ambient light
+        assert_eq!(lighting(Vector::new(0, 1, 0)), Color::new(0.1, 0.1, 0.1));
+        let edge = lighting(Vector::new(0.0, 0.15, 1.0));
+        assert!(edge.red > 0.1 && edge.red < 1.9);
+    }
+
     #[test]
     fn lighting_eye_between_eye_surface_eye_offset_45deg() {
         let m = Material::default();
         let position = Point::origin();
         let eyev = Vector::new(0.0, 2.0f64.sqrt() / 2.0, -(2.0f64.sqrt() / 2.0));
         let normalv = Vector::new(0, 0, -1);
-        let light = PointLight::new(Point::new(0, 0, -10), Color::new(1.0, 1.0, 1.0));
+        let light = Light::from(PointLight::new(Point::new(0, 0, -10), Color::new(1.0, 1.0, 1.0)));
         let result = m.lighting(
             &Sphere::default(),
             &light,
@@ -144,7 +538,7 @@ mod tests {
         let position = Point::origin();
         let eyev = Vector::new(0, 0, -1);
         let normalv = Vector::new(0, 0, -1);
-        let light = PointLight::new(Point::new(0, 10, -10), Color::new(1.0, 1.0, 1.0));
+        let light = Light::from(PointLight::new(Point::new(0, 10, -10), Color::new(1.0, 1.0, 1.0)));
         let result = m.lighting(
             &Sphere::default(),
             &light,
@@ -162,7 +556,7 @@ mod tests {
         let position = Point::origin();
         let eyev = Vector::new(0.0, -(2.0f64.sqrt() / 2.0), -(2.0f64.sqrt() / 2.0));
         let normalv = Vector::new(0, 0, -1);
-        let light = PointLight::new(Point::new(0, 10, -10), Color::new(1.0, 1.0, 1.0));
+        let light = Light::from(PointLight::new(Point::new(0, 10, -10), Color::new(1.0, 1.0, 1.0)));
         let result = m.lighting(
             &Sphere::default(),
             &light,
@@ -180,7 +574,7 @@ mod tests {
         let position = Point::origin();
         let eyev = Vector::new(0, 0, -1);
         let normalv = Vector::new(0, 0, -1);
-        let light = PointLight::new(Point::new(0, 0, 10), Color::new(1.0, 1.0, 1.0));
+        let light = Light::from(PointLight::new(Point::new(0, 0, 10), Color::new(1.0, 1.0, 1.0)));
         let result = m.lighting(
             &Sphere::default(),
             &light,
@@ -198,7 +592,7 @@ mod tests {
         let position = Point::origin();
         let eyev = Vector::new(0, 0, -1);
         let normalv = Vector::new(0, 0, -1);
-        let light = PointLight::new(Point::new(0, 0, -10), Color::new(1.0, 1.0, 1.0));
+        let light = Light::from(PointLight::new(Point::new(0, 0, -10), Color::new(1.0, 1.0, 1.0)));
         let in_shadow = true;
         let result = m.lighting(
             &Sphere::default(),
@@ -220,7 +614,7 @@ mod tests {
         m.specular = 0.0;
         let eyev = Vector::new(0, 0, -1);
         let normalv = Vector::new(0, 0, -1);
-        let light = PointLight::new(Point::new(0, 0, -10), Color::new(1.0, 1.0, 1.0));
+        let light = Light::from(PointLight::new(Point::new(0, 0, -10), Color::new(1.0, 1.0, 1.0)));
         let c1 = m.lighting(
             &Sphere::default(),
             &light,
@@ -241,6 +635,178 @@ mod tests {
         assert_eq!(c2, Color::black());
     }
 
+    #[test]
+    fn image_patterns_use_the_hit_uv() {
+        use crate::{canvas::Canvas, geometry::uv::UvMapping, pattern::image_pattern};
+
+        let mut image = Canvas::new(2, 2);
+        image.set_pixel(0, 0, Color::new(1.0, 0.0, 0.0));
+        image.set_pixel(0, 1, Color::new(0.0, 1.0, 0.0));
+        image.set_pixel(1, 1, Color::new(0.0, 0.0, 1.0));
+        let mut m = Material::default();
+        m.set_pattern(image_pattern(image, UvMapping::Shape));
+        let s = Sphere::default();
+        // the front of the sphere is u 0, just above the equator is the top row
+        let front = Point::new(0.0, 0.1, -(0.99f64).sqrt());
+        assert_eq!(
+            m.surface_color(&s, &front, None, None),
+            Color::new(1.0, 0.0, 0.0)
+        );
+        assert_eq!(
+            m.surface_color(&s, &front, None, Some((1.0, 0.0))),
+            Color::new(0.0, 0.0, 1.0)
+        );
+    }
+
+    #[test]
+    fn pattern_extent_shows_the_plain_color_outside() {
+        use crate::{bounding_box::BoundingBox, geometry::shape::Plane, transform::scaling};
+
+        let mut pattern = stripe_pattern(Color::white(), Color::black());
+        pattern.set_transform(scaling(0.5, 1.0, 1.0));
+        // four stripes along x, endless along z
+        pattern.set_extent(BoundingBox::new(
+            Point::new(0.0, f64::NEG_INFINITY, f64::NEG_INFINITY),
+            Point::new(4.0, f64::INFINITY, f64::INFINITY),
+        ));
+        let mut m = Material {
+            color: Color::new(0.2, 0.4, 0.6),
+            ..Default::default()
+        };
+        m.set_pattern(pattern);
+        let plane = Plane::default();
+        let color_at = |x: f64, z: f64| m.surface_color(&plane, &Point::new(x, 0.0, z), None, None);
+        assert_eq!(color_at(0.25, 100.0), Color::white());
+        assert_eq!(color_at(0.75, -3.0), Color::black());
+        assert_eq!(color_at(1.75, 0.0), Color::black());
+        assert_eq!(color_at(2.25, 0.0), m.color);
+        assert_eq!(color_at(-0.25, 0.0), m.color);
+    }
+
+    #[test]
+    fn toon_shading_quantizes_diffuse() {
+        let m = Material {
+            specular: 0.0,
+            shading: ShadingModel::Toon { levels: 2 },
+            ..Default::default()
+        };
+        let eyev = Vector::new(0, 0, -1);
+        let normalv = Vector::new(0, 0, -1);
+        let light = Light::from(PointLight::new(Point::new(0, 10, -10), Color::new(1.0, 1.0, 1.0)));
+        let result = m.lighting(
+            &Sphere::default(),
+            &light,
+            &Point::origin(),
+            &eyev,
+            &normalv,
+            false,
+        );
+        assert_eq!(result, Color::new(1.0, 1.0, 1.0));
+
+        let light = Light::from(PointLight::new(Point::new(0, 10, -1), Color::new(1.0, 1.0, 1.0)));
+        let result = m.lighting(
+            &Sphere::default(),
+            &light,
+            &Point::origin(),
+            &eyev,
+            &normalv,
+            false,
+        );
+        assert_eq!(result, Color::new(0.55, 0.55, 0.55));
+    }
+
+    #[test]
+    fn toon_shading_darkens_silhouettes() {
+        let m = Material {
+            shading: ShadingModel::Toon { levels: 3 },
+            ..Default::default()
+        };
+        let eyev = Vector::new(0, 0, -1);
+        let normalv = Vector::new(0.0, 0.99, -0.1).normalize();
+        let light = Light::from(PointLight::new(Point::new(0, 0, -10), Color::new(1.0, 1.0, 1.0)));
+        let result = m.lighting(
+            &Sphere::default(),
+            &light,
+            &Point::origin(),
+            &eyev,
+            &normalv,
+            false,
+        );
+        assert_eq!(result, Color::black());
+    }
+
+    #[test]
+    fn lighting_with_blinn_phong() {
+        let m = Material {
+            brdf: BrdfModel::BlinnPhong,
+            ..Default::default()
+        };
+        let eyev = Vector::new(0, 0, -1);
+        let normalv = Vector::new(0, 0, -1);
+        let light = Light::from(PointLight::new(Point::new(0, 0, -10), Color::new(1.0, 1.0, 1.0)));
+        let result = m.lighting(
+            &Sphere::default(),
+            &light,
+            &Point::origin(),
+            &eyev,
+            &normalv,
+            false,
+        );
+        assert_eq!(result, Color::new(1.9, 1.9, 1.9));
+    }
+
+    #[test]
+    fn translucent_lighting_from_behind() {
+        let m = Material {
+            translucency: 1.0,
+            ..Default::default()
+        };
+        let eyev = Vector::new(0, 0, -1);
+        let normalv = Vector::new(0, 0, -1);
+        let light = Light::from(PointLight::new(Point::new(0, 0, 10), Color::new(1.0, 1.0, 1.0)));
+        let thin = m.translucent_lighting(
+            &Sphere::default(),
+            &light,
+            &Point::origin(),
+            &eyev,
+            &normalv,
+            0.0,
+        );
+        assert_eq!(thin, Color::white());
+
+        let thick = m.translucent_lighting(
+            &Sphere::default(),
+            &light,
+            &Point::origin(),
+            &eyev,
+            &normalv,
+            2.0,
+        );
+        let e = (-2.0f64).exp();
+        assert_eq!(thick, Color::new(e, e, e));
+    }
+
+    #[test]
+    fn no_translucent_lighting_from_the_front() {
+        let m = Material {
+            translucency: 1.0,
+            ..Default::default()
+        };
+        let eyev = Vector::new(0, 0, -1);
+        let normalv = Vector::new(0, 0, -1);
+        let light = Light::from(PointLight::new(Point::new(0, 0, -10), Color::new(1.0, 1.0, 1.0)));
+        let result = m.translucent_lighting(
+            &Sphere::default(),
+            &light,
+            &Point::origin(),
+            &eyev,
+            &normalv,
+            0.0,
+        );
+        assert_eq!(result, Color::black());
+        assert_eq!(Material::default().translucency, 0.0);
+    }
+
     #[test]
     fn default_material_reflectivity() {
         let m = Material::default();
@@ -253,4 +819,65 @@ mod tests {
         assert!(equal(m.transparency, 0.0));
         assert!(equal(m.refractive_index, 1.0));
     }
+
+    #[test]
+    fn default_material_has_no_fresnel() {
+        let m = Material::default();
+        assert!(!m.fresnel);
+    }
+
+    #[test]
+    fn displacement_bumps_the_shading_normal() {
+        let s = Sphere::default();
+        let point = Point::new(0.3, 0.4, -0.866);
+        let normalv = Vector::new(0.3, 0.4, -0.866).normalize();
+        assert_eq!(
+            Material::default().shading_normal(&s, &point, normalv, None),
+            normalv
+        );
+
+        let m = Material {
+            displacement: Some(Displacement {
+                amplitude: 0.5,
+                frequency: 4.0,
+            }),
+            ..Default::default()
+        };
+        let bumped = m.shading_normal(&s, &point, normalv, None);
+        assert!(bumped != normalv);
+        assert!(equal(bumped.magnitude(), 1.0));
+        assert_eq!(m.shading_normal(&s, &point, normalv, None), bumped);
+
+        // bumps a quarter unit across stay for small pixels, not for big ones
+        assert_eq!(m.shading_normal(&s, &point, normalv, Some(0.01)), bumped);
+        assert_eq!(m.shading_normal(&s, &point, normalv, Some(0.3)), normalv);
+    }
+
+    #[test]
+    fn displacement_follows_the_pattern_transform() {
+        use crate::transform::translation;
+
+        let displacement = Some(Displacement {
+            amplitude: 0.5,
+            frequency: 3.0,
+        });
+        let s = Sphere::default();
+        let normalv = Vector::new(0, 0, -1);
+        let plain = Material {
+            displacement,
+            ..Default::default()
+        };
+
+        let mut pattern = stripe_pattern(Color::white(), Color::black());
+        pattern.set_transform(translation(1, 0, 0));
+        let mut patterned = plain.clone();
+        patterned.set_pattern(pattern);
+
+        let point = Point::new(0.2, 0.1, -1.0);
+        let moved = Point::new(1.2, 0.1, -1.0);
+        assert_eq!(
+            patterned.shading_normal(&s, &moved, normalv, None),
+            plain.shading_normal(&s, &point, normalv, None)
+        );
+    }
 }
@@ -1,12 +1,15 @@
 use std::{
+    fmt,
     ops::{Index, IndexMut, Mul},
     vec,
 };
 
 use crate::{
+    approx::relative_eq,
     point::Point,
     transform::{rotation_x, rotation_y, rotation_z, scaling, shearing, translation},
     vector::Vector,
+    DISPLAY_PRECISION, EPSILON,
 };
 
 #[derive(Debug, Clone)]
@@ -72,6 +75,16 @@ impl Matrix {
         self.columns
     }
 
+    pub fn approx_eq(&self, other: &Self, epsilon: f64) -> bool {
+        self.rows == other.rows
+            && self.columns == other.columns
+            && self
+                .elements
+                .iter()
+                .zip(other.elements.iter())
+                .all(|(&l, &r)| relative_eq(l, r, epsilon))
+    }
+
     fn idx(&self, i: usize, j: usize) -> usize {
         i * self.columns + j
     }
@@ -200,14 +213,32 @@ impl IndexMut<(usize, usize)> for Matrix {
 
 impl PartialEq for Matrix {
     fn eq(&self, rhs: &Self) -> bool {
-        self.elements
-            .iter()
-            .zip(rhs.elements.iter())
-            .all(|(&l, &r)| crate::equal(l, r))
+        self.approx_eq(rhs, EPSILON)
+    }
+}
+
+// one row per line
+impl fmt::Display for Matrix {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let p = f.precision().unwrap_or(DISPLAY_PRECISION);
+        for row in 0..self.rows {
+            if row > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "[")?;
+            for column in 0..self.columns {
+                if column > 0 {
+                    write!(f, ", ")?;
+                }
+                write!(f, "{:.*}", p, self[(row, column)])?;
+            }
+            write!(f, "]")?;
+        }
+        Ok(())
     }
 }
 
-impl<'a, 'b> Mul<&'b Matrix> for &'a Matrix {
+impl<'b> Mul<&'b Matrix> for &Matrix {
     type Output = Matrix;
 
     fn mul(self, rhs: &'b Matrix) -> Matrix {
@@ -265,6 +296,23 @@ mod tests {
 
     use super::*;
 
+    #[test]
+    fn display_matrix() {
+        let m = Matrix::from_slice(2, 2, &[1.0, -2.5, 1.0 / 3.0, 0.0]);
+        assert_eq!(m.to_string(), "[1.0000, -2.5000]\n[0.3333, 0.0000]");
+        assert_eq!(format!("{:.0}", m), "[1, -2]\n[0, 0]");
+    }
+
+    #[test]
+    fn approx_eq_matrix() {
+        let a = Matrix::identity(4, 4);
+        let mut b = Matrix::identity(4, 4);
+        b[(1, 2)] = 0.01;
+        assert!(a.approx_eq(&b, 0.1));
+        assert!(!a.approx_eq(&b, 0.001));
+        assert!(!a.approx_eq(&Matrix::identity(3, 3), 0.1));
+    }
+
     #[should_panic]
     #[test]
     fn create_matrix_wrong_dimensions() {
@@ -0,0 +1,145 @@
+use crate::{point::Point, vector::Vector};
+
+// ken perlin's reference permutation
+#[rustfmt::skip]
+const PERMUTATION: [u8; 256] = [
+    151, 160, 137, 91, 90, 15, 131, 13, 201, 95, 96, 53, 194, 233, 7, 225,
+    140, 36, 103, 30, 69, 142, 8, 99, 37, 240, 21, 10, 23, 190, 6, 148,
+    247, 120, 234, 75, 0, 26, 197, 62, 94, 252, 219, 203, 117, 35, 11, 32,
+    57, 177, 33, 88, 237, 149, 56, 87, 174, 20, 125, 136, 171, 168, 68, 175,
+    74, 165, 71, 134, 139, 48, 27, 166, 77, 146, 158, 231, 83, 111, 229, 122,
+    60, 211, 133, 230, 220, 105, 92, 41, 55, 46, 245, 40, 244, 102, 143, 54,
+    65, 25, 63, 161, 1, 216, 80, 73, 209, 76, 132, 187, 208, 89, 18, 169,
+    200, 196, 135, 130, 116, 188, 159, 86, 164, 100, 109, 198, 173, 186, 3, 64,
+    52, 217, 226, 250, 124, 123, 5, 202, 38, 147, 118, 126, 255, 82, 85, 212,
+    207, 206, 59, 227, 47, 16, 58, 17, 182, 189, 28, 42, 223, 183, 170, 213,
+    119, 248, 152, 2, 44, 154, 163, 70, 221, 153, 101, 155, 167, 43, 172, 9,
+    129, 22, 39, 253, 19, 98, 108, 110, 79, 113, 224, 232, 178, 185, 112, 104,
+    218, 246, 97, 228, 251, 34, 242, 193, 238, 210, 144, 12, 191, 179, 162, 241,
+    81, 51, 145, 235, 249, 14, 239, 107, 49, 192, 214, 31, 181, 199, 106, 157,
+    184, 84, 204, 176, 115, 121, 50, 45, 127, 4, 150, 254, 138, 236, 205, 93,
+    222, 114, 67, 29, 24, 72, 243, 141, 128, 195, 78, 66, 215, 61, 156, 180,
+];
+
+// improved perlin noise, roughly in [-1, 1] and 0 at every integer point
+pub fn perlin(point: Point) -> f64 {
+    let cell = |v: f64| (v.floor() as i64 & 255) as usize;
+    let (xi, yi, zi) = (cell(point.x), cell(point.y), cell(point.z));
+    let (x, y, z) = (
+        point.x - point.x.floor(),
+        point.y - point.y.floor(),
+        point.z - point.z.floor(),
+    );
+    let (u, v, w) = (fade(x), fade(y), fade(z));
+    let p = |i: usize| PERMUTATION[i & 255] as usize;
+
+    let a = p(xi) + yi;
+    let (aa, ab) = (p(a) + zi, p(a + 1) + zi);
+    let b = p(xi + 1) + yi;
+    let (ba, bb) = (p(b) + zi, p(b + 1) + zi);
+
+    lerp(
+        w,
+        lerp(
+            v,
+            lerp(u, grad(p(aa), x, y, z), grad(p(ba), x - 1.0, y, z)),
+            lerp(
+                u,
+                grad(p(ab), x, y - 1.0, z),
+                grad(p(bb), x - 1.0, y - 1.0, z),
+            ),
+        ),
+        lerp(
+            v,
+            lerp(
+                u,
+                grad(p(aa + 1), x, y, z - 1.0),
+                grad(p(ba + 1), x - 1.0, y, z - 1.0),
+            ),
+            lerp(
+                u,
+                grad(p(ab + 1), x, y - 1.0, z - 1.0),
+                grad(p(bb + 1), x - 1.0, y - 1.0, z - 1.0),
+            ),
+        ),
+    )
+}
+
+// one noise value per axis, sampled far enough apart to be unrelated
+pub fn perlin_vector(point: Point) -> Vector {
+    let offset = |dx: f64| Point::new(point.x + dx, point.y + dx * 0.5, point.z - dx);
+    Vector::new(perlin(point), perlin(offset(31.4)), perlin(offset(-57.2)))
+}
+
+// how much of a noise octave with features feature_size across should show
+// where a pixel is pixel_width across: all of it for features two pixels wide
+// or more, fading to none at one pixel, below which it only adds sparkle
+pub fn octave_weight(feature_size: f64, pixel_width: Option<f64>) -> f64 {
+    match pixel_width {
+        Some(width) if width > 0.0 => {
+            let x = (feature_size / width - 1.0).clamp(0.0, 1.0);
+            x * x * (3.0 - 2.0 * x)
+        }
+        _ => 1.0,
+    }
+}
+
+fn fade(t: f64) -> f64 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn lerp(t: f64, a: f64, b: f64) -> f64 {
+    a + t * (b - a)
+}
+
+// dot product with one of 12 gradient directions picked by the hash
+fn grad(hash: usize, x: f64, y: f64, z: f64) -> f64 {
+    let h = hash & 15;
+    let u = if h < 8 { x } else { y };
+    let v = if h < 4 {
+        y
+    } else if h == 12 || h == 14 {
+        x
+    } else {
+        z
+    };
+    (if h & 1 == 0 { u } else { -u }) + (if h & 2 == 0 { v } else { -v })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sub_pixel_octaves_fade_out() {
+        assert_eq!(octave_weight(0.1, None), 1.0);
+        assert_eq!(octave_weight(0.1, Some(0.01)), 1.0);
+        assert_eq!(octave_weight(0.1, Some(0.05)), 1.0);
+        assert_eq!(octave_weight(0.1, Some(0.1)), 0.0);
+        assert_eq!(octave_weight(0.1, Some(1.0)), 0.0);
+        let between = octave_weight(0.1, Some(0.07));
+        assert!(between > 0.0 && between < 1.0);
+    }
+
+    #[test]
+    fn noise_is_zero_on_the_lattice() {
+        for &(x, y, z) in &[(0, 0, 0), (1, 2, 3), (-4, 7, -1)] {
+            assert_eq!(perlin(Point::new(x, y, z)), 0.0);
+        }
+    }
+
+    #[test]
+    fn noise_is_bounded_and_continuous() {
+        let mut nonzero = false;
+        for i in 0..1000 {
+            let t = i as f64 * 0.0137;
+            let p = Point::new(t, t * 1.3 - 2.0, 5.0 - t * 0.7);
+            let n = perlin(p);
+            assert!((-1.0..=1.0).contains(&n));
+            nonzero |= n.abs() > 0.1;
+            let close = perlin(Point::new(p.x + 1e-6, p.y, p.z));
+            assert!((n - close).abs() < 1e-4);
+        }
+        assert!(nonzero);
+    }
+}
@@ -1,23 +1,53 @@
+pub mod mtl;
+
 use anyhow::Result;
 use std::{
     collections::HashMap,
-    f64::{INFINITY, NEG_INFINITY},
     fs,
-    path::Path,
+    path::{Path, PathBuf},
+    sync::Arc,
 };
 
 use crate::{
-    geometry::shape::{Group, SmoothTriangle, Triangle},
+    geometry::{
+        shape::{Group, PartitionMode, SmoothTriangle, Triangle},
+        Shape,
+    },
+    material::Material,
     point::Point,
     vector::Vector,
 };
 
+use self::mtl::parse_mtl;
+
+// models with at least this many faces come out of as_group divided with
+// divide_sah into leaves of at most OBJ_MAX_LEAF faces, unless set_max_leaf
+// asks for another leaf size
+pub const OBJ_SAH_FACES: usize = 1024;
+pub const OBJ_MAX_LEAF: usize = 4;
+
 pub struct Parser {
     ignored: usize,
     vertices: Vec<Point>,
     vertex_normals: Vec<Vector>,
     groups: HashMap<String, Group>,
     selected_group: String,
+    // where mtllib paths start from
+    dir: Option<PathBuf>,
+    // the materials usemtl names, in the order they first show up
+    material_names: Vec<String>,
+    selected_material: Option<usize>,
+    // for every group, the index into material_names of each of its faces in
+    // the order of its children. faces before any usemtl have none
+    face_materials: HashMap<String, Vec<Option<usize>>>,
+    // what the mtllib files define
+    library: HashMap<String, Material>,
+    // every mtllib path the model names, whether it could be read or not
+    library_files: Vec<PathBuf>,
+    // the mtllib paths that couldn't be read, their faces keep the default
+    // material
+    unreadable_libraries: Vec<PathBuf>,
+    max_leaf: Option<usize>,
 }
 
 impl Parser {
@@ -32,6 +62,14 @@ impl Parser {
             vertex_normals: vec![Vector::new(0, 0, 0)],
             groups,
             selected_group: "default".to_string(),
+            dir: None,
+            material_names: vec![],
+            selected_material: None,
+            face_materials: HashMap::new(),
+            library: HashMap::new(),
+            library_files: vec![],
+            unreadable_libraries: vec![],
+            max_leaf: None,
         }
     }
 
@@ -65,14 +103,13 @@ impl Parser {
                             items.map(str::parse::<usize>).map(Result::unwrap).collect();
 
                         for triangle in self.fan_triangulation(&indices) {
-                            let group = self.groups.get_mut(&self.selected_group).unwrap();
-                            group.add_child(Box::new(triangle));
+                            self.add_face(triangle);
                         }
                     } else {
                         let faces: Vec<_> = items
                             .map(|item| {
                                 let mut split = item.split('/');
-                                (split.next().unwrap(), split.last().unwrap())
+                                (split.next().unwrap(), split.next_back().unwrap())
                             })
                             .map(|(index, normal)| {
                                 (
@@ -82,8 +119,7 @@ impl Parser {
                             })
                             .collect();
                         for triangle in self.smooth_fan_triangulation(&faces) {
-                            let group = self.groups.get_mut(&self.selected_group).unwrap();
-                            group.add_child(Box::new(triangle));
+                            self.add_face(triangle);
                         }
                     }
                 }
@@ -92,6 +128,23 @@ impl Parser {
 
                     self.selected_group = name.to_string();
                     self.groups.insert(name.to_string(), Group::default());
+                    self.face_materials.remove(name);
+                }
+                "usemtl" => {
+                    let name = items.collect::<Vec<_>>().join(" ");
+                    let idx = match self.material_names.iter().position(|n| *n == name) {
+                        Some(idx) => idx,
+                        None => {
+                            self.material_names.push(name);
+                            self.material_names.len() - 1
+                        }
+                    };
+                    self.selected_material = Some(idx);
+                }
+                "mtllib" => {
+                    for file in items {
+                        self.load_library(file);
+                    }
                 }
                 _ => {
                     self.ignored += 1;
@@ -100,6 +153,73 @@ impl Parser {
         }
     }
 
+    fn add_face<T: 'static + Shape>(&mut self, face: T) {
+        let group = self.groups.get_mut(&self.selected_group).unwrap();
+        group.add(face);
+        self.face_materials
+            .entry(self.selected_group.clone())
+            .or_default()
+            .push(self.selected_material);
+    }
+
+    fn load_library(&mut self, file: &str) {
+        let path = match &self.dir {
+            Some(dir) => dir.join(file),
+            None => PathBuf::from(file),
+        };
+        match fs::read_to_string(&path) {
+            Ok(contents) => self.library.extend(parse_mtl(&contents)),
+            Err(_) => self.unreadable_libraries.push(path.clone()),
+        }
+        self.library_files.push(path);
+    }
+
+    pub fn library_files(&self) -> &[PathBuf] {
+        &self.library_files
+    }
+
+    pub fn unreadable_libraries(&self) -> &[PathBuf] {
+        &self.unreadable_libraries
+    }
+
+    // as_group divides the model into leaves of at most max_leaf faces, even
+    // when it is too small to be divided on its own
+    pub fn set_max_leaf(&mut self, max_leaf: usize) {
+        self.max_leaf = Some(max_leaf);
+    }
+
+    pub fn material_names(&self) -> &[String] {
+        &self.material_names
+    }
+
+    // the material of every face is materials[idx] where idx is the index of
+    // its usemtl name in material_names. faces without one, or past the end of
+    // materials, keep the default material. faces using the same material
+    // share it, and a group can mix any number of them
+    pub fn as_group_with_materials(&mut self, materials: &[Arc<Material>]) -> Group {
+        for (name, group) in self.groups.iter_mut() {
+            let faces = match self.face_materials.get(name) {
+                Some(faces) => faces,
+                None => continue,
+            };
+            for (child, face) in group.children.iter_mut().zip(faces) {
+                if let Some(material) = face.and_then(|idx| materials.get(idx)) {
+                    child.set_shared_material(material.clone());
+                }
+            }
+        }
+        self.as_group()
+    }
+
+    // the materials the mtllib files define for the usemtl names, unknown
+    // names get the default material
+    pub fn library_materials(&self) -> Vec<Arc<Material>> {
+        self.material_names
+            .iter()
+            .map(|name| Arc::new(self.library.get(name).cloned().unwrap_or_default()))
+            .collect()
+    }
+
     fn fan_triangulation(&self, vertices: &[usize]) -> Vec<Triangle> {
         let mut triangles = vec![];
 
@@ -134,26 +254,42 @@ impl Parser {
     }
 
     pub fn as_group(&mut self) -> Group {
-        if self.groups.len() == 1 {
-            return self.groups.remove("default").unwrap();
+        let big = self
+            .groups
+            .values()
+            .map(|g| g.children.len())
+            .sum::<usize>()
+            >= OBJ_SAH_FACES;
+        let max_leaf = self.max_leaf.or(big.then_some(OBJ_MAX_LEAF));
+        if max_leaf.is_some() {
+            for g in self.groups.values_mut() {
+                g.set_partition_mode(PartitionMode::Sah);
+            }
         }
 
-        let mut group = Group::default();
+        let mut group = if self.groups.len() == 1 {
+            self.groups.remove("default").unwrap()
+        } else {
+            let mut group = Group::default();
+            for (_, child) in self.groups.drain().filter(|(_, g)| !g.children.is_empty()) {
+                group.add(child);
+            }
+            group
+        };
 
-        for (_, child) in self.groups.drain().filter(|(_, g)| !g.children.is_empty()) {
-            group.add_child(Box::new(child));
+        if let Some(max_leaf) = max_leaf {
+            group.divide_sah(max_leaf);
         }
-
         group
     }
 
     pub fn print_bounds(&self) {
-        let mut min_x = INFINITY;
-        let mut max_x = NEG_INFINITY;
-        let mut min_y = INFINITY;
-        let mut max_y = NEG_INFINITY;
-        let mut min_z = INFINITY;
-        let mut max_z = NEG_INFINITY;
+        let mut min_x = f64::INFINITY;
+        let mut max_x = f64::NEG_INFINITY;
+        let mut min_y = f64::INFINITY;
+        let mut max_y = f64::NEG_INFINITY;
+        let mut min_z = f64::INFINITY;
+        let mut max_z = f64::NEG_INFINITY;
 
         for vertex in &self.vertices {
             if vertex.x < min_x {
@@ -185,6 +321,7 @@ impl Parser {
 
 pub fn parse_obj_file(path: &Path) -> Result<Parser> {
     let mut p = Parser::new();
+    p.dir = path.parent().map(Path::to_path_buf);
     let contents = fs::read_to_string(path)?;
     p.parse(&contents);
     Ok(p)
@@ -193,6 +330,7 @@ pub fn parse_obj_file(path: &Path) -> Result<Parser> {
 #[cfg(test)]
 mod tests {
     use crate::{
+        color::Color,
         geometry::shape::{SmoothTriangle, Triangle},
         vector::Vector,
     };
@@ -325,6 +463,92 @@ mod tests {
         assert_eq!(t1, t2);
     }
 
+    #[test]
+    fn record_unreadable_material_libraries() {
+        let mut p = Parser::new();
+        p.parse("mtllib nowhere.mtl\nusemtl red\nv 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3");
+        assert_eq!(p.unreadable_libraries(), &[PathBuf::from("nowhere.mtl")]);
+        assert_eq!(p.library_files(), p.unreadable_libraries());
+        assert_eq!(*p.library_materials()[0], Material::default());
+
+        let p = parse_obj_file(Path::new("./src/obj_parser/test_data/materials.obj")).unwrap();
+        assert!(p.unreadable_libraries().is_empty());
+    }
+
+    #[test]
+    fn faces_use_their_materials() {
+        let mut parser =
+            parse_obj_file(Path::new("./src/obj_parser/test_data/materials.obj")).unwrap();
+        assert_eq!(parser.material_names(), &["red", "blue", "green"]);
+        assert_eq!(
+            parser.library_files(),
+            &[PathBuf::from("./src/obj_parser/test_data/materials.mtl")]
+        );
+        let materials = parser.library_materials();
+        assert_eq!(materials[0].color, Color::new(1.0, 0.0, 0.0));
+        assert_eq!(materials[1].shininess, 20.0);
+        assert_eq!(*materials[2], Material::default());
+
+        let default = parser.groups["default"].children[0]
+            .shared_material()
+            .clone();
+        let g = parser.as_group_with_materials(&materials);
+        let colors = |group: &Group| -> Vec<Color> {
+            group.children.iter().map(|c| c.material().color).collect()
+        };
+        let (mut first, mut second) = (
+            g.children[0].as_any().downcast_ref::<Group>().unwrap(),
+            g.children[1].as_any().downcast_ref::<Group>().unwrap(),
+        );
+        if first.children.len() != 2 {
+            std::mem::swap(&mut first, &mut second);
+        }
+        assert!(Arc::ptr_eq(first.children[0].shared_material(), &default));
+        assert_eq!(
+            colors(first),
+            vec![Color::white(), Color::new(1.0, 0.0, 0.0)]
+        );
+        // the red faces of both groups share one material
+        assert!(Arc::ptr_eq(
+            first.children[1].shared_material(),
+            second.children[2].shared_material()
+        ));
+        assert_eq!(
+            colors(second),
+            vec![
+                Color::new(1.0, 0.0, 0.0),
+                Color::new(0.0, 0.0, 1.0),
+                Color::new(1.0, 0.0, 0.0),
+                Color::white()
+            ]
+        );
+    }
+
+    #[test]
+    fn big_models_are_divided() {
+        let mut contents = String::new();
+        for i in 0..OBJ_SAH_FACES {
+            let x = i as f64;
+            contents += &format!("v {} 0 0\nv {} 1 0\nv {} 0 1\n", x, x, x + 0.5);
+            contents += &format!("f {} {} {}\n", 3 * i + 1, 3 * i + 2, 3 * i + 3);
+        }
+        let mut parser = Parser::new();
+        parser.parse(&contents);
+        let g = parser.as_group();
+        assert_eq!(g.partition_mode(), PartitionMode::Sah);
+        assert!(g.children.iter().all(|c| c.as_any().is::<Group>()));
+
+        let small = parse_obj_file(Path::new("./src/obj_parser/test_data/triangles.obj"))
+            .unwrap()
+            .as_group();
+        assert_eq!(small.partition_mode(), PartitionMode::Contained);
+
+        let mut parser =
+            parse_obj_file(Path::new("./src/obj_parser/test_data/triangles.obj")).unwrap();
+        parser.set_max_leaf(1);
+        assert_eq!(parser.as_group().partition_mode(), PartitionMode::Sah);
+    }
+
     #[test]
     fn test_parse_line() {
         let s = "v  7.0000 0.0000 12.0000";
@@ -0,0 +1,81 @@
+use std::collections::HashMap;
+
+use crate::{color::Color, material::Material};
+
+// the materials of an mtl library by name. reads the diffuse color, the
+// specular strength and exponent, the dissolve and the index of refraction,
+// everything else keeps the default material's values
+pub fn parse_mtl(contents: &str) -> HashMap<String, Material> {
+    let mut materials = HashMap::new();
+    let mut current: Option<(String, Material)> = None;
+
+    for line in contents.lines() {
+        let mut items = line.split_ascii_whitespace();
+        let kind = match items.next() {
+            Some(kind) => kind,
+            None => continue,
+        };
+        if kind == "newmtl" {
+            if let Some((name, material)) = current.take() {
+                materials.insert(name, material);
+            }
+            let name = items.collect::<Vec<_>>().join(" ");
+            current = Some((name, Material::default()));
+            continue;
+        }
+
+        let (_, material) = match current.as_mut() {
+            Some(current) => current,
+            None => continue,
+        };
+        let numbers: Vec<f64> = items.filter_map(|item| item.parse().ok()).collect();
+        let mean = || numbers.iter().sum::<f64>() / numbers.len() as f64;
+        match (kind, numbers.len()) {
+            ("Kd", 3) => material.color = Color::new(numbers[0], numbers[1], numbers[2]),
+            ("Ks", 1..=3) => material.specular = mean(),
+            ("Ns", 1) => material.shininess = numbers[0],
+            ("d", 1) => material.transparency = 1.0 - numbers[0],
+            ("Tr", 1) => material.transparency = numbers[0],
+            ("Ni", 1) => material.refractive_index = numbers[0],
+            _ => {}
+        }
+    }
+
+    if let Some((name, material)) = current {
+        materials.insert(name, material);
+    }
+    materials
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_mtl_library() {
+        let materials = parse_mtl(
+            "# two materials
+newmtl red paint
+Kd 1 0 0
+Ks 0.5 0.5 0.5
+Ns 50
+illum 2
+
+newmtl glass
+Kd 0 0 0
+d 0.1
+Ni 1.5
+",
+        );
+        assert_eq!(materials.len(), 2);
+        let red = &materials["red paint"];
+        assert_eq!(red.color, Color::new(1.0, 0.0, 0.0));
+        assert_eq!(red.specular, 0.5);
+        assert_eq!(red.shininess, 50.0);
+        assert_eq!(red.transparency, 0.0);
+        let glass = &materials["glass"];
+        assert_eq!(glass.color, Color::black());
+        assert!(crate::equal(glass.transparency, 0.9));
+        assert_eq!(glass.refractive_index, 1.5);
+    }
+}
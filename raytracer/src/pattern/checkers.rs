@@ -1,4 +1,4 @@
-use crate::{color::Color, point::Point};
+use crate::{color::Color, point::Point, vector::Vector, EPSILON};
 
 #[derive(Debug, PartialEq, Clone)]
 pub struct CheckersPattern {
@@ -19,6 +19,30 @@ impl CheckersPattern {
             self.b
         }
     }
+
+    // the checkers box filtered over the footprint dpdx, dpdy. they are the
+    // product of a square wave along each axis, and the average of a square
+    // wave over an interval comes from its integral, a triangle wave
+    pub fn filtered_color_at(&self, point: Point, dpdx: Vector, dpdy: Vector) -> Color {
+        let wave = |u: f64, du: f64, dv: f64| {
+            let width = du.abs().max(dv.abs());
+            if width < EPSILON {
+                if u.floor().rem_euclid(2.0) == 0.0 {
+                    1.0
+                } else {
+                    -1.0
+                }
+            } else {
+                let integral = |u: f64| 1.0 - (u.rem_euclid(2.0) - 1.0).abs();
+                (integral(u + width / 2.0) - integral(u - width / 2.0)) / width
+            }
+        };
+        let product = wave(point.x, dpdx.x, dpdy.x)
+            * wave(point.y, dpdx.y, dpdy.y)
+            * wave(point.z, dpdx.z, dpdy.z);
+        let a = (1.0 + product) / 2.0;
+        self.a * a + self.b * (1.0 - a)
+    }
 }
 
 #[cfg(test)]
@@ -48,6 +72,47 @@ mod tests {
         assert_eq!(pattern.color_at(Point::new(0.0, 1.01, 0.0)), black)
     }
 
+    #[test]
+    fn filtering_over_no_footprint_matches_checkers() {
+        let pattern = CheckersPattern::new(Color::white(), Color::black());
+        let none = Vector::new(0, 0, 0);
+        for p in [
+            Point::new(0.5, 0.0, 0.5),
+            Point::new(1.5, 0.0, 0.5),
+            Point::new(-0.5, 0.2, 3.7),
+            Point::new(-1.5, -0.2, -2.1),
+        ] {
+            assert_eq!(
+                pattern.filtered_color_at(p, none, none),
+                pattern.color_at(p)
+            );
+        }
+    }
+
+    #[test]
+    fn filtering_averages_over_footprint() {
+        let pattern = CheckersPattern::new(Color::white(), Color::black());
+        let gray = Color::new(0.5, 0.5, 0.5);
+        // straddling an edge, half of each color
+        let p = Point::new(1.0, 0.5, 0.5);
+        assert_eq!(
+            pattern.filtered_color_at(p, Vector::new(1, 0, 0), Vector::new(0, 0, 0)),
+            gray
+        );
+        // a footprint a whole number of periods wide
+        let p = Point::new(0.3, 0.5, 0.5);
+        assert_eq!(
+            pattern.filtered_color_at(p, Vector::new(0, 0, 0), Vector::new(0, 0, 4)),
+            gray
+        );
+        // inside a square, a quarter of it across, the color is unchanged
+        let p = Point::new(0.5, 0.5, 0.5);
+        assert_eq!(
+            pattern.filtered_color_at(p, Vector::new(0.25, 0.0, 0.0), Vector::new(0, 0, 0)),
+            Color::white()
+        );
+    }
+
     #[test]
     fn checkers_repeat_in_z() {
         let white = Color::white();
@@ -0,0 +1,87 @@
+use std::sync::Arc;
+
+use crate::{canvas::Canvas, color::Color, geometry::uv::UvMapping, point::Point};
+
+// an image wrapped around shapes, shared between the patterns using it
+#[derive(Debug, Clone)]
+pub struct ImageTexture {
+    image: Arc<Canvas>,
+    mapping: UvMapping,
+}
+
+impl PartialEq for ImageTexture {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.image, &other.image) && self.mapping == other.mapping
+    }
+}
+
+impl ImageTexture {
+    pub fn new(image: Canvas, mapping: UvMapping) -> Self {
+        assert!(image.width() > 0 && image.height() > 0);
+        Self {
+            image: Arc::new(image),
+            mapping,
+        }
+    }
+
+    pub fn mapping(&self) -> UvMapping {
+        self.mapping
+    }
+
+    // uv is the hit's own, used with UvMapping::Shape. without any the
+    // texture's center shows
+    pub fn color_at(&self, point: Point, uv: Option<(f64, f64)>) -> Color {
+        let (u, v) = self.mapping.map(point).or(uv).unwrap_or((0.5, 0.5));
+        self.uv_color(u, v)
+    }
+
+    // the nearest pixel, v runs from the bottom of the image to the top
+    fn uv_color(&self, u: f64, v: f64) -> Color {
+        let (width, height) = (self.image.width(), self.image.height());
+        let x = (u.clamp(0.0, 1.0) * (width - 1) as f64).round() as usize;
+        let y = ((1.0 - v.clamp(0.0, 1.0)) * (height - 1) as f64).round() as usize;
+        self.image.get_pixel(x, y)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a 10x10 image whose pixels encode their own coordinates
+    fn coordinates() -> Canvas {
+        let mut image = Canvas::new(10, 10);
+        for y in 0..10 {
+            for x in 0..10 {
+                image.set_pixel(x, y, Color::new(x as f64 / 10.0, y as f64 / 10.0, 0.0));
+            }
+        }
+        image
+    }
+
+    #[test]
+    fn texture_lookup_by_uv() {
+        let texture = ImageTexture::new(coordinates(), UvMapping::Shape);
+        let cases = [
+            ((0.0, 0.0), (0, 9)),
+            ((0.3, 0.0), (3, 9)),
+            ((0.6, 0.3), (5, 6)),
+            ((1.0, 1.0), (9, 0)),
+            ((1.5, -0.5), (9, 9)),
+        ];
+        for ((u, v), (x, y)) in cases {
+            assert_eq!(
+                texture.color_at(Point::origin(), Some((u, v))),
+                Color::new(x as f64 / 10.0, y as f64 / 10.0, 0.0)
+            );
+        }
+        // the mapping wins over the hit's uvs
+        let planar = ImageTexture::new(coordinates(), UvMapping::Planar);
+        assert_eq!(
+            planar.color_at(Point::new(0.3, 0.0, 0.0), Some((1.0, 1.0))),
+            Color::new(0.3, 0.9, 0.0)
+        );
+        assert_ne!(planar, ImageTexture::new(coordinates(), UvMapping::Planar));
+        assert_eq!(planar, planar.clone());
+    }
+}
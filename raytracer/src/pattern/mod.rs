@@ -1,14 +1,24 @@
 use checkers::CheckersPattern;
 use gradient::GradientPattern;
+use image_texture::ImageTexture;
 use ring::RingPattern;
 use stripe::StripePattern;
 
-use crate::{color::Color, geometry::Shape, matrix::Matrix, point::Point};
+use crate::{
+    bounding_box::BoundingBox,
+    canvas::Canvas,
+    color::Color,
+    geometry::{intersection::Intersection, uv::UvMapping, Shape},
+    matrix::Matrix,
+    point::Point,
+    ray::Footprint,
+};
 
 use self::test_pattern::TestPattern;
 
 mod checkers;
 mod gradient;
+mod image_texture;
 mod ring;
 mod stripe;
 mod test_pattern;
@@ -18,6 +28,9 @@ pub struct Pattern {
     transform: Matrix,
     transform_inverse: Matrix,
     pattern: Kind,
+    // where in pattern space the pattern is drawn, the material's own color
+    // shows everywhere else. without one the pattern repeats forever
+    extent: Option<BoundingBox>,
 }
 
 impl Default for Pattern {
@@ -26,6 +39,7 @@ impl Default for Pattern {
             transform: Matrix::identity(4, 4),
             transform_inverse: Matrix::identity(4, 4),
             pattern: Kind::Test(TestPattern {}),
+            extent: None,
         }
     }
 }
@@ -36,15 +50,82 @@ impl Pattern {
         self.transform_inverse = self.transform.inverse();
     }
 
-    pub fn color_at_shape(&self, shape: &dyn Shape, world_point: Point) -> Color {
+    pub fn transform(&self) -> &Matrix {
+        &self.transform
+    }
+
+    // the extent is in pattern space, so with the pattern scaled down it still
+    // spans as many stripes or squares. axes left infinite repeat forever, e.g.
+    // x and z from 0 to 8 make a board of checkers on an endless plane
+    pub fn set_extent(&mut self, extent: BoundingBox) {
+        self.extent = Some(extent);
+    }
+
+    pub fn extent(&self) -> Option<&BoundingBox> {
+        self.extent.as_ref()
+    }
+
+    // whether the pattern is drawn at world_point
+    pub fn covers(&self, shape: &dyn Shape, world_point: Point) -> bool {
+        match &self.extent {
+            Some(extent) => extent.contains_point(self.to_pattern_space(shape, world_point)),
+            None => true,
+        }
+    }
+
+    pub fn to_pattern_space(&self, shape: &dyn Shape, world_point: Point) -> Point {
         let object_point = &shape.get_base().transform_inverse * world_point;
-        let pattern_point = &self.transform_inverse * object_point;
+        &self.transform_inverse * object_point
+    }
+
+    pub fn color_at_shape(&self, shape: &dyn Shape, world_point: Point) -> Color {
+        self.color_at_hit(shape, world_point, None)
+    }
+
+    // uv is what the hit carries, images mapped by the shape's own texture
+    // coordinates work them out from the point without it
+    fn color_at_hit(&self, shape: &dyn Shape, world_point: Point, uv: Option<(f64, f64)>) -> Color {
+        let pattern_point = self.to_pattern_space(shape, world_point);
         match &self.pattern {
+            Kind::Image(texture) => {
+                let uv = match (texture.mapping(), uv) {
+                    (UvMapping::Shape, None) => {
+                        shape.uv_at(world_point, &Intersection::new(0.0, shape))
+                    }
+                    _ => uv,
+                };
+                texture.color_at(pattern_point, uv)
+            }
             Kind::Test(test_pattern) => test_pattern.color_at(pattern_point),
             Kind::Stripe(stripe_pattern) => stripe_pattern.color_at(pattern_point),
             Kind::Gradient(gradient_pattern) => gradient_pattern.color_at(pattern_point),
             Kind::Ring(ring_pattern) => ring_pattern.color_at(pattern_point),
-            Kind::Checkers(checkers_pattern) => checkers_pattern.color_at(pattern_point),
+            Kind::Checkers(checkers_pattern) | Kind::FilteredCheckers(checkers_pattern) => {
+                checkers_pattern.color_at(pattern_point)
+            }
+        }
+    }
+
+    // like color_at_shape, but patterns that can be filtered are averaged over
+    // the pixel's footprint on the surface, and images can use the hit's uv
+    pub fn color_at_footprint(
+        &self,
+        shape: &dyn Shape,
+        world_point: Point,
+        footprint: Option<&Footprint>,
+        uv: Option<(f64, f64)>,
+    ) -> Color {
+        match (&self.pattern, footprint) {
+            (Kind::FilteredCheckers(checkers_pattern), Some(footprint)) => {
+                let to_pattern_space =
+                    |v| &self.transform_inverse * (&shape.get_base().transform_inverse * v);
+                checkers_pattern.filtered_color_at(
+                    self.to_pattern_space(shape, world_point),
+                    to_pattern_space(footprint.dpdx),
+                    to_pattern_space(footprint.dpdy),
+                )
+            }
+            _ => self.color_at_hit(shape, world_point, uv),
         }
     }
 }
@@ -56,6 +137,8 @@ enum Kind {
     Gradient(GradientPattern),
     Ring(RingPattern),
     Checkers(CheckersPattern),
+    FilteredCheckers(CheckersPattern),
+    Image(ImageTexture),
 }
 
 pub fn test_pattern() -> Pattern {
@@ -89,3 +172,23 @@ pub fn checkers_pattern(a: Color, b: Color) -> Pattern {
         ..Default::default()
     }
 }
+
+// checkers that blur into their average color where a pixel covers many
+// squares, instead of shimmering. needs rays with differentials, i.e. camera
+// rays and their reflections, anything else sees plain checkers
+pub fn filtered_checkers_pattern(a: Color, b: Color) -> Pattern {
+    Pattern {
+        pattern: Kind::FilteredCheckers(CheckersPattern::new(a, b)),
+        ..Default::default()
+    }
+}
+
+// an image wrapped around shapes by mapping. the explicit mappings work in
+// pattern space, so the pattern's transform places the image, the shape's own
+// texture coordinates ignore it
+pub fn image_pattern(image: Canvas, mapping: UvMapping) -> Pattern {
+    Pattern {
+        pattern: Kind::Image(ImageTexture::new(image, mapping)),
+        ..Default::default()
+    }
+}
@@ -1,6 +1,9 @@
-use std::ops::{Add, Sub};
+use std::{
+    fmt,
+    ops::{Add, Sub},
+};
 
-use crate::{equal, equal_ignore_inf, vector::Vector};
+use crate::{approx::relative_eq, vector::Vector, DISPLAY_PRECISION, EPSILON};
 
 #[derive(Debug, Clone, Copy)]
 pub struct Point {
@@ -22,16 +25,27 @@ impl Point {
         Self::new(0, 0, 0)
     }
 
-    pub fn eq_ignore_inf(&self, &other: &Self) -> bool {
-        equal_ignore_inf(self.x, other.x)
-            && equal_ignore_inf(self.y, other.y)
-            && equal_ignore_inf(self.z, other.z)
+    pub fn approx_eq(&self, other: &Self, epsilon: f64) -> bool {
+        relative_eq(self.x, other.x, epsilon)
+            && relative_eq(self.y, other.y, epsilon)
+            && relative_eq(self.z, other.z, epsilon)
     }
 }
 
 impl PartialEq for Point {
     fn eq(&self, other: &Self) -> bool {
-        equal(self.x, other.x) && equal(self.y, other.y) && equal(self.z, other.z)
+        self.approx_eq(other, EPSILON)
+    }
+}
+
+impl fmt::Display for Point {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let p = f.precision().unwrap_or(DISPLAY_PRECISION);
+        write!(
+            f,
+            "point({:.*}, {:.*}, {:.*})",
+            p, self.x, p, self.y, p, self.z
+        )
     }
 }
 
@@ -63,6 +77,20 @@ impl Sub<Vector> for Point {
 mod tests {
     use super::*;
 
+    #[test]
+    fn display_point() {
+        let p = Point::new(1.0, -2.0, 0.123456);
+        assert_eq!(p.to_string(), "point(1.0000, -2.0000, 0.1235)");
+        assert_eq!(format!("{:.2}", p), "point(1.00, -2.00, 0.12)");
+    }
+
+    #[test]
+    fn approx_eq_point() {
+        let a = Point::new(1.0, 2.0, 3.0);
+        assert!(a.approx_eq(&Point::new(1.0, 2.05, 3.0), 0.1));
+        assert!(!a.approx_eq(&Point::new(1.0, 2.05, 3.0), 0.01));
+    }
+
     #[test]
     fn create_point() {
         let a = Point::new(4.3, -4.2, 3.1);
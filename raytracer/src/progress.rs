@@ -0,0 +1,89 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+// sent by a render every time a row is finished
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RenderProgress {
+    pub rows_done: usize,
+    pub total_rows: usize,
+}
+
+impl RenderProgress {
+    pub fn is_done(&self) -> bool {
+        self.rows_done >= self.total_rows
+    }
+}
+
+// rows per second over the last few progress updates, kept in a ring buffer so
+// the estimate follows the render speeding up or slowing down
+#[derive(Debug)]
+pub struct ProgressMeter {
+    window: VecDeque<(Instant, usize)>,
+    capacity: usize,
+}
+
+impl ProgressMeter {
+    pub fn new(capacity: usize) -> Self {
+        assert!(capacity >= 2);
+        Self {
+            window: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    pub fn record(&mut self, at: Instant, rows_done: usize) {
+        if self.window.len() == self.capacity {
+            self.window.pop_front();
+        }
+        self.window.push_back((at, rows_done));
+    }
+
+    pub fn rows_per_sec(&self) -> Option<f64> {
+        let (first, last) = (self.window.front()?, self.window.back()?);
+        let elapsed = last.0.duration_since(first.0).as_secs_f64();
+        if elapsed <= 0.0 {
+            return None;
+        }
+        Some(last.1.saturating_sub(first.1) as f64 / elapsed)
+    }
+
+    pub fn eta(&self, total_rows: usize) -> Option<Duration> {
+        let rate = self.rows_per_sec()?;
+        if rate <= 0.0 {
+            return None;
+        }
+        let remaining = total_rows.saturating_sub(self.window.back()?.1);
+        Some(Duration::from_secs_f64(remaining as f64 / rate))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn meter_needs_two_updates() {
+        let mut meter = ProgressMeter::new(4);
+        assert_eq!(meter.rows_per_sec(), None);
+        meter.record(Instant::now(), 1);
+        assert_eq!(meter.rows_per_sec(), None);
+        assert_eq!(meter.eta(10), None);
+    }
+
+    #[test]
+    fn meter_estimates_from_recent_updates() {
+        let start = Instant::now();
+        let mut meter = ProgressMeter::new(3);
+        // slow at first, then 10 rows per second
+        meter.record(start, 0);
+        meter.record(start + Duration::from_secs(10), 1);
+        meter.record(start + Duration::from_secs(11), 11);
+        meter.record(start + Duration::from_secs(12), 21);
+
+        assert!((meter.rows_per_sec().unwrap() - 10.0).abs() < 1e-9);
+        let eta = meter.eta(121).unwrap();
+        assert!((eta.as_secs_f64() - 10.0).abs() < 1e-9);
+    }
+}
@@ -1,14 +1,82 @@
-use crate::{matrix::Matrix, point::Point, vector::Vector};
+use std::fmt;
 
-#[derive(Debug, PartialEq)]
+use crate::{
+    matrix::Matrix,
+    point::Point,
+    vector::{dot, Vector},
+    EPSILON,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Ray {
     origin: Point,
     direction: Vector,
+    differentials: Option<RayDifferentials>,
+}
+
+// the rays through the next pixel across (x) and down (y), which tell how much
+// of a surface the pixel covers where the ray hits it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RayDifferentials {
+    pub rx_origin: Point,
+    pub rx_direction: Vector,
+    pub ry_origin: Point,
+    pub ry_direction: Vector,
+}
+
+// the offsets from a hit point to where the differential rays meet the surface's
+// tangent plane
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Footprint {
+    pub dpdx: Vector,
+    pub dpdy: Vector,
 }
 
 impl Ray {
     pub fn new(origin: Point, direction: Vector) -> Self {
-        Self { origin, direction }
+        Self {
+            origin,
+            direction,
+            differentials: None,
+        }
+    }
+
+    pub fn with_differentials(mut self, differentials: RayDifferentials) -> Self {
+        self.differentials = Some(differentials);
+        self
+    }
+
+    pub fn differentials(&self) -> Option<&RayDifferentials> {
+        self.differentials.as_ref()
+    }
+
+    // the footprint at point, on the plane through it with the given normal.
+    // None without differentials, or when they run parallel to the plane
+    pub fn footprint(&self, point: Point, normal: Vector) -> Option<Footprint> {
+        let d = self.differentials?;
+        let offset = |origin: Point, direction: Vector| {
+            let denom = dot(direction, normal);
+            if denom.abs() < EPSILON {
+                return None;
+            }
+            let t = dot(point - origin, normal) / denom;
+            Some(origin + direction * t - point)
+        };
+        Some(Footprint {
+            dpdx: offset(d.rx_origin, d.rx_direction)?,
+            dpdy: offset(d.ry_origin, d.ry_direction)?,
+        })
+    }
+
+    // how far the differential rays are from this one after t, across the ray
+    // rather than on a surface like footprint. for camera rays about the
+    // camera's pixel size times the distance
+    pub fn pixel_width_at(&self, t: f64) -> Option<f64> {
+        let d = self.differentials?;
+        let point = self.position(t);
+        let dx = (d.rx_origin + d.rx_direction * t - point).magnitude();
+        let dy = (d.ry_origin + d.ry_direction * t - point).magnitude();
+        Some(dx.max(dy))
     }
 
     pub fn origin(&self) -> Point {
@@ -23,11 +91,31 @@ impl Ray {
         self.origin + self.direction * t.into()
     }
 
+    pub fn at(&self, t: f64) -> Point {
+        self.position(t)
+    }
+
+    // the point a fraction s of the way from distance t0 to t1 along the ray
+    pub fn lerp_point(&self, t0: f64, t1: f64, s: f64) -> Point {
+        self.position(t0 + (t1 - t0) * s)
+    }
+
+    // differentials are only needed by shading, in world space, so they are
+    // dropped rather than transformed along with every intersection test
     pub fn transform(&self, m: &Matrix) -> Self {
         Self::new(m * self.origin, m * self.direction)
     }
 }
 
+impl fmt::Display for Ray {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match f.precision() {
+            Some(p) => write!(f, "{:.*} -> {:.*}", p, self.origin, p, self.direction),
+            None => write!(f, "{} -> {}", self.origin, self.direction),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::transform::{scaling, translation};
@@ -53,6 +141,71 @@ mod tests {
         assert_eq!(r.position(2.5), Point::new(4.5, 3.0, 4.0));
     }
 
+    #[test]
+    fn ray_at_and_lerp_point() {
+        let r = Ray::new(Point::new(2, 3, 4), Vector::new(1, 0, 0));
+        assert_eq!(r.at(2.5), r.position(2.5));
+        assert_eq!(r.lerp_point(1.0, 3.0, 0.25), Point::new(3.5, 3.0, 4.0));
+    }
+
+    #[test]
+    fn display_ray() {
+        let r = Ray::new(Point::new(1, 2, 3), Vector::new(0, 0, 1));
+        assert_eq!(
+            format!("{:.1}", r),
+            "point(1.0, 2.0, 3.0) -> vector(0.0, 0.0, 1.0)"
+        );
+    }
+
+    #[test]
+    fn footprint_on_plane() {
+        let r = Ray::new(Point::new(0, 1, 0), Vector::new(0, -1, 0)).with_differentials(
+            RayDifferentials {
+                rx_origin: Point::new(0, 1, 0),
+                rx_direction: Vector::new(0.1, -1.0, 0.0),
+                ry_origin: Point::new(0, 1, 0),
+                ry_direction: Vector::new(0.0, -1.0, 0.2),
+            },
+        );
+        let f = r.footprint(Point::origin(), Vector::new(0, 1, 0)).unwrap();
+        assert_eq!(f.dpdx, Vector::new(0.1, 0.0, 0.0));
+        assert_eq!(f.dpdy, Vector::new(0.0, 0.0, 0.2));
+
+        assert_eq!(r.footprint(Point::origin(), Vector::new(1, 0, 0)), None);
+        let plain = Ray::new(Point::new(0, 1, 0), Vector::new(0, -1, 0));
+        assert_eq!(plain.footprint(Point::origin(), Vector::new(0, 1, 0)), None);
+    }
+
+    #[test]
+    fn pixel_width_grows_with_distance() {
+        let r =
+            Ray::new(Point::origin(), Vector::new(0, 0, 1)).with_differentials(RayDifferentials {
+                rx_origin: Point::origin(),
+                rx_direction: Vector::new(0.01, 0.0, 1.0),
+                ry_origin: Point::new(0.0, 0.5, 0.0),
+                ry_direction: Vector::new(0, 0, 1),
+            });
+        assert!(crate::equal(r.pixel_width_at(10.0).unwrap(), 0.5));
+        assert!(crate::equal(r.pixel_width_at(100.0).unwrap(), 1.0));
+        assert_eq!(
+            Ray::new(Point::origin(), Vector::new(0, 0, 1)).pixel_width_at(1.0),
+            None
+        );
+    }
+
+    #[test]
+    fn transforming_drops_differentials() {
+        let d = RayDifferentials {
+            rx_origin: Point::origin(),
+            rx_direction: Vector::new(1, 0, 0),
+            ry_origin: Point::origin(),
+            ry_direction: Vector::new(0, 1, 0),
+        };
+        let r = Ray::new(Point::origin(), Vector::new(0, 0, 1)).with_differentials(d);
+        assert_eq!(r.differentials(), Some(&d));
+        assert_eq!(r.transform(&translation(1, 0, 0)).differentials(), None);
+    }
+
     #[test]
     fn translate_ray() {
         let r = Ray::new(Point::new(1, 2, 3), Vector::new(0, 1, 0));
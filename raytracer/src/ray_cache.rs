@@ -0,0 +1,132 @@
+use crate::{color::Color, geometry::RayKind, ray::Ray, EPSILON};
+
+// rays closer than this in every coordinate share a cache entry
+const QUANTUM: f64 = EPSILON / 100.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct Key {
+    origin: [i64; 3],
+    direction: [i64; 3],
+    remaining: usize,
+    kind: RayKind,
+}
+
+impl Key {
+    fn new(ray: &Ray, remaining: usize, kind: RayKind) -> Self {
+        let q = |v: f64| (v / QUANTUM).round() as i64;
+        let (o, d) = (ray.origin(), ray.direction());
+        Self {
+            origin: [q(o.x), q(o.y), q(o.z)],
+            direction: [q(d.x), q(d.y), q(d.z)],
+            remaining,
+            kind,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct RayCacheStats {
+    pub hits: usize,
+    pub misses: usize,
+}
+
+impl RayCacheStats {
+    pub fn hit_rate(&self) -> f64 {
+        let lookups = self.hits + self.misses;
+        if lookups == 0 {
+            0.0
+        } else {
+            self.hits as f64 / lookups as f64
+        }
+    }
+}
+
+// a small least recently used cache of traced colors, meant to live for one
+// pixel so rays that repeat exactly (halls of mirrors) are only traced once
+#[derive(Debug)]
+pub struct RayCache {
+    capacity: usize,
+    // least recently used first
+    entries: Vec<(Key, Color)>,
+    stats: RayCacheStats,
+}
+
+impl RayCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: Vec::with_capacity(capacity),
+            stats: RayCacheStats::default(),
+        }
+    }
+
+    pub fn stats(&self) -> RayCacheStats {
+        self.stats
+    }
+
+    pub(crate) fn get(&mut self, ray: &Ray, remaining: usize, kind: RayKind) -> Option<Color> {
+        let key = Key::new(ray, remaining, kind);
+        match self.entries.iter().position(|(k, _)| *k == key) {
+            Some(idx) => {
+                self.stats.hits += 1;
+                let entry = self.entries.remove(idx);
+                self.entries.push(entry);
+                Some(entry.1)
+            }
+            None => {
+                self.stats.misses += 1;
+                None
+            }
+        }
+    }
+
+    pub(crate) fn insert(&mut self, ray: &Ray, remaining: usize, kind: RayKind, color: Color) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.entries.len() == self.capacity {
+            self.entries.remove(0);
+        }
+        self.entries.push((Key::new(ray, remaining, kind), color));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{point::Point, vector::Vector};
+
+    use super::*;
+
+    fn ray(x: f64) -> Ray {
+        Ray::new(Point::new(x, 0.0, 0.0), Vector::new(0, 0, 1))
+    }
+
+    #[test]
+    fn cache_hits_and_misses() {
+        let mut cache = RayCache::new(4);
+        assert_eq!(cache.get(&ray(0.0), 5, RayKind::Secondary), None);
+        cache.insert(&ray(0.0), 5, RayKind::Secondary, Color::white());
+
+        assert_eq!(
+            cache.get(&ray(QUANTUM / 10.0), 5, RayKind::Secondary),
+            Some(Color::white())
+        );
+        assert_eq!(cache.get(&ray(0.0), 4, RayKind::Secondary), None);
+        assert_eq!(cache.get(&ray(0.0), 5, RayKind::Camera), None);
+        assert_eq!(cache.stats(), RayCacheStats { hits: 1, misses: 3 });
+        assert!((cache.stats().hit_rate() - 0.25).abs() < 1e-12);
+    }
+
+    #[test]
+    fn cache_evicts_least_recently_used() {
+        let mut cache = RayCache::new(2);
+        cache.insert(&ray(0.0), 1, RayKind::Secondary, Color::white());
+        cache.insert(&ray(1.0), 1, RayKind::Secondary, Color::black());
+        assert!(cache.get(&ray(0.0), 1, RayKind::Secondary).is_some());
+        cache.insert(&ray(2.0), 1, RayKind::Secondary, Color::white());
+
+        assert!(cache.get(&ray(1.0), 1, RayKind::Secondary).is_none());
+        assert!(cache.get(&ray(0.0), 1, RayKind::Secondary).is_some());
+        assert!(cache.get(&ray(2.0), 1, RayKind::Secondary).is_some());
+    }
+}
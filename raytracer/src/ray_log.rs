@@ -0,0 +1,200 @@
+use std::fmt;
+
+use crate::{
+    color::Color,
+    geometry::{
+        intersection::{Computations, Intersection},
+        RayKind,
+    },
+    material::{LightingTerms, Material},
+    point::Point,
+    ray::Ray,
+    vector::Vector,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RayLogHit {
+    pub t: f64,
+    pub point: Point,
+    pub normal: Vector,
+    pub inside: bool,
+    pub material: Material,
+}
+
+// what one light added at a hit, already scaled by the world's light normalization
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LightContribution {
+    // index into the world's lights
+    pub light: usize,
+    pub shadowed: bool,
+    pub lighting: LightingTerms,
+    pub translucent: Color,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShadingTerms {
+    pub lights: Vec<LightContribution>,
+    // reflected and refracted light as added to the hit, after fresnel weighting
+    pub reflected: Color,
+    pub refracted: Color,
+}
+
+impl ShadingTerms {
+    pub fn surface(&self) -> Color {
+        self.lights
+            .iter()
+            .map(|l| l.lighting.total() + l.translucent)
+            .sum()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RayLogEntry {
+    // 0 for camera rays, +1 for every bounce
+    pub depth: usize,
+    pub ray: Ray,
+    pub kind: RayKind,
+    pub remaining: usize,
+    // t of every intersection along the ray, hit or not
+    pub intersections: Vec<f64>,
+    pub hit: Option<RayLogHit>,
+    pub shading: Option<ShadingTerms>,
+    // the color came from the ray cache, so no children were traced
+    pub cached: bool,
+    pub color: Color,
+}
+
+// records every ray traced for a pixel as a tree, in the order they were
+// traced: an entry's children are the entries right after it one level deeper
+#[derive(Debug, Default)]
+pub struct RayLog {
+    entries: Vec<RayLogEntry>,
+    depth: usize,
+}
+
+impl RayLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn entries(&self) -> &[RayLogEntry] {
+        &self.entries
+    }
+
+    pub fn children(&self, idx: usize) -> Vec<&RayLogEntry> {
+        let depth = self.entries[idx].depth;
+        self.entries[idx + 1..]
+            .iter()
+            .take_while(|e| e.depth > depth)
+            .filter(|e| e.depth == depth + 1)
+            .collect()
+    }
+
+    pub(crate) fn enter(&mut self, ray: &Ray, kind: RayKind, remaining: usize) -> usize {
+        self.entries.push(RayLogEntry {
+            depth: self.depth,
+            ray: *ray,
+            kind,
+            remaining,
+            intersections: Vec::new(),
+            hit: None,
+            shading: None,
+            cached: false,
+            color: Color::black(),
+        });
+        self.depth += 1;
+        self.entries.len() - 1
+    }
+
+    pub(crate) fn intersections(&mut self, idx: usize, xs: &[Intersection]) {
+        self.entries[idx].intersections = xs.iter().map(|i| i.t()).collect();
+    }
+
+    // material is the one the hit was shaded with
+    pub(crate) fn hit(&mut self, idx: usize, comps: &Computations, material: &Material) {
+        self.entries[idx].hit = Some(RayLogHit {
+            t: comps.t,
+            point: comps.point,
+            normal: comps.normalv,
+            inside: comps.inside,
+            material: material.clone(),
+        });
+    }
+
+    pub(crate) fn shading(&mut self, idx: usize, shading: ShadingTerms) {
+        self.entries[idx].shading = Some(shading);
+    }
+
+    pub(crate) fn leave(&mut self, idx: usize, color: Color, cached: bool) {
+        let entry = &mut self.entries[idx];
+        entry.color = color;
+        entry.cached = cached;
+        self.depth -= 1;
+    }
+}
+
+impl fmt::Display for RayLog {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for entry in &self.entries {
+            let kind = match entry.kind {
+                RayKind::Camera => "camera",
+                RayKind::Shadow => "shadow",
+                RayKind::Secondary => "secondary",
+            };
+            write!(
+                f,
+                "{:indent$}{} ray {} (remaining {}): ",
+                "",
+                kind,
+                entry.ray,
+                entry.remaining,
+                indent = entry.depth * 2
+            )?;
+            if entry.cached {
+                write!(f, "cached")?;
+            } else {
+                match &entry.hit {
+                    None => write!(f, "miss")?,
+                    Some(hit) => write!(
+                        f,
+                        "hit t={:.4} at {} normal {}{}, material {} reflective {} transparency {}",
+                        hit.t,
+                        hit.point,
+                        hit.normal,
+                        if hit.inside { " (inside)" } else { "" },
+                        hit.material.color,
+                        hit.material.reflective,
+                        hit.material.transparency
+                    )?,
+                }
+            }
+            writeln!(f, " => {}", entry.color)?;
+            if let Some(shading) = &entry.shading {
+                let indent = entry.depth * 2 + 4;
+                for l in &shading.lights {
+                    writeln!(
+                        f,
+                        "{:indent$}light {}{}: ambient {} diffuse {} specular {} translucent {}",
+                        "",
+                        l.light,
+                        if l.shadowed { " (shadowed)" } else { "" },
+                        l.lighting.ambient,
+                        l.lighting.diffuse,
+                        l.lighting.specular,
+                        l.translucent,
+                        indent = indent
+                    )?;
+                }
+                writeln!(
+                    f,
+                    "{:indent$}reflected {} refracted {}",
+                    "",
+                    shading.reflected,
+                    shading.refracted,
+                    indent = indent
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,230 @@
+use std::{
+    collections::BTreeMap,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    camera::Camera,
+    canvas::Canvas,
+    ray_cache::RayCacheStats,
+    world::{RenderMode, ShadowRayStats, World},
+};
+
+// a camera and the world it looks at. render settings stay in the camera's
+// render_opts
+pub struct Scene {
+    pub camera: Camera,
+    pub world: World,
+    // set once the renderer has divided the world
+    prepared: bool,
+}
+
+impl Scene {
+    pub fn new(camera: Camera, world: World) -> Self {
+        Self {
+            camera,
+            world,
+            prepared: false,
+        }
+    }
+}
+
+// images rendered along with the color image, for compositing and look-dev
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Pass {
+    // how much light reaches what each pixel sees, see Camera::render_shadows
+    Shadow,
+    // the image again in RenderMode::Clay
+    Clay,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct RendererOptions {
+    // before its first render, the world's groups are divided into
+    // hierarchies with at most this many children per group
+    pub divide_threshold: Option<usize>,
+    pub passes: Vec<Pass>,
+}
+
+// what rendering the color image took
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct RenderStats {
+    pub elapsed: Duration,
+    pub shadow_rays: ShadowRayStats,
+    pub ray_cache: RayCacheStats,
+}
+
+pub struct RenderOutput {
+    pub color: Canvas,
+    pub aovs: BTreeMap<Pass, Canvas>,
+    pub stats: RenderStats,
+}
+
+// everything between a scene and its images: preparing the world, rendering
+// on the camera's threads with its sampling and exposure, then the passes
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Renderer {
+    opts: RendererOptions,
+}
+
+impl Renderer {
+    pub fn new(opts: RendererOptions) -> Self {
+        Self { opts }
+    }
+
+    pub fn options(&self) -> &RendererOptions {
+        &self.opts
+    }
+
+    // the scene is mutable for the world's preparation, and a scene rendered
+    // again after moving shapes gets its bounds refitted
+    pub fn render(&self, scene: &mut Scene) -> RenderOutput {
+        self.prepare(scene);
+
+        let opts = &scene.camera.render_opts;
+        let (shadow_rays, ray_cache) = (opts.shadow_ray_stats(), opts.ray_cache_stats());
+        let start = Instant::now();
+        let color = scene.camera.render_parallel(&scene.world);
+        let elapsed = start.elapsed();
+        let opts = &scene.camera.render_opts;
+        let (shadow_rays_after, ray_cache_after) =
+            (opts.shadow_ray_stats(), opts.ray_cache_stats());
+        let stats = RenderStats {
+            elapsed,
+            shadow_rays: ShadowRayStats {
+                cast: shadow_rays_after.cast - shadow_rays.cast,
+                blocked: shadow_rays_after.blocked - shadow_rays.blocked,
+            },
+            ray_cache: RayCacheStats {
+                hits: ray_cache_after.hits - ray_cache.hits,
+                misses: ray_cache_after.misses - ray_cache.misses,
+            },
+        };
+
+        let aovs = self
+            .opts
+            .passes
+            .iter()
+            .map(|&pass| (pass, render_pass(scene, pass)))
+            .collect();
+        RenderOutput { color, aovs, stats }
+    }
+
+    fn prepare(&self, scene: &mut Scene) {
+        if scene.prepared {
+            scene.world.refit();
+            return;
+        }
+        if let Some(threshold) = self.opts.divide_threshold {
+            scene.world.divide(threshold);
+        }
+        scene.prepared = true;
+    }
+}
+
+fn render_pass(scene: &mut Scene, pass: Pass) -> Canvas {
+    let camera = &mut scene.camera;
+    match pass {
+        Pass::Shadow => camera.render_shadows(&scene.world),
+        Pass::Clay => {
+            let mode = camera.render_opts.get_render_mode();
+            camera.render_opts.render_mode(RenderMode::Clay);
+            let image = camera.render_parallel(&scene.world);
+            camera.render_opts.render_mode(mode);
+            image
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::PI;
+
+    use crate::{
+        color::Color,
+        geometry::{
+            shape::{Group, Plane, Sphere},
+            Shape,
+        },
+        light::PointLight,
+        point::Point,
+        transform::{scaling, translation, view_transform},
+        vector::Vector,
+    };
+
+    use super::*;
+
+    fn scene() -> Scene {
+        let mut world = World::new();
+        world.add_light(PointLight::new(Point::new(-10, 10, -10), Color::white()));
+        let mut floor = Plane::default();
+        floor.set_transform(translation(0, -1, 0));
+        floor.material_mut().color = Color::new(1.0, 0.2, 0.2);
+        world.add_object(floor);
+        let mut group = Group::default();
+        for x in -2..=2 {
+            let mut s = Sphere::default();
+            s.set_transform(&translation(x, 0, 0) * &scaling(0.4, 0.4, 0.4));
+            group.add_child(Box::new(s));
+        }
+        world.add_object(group);
+
+        let mut camera = Camera::new(16, 12, PI / 3.0);
+        camera.set_transform(view_transform(
+            Point::new(0, 2, -6),
+            Point::origin(),
+            Vector::new(0, 1, 0),
+        ));
+        camera.render_opts.num_threads(2);
+        Scene::new(camera, world)
+    }
+
+    fn pixels(image: &Canvas) -> Vec<Color> {
+        image.rows(0..image.height()).into_owned()
+    }
+
+    #[test]
+    fn renderer_matches_the_camera_render() {
+        let mut scene = scene();
+        let expected = scene.camera.render_parallel(&scene.world);
+
+        let renderer = Renderer::new(RendererOptions {
+            divide_threshold: Some(2),
+            ..Default::default()
+        });
+        let output = renderer.render(&mut scene);
+        assert_eq!(pixels(&output.color), pixels(&expected));
+        assert!(output.aovs.is_empty());
+        // one light, for the pixels that don't see the sky
+        let cast = output.stats.shadow_rays.cast;
+        assert!(cast > 0 && cast < 16 * 12);
+        assert_eq!(output.stats.ray_cache, RayCacheStats::default());
+
+        // the world is only divided once
+        let again = renderer.render(&mut scene);
+        assert_eq!(pixels(&again.color), pixels(&expected));
+        assert_eq!(again.stats.shadow_rays, output.stats.shadow_rays);
+    }
+
+    #[test]
+    fn renderer_adds_passes() {
+        let mut scene = scene();
+        let renderer = Renderer::new(RendererOptions {
+            passes: vec![Pass::Clay, Pass::Shadow],
+            ..Default::default()
+        });
+        let output = renderer.render(&mut scene);
+        assert_eq!(
+            output.aovs.keys().collect::<Vec<_>>(),
+            [&Pass::Shadow, &Pass::Clay]
+        );
+        assert_eq!(
+            pixels(&output.aovs[&Pass::Shadow]),
+            pixels(&scene.camera.render_shadows(&scene.world))
+        );
+        let clay = output.aovs[&Pass::Clay].get_pixel(8, 11);
+        assert!(clay.red == clay.green && clay.green == clay.blue);
+        assert_ne!(output.color.get_pixel(8, 11), clay);
+        assert_eq!(scene.camera.render_opts.get_render_mode(), RenderMode::Full);
+    }
+}
@@ -0,0 +1,140 @@
+// splits a per-pixel sample budget between every stochastic effect at once.
+// each sample gets a 2d point for the pixel and one for soft lights, and in
+// both of those dimensions the n samples fall in n different strata (latin
+// hypercube), so n samples stratify all effects instead of multiplying.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sample {
+    pub pixel: (f64, f64),
+    pub light: (f64, f64),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sampler {
+    samples_per_pixel: usize,
+    seed: u64,
+}
+
+impl Sampler {
+    pub fn new(samples_per_pixel: usize) -> Self {
+        assert!(samples_per_pixel > 0);
+        Self {
+            samples_per_pixel,
+            seed: 0,
+        }
+    }
+
+    pub fn samples_per_pixel(&self) -> usize {
+        self.samples_per_pixel
+    }
+
+    pub fn seed(&mut self, seed: u64) {
+        self.seed = seed;
+    }
+
+    // the same pixel always gets the same samples, so renders are repeatable
+    // and don't depend on how pixels are spread over threads
+    pub fn samples(&self, px: usize, py: usize) -> Vec<Sample> {
+        let n = self.samples_per_pixel;
+        let mut rng = Rng::new(self.seed ^ ((px as u64) << 32 | py as u64));
+        let mut dimension = || {
+            let xs = stratified(n, &mut rng);
+            let ys = stratified(n, &mut rng);
+            xs.into_iter().zip(ys).collect::<Vec<_>>()
+        };
+        let pixel = dimension();
+        let light = dimension();
+
+        (0..n)
+            .map(|i| Sample {
+                pixel: pixel[i],
+                light: light[i],
+            })
+            .collect()
+    }
+}
+
+// one jittered value in each of the n strata of [0, 1), in random order
+pub(crate) fn stratified(n: usize, rng: &mut Rng) -> Vec<f64> {
+    let mut values: Vec<f64> = (0..n)
+        .map(|i| (i as f64 + rng.next_f64()) / n as f64)
+        .collect();
+    for i in (1..n).rev() {
+        let j = (rng.next_u64() % (i as u64 + 1)) as usize;
+        values.swap(i, j);
+    }
+    values
+}
+
+// a uniform value in [0, 1) that depends only on the given numbers
+pub(crate) fn hash_to_unit(values: &[f64]) -> f64 {
+    let mut rng = Rng::new(0);
+    for value in values {
+        rng = Rng::new(rng.next_u64() ^ value.to_bits());
+    }
+    rng.next_f64()
+}
+
+// splitmix64, good enough for sample placement and needs no dependency
+pub(crate) struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub(crate) fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    pub(crate) fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strata(values: impl Iterator<Item = f64>, n: usize) -> Vec<usize> {
+        let mut strata: Vec<usize> = values.map(|v| (v * n as f64) as usize).collect();
+        strata.sort_unstable();
+        strata
+    }
+
+    #[test]
+    fn every_dimension_is_stratified() {
+        let n = 16;
+        let samples = Sampler::new(n).samples(3, 7);
+        assert_eq!(samples.len(), n);
+
+        let all: Vec<usize> = (0..n).collect();
+        assert_eq!(strata(samples.iter().map(|s| s.pixel.0), n), all);
+        assert_eq!(strata(samples.iter().map(|s| s.pixel.1), n), all);
+        assert_eq!(strata(samples.iter().map(|s| s.light.0), n), all);
+        assert_eq!(strata(samples.iter().map(|s| s.light.1), n), all);
+    }
+
+    #[test]
+    fn samples_are_repeatable_per_pixel() {
+        let sampler = Sampler::new(4);
+        assert_eq!(sampler.samples(1, 2), sampler.samples(1, 2));
+        assert_ne!(sampler.samples(1, 2), sampler.samples(2, 1));
+
+        let mut reseeded = Sampler::new(4);
+        reseeded.seed(42);
+        assert_ne!(sampler.samples(1, 2), reseeded.samples(1, 2));
+    }
+
+    #[test]
+    fn single_sample_stays_in_pixel() {
+        let samples = Sampler::new(1).samples(0, 0);
+        let (x, y) = samples[0].pixel;
+        assert!((0.0..1.0).contains(&x) && (0.0..1.0).contains(&y));
+    }
+}
@@ -0,0 +1,224 @@
+use std::f64::consts::{FRAC_PI_2, PI};
+
+use crate::{
+    color::Color,
+    light::PointLight,
+    point::Point,
+    vector::{dot, Vector},
+    EPSILON,
+};
+
+// how far away the sun light is placed, far enough to be treated as directional
+const SUN_DISTANCE: f64 = 1.0e6;
+
+// zenith luminance comes out in kcd/m², this brings a clear noon sky to roughly [0, 1]
+const LUMINANCE_SCALE: f64 = 0.05;
+
+// Preetham et al. "A Practical Analytic Model for Daylight".
+// angles are in radians, elevation is measured up from the horizon and azimuth
+// turns from +z towards +x.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sky {
+    sun_elevation: f64,
+    sun_azimuth: f64,
+    turbidity: f64,
+    intensity: f64,
+    perez_y: [f64; 5],
+    perez_x: [f64; 5],
+    perez_yc: [f64; 5],
+    zenith: (f64, f64, f64),
+}
+
+impl Sky {
+    pub fn new(sun_elevation: f64, sun_azimuth: f64, turbidity: f64) -> Self {
+        let t = turbidity;
+        let perez_y = [
+            0.1787 * t - 1.4630,
+            -0.3554 * t + 0.4275,
+            -0.0227 * t + 5.3251,
+            0.1206 * t - 2.5771,
+            -0.0670 * t + 0.3703,
+        ];
+        let perez_x = [
+            -0.0193 * t - 0.2592,
+            -0.0665 * t + 0.0008,
+            -0.0004 * t + 0.2125,
+            -0.0641 * t - 0.8989,
+            -0.0033 * t + 0.0452,
+        ];
+        let perez_yc = [
+            -0.0167 * t - 0.2608,
+            -0.0950 * t + 0.0092,
+            -0.0079 * t + 0.2102,
+            -0.0441 * t - 1.6537,
+            -0.0109 * t + 0.0529,
+        ];
+
+        let theta_s = FRAC_PI_2 - sun_elevation.clamp(0.0, FRAC_PI_2);
+        let (th, th2, th3) = (theta_s, theta_s * theta_s, theta_s * theta_s * theta_s);
+        let chi = (4.0 / 9.0 - t / 120.0) * (PI - 2.0 * theta_s);
+        let zenith_y = (4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192;
+        let zenith_x = t * t * (0.00166 * th3 - 0.00375 * th2 + 0.00209 * th)
+            + t * (-0.02903 * th3 + 0.06377 * th2 - 0.03202 * th + 0.00394)
+            + (0.11693 * th3 - 0.21196 * th2 + 0.06052 * th + 0.25886);
+        let zenith_yc = t * t * (0.00275 * th3 - 0.00610 * th2 + 0.00317 * th)
+            + t * (-0.04214 * th3 + 0.08970 * th2 - 0.04153 * th + 0.00516)
+            + (0.15346 * th3 - 0.26756 * th2 + 0.06670 * th + 0.26688);
+
+        Self {
+            sun_elevation,
+            sun_azimuth,
+            turbidity,
+            intensity: 1.0,
+            perez_y,
+            perez_x,
+            perez_yc,
+            zenith: (zenith_y.max(0.0), zenith_x, zenith_yc),
+        }
+    }
+
+    pub fn sun_elevation(&self) -> f64 {
+        self.sun_elevation
+    }
+
+    pub fn sun_azimuth(&self) -> f64 {
+        self.sun_azimuth
+    }
+
+    pub fn turbidity(&self) -> f64 {
+        self.turbidity
+    }
+
+    pub fn intensity(&mut self, intensity: f64) {
+        self.intensity = intensity;
+    }
+
+    pub fn sun_direction(&self) -> Vector {
+        let (e, a) = (self.sun_elevation, self.sun_azimuth);
+        Vector::new(e.cos() * a.sin(), e.sin(), e.cos() * a.cos())
+    }
+
+    pub fn color_at(&self, direction: Vector) -> Color {
+        let mut direction = direction.normalize();
+        // below the horizon, reuse the horizon color
+        if direction.y < 0.0 {
+            let horizontal = Vector::new(direction.x, 0.0, direction.z);
+            direction = if horizontal.magnitude() > EPSILON {
+                horizontal.normalize()
+            } else {
+                Vector::new(0, 0, 1)
+            };
+        }
+        let cos_theta = direction.y.max(0.01);
+        let theta = cos_theta.acos();
+        let gamma = dot(direction, self.sun_direction()).clamp(-1.0, 1.0).acos();
+        let theta_s = FRAC_PI_2 - self.sun_elevation.clamp(0.0, FRAC_PI_2);
+
+        let distribution = |coefficients: &[f64; 5], zenith: f64| {
+            zenith * perez(coefficients, theta, gamma) / perez(coefficients, 0.0, theta_s)
+        };
+
+        let luminance = distribution(&self.perez_y, self.zenith.0);
+        let x = distribution(&self.perez_x, self.zenith.1);
+        let y = distribution(&self.perez_yc, self.zenith.2);
+
+        xyy_to_rgb(x, y, luminance * LUMINANCE_SCALE * self.intensity)
+    }
+
+    pub fn sun_color(&self) -> Color {
+        let elevation = self.sun_elevation.max(0.0);
+        let zenith_degrees = 90.0 - elevation.to_degrees();
+        // Kasten & Young air mass approximation
+        let air_mass =
+            1.0 / (elevation.sin() + 0.50572 * (96.07995 - zenith_degrees).powf(-1.6364));
+        let extinction = |beta: f64| (-beta * self.turbidity * air_mass).exp();
+        Color::new(extinction(0.02), extinction(0.04), extinction(0.09)) * self.intensity
+    }
+
+    pub fn sun_light(&self) -> PointLight {
+        PointLight::new(
+            Point::origin() + self.sun_direction() * SUN_DISTANCE,
+            self.sun_color(),
+        )
+    }
+}
+
+fn perez(coefficients: &[f64; 5], theta: f64, gamma: f64) -> f64 {
+    let [a, b, c, d, e] = *coefficients;
+    (1.0 + a * (b / theta.cos()).exp()) * (1.0 + c * (d * gamma).exp() + e * gamma.cos().powi(2))
+}
+
+fn xyy_to_rgb(x: f64, y: f64, luminance: f64) -> Color {
+    if y <= 0.0 {
+        return Color::black();
+    }
+    let cx = x * luminance / y;
+    let cz = (1.0 - x - y) * luminance / y;
+    let cy = luminance;
+
+    Color::new(
+        (3.2406 * cx - 1.5372 * cy - 0.4986 * cz).max(0.0),
+        (-0.9689 * cx + 1.8758 * cy + 0.0415 * cz).max(0.0),
+        (0.0557 * cx - 0.2040 * cy + 1.0570 * cz).max(0.0),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::FRAC_PI_4;
+
+    use super::*;
+
+    fn luminance(c: Color) -> f64 {
+        0.2126 * c.red + 0.7152 * c.green + 0.0722 * c.blue
+    }
+
+    #[test]
+    fn sun_direction_follows_elevation_and_azimuth() {
+        let sky = Sky::new(FRAC_PI_2, 0.0, 2.0);
+        assert_eq!(sky.sun_direction(), Vector::new(0, 1, 0));
+
+        let sky = Sky::new(0.0, FRAC_PI_2, 2.0);
+        assert_eq!(sky.sun_direction(), Vector::new(1, 0, 0));
+    }
+
+    #[test]
+    fn clear_sky_is_blue_away_from_the_sun() {
+        let sky = Sky::new(FRAC_PI_4, 0.0, 2.0);
+        let c = sky.color_at(Vector::new(0.0, 1.0, -1.0));
+        assert!(c.blue > c.red);
+        assert!(c.red >= 0.0 && c.green >= 0.0);
+    }
+
+    #[test]
+    fn sky_is_brighter_towards_the_sun() {
+        let sky = Sky::new(FRAC_PI_4, 0.0, 3.0);
+        let towards = sky.color_at(Vector::new(0.0, 1.0, 1.1));
+        let away = sky.color_at(Vector::new(0.0, 1.0, -1.1));
+        assert!(luminance(towards) > luminance(away));
+    }
+
+    #[test]
+    fn sky_below_horizon_matches_horizon() {
+        let sky = Sky::new(FRAC_PI_4, 0.0, 2.0);
+        let below = sky.color_at(Vector::new(1.0, -0.5, 0.0));
+        let horizon = sky.color_at(Vector::new(1, 0, 0));
+        assert_eq!(below, horizon);
+    }
+
+    #[test]
+    fn setting_sun_is_redder_than_noon_sun() {
+        let noon = Sky::new(FRAC_PI_2, 0.0, 2.0).sun_color();
+        let sunset = Sky::new(0.05, 0.0, 2.0).sun_color();
+        assert!(noon.blue / noon.red > sunset.blue / sunset.red);
+        assert!(noon.red <= 1.0);
+    }
+
+    #[test]
+    fn sun_light_is_placed_along_sun_direction() {
+        let sky = Sky::new(FRAC_PI_2, 0.0, 2.0);
+        let light = sky.sun_light();
+        assert_eq!(light.position(), Point::new(0.0, SUN_DISTANCE, 0.0));
+        assert_eq!(light.intensity(), sky.sun_color());
+    }
+}
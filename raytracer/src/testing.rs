@@ -0,0 +1,103 @@
+use crate::{
+    color::Color,
+    geometry::{
+        shape::{Group, Sphere},
+        Shape,
+    },
+    material::Material,
+    sampler::Rng,
+    transform::{scaling, translation},
+};
+
+// n spheres scattered through a cube that grows with n so that their density,
+// and what a ray passes through, stays about the same. radii are between 0.2
+// and 0.5, and materials are random matte, shiny, mirror or glass ones. the
+// same seed always gives the same field
+pub fn generate_sphere_field(n: usize, seed: u64) -> Group {
+    let mut rng = Rng::new(seed);
+    let half_side = (n as f64).cbrt() * 1.5;
+    let mut field = Group::default();
+    for _ in 0..n {
+        let mut coord = || (rng.next_f64() * 2.0 - 1.0) * half_side;
+        let (x, y, z) = (coord(), coord(), coord());
+        let radius = 0.2 + rng.next_f64() * 0.3;
+        let mut sphere = Sphere::default();
+        sphere.set_transform(&translation(x, y, z) * &scaling(radius, radius, radius));
+        sphere.set_material(random_material(&mut rng));
+        field.add_child(Box::new(sphere));
+    }
+    field
+}
+
+fn random_material(rng: &mut Rng) -> Material {
+    let mut m = Material::default();
+    m.color = Color::new(rng.next_f64(), rng.next_f64(), rng.next_f64());
+    match rng.next_u64() % 4 {
+        0 => m.specular = 0.1,
+        1 => m.shininess = 20.0 + rng.next_f64() * 280.0,
+        2 => m.reflective = 0.5 + rng.next_f64() * 0.5,
+        _ => {
+            m.color = m.color * 0.1;
+            m.diffuse = 0.1;
+            m.transparency = 0.9;
+            m.reflective = 0.9;
+            m.refractive_index = 1.5;
+        }
+    }
+    m
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        geometry::shape::PartitionMode, point::Point, ray::Ray, sampler::Rng, vector::Vector,
+    };
+
+    use super::*;
+
+    #[test]
+    fn field_is_reproducible() {
+        let a = generate_sphere_field(50, 7);
+        assert_eq!(a.children.len(), 50);
+        assert!(a.equals(&generate_sphere_field(50, 7)));
+        assert!(!a.equals(&generate_sphere_field(50, 8)));
+        for child in &a.children {
+            assert!(child.material().problems().is_empty());
+        }
+    }
+
+    // shapes whose bounds a ray is tested against, walking down the tree
+    fn tests_along(shape: &dyn Shape, ray: &Ray) -> usize {
+        let mut tests = 1;
+        if shape.parent_space_bounds().intersects(ray) {
+            shape.for_each_child(&mut |child| tests += tests_along(child, ray));
+        }
+        tests
+    }
+
+    fn tests_per_ray(n: usize) -> f64 {
+        let mut field = generate_sphere_field(n, 1);
+        field.set_partition_mode(PartitionMode::Centroid);
+        field.divide(4);
+        let mut rng = Rng::new(2);
+        let rays = 200;
+        let total: usize = (0..rays)
+            .map(|_| {
+                let mut coord = || rng.next_f64() * 2.0 - 1.0;
+                let origin = Point::new(coord(), coord(), -1000.0);
+                let direction = Vector::new(coord() * 0.01, coord() * 0.01, 1.0).normalize();
+                tests_along(&field, &Ray::new(origin, direction))
+            })
+            .sum();
+        total as f64 / rays as f64
+    }
+
+    #[test]
+    fn divided_field_scales_sublinearly() {
+        // rays cross the whole field, so they pass near n^(1/3) spheres times
+        // the depth of the tree, 16 times the spheres shouldn't cost 16 times
+        let small = tests_per_ray(250);
+        let large = tests_per_ray(4000);
+        assert!(large / small < 6.0, "{} vs {}", small, large);
+    }
+}
@@ -0,0 +1,186 @@
+use std::cmp::Ordering;
+
+// the order tiles are handed out to render threads in
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum TileOrder {
+    // left to right, top to bottom
+    #[default]
+    Scanline,
+    // along a hilbert curve, so consecutive tiles stay close to each other
+    Hilbert,
+    // from the center of the image outwards, shows the interesting part first
+    Spiral,
+}
+
+// the pixels in x0..x1, y0..y1
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tile {
+    pub x0: usize,
+    pub y0: usize,
+    pub x1: usize,
+    pub y1: usize,
+}
+
+impl Tile {
+    pub fn pixels(&self) -> impl Iterator<Item = (usize, usize)> {
+        let (x0, x1) = (self.x0, self.x1);
+        (self.y0..self.y1).flat_map(move |y| (x0..x1).map(move |x| (x, y)))
+    }
+}
+
+// splits a width x height image into size x size tiles, smaller along the right
+// and bottom edges, in the given order
+pub fn tiles(width: usize, height: usize, size: usize, order: TileOrder) -> Vec<Tile> {
+    assert!(size > 0);
+    let (columns, rows) = (width.div_ceil(size), height.div_ceil(size));
+    let tile = |(column, row): (usize, usize)| Tile {
+        x0: column * size,
+        y0: row * size,
+        x1: ((column + 1) * size).min(width),
+        y1: ((row + 1) * size).min(height),
+    };
+
+    let mut cells: Vec<(usize, usize)> = (0..rows)
+        .flat_map(|row| (0..columns).map(move |column| (column, row)))
+        .collect();
+    match order {
+        TileOrder::Scanline => {}
+        TileOrder::Hilbert => {
+            let n = columns.max(rows).next_power_of_two();
+            cells = (0..n * n)
+                .map(|d| hilbert_cell(n, d))
+                .filter(|&(column, row)| column < columns && row < rows)
+                .collect();
+        }
+        TileOrder::Spiral => {
+            let center = ((columns - 1) as f64 / 2.0, (rows - 1) as f64 / 2.0);
+            // ring around the center first, then the angle within the ring
+            let key = |&(column, row): &(usize, usize)| {
+                let (dx, dy) = (column as f64 - center.0, row as f64 - center.1);
+                (dx.abs().max(dy.abs()), dy.atan2(dx))
+            };
+            cells.sort_by(|a, b| {
+                let (a, b) = (key(a), key(b));
+                a.partial_cmp(&b).unwrap_or(Ordering::Equal)
+            });
+        }
+    }
+    cells.into_iter().map(tile).collect()
+}
+
+// the d-th cell along a hilbert curve filling an n x n grid, n a power of two
+fn hilbert_cell(n: usize, d: usize) -> (usize, usize) {
+    let (mut x, mut y, mut t) = (0, 0, d);
+    let mut s = 1;
+    while s < n {
+        let rx = 1 & (t / 2);
+        let ry = 1 & (t ^ rx);
+        if ry == 0 {
+            if rx == 1 {
+                x = s - 1 - x;
+                y = s - 1 - y;
+            }
+            std::mem::swap(&mut x, &mut y);
+        }
+        x += s * rx;
+        y += s * ry;
+        t /= 4;
+        s *= 2;
+    }
+    (x, y)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn covered(tiles: &[Tile], width: usize, height: usize) -> bool {
+        let mut seen = vec![0; width * height];
+        for tile in tiles {
+            for (x, y) in tile.pixels() {
+                seen[y * width + x] += 1;
+            }
+        }
+        seen.iter().all(|&n| n == 1)
+    }
+
+    #[test]
+    fn every_order_covers_the_image_once() {
+        for &order in &[TileOrder::Scanline, TileOrder::Hilbert, TileOrder::Spiral] {
+            let ts = tiles(10, 7, 3, order);
+            assert_eq!(ts.len(), 12);
+            assert!(covered(&ts, 10, 7), "{:?}", order);
+        }
+    }
+
+    #[test]
+    fn scanline_tiles() {
+        let ts = tiles(5, 4, 3, TileOrder::Scanline);
+        assert_eq!(
+            ts,
+            vec![
+                Tile {
+                    x0: 0,
+                    y0: 0,
+                    x1: 3,
+                    y1: 3
+                },
+                Tile {
+                    x0: 3,
+                    y0: 0,
+                    x1: 5,
+                    y1: 3
+                },
+                Tile {
+                    x0: 0,
+                    y0: 3,
+                    x1: 3,
+                    y1: 4
+                },
+                Tile {
+                    x0: 3,
+                    y0: 3,
+                    x1: 5,
+                    y1: 4
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn hilbert_tiles_are_adjacent() {
+        let ts = tiles(8, 8, 2, TileOrder::Hilbert);
+        assert_eq!(
+            ts[0],
+            Tile {
+                x0: 0,
+                y0: 0,
+                x1: 2,
+                y1: 2
+            }
+        );
+        for pair in ts.windows(2) {
+            let dx = (pair[0].x0 as i64 - pair[1].x0 as i64).abs();
+            let dy = (pair[0].y0 as i64 - pair[1].y0 as i64).abs();
+            assert_eq!(dx + dy, 2);
+        }
+    }
+
+    #[test]
+    fn spiral_starts_at_the_center() {
+        let ts = tiles(15, 15, 3, TileOrder::Spiral);
+        assert_eq!(
+            ts[0],
+            Tile {
+                x0: 6,
+                y0: 6,
+                x1: 9,
+                y1: 9
+            }
+        );
+        // then the ring of eight tiles around it
+        assert!(ts[1..9]
+            .iter()
+            .all(|t| (3..=9).contains(&t.x0) && (3..=9).contains(&t.y0)));
+    }
+}
@@ -1,7 +1,11 @@
+use std::fmt;
+
 use crate::{
+    bounding_box::BoundingBox,
     matrix::Matrix,
     point::Point,
     vector::{cross, Vector},
+    EPSILON,
 };
 
 pub fn translation<T: Into<f64> + Copy>(x: T, y: T, z: T) -> Matrix {
@@ -70,6 +74,42 @@ pub fn shearing<T: Into<f64> + Copy>(xy: T, xz: T, yx: T, yz: T, zx: T, zy: T) -
     s
 }
 
+// why from, to and up don't make a view transform
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ViewError {
+    // from and to are the same point, so there is no direction to look in
+    NoViewDirection,
+    // up is zero or runs along the view direction, so the view can roll any way
+    UpAlongViewDirection,
+}
+
+impl fmt::Display for ViewError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoViewDirection => write!(f, "the view looks from the point it looks at"),
+            Self::UpAlongViewDirection => {
+                write!(f, "the up vector is zero or parallel to the view direction")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ViewError {}
+
+// view_transform for from, to and up read from input, which may not pin down
+// which way the view looks or which way is up
+pub fn try_view_transform(from: Point, to: Point, up: Vector) -> Result<Matrix, ViewError> {
+    let forward = to - from;
+    if forward.magnitude() < EPSILON {
+        return Err(ViewError::NoViewDirection);
+    }
+    if up.magnitude() < EPSILON || cross(forward.normalize(), up.normalize()).magnitude() < EPSILON
+    {
+        return Err(ViewError::UpAlongViewDirection);
+    }
+    Ok(view_transform(from, to, up))
+}
+
 pub fn view_transform(from: Point, to: Point, up: Vector) -> Matrix {
     let forward = (to - from).normalize();
     let upn = up.normalize();
@@ -89,6 +129,105 @@ pub fn view_transform(from: Point, to: Point, up: Vector) -> Matrix {
     &orientation * &translation(-from.x, -from.y, -from.z)
 }
 
+// from, to and up for a camera looking along direction with the whole of bounds
+// in view. fov is the camera's field of view, which covers the longer side of
+// an image with the given width / height aspect. None for empty or infinite bounds
+pub fn view_transform_to_fit(
+    bounds: &BoundingBox,
+    fov: f64,
+    aspect: f64,
+    direction: Vector,
+) -> Option<(Point, Point, Vector)> {
+    if !bounds.is_finite() {
+        return None;
+    }
+    let to = bounds.center();
+    let radius = (bounds.get_max() - to).magnitude().max(EPSILON);
+
+    // the bounding sphere has to fit the narrower of the two view angles
+    let half_view = (fov / 2.0).tan();
+    let half_narrow = if aspect >= 1.0 {
+        half_view / aspect
+    } else {
+        half_view * aspect
+    };
+    let distance = radius / half_narrow.atan().sin();
+
+    let direction = direction.normalize();
+    let from = to - direction * distance;
+    let up = if cross(direction, Vector::new(0, 1, 0)).magnitude() < EPSILON {
+        Vector::new(0, 0, 1)
+    } else {
+        Vector::new(0, 1, 0)
+    };
+    Some((from, to, up))
+}
+
+// translation, euler rotation and scale making up a transform, composed as
+// translation * rotation_z * rotation_y * rotation_x * scaling.
+// shearing can't be represented and is lost when decomposing.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DecomposedTransform {
+    pub translation: Vector,
+    pub rotation: Vector,
+    pub scale: Vector,
+}
+
+impl Default for DecomposedTransform {
+    fn default() -> Self {
+        Self {
+            translation: Vector::new(0, 0, 0),
+            rotation: Vector::new(0, 0, 0),
+            scale: Vector::new(1, 1, 1),
+        }
+    }
+}
+
+impl DecomposedTransform {
+    pub fn to_matrix(&self) -> Matrix {
+        let t = self.translation;
+        let r = self.rotation;
+        let s = self.scale;
+        &(&(&(&translation(t.x, t.y, t.z) * &rotation_z(r.z)) * &rotation_y(r.y))
+            * &rotation_x(r.x))
+            * &scaling(s.x, s.y, s.z)
+    }
+}
+
+pub fn decompose(m: &Matrix) -> DecomposedTransform {
+    let translation = Vector::new(m[(0, 3)], m[(1, 3)], m[(2, 3)]);
+
+    let column = |j: usize| Vector::new(m[(0, j)], m[(1, j)], m[(2, j)]);
+    let mut scale = Vector::new(
+        column(0).magnitude(),
+        column(1).magnitude(),
+        column(2).magnitude(),
+    );
+    // a mirrored transform has a negative determinant, put the flip on x
+    if m.determinant() < 0.0 {
+        scale.x = -scale.x;
+    }
+
+    let r = |i: usize, j: usize| {
+        let s = [scale.x, scale.y, scale.z][j];
+        m[(i, j)] / s
+    };
+
+    let y = (-r(2, 0)).clamp(-1.0, 1.0).asin();
+    let (x, z) = if y.cos().abs() > EPSILON {
+        (r(2, 1).atan2(r(2, 2)), r(1, 0).atan2(r(0, 0)))
+    } else {
+        // gimbal lock: only x + z (or x - z) is known, keep z at 0
+        ((-r(1, 2)).atan2(r(1, 1)), 0.0)
+    };
+
+    DecomposedTransform {
+        translation,
+        rotation: Vector::new(x, y, z),
+        scale,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::f64::consts::PI;
@@ -97,6 +236,34 @@ mod tests {
 
     use super::*;
 
+    #[test]
+    fn view_transform_to_fit_bounds() {
+        let bounds = BoundingBox::new(Point::new(-1, -1, -1), Point::new(1, 1, 1));
+        let (from, to, up) =
+            view_transform_to_fit(&bounds, PI / 2.0, 1.0, Vector::new(0, 0, 2)).unwrap();
+        // the sphere of radius sqrt(3) fits a 45 degree half angle at sqrt(6)
+        assert_eq!(to, Point::origin());
+        assert_eq!(from, Point::new(0.0, 0.0, -6f64.sqrt()));
+        assert_eq!(up, Vector::new(0, 1, 0));
+
+        // the vertical angle is the narrow one for wide images
+        let (from, _, _) =
+            view_transform_to_fit(&bounds, PI / 2.0, 2.0, Vector::new(0, 0, 1)).unwrap();
+        let expected = 3f64.sqrt() / 0.5f64.atan().sin();
+        assert!((from.z + expected).abs() < EPSILON);
+
+        // looking straight down
+        let (from, _, up) =
+            view_transform_to_fit(&bounds, PI / 2.0, 1.0, Vector::new(0, -1, 0)).unwrap();
+        assert!(from.y > 0.0);
+        assert_eq!(up, Vector::new(0, 0, 1));
+
+        assert!(
+            view_transform_to_fit(&BoundingBox::default(), 1.0, 1.0, Vector::new(0, 0, 1))
+                .is_none()
+        );
+    }
+
     #[test]
     fn translate_point() {
         let transform = translation(5, -3, 2);
@@ -267,7 +434,7 @@ mod tests {
     }
 
     #[test]
-    #[allow(clippy::clippy::many_single_char_names)]
+    #[allow(clippy::many_single_char_names)]
     fn chained_transformations_reverse_order() {
         let p = Point::new(1, 0, 1);
         let a = rotation_x(PI / 2.0);
@@ -322,5 +489,64 @@ mod tests {
             ],
         );
         assert_eq!(t, expected);
+        assert_eq!(try_view_transform(from, to, up), Ok(expected));
+    }
+
+    #[test]
+    fn degenerate_view_transformations() {
+        let from = Point::new(1, 3, 2);
+        assert_eq!(
+            try_view_transform(from, from, Vector::new(0, 1, 0)),
+            Err(ViewError::NoViewDirection)
+        );
+        for &up in &[
+            Vector::new(0, 0, 0),
+            Vector::new(0, 2, 0),
+            Vector::new(0, -1, 0),
+        ] {
+            assert_eq!(
+                try_view_transform(from, Point::new(1, 5, 2), up),
+                Err(ViewError::UpAlongViewDirection)
+            );
+        }
+    }
+
+    #[test]
+    fn decompose_identity() {
+        let d = decompose(&Matrix::identity(4, 4));
+        assert_eq!(d, DecomposedTransform::default());
+    }
+
+    #[test]
+    fn decompose_translation_rotation_scaling() {
+        let m = &(&translation(1, -2, 3) * &rotation_y(PI / 3.0)) * &scaling(2, 3, 4);
+        let d = decompose(&m);
+        assert_eq!(d.translation, Vector::new(1, -2, 3));
+        assert_eq!(d.rotation, Vector::new(0.0, PI / 3.0, 0.0));
+        assert_eq!(d.scale, Vector::new(2, 3, 4));
+    }
+
+    #[test]
+    fn decompose_and_recompose_transform() {
+        let m = &(&(&(&translation(-4.0, 0.5, 2.0) * &rotation_z(0.3)) * &rotation_y(-1.1))
+            * &rotation_x(2.2))
+            * &scaling(0.5, 1.5, 3.0);
+        let d = decompose(&m);
+        assert_eq!(d.to_matrix(), m);
+    }
+
+    #[test]
+    fn decompose_mirrored_transform() {
+        let m = &rotation_x(0.5) * &scaling(-1, 2, 2);
+        let d = decompose(&m);
+        assert!(d.scale.x < 0.0);
+        assert_eq!(d.to_matrix(), m);
+    }
+
+    #[test]
+    fn decompose_gimbal_locked_transform() {
+        let m = &rotation_y(PI / 2.0) * &rotation_x(0.7);
+        let d = decompose(&m);
+        assert_eq!(d.to_matrix(), m);
     }
 }
@@ -1,6 +1,9 @@
-use std::ops::{Add, Div, Mul, Neg, Sub};
+use std::{
+    fmt,
+    ops::{Add, Div, Mul, Neg, Sub},
+};
 
-use crate::point::Point;
+use crate::{approx::relative_eq, point::Point, DISPLAY_PRECISION, EPSILON};
 
 #[derive(Debug, Clone, Copy)]
 pub struct Vector {
@@ -27,16 +30,70 @@ impl Vector {
         Self::new(self.x / magnitude, self.y / magnitude, self.z / magnitude)
     }
 
+    pub fn approx_eq(&self, other: &Self, epsilon: f64) -> bool {
+        relative_eq(self.x, other.x, epsilon)
+            && relative_eq(self.y, other.y, epsilon)
+            && relative_eq(self.z, other.z, epsilon)
+    }
+
     pub fn reflect(&self, normal: Vector) -> Vector {
         *self - normal * 2.0 * dot(*self, normal)
     }
+
+    // bends the incoming direction through a surface, eta is n1 / n2 and the
+    // normal points back against the incoming direction. None on total
+    // internal reflection.
+    pub fn refract(&self, normal: Vector, eta: f64) -> Option<Vector> {
+        let cos_i = -dot(*self, normal);
+        let sin2_t = eta * eta * (1.0 - cos_i * cos_i);
+        if sin2_t > 1.0 {
+            return None;
+        }
+        let cos_t = (1.0 - sin2_t).sqrt();
+        Some(*self * eta + normal * (eta * cos_i - cos_t))
+    }
+
+    // two unit vectors that form a right handed orthonormal basis with this
+    // one (normalized). Duff et al. "Building an Orthonormal Basis, Revisited"
+    pub fn any_orthonormal_basis(&self) -> (Vector, Vector) {
+        let n = self.normalize();
+        let sign = 1.0f64.copysign(n.z);
+        let a = -1.0 / (sign + n.z);
+        let b = n.x * n.y * a;
+        let tangent = Vector::new(1.0 + sign * n.x * n.x * a, sign * b, -sign * n.x);
+        let bitangent = Vector::new(b, sign + n.y * n.y * a, -n.y);
+        (tangent, bitangent)
+    }
+
+    pub fn lerp(&self, other: Vector, t: f64) -> Vector {
+        *self + (other - *self) * t
+    }
+
+    // scales the vector down if it is longer than max_length
+    pub fn clamp_length(&self, max_length: f64) -> Vector {
+        let magnitude = self.magnitude();
+        if magnitude > max_length && magnitude > 0.0 {
+            *self * (max_length / magnitude)
+        } else {
+            *self
+        }
+    }
 }
 
 impl PartialEq for Vector {
     fn eq(&self, other: &Self) -> bool {
-        crate::equal(self.x, other.x)
-            && crate::equal(self.y, other.y)
-            && crate::equal(self.z, other.z)
+        self.approx_eq(other, EPSILON)
+    }
+}
+
+impl fmt::Display for Vector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let p = f.precision().unwrap_or(DISPLAY_PRECISION);
+        write!(
+            f,
+            "vector({:.*}, {:.*}, {:.*})",
+            p, self.x, p, self.y, p, self.z
+        )
     }
 }
 
@@ -248,4 +305,69 @@ mod tests {
         let r = v.reflect(n);
         assert_eq!(r, Vector::new(1, 0, 0));
     }
+
+    #[test]
+    fn display_vector() {
+        let v = Vector::new(1.0, -0.5, 1.0 / 3.0);
+        assert_eq!(v.to_string(), "vector(1.0000, -0.5000, 0.3333)");
+        assert_eq!(format!("{:.1}", v), "vector(1.0, -0.5, 0.3)");
+    }
+
+    #[test]
+    fn approx_eq_vector() {
+        let a = Vector::new(1.0, 2.0, 3.0);
+        let b = Vector::new(1.01, 2.0, 3.0);
+        assert!(a.approx_eq(&b, 0.1));
+        assert!(!a.approx_eq(&b, 0.001));
+    }
+
+    #[test]
+    fn refract_vector() {
+        let v = Vector::new(0, 0, 1);
+        let n = Vector::new(0, 0, -1);
+        assert_eq!(v.refract(n, 1.5).unwrap(), v);
+
+        let v = Vector::new(1, 0, 1).normalize();
+        let r = v.refract(n, 1.0 / 1.5).unwrap();
+        let sin_t = r.x / r.magnitude();
+        assert!(crate::equal(sin_t, (2.0f64.sqrt() / 2.0) / 1.5));
+        assert!(crate::equal(r.magnitude(), 1.0));
+
+        assert!(v.refract(n, 1.5).is_none());
+    }
+
+    #[test]
+    fn orthonormal_basis() {
+        for v in &[
+            Vector::new(0, 0, 1),
+            Vector::new(0, 0, -1),
+            Vector::new(1, 2, 3),
+            Vector::new(-0.3, 0.1, -2.0),
+        ] {
+            let n = v.normalize();
+            let (t, b) = v.any_orthonormal_basis();
+            assert!(crate::equal(t.magnitude(), 1.0));
+            assert!(crate::equal(b.magnitude(), 1.0));
+            assert!(crate::equal(dot(t, n), 0.0));
+            assert!(crate::equal(dot(b, n), 0.0));
+            assert!(crate::equal(dot(t, b), 0.0));
+            assert_eq!(cross(t, b), n);
+        }
+    }
+
+    #[test]
+    fn lerp_vectors() {
+        let a = Vector::new(0, 0, 0);
+        let b = Vector::new(2, 4, -6);
+        assert_eq!(a.lerp(b, 0.5), Vector::new(1, 2, -3));
+        assert_eq!(a.lerp(b, 1.0), b);
+    }
+
+    #[test]
+    fn clamp_vector_length() {
+        let v = Vector::new(3, 4, 0);
+        assert_eq!(v.clamp_length(10.0), v);
+        assert_eq!(v.clamp_length(2.5), Vector::new(1.5, 2.0, 0.0));
+        assert_eq!(Vector::new(0, 0, 0).clamp_length(1.0), Vector::new(0, 0, 0));
+    }
 }
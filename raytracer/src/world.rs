@@ -1,23 +1,57 @@
 use crate::{
+    bounding_box::BoundingBox,
     color::Color,
     equal,
+    fog::Fog,
     geometry::{
-        intersection::{hit, intersections, shadow_hit, Computations, Intersection},
+        bundle::RayBundle,
+        intersection::{blocks_light, hit_in_range, intersections, Computations, Intersection},
         shape::Sphere,
-        Shape,
+        visitor::{walk, Visitor},
+        RayKind, Shape, ShapeId,
     },
-    light::PointLight,
+    light::{Light, LightNormalization, PointLight},
+    material::{LightingTerms, Material},
+    matrix::Matrix,
     point::Point,
     ray::Ray,
+    ray_cache::RayCache,
+    ray_log::{LightContribution, RayLog, ShadingTerms},
+    sampler::{hash_to_unit, stratified, Rng},
+    sky::Sky,
     transform::scaling,
-    vector::dot,
+    vector::{dot, Vector},
 };
 
 pub const MAX_RECURSION_DEPTH: usize = 5;
+// clay mode's occlusion rays per hit, and how far they look for something in the way
+const CLAY_OCCLUSION_SAMPLES: usize = 16;
+const CLAY_OCCLUSION_DISTANCE: f64 = 1.0;
+// rough refraction averages this many rays, jittered over a cone that is this
+// wide around the refracted direction at roughness 1
+const ROUGH_REFRACTION_SAMPLES: usize = 8;
+// shadow rays per light with a shadow softness
+const SOFT_SHADOW_SAMPLES: usize = 16;
+const ROUGH_REFRACTION_MAX_ANGLE: f64 = std::f64::consts::FRAC_PI_4;
+// what the nan guard shades NaN colors with
+pub const NAN_COLOR: Color = Color {
+    red: 1.0,
+    green: 0.0,
+    blue: 1.0,
+};
 
 pub struct World {
     objects: Vec<Box<dyn Shape>>,
-    lights: Vec<PointLight>,
+    lights: Vec<Light>,
+    sky: Option<Sky>,
+    fog: Option<Fog>,
+    light_normalization: LightNormalization,
+    // how much diffuse light reaches shadowed points, for lights that don't set their own
+    shadow_fill: f64,
+    // shade NaN colors magenta instead of letting them spread
+    nan_guard: bool,
+    // materials shading uses instead of the shapes' own, by tag
+    material_overrides: Vec<(String, Material)>,
 }
 
 impl World {
@@ -25,63 +59,385 @@ impl World {
         Self {
             objects: vec![],
             lights: vec![],
+            sky: None,
+            fog: None,
+            light_normalization: LightNormalization::default(),
+            shadow_fill: 0.0,
+            nan_guard: false,
+            material_overrides: vec![],
         }
     }
 
-    pub fn intersect<'a, 'b>(&'a self, ray: &'b Ray) -> Vec<Intersection> {
+    // hits on the parts of surfaces that material cutouts remove are dropped
+    pub fn intersect(&self, ray: &Ray) -> Vec<Intersection<'_>> {
         let xs: Vec<Intersection> = self
             .objects
             .iter()
             .flat_map(|obj| obj.intersect(ray))
+            .filter(|i| !i.is_cut_out(ray))
             .collect();
         intersections(&xs)
     }
 
+    // like intersect, but drops shapes that are hidden from this kind of ray
+    pub fn intersect_for(&self, ray: &Ray, kind: RayKind) -> Vec<Intersection<'_>> {
+        let mut xs = self.intersect(ray);
+        xs.retain(|i| i.is_visible_to(kind));
+        xs
+    }
+
     pub fn shade_hit(&self, comps: &Computations, remaining: usize) -> Color {
+        self.shade(comps, remaining, &mut TraceOptions::default(), None)
+    }
+
+    // entry is where the ray being shaded sits in options.log, if logging
+    fn shade(
+        &self,
+        comps: &Computations,
+        remaining: usize,
+        options: &mut TraceOptions,
+        entry: Option<usize>,
+    ) -> Color {
+        if options.render_mode == RenderMode::Clay {
+            return self.shade_clay(comps, options);
+        }
+        let mut contributions = Vec::new();
+        let material = self.material_of(comps.material_shape);
+        // looked up once, filtered patterns aren't cheap
+        let color = material.surface_color(
+            comps.object,
+            &comps.surface_point,
+            comps.footprint.as_ref(),
+            comps.uv,
+        );
+        let occlusion = self.occlusion_mask_with(
+            comps.over_point,
+            &self.lights,
+            options.light_sample,
+            &mut options.shadow_rays,
+        );
         let surface: Color = self
             .lights
             .iter()
-            .map(|light| {
-                let shadowed = self.is_shadowed(comps.over_point, light);
-
-                comps.object.material().lighting(
-                    comps.object,
-                    light,
-                    &comps.over_point,
-                    &comps.eyev,
-                    &comps.normalv,
-                    shadowed,
-                )
+            .enumerate()
+            .map(|(idx, light)| {
+                let visibility = 1.0 - occlusion[idx];
+                let shadowed = visibility < 1.0;
+                let scale = self
+                    .light_normalization
+                    .scale(&self.lights, light, comps.over_point);
+
+                let terms = |in_shadow: bool| {
+                    material.lighting_terms_with(
+                        color,
+                        light,
+                        &comps.over_point,
+                        &comps.eyev,
+                        &comps.normalv,
+                        in_shadow,
+                    )
+                };
+                let fill = light.shadow_fill().unwrap_or(self.shadow_fill);
+                let lit = if !shadowed {
+                    terms(false)
+                } else {
+                    let unshadowed = terms(false);
+                    let dark = match light.shadow_color() {
+                        Some(tint) => unshadowed.tinted(tint),
+                        None if fill > 0.0 => unshadowed.in_shadow(fill),
+                        None => terms(true),
+                    };
+                    unshadowed.blend(&dark, visibility)
+                };
+                let translucent = match comps.thickness {
+                    Some(thickness) => material.translucent_lighting_with(
+                        color,
+                        light,
+                        &comps.over_point,
+                        &comps.eyev,
+                        &comps.normalv,
+                        thickness,
+                    ),
+                    None => Color::black(),
+                };
+                if entry.is_some() {
+                    contributions.push(LightContribution {
+                        light: idx,
+                        shadowed,
+                        lighting: LightingTerms {
+                            ambient: lit.ambient * scale,
+                            diffuse: lit.diffuse * scale,
+                            specular: lit.specular * scale,
+                        },
+                        translucent: translucent * scale,
+                    });
+                }
+                // guarded before the sum, which asserts finite colors in debug builds
+                self.guard_nan((lit.total() + translucent) * scale)
             })
             .sum();
 
-        let reflected = self.reflected_color(comps, remaining);
-        let refracted = self.refracted_color(comps, remaining);
+        let reflected = self.reflected(comps, remaining, options);
+        let refracted = self.refracted(comps, remaining, options);
 
-        let material = &comps.object.material();
-        if material.reflective > 0.0 && material.transparency > 0.0 {
+        let (reflected, refracted) = if material.reflective > 0.0 && material.transparency > 0.0 {
             let reflectance = comps.schlick();
-            surface + reflected * reflectance + refracted * (1.0 - reflectance)
+            (reflected * reflectance, refracted * (1.0 - reflectance))
+        } else if material.reflective > 0.0 && material.fresnel {
+            (reflected * comps.schlick(), refracted)
         } else {
-            surface + reflected + refracted
+            (reflected, refracted)
+        };
+        options.with_log(entry, |log, idx| {
+            log.shading(
+                idx,
+                ShadingTerms {
+                    lights: contributions,
+                    reflected,
+                    refracted,
+                },
+            )
+        });
+        self.guard_nan(surface + reflected + refracted)
+    }
+
+    fn guard_nan(&self, color: Color) -> Color {
+        if self.nan_guard && color.is_nan() {
+            NAN_COLOR
+        } else {
+            color
         }
     }
 
     pub fn color_at(&self, ray: &Ray, remaining: usize) -> Color {
-        let xs = self.intersect(ray);
-        let hit = hit(&xs);
+        self.color_at_clipped(ray, remaining, 0.0, f64::INFINITY)
+    }
 
-        match hit {
-            None => Color::black(),
+    // only hits with near <= t <= far are shaded, anything else sees the background
+    pub fn color_at_clipped(&self, ray: &Ray, remaining: usize, near: f64, far: f64) -> Color {
+        self.trace(
+            ray,
+            remaining,
+            near,
+            far,
+            RayKind::Camera,
+            &mut TraceOptions::default(),
+        )
+    }
+
+    // like color_at_clipped, with the extras in options turned on
+    pub fn color_at_with(
+        &self,
+        ray: &Ray,
+        near: f64,
+        far: f64,
+        options: &mut TraceOptions,
+    ) -> Color {
+        let remaining = options
+            .russian_roulette
+            .map_or(MAX_RECURSION_DEPTH, |roulette| roulette.max_depth);
+        self.trace(ray, remaining, near, far, RayKind::Camera, options)
+    }
+
+    fn trace(
+        &self,
+        ray: &Ray,
+        remaining: usize,
+        near: f64,
+        far: f64,
+        kind: RayKind,
+        options: &mut TraceOptions,
+    ) -> Color {
+        let entry = options
+            .log
+            .as_deref_mut()
+            .map(|log| log.enter(ray, kind, remaining));
+        if let Some(cache) = options.cache.as_deref_mut() {
+            if let Some(color) = cache.get(ray, remaining, kind) {
+                options.with_log(entry, |log, idx| log.leave(idx, color, true));
+                return color;
+            }
+        }
+
+        let xs = self.intersect_for(ray, kind);
+        let hit = hit_in_range(&xs, near, far);
+        options.with_log(entry, |log, idx| log.intersections(idx, &xs));
+
+        let (color, t) = match hit {
+            None => (self.background(ray), f64::INFINITY),
             Some(hit) => {
                 let comps = hit.prepare_computations(ray, &xs);
-                self.shade_hit(&comps, remaining)
+                options.with_log(entry, |log, idx| {
+                    log.hit(idx, &comps, self.material_of(comps.material_shape))
+                });
+                (self.shade(&comps, remaining, options, entry), hit.t())
+            }
+        };
+
+        let color = match &self.fog {
+            Some(fog) => fog.apply(color, t * ray.direction().magnitude()),
+            None => color,
+        };
+        if let Some(cache) = options.cache.as_deref_mut() {
+            cache.insert(ray, remaining, kind, color);
+        }
+        options.with_log(entry, |log, idx| log.leave(idx, color, false));
+        color
+    }
+
+    // what a ray that misses everything sees, fog included
+    fn environment(&self, ray: &Ray) -> Color {
+        let color = self.background(ray);
+        match &self.fog {
+            Some(fog) => fog.apply(color, f64::INFINITY),
+            None => color,
+        }
+    }
+
+    // shapes tagged with tag are shaded with material until the override is
+    // cleared, their own materials are left alone. when a shape has several
+    // overridden tags the latest override wins. refractive indices between
+    // overlapping shapes still come from their own materials
+    pub fn override_material(&mut self, tag: &str, material: Material) {
+        self.clear_material_override(tag);
+        self.material_overrides.push((tag.to_string(), material));
+    }
+
+    pub fn clear_material_override(&mut self, tag: &str) {
+        self.material_overrides.retain(|(t, _)| t != tag);
+    }
+
+    pub fn material_overrides(&self) -> &[(String, Material)] {
+        &self.material_overrides
+    }
+
+    // the material shape is shaded with
+    pub fn material_of<'a>(&'a self, shape: &'a dyn Shape) -> &'a Material {
+        self.material_overrides
+            .iter()
+            .rev()
+            .find(|(tag, _)| shape.has_tag(tag))
+            .map_or(shape.material(), |(_, material)| material)
+    }
+
+    fn background(&self, ray: &Ray) -> Color {
+        match &self.sky {
+            Some(sky) => sky.color_at(ray.direction()),
+            None => Color::black(),
+        }
+    }
+
+    pub fn sky(&self) -> Option<&Sky> {
+        self.sky.as_ref()
+    }
+
+    pub fn set_sky(&mut self, sky: Sky) {
+        self.sky = Some(sky);
+    }
+
+    pub fn set_light_normalization(&mut self, normalization: LightNormalization) {
+        self.light_normalization = normalization;
+    }
+
+    // a debugging aid: surfaces that shade to NaN, e.g. because of a bad
+    // material, show up magenta and reflect as magenta rather than as NaN
+    pub fn set_nan_guard(&mut self, guard: bool) {
+        self.nan_guard = guard;
+    }
+
+    pub fn set_shadow_fill(&mut self, fill: f64) {
+        self.shadow_fill = fill;
+    }
+
+    pub fn set_fog(&mut self, fog: Fog) {
+        self.fog = Some(fog);
+    }
+
+    // moves animated shapes wherever they are, then refits the groups above them
+    pub fn advance_time(&mut self, time: f64) {
+        for object in self.objects.iter_mut() {
+            animate_in(object.as_mut(), &Matrix::identity(4, 4), time);
+            object.refit();
+        }
+    }
+
+    // changes the shape with the given id wherever it is, e.g. inside a divided
+    // group, and returns false if there's no such shape. transforms of shapes in
+    // groups include the groups'. the bounds of the groups above the shape are
+    // refitted right away, shapes moved through objects_mut wait for refit
+    pub fn update_shape(&mut self, id: ShapeId, f: impl FnOnce(&mut dyn Shape)) -> bool {
+        let mut f = Some(f);
+        for object in self.objects.iter_mut() {
+            update_in(object.as_mut(), id, &mut f);
+            if f.is_none() {
+                object.refit();
+                return true;
             }
         }
+        false
+    }
+
+    // splits every group in the world into a hierarchy of subgroups with at
+    // most threshold children each
+    pub fn divide(&mut self, threshold: usize) {
+        for object in self.objects.iter_mut() {
+            object.divide(threshold);
+        }
+    }
+
+    // brings the bounds above moved shapes up to date for the next frame,
+    // cheaper than dividing the world again when only a few shapes move
+    pub fn refit(&mut self) {
+        for object in self.objects.iter_mut() {
+            object.refit();
+        }
+    }
+
+    // walks every object in the world, depth 0 being the top level objects
+    pub fn walk(&self, visitor: &mut dyn Visitor) {
+        for object in &self.objects {
+            walk(object.as_ref(), visitor);
+        }
+    }
+
+    pub fn objects(&self) -> &[Box<dyn Shape>] {
+        &self.objects
+    }
+
+    pub fn objects_mut(&mut self) -> &mut [Box<dyn Shape>] {
+        &mut self.objects
+    }
+
+    pub fn lights(&self) -> &[Light] {
+        &self.lights
+    }
+
+    pub fn lights_mut(&mut self) -> &mut [Light] {
+        &mut self.lights
+    }
+
+    // the box around every object, without planes and other unbounded shapes
+    // that would make it infinite, also inside groups. empty when there's
+    // nothing bounded
+    pub fn bounds(&self) -> BoundingBox {
+        let mut bounds = BoundingBox::default();
+        for object in &self.objects {
+            add_finite_bounds(object.as_ref(), &Matrix::identity(4, 4), &mut bounds);
+        }
+        bounds
     }
 
-    pub fn add_light(&mut self, light: PointLight) {
-        self.lights.push(light);
+    // number of top level objects, the children of groups aren't counted
+    pub fn len(&self) -> usize {
+        self.objects.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.objects.is_empty()
+    }
+
+    pub fn add_light(&mut self, light: impl Into<Light>) {
+        self.lights.push(light.into());
     }
 
     pub fn add_object<T: 'static + Shape>(&mut self, object: T) {
@@ -92,45 +448,426 @@ impl World {
         self.objects.push(object);
     }
 
-    pub fn is_shadowed(&self, point: Point, light: &PointLight) -> bool {
-        let v = light.position() - point;
-        let distance = v.magnitude();
-        let direction = v.normalize();
+    // the fraction of the lights that reach the first surface the ray sees, 1
+    // when it sees none or there are no lights. what the shadow pass shows
+    pub fn shadow_factor(&self, ray: &Ray, near: f64, far: f64) -> f64 {
+        let xs = self.intersect_for(ray, RayKind::Camera);
+        let hit = match hit_in_range(&xs, near, far) {
+            Some(hit) => hit,
+            None => return 1.0,
+        };
+        if self.lights.is_empty() {
+            return 1.0;
+        }
+        let comps = hit.prepare_computations(ray, &xs);
+        let lit = self
+            .lights
+            .iter()
+            .map(|light| self.light_visibility(comps.over_point, light))
+            .sum::<f64>();
+        lit / self.lights.len() as f64
+    }
 
-        let r = Ray::new(point, direction);
-        let intersections = self.intersect(&r);
-        let h = shadow_hit(&intersections);
+    // matte gray everywhere, with the ambient light darkened where the hit is
+    // occluded. reflection and refraction are left out
+    fn shade_clay(&self, comps: &Computations, options: &mut TraceOptions) -> Color {
+        let mut clay = Material::default();
+        clay.color = Color::new(0.8, 0.8, 0.8);
+        clay.ambient = 0.3;
+        clay.diffuse = 0.7;
+        clay.specular = 0.0;
+        let open = 1.0
+            - self.occlusion(
+                comps.over_point,
+                comps.normalv,
+                CLAY_OCCLUSION_SAMPLES,
+                CLAY_OCCLUSION_DISTANCE,
+            );
+        let occlusion = self.occlusion_mask_with(
+            comps.over_point,
+            &self.lights,
+            options.light_sample,
+            &mut options.shadow_rays,
+        );
+        self.lights
+            .iter()
+            .zip(occlusion)
+            .map(|(light, occlusion)| {
+                let visibility = 1.0 - occlusion;
+                let scale = self
+                    .light_normalization
+                    .scale(&self.lights, light, comps.over_point);
+                let lit = clay.lighting_terms_with(
+                    clay.color,
+                    light,
+                    &comps.over_point,
+                    &comps.eyev,
+                    &comps.normalv,
+                    false,
+                );
+                (lit.ambient * open + lit.diffuse * visibility) * scale
+            })
+            .sum()
+    }
+
+    // the fraction of cosine weighted rays leaving point around normal that
+    // hit a shadow casting shape within distance. the rays only depend on the
+    // point, so renders are repeatable
+    pub fn occlusion(&self, point: Point, normal: Vector, samples: usize, distance: f64) -> f64 {
+        assert!(samples > 0);
+        let seed = hash_to_unit(&[point.x, point.y, point.z]).to_bits();
+        let mut rng = Rng::new(seed);
+        let (us, vs) = (stratified(samples, &mut rng), stratified(samples, &mut rng));
+        let (tangent, bitangent) = normal.any_orthonormal_basis();
+        let blocked = us
+            .iter()
+            .zip(&vs)
+            .filter(|&(&u, &v)| {
+                let (r, phi) = (u.sqrt(), 2.0 * std::f64::consts::PI * v);
+                let direction = tangent * (r * phi.cos())
+                    + bitangent * (r * phi.sin())
+                    + normal * (1.0 - u).sqrt();
+                let ray = Ray::new(point, direction);
+                let xs = self.intersect_for(&ray, RayKind::Shadow);
+                blocks_light(&xs, &ray, distance)
+            })
+            .count();
+        blocked as f64 / samples as f64
+    }
+
+    // nothing of the light reaches point
+    pub fn is_shadowed(&self, point: Point, light: &Light) -> bool {
+        self.light_visibility(point, light) == 0.0
+    }
+
+    // the fraction of the light reaching point, 0 or 1 for hard shadows
+    pub fn light_visibility(&self, point: Point, light: &Light) -> f64 {
+        1.0 - self.occlusion_mask(point, std::slice::from_ref(light))[0]
+    }
+
+    // how much of each light is hidden from point, 0 where all of it gets
+    // through and 1 where none does. the shadow rays of all the lights go
+    // through the world as one bundle
+    pub fn occlusion_mask(&self, point: Point, lights: &[Light]) -> Vec<f64> {
+        self.occlusion_mask_with(point, lights, None, &mut ShadowRayStats::default())
+    }
+
+    fn occlusion_mask_with(
+        &self,
+        point: Point,
+        lights: &[Light],
+        light_sample: Option<(f64, f64)>,
+        stats: &mut ShadowRayStats,
+    ) -> Vec<f64> {
+        let targets: Vec<Vec<Point>> = lights
+            .iter()
+            .map(|light| shadow_targets(point, light, light_sample))
+            .collect();
+        let mut bundle = RayBundle::towards(point, &targets.concat());
+        // a lone ray gains nothing from the bookkeeping
+        if bundle.len() == 1 {
+            let xs = self.intersect(bundle.ray(0));
+            bundle.block_with(0, &xs);
+        } else {
+            for object in &self.objects {
+                let active = bundle.unblocked();
+                if active.is_empty() {
+                    break;
+                }
+                object.intersect_bundle(&mut bundle, &active);
+            }
+        }
 
-        h.is_some() && h.unwrap().t() < distance
+        let mut first = 0;
+        targets
+            .iter()
+            .map(|light_targets| {
+                let rays = first..first + light_targets.len();
+                first = rays.end;
+                let blocked = rays
+                    .filter(|&idx| {
+                        let blocked = bundle.is_blocked(idx);
+                        stats.record(blocked);
+                        blocked
+                    })
+                    .count();
+                blocked as f64 / light_targets.len() as f64
+            })
+            .collect()
     }
 
     pub fn reflected_color(&self, comps: &Computations, remaining: usize) -> Color {
-        if equal(comps.object.material().reflective, 0.0) || remaining == 0 {
+        self.reflected(comps, remaining, &mut TraceOptions::default())
+    }
+
+    fn reflected(
+        &self,
+        comps: &Computations,
+        remaining: usize,
+        options: &mut TraceOptions,
+    ) -> Color {
+        let material = self.material_of(comps.material_shape);
+        let reflective = material.reflective;
+        if equal(reflective, 0.0) || remaining == 0 {
             return Color::black();
         }
-        let reflect_ray = Ray::new(comps.over_point, comps.reflectv);
-        let color = self.color_at(&reflect_ray, remaining - 1);
-        color * comps.object.material().reflective
+        let reflect_ray = comps.reflected_ray();
+        if self.sky.is_some()
+            && (options.environment_reflections || material.environment_reflections)
+        {
+            return self.environment(&reflect_ray) * reflective;
+        }
+        let weight = match options.russian_roulette {
+            Some(roulette) => match roulette.survival_weight(remaining, reflective, &reflect_ray) {
+                Some(weight) => weight,
+                None => return Color::black(),
+            },
+            None => 1.0,
+        };
+        let color = self.trace(
+            &reflect_ray,
+            remaining - 1,
+            0.0,
+            f64::INFINITY,
+            RayKind::Secondary,
+            options,
+        );
+        color * (reflective * weight)
     }
 
     pub fn refracted_color(&self, comps: &Computations, remaining: usize) -> Color {
-        if equal(comps.object.material().transparency, 0.0) || remaining == 0 {
+        self.refracted(comps, remaining, &mut TraceOptions::default())
+    }
+
+    fn refracted(
+        &self,
+        comps: &Computations,
+        remaining: usize,
+        options: &mut TraceOptions,
+    ) -> Color {
+        let material = self.material_of(comps.material_shape);
+        let transparency = material.transparency;
+        if equal(transparency, 0.0) || remaining == 0 {
             return Color::black();
         }
 
         let n_ratio = comps.n1 / comps.n2;
-        let cos_i = dot(comps.eyev, comps.normalv);
-        let sin2_t = n_ratio * n_ratio * (1.0 - cos_i * cos_i);
+        let direction = match (-comps.eyev).refract(comps.normalv, n_ratio) {
+            Some(direction) => direction,
+            None => return Color::black(),
+        };
+        let refract_ray = Ray::new(comps.under_point, direction);
+        let weight = match options.russian_roulette {
+            Some(roulette) => match roulette.survival_weight(remaining, transparency, &refract_ray)
+            {
+                Some(weight) => weight,
+                None => return Color::black(),
+            },
+            None => 1.0,
+        };
+
+        let color = if material.roughness > 0.0 {
+            self.rough_refracted(comps, direction, material.roughness, remaining, options)
+        } else {
+            self.trace(
+                &refract_ray,
+                remaining - 1,
+                0.0,
+                f64::INFINITY,
+                RayKind::Secondary,
+                options,
+            )
+        };
+        color * (transparency * weight)
+    }
 
-        if sin2_t > 1.0 {
-            return Color::black();
+    // frosted glass: the average of rays jittered over a cone around the
+    // refracted direction. only the first rough refraction along a path takes
+    // several samples, the ones below it take one each instead of multiplying
+    fn rough_refracted(
+        &self,
+        comps: &Computations,
+        direction: Vector,
+        roughness: f64,
+        remaining: usize,
+        options: &mut TraceOptions,
+    ) -> Color {
+        let samples = if options.within_rough_refraction {
+            1
+        } else {
+            ROUGH_REFRACTION_SAMPLES
+        };
+        let point = comps.point;
+        let mut rng = Rng::new(hash_to_unit(&[point.x, point.y, point.z]).to_bits());
+        let (us, vs) = (stratified(samples, &mut rng), stratified(samples, &mut rng));
+        let cos_max = (roughness.min(1.0) * ROUGH_REFRACTION_MAX_ANGLE).cos();
+        let direction = direction.normalize();
+        let (tangent, bitangent) = direction.any_orthonormal_basis();
+
+        let nested = std::mem::replace(&mut options.within_rough_refraction, true);
+        let mut total = Color::black();
+        for (&u, &v) in us.iter().zip(&vs) {
+            let cos_theta = 1.0 - u * (1.0 - cos_max);
+            let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
+            let phi = 2.0 * std::f64::consts::PI * v;
+            let mut jittered = tangent * (sin_theta * phi.cos())
+                + bitangent * (sin_theta * phi.sin())
+                + direction * cos_theta;
+            // jittered back through the surface, it would leave on the side
+            // the ray came from
+            if dot(jittered, comps.normalv) >= 0.0 {
+                jittered = direction;
+            }
+            total = total
+                + self.trace(
+                    &Ray::new(comps.under_point, jittered),
+                    remaining - 1,
+                    0.0,
+                    f64::INFINITY,
+                    RayKind::Secondary,
+                    options,
+                );
         }
+        options.within_rough_refraction = nested;
+        total * (1.0 / samples as f64)
+    }
+}
 
-        let cos_t = (1.0 - sin2_t).sqrt();
-        let direction = comps.normalv * (n_ratio * cos_i - cos_t) - comps.eyev * n_ratio;
-        let refract_ray = Ray::new(comps.under_point, direction);
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ShadowRayStats {
+    pub cast: usize,
+    // the ones that found something between the point and the light
+    pub blocked: usize,
+}
+
+impl ShadowRayStats {
+    fn record(&mut self, blocked: bool) {
+        self.cast += 1;
+        if blocked {
+            self.blocked += 1;
+        }
+    }
 
-        self.color_at(&refract_ray, remaining - 1) * comps.object.material().transparency
+    pub fn add(&mut self, other: &ShadowRayStats) {
+        self.cast += other.cast;
+        self.blocked += other.blocked;
+    }
+}
+
+// the points a light's shadow rays aim at: its position for hard shadows, soft
+// lights jitter stratified points over the sphere of their softness around it,
+// picked from the point so renders are repeatable. with a light sample from
+// the camera's sampler they aim one ray at the point it picks instead, and
+// the pixel's samples stratify the light between them
+fn shadow_targets(point: Point, light: &Light, sample: Option<(f64, f64)>) -> Vec<Point> {
+    let (at, softness) = (light.position(), light.shadow_softness());
+    if softness <= 0.0 {
+        return vec![at];
+    }
+    let seed = hash_to_unit(&[point.x, point.y, point.z, at.x, at.y, at.z]).to_bits();
+    let mut rng = Rng::new(seed);
+    let (us, vs) = match sample {
+        Some((u, v)) => (vec![u], vec![v]),
+        None => {
+            let samples = SOFT_SHADOW_SAMPLES;
+            (stratified(samples, &mut rng), stratified(samples, &mut rng))
+        }
+    };
+    us.iter()
+        .zip(&vs)
+        .map(|(&u, &v)| {
+            let z = 1.0 - 2.0 * u;
+            let ring = (1.0 - z * z).max(0.0).sqrt();
+            let phi = 2.0 * std::f64::consts::PI * v;
+            let radius = softness * rng.next_f64().cbrt();
+            at + Vector::new(ring * phi.cos(), ring * phi.sin(), z) * radius
+        })
+        .collect()
+}
+
+// optional extras for tracing a camera ray, all off by default
+#[derive(Debug, Default)]
+pub struct TraceOptions<'a> {
+    pub russian_roulette: Option<&'a RussianRoulette>,
+    // remembers traced rays so exact repeats aren't traced again
+    pub cache: Option<&'a mut RayCache>,
+    // records the bounce tree of every traced ray
+    pub log: Option<&'a mut RayLog>,
+    // counts the shadow rays cast while tracing
+    pub shadow_rays: ShadowRayStats,
+    // treats every material as if it had environment_reflections set
+    pub environment_reflections: bool,
+    pub render_mode: RenderMode,
+    // set while tracing the rays a rough refraction averages
+    pub within_rough_refraction: bool,
+    // where this ray samples soft lights, from the camera's sampler
+    pub light_sample: Option<(f64, f64)>,
+}
+
+// how hits are shaded
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RenderMode {
+    #[default]
+    Full,
+    // ignores every material for a neutral diffuse gray with ambient
+    // occlusion, to judge modeling and lighting on their own
+    Clay,
+}
+
+impl TraceOptions<'_> {
+    fn with_log(&mut self, entry: Option<usize>, f: impl FnOnce(&mut RayLog, usize)) {
+        if let (Some(log), Some(idx)) = (self.log.as_deref_mut(), entry) {
+            f(log, idx);
+        }
+    }
+}
+
+// past min_depth bounces a secondary ray only continues with a probability
+// given by how much it contributes, and survivors are weighted up so the
+// result stays correct on average. max_depth is still a hard limit.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RussianRoulette {
+    pub min_depth: usize,
+    pub max_depth: usize,
+}
+
+impl Default for RussianRoulette {
+    fn default() -> Self {
+        Self {
+            min_depth: 3,
+            max_depth: 32,
+        }
+    }
+}
+
+// rays are never kept with less than this probability, which bounds the weights
+const MIN_SURVIVAL_PROBABILITY: f64 = 0.05;
+
+impl RussianRoulette {
+    // None when the ray is terminated, otherwise the weight to apply to it.
+    // the decision is a hash of the ray, so renders stay repeatable.
+    fn survival_weight(&self, remaining: usize, contribution: f64, ray: &Ray) -> Option<f64> {
+        let depth = self.max_depth.saturating_sub(remaining);
+        if depth < self.min_depth {
+            return Some(1.0);
+        }
+        let probability = contribution.clamp(MIN_SURVIVAL_PROBABILITY, 1.0);
+        let origin = ray.origin();
+        let direction = ray.direction();
+        let u = hash_to_unit(&[
+            origin.x,
+            origin.y,
+            origin.z,
+            direction.x,
+            direction.y,
+            direction.z,
+            remaining as f64,
+        ]);
+        if u < probability {
+            Some(1.0 / probability)
+        } else {
+            None
+        }
     }
 }
 
@@ -138,27 +875,103 @@ impl Default for World {
     fn default() -> Self {
         let light = PointLight::new(Point::new(-10, 10, -10), Color::new(1.0, 1.0, 1.0));
         let mut s1 = Sphere::default();
-        s1.get_base_mut().material.color = Color::new(0.8, 1.0, 0.6);
-        s1.get_base_mut().material.diffuse = 0.7;
-        s1.get_base_mut().material.specular = 0.2;
+        s1.material_mut().color = Color::new(0.8, 1.0, 0.6);
+        s1.material_mut().diffuse = 0.7;
+        s1.material_mut().specular = 0.2;
         let mut s2 = Sphere::default();
         s2.set_transform(scaling(0.5, 0.5, 0.5));
         Self {
             objects: vec![Box::new(s1), Box::new(s2)],
-            lights: vec![light],
+            lights: vec![light.into()],
+            sky: None,
+            fog: None,
+            light_normalization: LightNormalization::default(),
+            shadow_fill: 0.0,
+            nan_guard: false,
+            material_overrides: vec![],
+        }
+    }
+}
+
+// parent takes the space shape's bounds are in to the world's
+fn add_finite_bounds(shape: &dyn Shape, parent: &Matrix, bounds: &mut BoundingBox) {
+    let shape_bounds = shape.parent_space_bounds();
+    if shape_bounds.is_finite() {
+        bounds.add_bounding_box(&shape_bounds.transform(parent));
+        return;
+    }
+    let children_parent = if shape.children_in_object_space() {
+        parent * shape.transform()
+    } else {
+        parent.clone()
+    };
+    shape.for_each_child(&mut |child| add_finite_bounds(child, &children_parent, bounds));
+}
+
+// animations are relative to the shape's parent, parent is what the parent's
+// space is to the space the shape's transform ends in
+fn animate_in(shape: &mut dyn Shape, parent: &Matrix, time: f64) {
+    if let Some(transform) = shape.animation().and_then(|a| a.transform_at(time)) {
+        shape.set_transform(parent * &transform);
+    }
+    // groups bake their transform into their children, csgs don't
+    let children_parent = if shape.children_in_object_space() {
+        Matrix::identity(4, 4)
+    } else {
+        shape.transform().clone()
+    };
+    shape.for_each_child_mut(&mut |child| animate_in(child, &children_parent, time));
+}
+
+fn update_in<F: FnOnce(&mut dyn Shape)>(shape: &mut dyn Shape, id: ShapeId, f: &mut Option<F>) {
+    if shape.id() == id {
+        if let Some(f) = f.take() {
+            f(shape);
         }
+        return;
     }
+    shape.for_each_child_mut(&mut |child| {
+        if f.is_some() {
+            update_in(child, id, f);
+        }
+    });
 }
 
 #[cfg(test)]
+#[allow(clippy::bool_assert_comparison)]
 mod tests {
+    use std::f64::consts::PI;
+
     use crate::{
-        equal, geometry::shape::Plane, pattern::test_pattern, transform::translation,
-        vector::Vector,
+        animation::{AnimationChannel, Easing},
+        equal,
+        geometry::intersection::hit,
+        geometry::{
+            shape::{Cube, Group, Plane},
+            Visibility,
+        },
+        material::Cutout,
+        pattern::{stripe_pattern, test_pattern},
+        transform::{rotation_x, translation, DecomposedTransform},
     };
 
     use super::*;
 
+    #[test]
+    fn world_accessors() {
+        let mut w = World::default();
+        assert_eq!(w.len(), 2);
+        assert!(!w.is_empty());
+        assert!(World::new().is_empty());
+        assert_eq!(w.lights().len(), 1);
+        assert_eq!(w.objects()[1].transform(), &scaling(0.5, 0.5, 0.5));
+
+        w.objects_mut()[0].material_mut().ambient = 1.0;
+        assert!(equal(w.objects()[0].material().ambient, 1.0));
+        w.lights_mut()[0] = PointLight::new(Point::origin(), Color::white()).into();
+        assert_eq!(w.lights()[0].position(), Point::origin());
+    }
+
     #[test]
     fn create_world() {
         let w = World::new();
@@ -166,20 +979,53 @@ mod tests {
         assert_eq!(w.lights.len(), 0);
     }
 
+    #[test]
+    fn world_bounds_leave_out_unbounded_shapes() {
+        use crate::geometry::shape::Plane;
+
+        let mut w = World::default();
+        w.add_object(Plane::default());
+        let mut s = Sphere::default();
+        s.set_transform(translation(5, 0, 0));
+        w.add_object(s);
+
+        let bounds = w.bounds();
+        assert_eq!(bounds.get_min(), Point::new(-1, -1, -1));
+        assert_eq!(bounds.get_max(), Point::new(6, 1, 1));
+        assert!(World::new().bounds().is_empty());
+    }
+
+    #[test]
+    fn world_bounds_look_inside_unbounded_groups() {
+        use crate::geometry::shape::Plane;
+
+        let mut g = Group::default();
+        g.add(Plane::default());
+        let mut s = Sphere::default();
+        s.set_transform(translation(5, 0, 0));
+        g.add(s);
+        let mut w = World::new();
+        w.add_object(g);
+
+        let bounds = w.bounds();
+        assert_eq!(bounds.get_min(), Point::new(4, -1, -1));
+        assert_eq!(bounds.get_max(), Point::new(6, 1, 1));
+    }
+
     #[test]
     fn create_default_world() {
         let light = PointLight::new(Point::new(-10, 10, -10), Color::new(1.0, 1.0, 1.0));
         let mut s1 = Sphere::default();
-        s1.get_base_mut().material.color = Color::new(0.8, 1.0, 0.6);
-        s1.get_base_mut().material.diffuse = 0.7;
-        s1.get_base_mut().material.specular = 0.2;
+        s1.material_mut().color = Color::new(0.8, 1.0, 0.6);
+        s1.material_mut().diffuse = 0.7;
+        s1.material_mut().specular = 0.2;
         let mut s2 = Sphere::default();
         s2.set_transform(scaling(0.5, 0.5, 0.5));
 
         let w = World::default();
-        assert!(w.lights.contains(&light));
-        assert!(w.objects.contains(&(Box::new(s1) as Box<dyn Shape>)));
-        assert!(w.objects.contains(&(Box::new(s2) as Box<dyn Shape>)));
+        assert!(w.lights.contains(&light.into()));
+        assert!(w.objects.iter().any(|o| o.equals(&s1)));
+        assert!(w.objects.iter().any(|o| o.equals(&s2)));
     }
 
     #[test]
@@ -194,6 +1040,44 @@ mod tests {
         assert!(equal(xs[3].t(), 6.0));
     }
 
+    #[test]
+    fn rays_go_through_cutouts() {
+        let mut w = World::new();
+        w.add_light(PointLight::new(Point::new(0, 10, 0), Color::white()));
+        let mut floor = Plane::default();
+        floor.set_transform(translation(0, -1, 0));
+        w.add_object(floor);
+        // stripes along x, white from 0 to 1 and black from 1 to 2
+        let mut fence = Plane::default();
+        fence.material_mut().cutout = Some(Cutout::new(
+            stripe_pattern(Color::white(), Color::black()),
+            0.5,
+        ));
+        w.add_object(fence);
+
+        let down = |x: f64| Ray::new(Point::new(x, 1.0, 0.0), Vector::new(0, -1, 0));
+        assert!(equal(hit(&w.intersect(&down(0.5))).unwrap().t(), 1.0));
+        let xs = w.intersect(&down(1.5));
+        assert_eq!(xs.len(), 1);
+        assert!(equal(xs[0].t(), 2.0));
+
+        // shadow rays too, the light is straight above
+        assert!(w.is_shadowed(Point::new(0.5, -0.99, 0.0), &w.lights[0]));
+        assert!(!w.is_shadowed(Point::new(1.5, -0.99, 0.0), &w.lights[0]));
+    }
+
+    #[test]
+    fn degenerate_shape_does_not_break_intersecting_the_world() {
+        let mut w = World::default();
+        let mut broken = Sphere::default();
+        broken.set_transform(translation(f64::NAN, 0.0, 0.0));
+        w.add_object(broken);
+        let r = Ray::new(Point::new(0, 0, -5), Vector::new(0, 0, 1));
+        let xs = w.intersect(&r);
+        assert_eq!(xs.len(), 4);
+        assert!(equal(xs[0].t(), 4.0));
+    }
+
     #[test]
     fn shade_intersection() {
         let w = World::default();
@@ -205,10 +1089,215 @@ mod tests {
         assert_eq!(c, Color::new(0.38066, 0.47583, 0.2855));
     }
 
+    #[test]
+    fn material_overrides_apply_to_tagged_shapes() {
+        let mut w = World::default();
+        w.objects[0].add_tag("hero");
+        w.objects[1].add_tag("glass");
+        let mut clay = Material::default();
+        clay.color = Color::new(0.5, 0.5, 0.5);
+        w.override_material("hero", clay.clone());
+
+        assert_eq!(w.material_of(w.objects[0].as_ref()), &clay);
+        assert_eq!(
+            w.material_of(w.objects[1].as_ref()),
+            w.objects[1].material()
+        );
+        // the shape keeps its own material
+        assert_eq!(w.objects[0].material().color, Color::new(0.8, 1.0, 0.6));
+
+        let r = Ray::new(Point::new(0, 0, -5), Vector::new(0, 0, 1));
+        let overridden = w.color_at(&r, MAX_RECURSION_DEPTH);
+        assert_ne!(overridden, Color::new(0.38066, 0.47583, 0.2855));
+        assert!(equal(overridden.red, overridden.green));
+
+        // the latest override wins for shapes with both tags
+        w.objects[0].add_tag("glass");
+        let mut red = Material::default();
+        red.color = Color::new(1.0, 0.0, 0.0);
+        w.override_material("glass", red.clone());
+        assert_eq!(w.material_of(w.objects[0].as_ref()), &red);
+
+        w.clear_material_override("glass");
+        w.clear_material_override("hero");
+        assert!(w.material_overrides().is_empty());
+        assert_eq!(
+            w.color_at(&r, MAX_RECURSION_DEPTH),
+            Color::new(0.38066, 0.47583, 0.2855)
+        );
+    }
+
+    #[test]
+    fn soft_and_colored_shadows() {
+        let mut light = PointLight::new(Point::new(0, 10, 0), Color::white());
+        let mut blocker = Cube::default();
+        blocker.set_transform(&translation(0.9, 5.0, 0.0) * &scaling(1.0, 0.1, 10.0));
+        let mut w = World::new();
+        w.add_object(blocker);
+        w.add_light(PointLight::new(Point::new(0, 10, 0), Color::white()));
+        let origin = Point::origin();
+        assert_eq!(w.light_visibility(origin, &w.lights[0]), 0.0);
+
+        // the blocker covers part of the light's sphere
+        light.set_shadow_softness(2.0);
+        let light = Light::from(light);
+        let partly = w.light_visibility(origin, &light);
+        assert!(partly > 0.1 && partly < 0.9);
+        assert_eq!(w.light_visibility(origin, &light), partly);
+        assert!(!w.is_shadowed(origin, &light));
+
+        // a shadow color lets that much of the blocked light through
+        let color_with = |blocked: bool, tint: Option<Color>| {
+            let mut w = World::new();
+            w.add_object(Plane::default());
+            if blocked {
+                let mut blocker = Cube::default();
+                blocker.set_transform(&translation(0, 5, 0) * &scaling(10.0, 0.1, 10.0));
+                w.add_object(blocker);
+            }
+            let mut light = PointLight::new(Point::new(0, 10, 0), Color::white());
+            if let Some(tint) = tint {
+                light.set_shadow_color(tint);
+            }
+            w.add_light(light);
+            let r = Ray::new(Point::new(0, 1, -1), Vector::new(0, -1, 1).normalize());
+            w.color_at(&r, 0)
+        };
+        let lit = color_with(false, None);
+        let dark = color_with(true, None);
+        let tint = Color::new(0.5, 0.25, 0.0);
+        assert_ne!(lit, dark);
+        assert_eq!(color_with(true, Some(tint)), dark + (lit - dark) * tint);
+    }
+
+    #[test]
+    fn light_samples_aim_one_shadow_ray_at_soft_lights() {
+        let mut w = World::new();
+        w.add_object(Plane::default());
+        let mut light = PointLight::new(Point::new(0, 10, 0), Color::white());
+        light.set_shadow_softness(2.0);
+        w.add_light(light);
+        let r = Ray::new(Point::new(0, 1, -1), Vector::new(0, -1, 1).normalize());
+        let cast = |light_sample| {
+            let mut options = TraceOptions {
+                light_sample,
+                ..Default::default()
+            };
+            w.color_at_with(&r, 0.0, f64::INFINITY, &mut options);
+            options.shadow_rays.cast
+        };
+        assert_eq!(cast(None), SOFT_SHADOW_SAMPLES);
+        assert_eq!(cast(Some((0.25, 0.75))), 1);
+    }
+
+    #[test]
+    fn occlusion_mask_matches_single_shadow_rays() {
+        let mut w = World::new();
+        w.add_object(Plane::default());
+        let mut group = Group::default();
+        for i in 0..12 {
+            let mut s = Sphere::default();
+            let angle = i as f64 * PI / 6.0;
+            s.set_transform(
+                &translation(angle.cos() * 3.0, 2.0, angle.sin() * 3.0) * &scaling(0.5, 0.5, 0.5),
+            );
+            if i % 4 == 0 {
+                s.set_visibility(Visibility {
+                    shadow: false,
+                    ..Default::default()
+                });
+            }
+            group.add_child(Box::new(s));
+        }
+        group.divide(2);
+        w.add_object(group);
+
+        let lights: Vec<Light> = (0..24)
+            .map(|i| {
+                let angle = i as f64 * PI / 12.0;
+                PointLight::new(
+                    Point::new(angle.cos() * 6.0, 4.0, angle.sin() * 6.0),
+                    Color::white(),
+                )
+                .into()
+            })
+            .collect();
+        for point in [Point::new(0.0, 0.001, 0.0), Point::new(1.0, 0.001, -0.5)] {
+            let mask = w.occlusion_mask(point, &lights);
+            let expected: Vec<f64> = lights
+                .iter()
+                .map(|light| {
+                    let v = light.position() - point;
+                    let ray = Ray::new(point, v.normalize());
+                    let xs = w.intersect_for(&ray, RayKind::Shadow);
+                    if blocks_light(&xs, &ray, v.magnitude()) {
+                        1.0
+                    } else {
+                        0.0
+                    }
+                })
+                .collect();
+            assert_eq!(mask, expected);
+            assert!(mask.contains(&0.0) && mask.contains(&1.0));
+        }
+    }
+
+    #[test]
+    fn occlusion_counts_blocked_rays() {
+        let mut w = World::new();
+        w.add_object(Plane::default());
+        let up = Vector::new(0, 1, 0);
+        let point = Point::new(0.0, 0.0001, 0.0);
+        assert_eq!(w.occlusion(point, up, 16, 1.0), 0.0);
+
+        let mut lid = Cube::default();
+        lid.set_transform(&translation(0.0, 0.5, 0.0) * &scaling(1000.0, 0.1, 1000.0));
+        w.add_object(lid);
+        assert_eq!(w.occlusion(point, up, 16, f64::INFINITY), 1.0);
+        // the lid is further away than the rays look
+        assert_eq!(w.occlusion(point, up, 16, 0.3), 0.0);
+
+        let mut wall = Cube::default();
+        wall.set_transform(&translation(1.0, 0.0, 0.0) * &scaling(0.5, 10.0, 10.0));
+        let mut w = World::new();
+        w.add_object(wall);
+        let partly = w.occlusion(point, up, 64, 5.0);
+        assert!(partly > 0.2 && partly < 0.8);
+        // repeatable for the same point
+        assert_eq!(w.occlusion(point, up, 64, 5.0), partly);
+    }
+
+    #[test]
+    fn clay_mode_ignores_materials() {
+        let mut w = World::default();
+        w.objects[0].material_mut().reflective = 0.8;
+        w.objects[0].material_mut().set_pattern(test_pattern());
+        let r = Ray::new(Point::new(0, 0, -5), Vector::new(0, 0, 1));
+        let mut options = TraceOptions {
+            render_mode: RenderMode::Clay,
+            ..Default::default()
+        };
+        let clay = w.color_at_with(&r, 0.0, f64::INFINITY, &mut options);
+        assert!(equal(clay.red, clay.green) && equal(clay.green, clay.blue));
+        assert!(clay.red > 0.0);
+        assert_eq!(options.shadow_rays.cast, 1);
+
+        let mut other = World::default();
+        other.objects[0].material_mut().color = Color::new(0.0, 0.0, 1.0);
+        let mut options = TraceOptions {
+            render_mode: RenderMode::Clay,
+            ..Default::default()
+        };
+        assert_eq!(
+            other.color_at_with(&r, 0.0, f64::INFINITY, &mut options),
+            clay
+        );
+    }
+
     #[test]
     fn shade_intersection_inside() {
         let mut w = World::default();
-        w.lights[0] = PointLight::new(Point::new(0.0, 0.25, 0.0), Color::new(1.0, 1.0, 1.0));
+        w.lights[0] = PointLight::new(Point::new(0.0, 0.25, 0.0), Color::new(1.0, 1.0, 1.0)).into();
         let r = Ray::new(Point::new(0, 0, 0), Vector::new(0, 0, 1));
         let shape = &w.objects[1];
         let i = Intersection::new(0.5, shape.as_ref());
@@ -225,6 +1314,327 @@ mod tests {
         assert_eq!(c, Color::black());
     }
 
+    #[test]
+    fn color_ray_miss_with_sky() {
+        let mut w = World::default();
+        let sky = Sky::new(0.5, 0.0, 2.0);
+        let expected = sky.color_at(Vector::new(0, 1, 0));
+        w.set_sky(sky);
+        let r = Ray::new(Point::new(0, 0, -5), Vector::new(0, 1, 0));
+        let c = w.color_at(&r, MAX_RECURSION_DEPTH);
+        assert_eq!(c, expected);
+    }
+
+    #[test]
+    fn color_ray_hit_with_fog() {
+        let mut w = World::default();
+        let r = Ray::new(Point::new(0, 0, -5), Vector::new(0, 0, 1));
+        let clear = w.color_at(&r, MAX_RECURSION_DEPTH);
+        let fog = Fog::new(Color::white(), 0.1);
+        let expected = fog.apply(clear, 4.0);
+        w.set_fog(fog);
+        let c = w.color_at(&r, MAX_RECURSION_DEPTH);
+        assert_eq!(c, expected);
+
+        let r = Ray::new(Point::new(0, 0, -5), Vector::new(0, 1, 0));
+        assert_eq!(w.color_at(&r, MAX_RECURSION_DEPTH), Color::white());
+    }
+
+    #[test]
+    fn color_ray_clipped() {
+        let w = World::default();
+        let r = Ray::new(Point::new(0, 0, -5), Vector::new(0, 0, 1));
+        let unclipped = w.color_at(&r, MAX_RECURSION_DEPTH);
+        assert_eq!(
+            w.color_at_clipped(&r, MAX_RECURSION_DEPTH, 0.0, 100.0),
+            unclipped
+        );
+        assert_eq!(
+            w.color_at_clipped(&r, MAX_RECURSION_DEPTH, 0.0, 3.0),
+            Color::black()
+        );
+        // skipping the outer sphere entirely shades the far side of the inner one
+        let clipped = w.color_at_clipped(&r, MAX_RECURSION_DEPTH, 6.0, 100.0);
+        assert_ne!(clipped, unclipped);
+    }
+
+    #[test]
+    fn camera_invisible_shapes_are_skipped() {
+        let mut w = World::default();
+        let r = Ray::new(Point::new(0, 0, -5), Vector::new(0, 0, 1));
+        w.objects[0].set_visibility(Visibility {
+            camera: false,
+            ..Default::default()
+        });
+        assert_eq!(w.intersect(&r).len(), 4);
+        assert_eq!(w.intersect_for(&r, RayKind::Camera).len(), 2);
+        assert_eq!(w.intersect_for(&r, RayKind::Shadow).len(), 4);
+    }
+
+    #[test]
+    fn shadow_invisible_shapes_cast_no_shadow() {
+        let mut w = World::default();
+        let p = Point::new(10, -10, 10);
+        assert!(w.is_shadowed(p, &w.lights[0]));
+        for object in w.objects.iter_mut() {
+            object.set_visibility(Visibility {
+                shadow: false,
+                ..Default::default()
+            });
+        }
+        assert!(!w.is_shadowed(p, &w.lights[0]));
+    }
+
+    #[test]
+    fn reflection_only_shapes() {
+        let mut w = World::default();
+        let mut mirror = Plane::default();
+        mirror.material_mut().reflective = 0.5;
+        mirror.set_transform(translation(0, -1, 0));
+        w.add_object(mirror);
+        let r = Ray::new(
+            Point::new(0, 0, -3),
+            Vector::new(0.0, -(2.0f64.sqrt() / 2.0), 2.0f64.sqrt() / 2.0),
+        );
+        let with_spheres = w.color_at(&r, MAX_RECURSION_DEPTH);
+        for object in w.objects[0..2].iter_mut() {
+            object.set_visibility(Visibility {
+                camera: false,
+                ..Default::default()
+            });
+        }
+        // the spheres still show up in the mirror
+        assert_eq!(w.color_at(&r, MAX_RECURSION_DEPTH), with_spheres);
+
+        for object in w.objects[0..2].iter_mut() {
+            object.set_visibility(Visibility {
+                secondary: false,
+                ..Default::default()
+            });
+        }
+        assert_ne!(w.color_at(&r, MAX_RECURSION_DEPTH), with_spheres);
+    }
+
+    #[test]
+    fn translucent_object_lit_from_behind() {
+        let mut w = World::new();
+        w.add_light(PointLight::new(
+            Point::new(0, 0, 10),
+            Color::new(1.0, 1.0, 1.0),
+        ));
+        let mut s = Sphere::default();
+        s.set_transform(scaling(0.5, 0.5, 0.5));
+        w.add_object(s);
+        let r = Ray::new(Point::new(0, 0, -5), Vector::new(0, 0, 1));
+        let opaque = w.color_at(&r, MAX_RECURSION_DEPTH);
+        assert_eq!(opaque, Color::new(0.1, 0.1, 0.1));
+
+        w.objects[0].material_mut().translucency = 0.5;
+        let translucent = w.color_at(&r, MAX_RECURSION_DEPTH);
+        let e = 0.1 + 0.5 * (-1.0f64).exp();
+        assert_eq!(translucent, Color::new(e, e, e));
+    }
+
+    fn mirror_corridor() -> World {
+        let mut w = World::new();
+        w.add_light(PointLight::new(Point::new(0, 0, 0), Color::white()));
+        for z in &[-1.0, 1.0] {
+            let mut p = Plane::default();
+            p.set_transform(&translation(0.0, 0.0, *z) * &rotation_x(PI / 2.0));
+            p.material_mut().reflective = 0.9;
+            p.material_mut().ambient = 0.5;
+            w.add_object(p);
+        }
+        w
+    }
+
+    #[test]
+    fn russian_roulette_goes_deeper_than_fixed_depth() {
+        let w = mirror_corridor();
+        let r = Ray::new(Point::new(0, 0, 0), Vector::new(0, 0, 1));
+        let fixed = w.color_at(&r, MAX_RECURSION_DEPTH);
+
+        let roulette = RussianRoulette {
+            min_depth: 100,
+            max_depth: 100,
+        };
+        let deep = w.color_at_with(
+            &r,
+            0.0,
+            f64::INFINITY,
+            &mut TraceOptions {
+                russian_roulette: Some(&roulette),
+                ..Default::default()
+            },
+        );
+        assert!(deep.red > fixed.red);
+
+        let roulette = RussianRoulette {
+            min_depth: MAX_RECURSION_DEPTH,
+            max_depth: MAX_RECURSION_DEPTH,
+        };
+        let same = w.color_at_with(
+            &r,
+            0.0,
+            f64::INFINITY,
+            &mut TraceOptions {
+                russian_roulette: Some(&roulette),
+                ..Default::default()
+            },
+        );
+        assert_eq!(same, fixed);
+    }
+
+    #[test]
+    fn ray_cache_reuses_repeated_rays() {
+        let w = World::default();
+        let r = Ray::new(Point::new(0, 0, -5), Vector::new(0, 0, 1));
+        let mut cache = RayCache::new(8);
+        let mut options = TraceOptions {
+            cache: Some(&mut cache),
+            ..Default::default()
+        };
+        let first = w.color_at_with(&r, 0.0, f64::INFINITY, &mut options);
+        let second = w.color_at_with(&r, 0.0, f64::INFINITY, &mut options);
+        assert_eq!(first, w.color_at(&r, MAX_RECURSION_DEPTH));
+        assert_eq!(second, first);
+        assert_eq!(cache.stats().hits, 1);
+    }
+
+    #[test]
+    fn ray_cache_does_not_change_mirror_corridor() {
+        let w = mirror_corridor();
+        let r = Ray::new(Point::new(0, 0, 0), Vector::new(0, 0, 1));
+        let mut cache = RayCache::new(8);
+        let cached = w.color_at_with(
+            &r,
+            0.0,
+            f64::INFINITY,
+            &mut TraceOptions {
+                cache: Some(&mut cache),
+                ..Default::default()
+            },
+        );
+        assert_eq!(cached, w.color_at(&r, MAX_RECURSION_DEPTH));
+    }
+
+    #[test]
+    fn ray_log_records_bounce_tree() {
+        let mut w = World::default();
+        let mut plane = Plane::default();
+        plane.material_mut().reflective = 0.5;
+        plane.set_transform(translation(0, -1, 0));
+        w.add_object(plane);
+
+        let r = Ray::new(
+            Point::new(0, 0, -3),
+            Vector::new(0.0, -2.0_f64.sqrt() / 2.0, 2.0_f64.sqrt() / 2.0),
+        );
+        let mut log = RayLog::new();
+        let color = w.color_at_with(
+            &r,
+            0.0,
+            f64::INFINITY,
+            &mut TraceOptions {
+                log: Some(&mut log),
+                ..Default::default()
+            },
+        );
+
+        let entries = log.entries();
+        assert_eq!(entries[0].depth, 0);
+        assert_eq!(entries[0].kind, RayKind::Camera);
+        assert_eq!(entries[0].color, color);
+        let hit = entries[0].hit.as_ref().unwrap();
+        assert!(equal(hit.t, 2.0_f64.sqrt()));
+        assert!(equal(hit.material.reflective, 0.5));
+
+        let children = log.children(0);
+        assert_eq!(children.len(), 1);
+        assert_eq!(children[0].kind, RayKind::Secondary);
+        assert!(log
+            .to_string()
+            .lines()
+            .any(|line| line.starts_with("  secondary ray")));
+    }
+
+    #[test]
+    fn ray_log_shading_terms_add_up() {
+        let mut w = World::default();
+        w.objects_mut()[0].material_mut().reflective = 0.3;
+        let r = Ray::new(Point::new(0, 0, -5), Vector::new(0, 0, 1));
+        let mut log = RayLog::new();
+        let color = w.color_at_with(
+            &r,
+            0.0,
+            f64::INFINITY,
+            &mut TraceOptions {
+                log: Some(&mut log),
+                ..Default::default()
+            },
+        );
+
+        let entry = &log.entries()[0];
+        assert_eq!(entry.intersections, vec![4.0, 4.5, 5.5, 6.0]);
+        let shading = entry.shading.as_ref().unwrap();
+        assert_eq!(shading.lights.len(), 1);
+        assert!(!shading.lights[0].shadowed);
+        assert_eq!(
+            shading.surface() + shading.reflected + shading.refracted,
+            color
+        );
+        assert_eq!(log.children(0).len(), 1);
+    }
+
+    #[test]
+    fn russian_roulette_weights_survivors() {
+        let roulette = RussianRoulette {
+            min_depth: 0,
+            max_depth: 10,
+        };
+        let ray = Ray::new(Point::new(0.3, 0.1, 0.2), Vector::new(0, 0, 1));
+        assert_eq!(roulette.survival_weight(10, 1.0, &ray), Some(1.0));
+
+        let mut survived = 0;
+        for i in 0..1000 {
+            let ray = Ray::new(Point::new(i as f64, 0.0, 0.0), Vector::new(0, 0, 1));
+            if let Some(weight) = roulette.survival_weight(10, 0.25, &ray) {
+                assert!(equal(weight, 4.0));
+                survived += 1;
+            }
+        }
+        assert!((200..300).contains(&survived));
+    }
+
+    #[test]
+    fn nan_guard_shades_nan_magenta() {
+        let mut w = World::default();
+        w.objects[0].material_mut().ambient = f64::NAN;
+        let r = Ray::new(Point::new(0, 0, -5), Vector::new(0, 0, 1));
+        w.set_nan_guard(true);
+        assert_eq!(w.color_at(&r, MAX_RECURSION_DEPTH), NAN_COLOR);
+        // sound surfaces are left alone
+        let miss = Ray::new(Point::new(0, 5, -5), Vector::new(0, 0, 1));
+        assert_eq!(w.color_at(&miss, MAX_RECURSION_DEPTH), Color::black());
+    }
+
+    #[test]
+    fn shade_hit_with_averaged_lights() {
+        let mut w = World::default();
+        let r = Ray::new(Point::new(0, 0, -5), Vector::new(0, 0, 1));
+        let single = w.color_at(&r, MAX_RECURSION_DEPTH);
+
+        w.add_light(PointLight::new(
+            Point::new(-10, 10, -10),
+            Color::new(1.0, 1.0, 1.0),
+        ));
+        let doubled = w.color_at(&r, MAX_RECURSION_DEPTH);
+        assert_eq!(doubled, single * 2.0);
+
+        w.set_light_normalization(LightNormalization::Average);
+        assert_eq!(w.color_at(&r, MAX_RECURSION_DEPTH), single);
+    }
+
     #[test]
     fn color_ray_hit() {
         let w = World::default();
@@ -233,17 +1643,16 @@ mod tests {
         assert_eq!(c, Color::new(0.38066, 0.47583, 0.2855));
     }
 
-    #[test]
     // fn color_intersection_behind_ray() {
     //     let mut w = World::default();
     //     let outer = w.objects[0].as_ref();
-    //     outer.get_base_mut().material.ambient = 1.0;
+    //     outer.material_mut().ambient = 1.0;
     //     let inner = &mut w.objects[1];
-    //     inner.get_base_mut().material.ambient = 1.0;
+    //     inner.material_mut().ambient = 1.0;
     //     let r = Ray::new(Point::new(0.0, 0.0, 0.75), Vector::new(0, 0, -1));
     //     let c = w.color_at(&r, MAX_RECURSION_DEPTH);
     //     let inner = &w.objects[1];
-    //     assert_eq!(c, inner.get_base().material.color);
+    //     assert_eq!(c, inner.material().color);
     // }
     #[test]
     fn no_shadow_when_nothing_is_collinear_with_point_and_light() {
@@ -292,13 +1701,67 @@ mod tests {
         assert_eq!(c, Color::new(0.1, 0.1, 0.1));
     }
 
+    #[test]
+    fn shadows_apply_ray_bias_and_priority() {
+        let p = Point::new(0.0, 0.0, 8.5);
+        let mut w = World::new();
+        w.add_light(PointLight::new(
+            Point::new(0, 0, -10),
+            Color::new(1.0, 1.0, 1.0),
+        ));
+        let mut s1 = Sphere::default();
+        s1.set_ray_bias(20.0);
+        w.add_object(s1);
+        let mut s2 = Sphere::default();
+        s2.set_transform(translation(0, 0, 10));
+        w.add_object(s2);
+        assert!(!w.is_shadowed(p, &w.lights[0]));
+        let r = Ray::new(Point::new(0, 0, 5), Vector::new(0, 0, 1));
+        let i = Intersection::new(4.0, w.objects[1].as_ref());
+        let comps = i.prepare_computations(&r, &[i]);
+        assert_ne!(w.shade_hit(&comps, MAX_RECURSION_DEPTH), Color::new(0.1, 0.1, 0.1));
+
+        // an unbiased twin in the same place wins on priority and blocks again
+        let mut twin = Sphere::default();
+        twin.set_priority(1);
+        w.add_object(twin);
+        assert!(w.is_shadowed(p, &w.lights[0]));
+        let i = Intersection::new(4.0, w.objects[1].as_ref());
+        let comps = i.prepare_computations(&r, &[i]);
+        assert_eq!(w.shade_hit(&comps, MAX_RECURSION_DEPTH), Color::new(0.1, 0.1, 0.1));
+    }
+
+    #[test]
+    fn shadow_fill_lifts_shadowed_points() {
+        let mut w = World::new();
+        w.add_light(PointLight::new(
+            Point::new(0, 0, -10),
+            Color::new(1.0, 1.0, 1.0),
+        ));
+        w.add_object(Sphere::default());
+        let mut s2 = Sphere::default();
+        s2.set_transform(translation(0, 0, 10));
+        w.add_object(s2);
+        w.set_shadow_fill(0.5);
+        let shade = |w: &World| {
+            let r = Ray::new(Point::new(0, 0, 5), Vector::new(0, 0, 1));
+            let i = Intersection::new(4.0, w.objects[1].as_ref());
+            let comps = i.prepare_computations(&r, &[i]);
+            w.shade_hit(&comps, MAX_RECURSION_DEPTH)
+        };
+        assert_eq!(shade(&w), Color::new(0.55, 0.55, 0.55));
+
+        w.lights_mut()[0].source_mut().set_shadow_fill(0.0);
+        assert_eq!(shade(&w), Color::new(0.1, 0.1, 0.1));
+    }
+
     #[test]
     fn reflected_color_of_non_reflective_surface() {
         let mut w = World::default();
         let r = Ray::new(Point::origin(), Vector::new(0, 0, 1));
         let mut shape = Sphere::default();
         shape.set_transform(scaling(0.5, 0.5, 0.5));
-        shape.get_base_mut().material.ambient = 1.0;
+        shape.material_mut().ambient = 1.0;
         w.objects[1] = Box::new(shape);
 
         let shape = &w.objects[1];
@@ -312,7 +1775,7 @@ mod tests {
     fn reflected_color_of_reflective_surface() {
         let mut w = World::default();
         let mut shape = Plane::default();
-        shape.get_base_mut().material.reflective = 0.5;
+        shape.material_mut().reflective = 0.5;
         shape.set_transform(translation(0, -1, 0));
         w.add_object(shape);
         let shape = &w.objects[2];
@@ -327,11 +1790,50 @@ mod tests {
         assert_eq!(color, Color::new(0.19033, 0.23791, 0.14274));
     }
 
+    #[test]
+    fn environment_reflections_look_up_the_sky() {
+        let sky = Sky::new(0.5, 0.0, 2.0);
+        let reflected = |material_flag: bool, with_sky: bool, options: &mut TraceOptions| {
+            let mut w = World::default();
+            if with_sky {
+                w.set_sky(sky.clone());
+            }
+            let mut shape = Plane::default();
+            shape.material_mut().reflective = 0.5;
+            shape.material_mut().environment_reflections = material_flag;
+            shape.set_transform(translation(0, -1, 0));
+            w.add_object(shape);
+            let r = Ray::new(
+                Point::new(0, 0, -3),
+                Vector::new(0.0, -(2.0f64.sqrt() / 2.0), 2.0f64.sqrt() / 2.0),
+            );
+            let i = Intersection::new(2.0f64.sqrt(), w.objects[2].as_ref());
+            let comps = i.prepare_computations(&r, &[i]);
+            w.reflected(&comps, MAX_RECURSION_DEPTH, options)
+        };
+        let traced = Color::new(0.19033, 0.23791, 0.14274);
+        let expected =
+            sky.color_at(Vector::new(0.0, 2.0f64.sqrt() / 2.0, 2.0f64.sqrt() / 2.0)) * 0.5;
+
+        assert_eq!(reflected(false, true, &mut TraceOptions::default()), traced);
+        assert_eq!(
+            reflected(true, true, &mut TraceOptions::default()),
+            expected
+        );
+        // no sky to look up, so the reflection is traced and sees the spheres
+        assert_eq!(reflected(true, false, &mut TraceOptions::default()), traced);
+        let mut options = TraceOptions {
+            environment_reflections: true,
+            ..Default::default()
+        };
+        assert_eq!(reflected(false, true, &mut options), expected);
+    }
+
     #[test]
     fn shade_hit_with_reflective_surface() {
         let mut w = World::default();
         let mut shape = Plane::default();
-        shape.get_base_mut().material.reflective = 0.5;
+        shape.material_mut().reflective = 0.5;
         shape.set_transform(translation(0, -1, 0));
         w.add_object(shape);
         let shape = &w.objects[2];
@@ -352,12 +1854,12 @@ mod tests {
         w.add_light(PointLight::new(Point::origin(), Color::white()));
 
         let mut lower = Plane::default();
-        lower.get_base_mut().material.reflective = 1.0;
+        lower.material_mut().reflective = 1.0;
         lower.set_transform(translation(0, -1, 0));
         w.add_object(lower);
 
         let mut upper = Plane::default();
-        upper.get_base_mut().material.reflective = 1.0;
+        upper.material_mut().reflective = 1.0;
         upper.set_transform(translation(0, 1, 0));
         w.add_object(upper);
 
@@ -369,7 +1871,7 @@ mod tests {
     fn reflected_color_maximum_recursive_depth() {
         let mut w = World::default();
         let mut shape = Plane::default();
-        shape.get_base_mut().material.reflective = 0.5;
+        shape.material_mut().reflective = 0.5;
         shape.set_transform(translation(0, -1, 0));
         w.add_object(shape);
         let r = Ray::new(
@@ -400,8 +1902,8 @@ mod tests {
     fn refracted_color_at_max_recursive_depth() {
         let mut w = World::default();
         let shape = &mut w.objects[0];
-        shape.get_base_mut().material.transparency = 1.0;
-        shape.get_base_mut().material.refractive_index = 1.5;
+        shape.material_mut().transparency = 1.0;
+        shape.material_mut().refractive_index = 1.5;
 
         let r = Ray::new(Point::new(0, 0, 5), Vector::new(0, 0, 1));
 
@@ -419,8 +1921,8 @@ mod tests {
     fn refracted_color_under_total_internal_reflection() {
         let mut w = World::default();
         let shape = &mut w.objects[0];
-        shape.get_base_mut().material.transparency = 1.0;
-        shape.get_base_mut().material.refractive_index = 1.5;
+        shape.material_mut().transparency = 1.0;
+        shape.material_mut().refractive_index = 1.5;
 
         let r = Ray::new(
             Point::new(0.0, 0.0, 2.0f64.sqrt() / 2.0),
@@ -441,12 +1943,12 @@ mod tests {
     fn refracted_color_with_refracted_ray() {
         let mut world = World::default();
         let a = &mut world.objects[0];
-        a.get_base_mut().material.ambient = 1.0;
-        a.get_base_mut().material.set_pattern(test_pattern());
+        a.material_mut().ambient = 1.0;
+        a.material_mut().set_pattern(test_pattern());
 
         let b = &mut world.objects[1];
-        b.get_base_mut().material.transparency = 1.0;
-        b.get_base_mut().material.refractive_index = 1.5;
+        b.material_mut().transparency = 1.0;
+        b.material_mut().refractive_index = 1.5;
 
         let r = Ray::new(Point::new(0.0, 0.0, 0.1), Vector::new(0, 1, 0));
         let a = &world.objects[0];
@@ -462,19 +1964,55 @@ mod tests {
         assert_eq!(c, Color::new(0.0, 0.99887, 0.04722));
     }
 
+    #[test]
+    fn rough_refraction_averages_jittered_rays() {
+        let refracted = |roughness: f64| {
+            let mut world = World::default();
+            let a = &mut world.objects[0];
+            a.material_mut().ambient = 1.0;
+            a.material_mut().set_pattern(test_pattern());
+            let b = &mut world.objects[1];
+            b.material_mut().transparency = 1.0;
+            b.material_mut().refractive_index = 1.5;
+            b.material_mut().roughness = roughness;
+
+            let r = Ray::new(Point::new(0.0, 0.0, 0.1), Vector::new(0, 1, 0));
+            let (a, b) = (world.objects[0].as_ref(), world.objects[1].as_ref());
+            let xs = intersections(&[
+                Intersection::new(-0.9899, a),
+                Intersection::new(-0.4899, b),
+                Intersection::new(0.4899, b),
+                Intersection::new(0.9899, a),
+            ]);
+            let comps = xs[2].prepare_computations(&r, &xs);
+            let mut options = TraceOptions::default();
+            let color = world.refracted(&comps, MAX_RECURSION_DEPTH, &mut options);
+            assert!(!options.within_rough_refraction);
+            (color, options.shadow_rays.cast)
+        };
+
+        let (smooth, smooth_rays) = refracted(0.0);
+        assert_eq!(smooth, Color::new(0.0, 0.99887, 0.04722));
+        let (frosted, frosted_rays) = refracted(0.5);
+        assert_ne!(frosted, smooth);
+        assert_eq!(frosted_rays, smooth_rays * ROUGH_REFRACTION_SAMPLES);
+        // the jitter only depends on the hit, so renders are repeatable
+        assert_eq!(refracted(0.5).0, frosted);
+    }
+
     #[test]
     fn shade_hit_with_transparent_material() {
         let mut w = World::default();
 
         let mut floor = Plane::default();
         floor.set_transform(translation(0, -1, 0));
-        floor.get_base_mut().material.transparency = 0.5;
-        floor.get_base_mut().material.refractive_index = 1.5;
+        floor.material_mut().transparency = 0.5;
+        floor.material_mut().refractive_index = 1.5;
         w.add_object(floor);
 
         let mut ball = Sphere::default();
-        ball.get_base_mut().material.color = Color::new(1.0, 0.0, 0.0);
-        ball.get_base_mut().material.ambient = 0.5;
+        ball.material_mut().color = Color::new(1.0, 0.0, 0.0);
+        ball.material_mut().ambient = 0.5;
         ball.set_transform(translation(0.0, -3.5, -0.5));
         w.add_object(ball);
 
@@ -496,14 +2034,14 @@ mod tests {
 
         let mut floor = Plane::default();
         floor.set_transform(translation(0, -1, 0));
-        floor.get_base_mut().material.reflective = 0.5;
-        floor.get_base_mut().material.transparency = 0.5;
-        floor.get_base_mut().material.refractive_index = 1.5;
+        floor.material_mut().reflective = 0.5;
+        floor.material_mut().transparency = 0.5;
+        floor.material_mut().refractive_index = 1.5;
         w.add_object(floor);
 
         let mut ball = Sphere::default();
-        ball.get_base_mut().material.color = Color::new(1.0, 0.0, 0.0);
-        ball.get_base_mut().material.ambient = 0.5;
+        ball.material_mut().color = Color::new(1.0, 0.0, 0.0);
+        ball.material_mut().ambient = 0.5;
         ball.set_transform(translation(0.0, -3.5, -0.5));
         w.add_object(ball);
 
@@ -518,4 +2056,145 @@ mod tests {
         let color = w.shade_hit(&comps, MAX_RECURSION_DEPTH);
         assert_eq!(color, Color::new(0.93391, 0.69643, 0.69243));
     }
+
+    #[test]
+    fn shade_hit_with_fresnel_opaque_material() {
+        let mut w = World::default();
+
+        let mut floor = Plane::default();
+        floor.set_transform(translation(0, -1, 0));
+        floor.material_mut().reflective = 0.5;
+        floor.material_mut().refractive_index = 1.5;
+        floor.material_mut().fresnel = true;
+        w.add_object(floor);
+
+        let floor = &w.objects[2];
+        let steep = Ray::new(Point::new(0, 0, -3), Vector::new(0, -1, 0));
+        let xs = intersections(&[Intersection::new(1.0, floor.as_ref())]);
+        let comps = xs[0].prepare_computations(&steep, &xs);
+        let surface = w.shade_hit(&comps, 0);
+        let reflected = w.reflected_color(&comps, MAX_RECURSION_DEPTH);
+        let color = w.shade_hit(&comps, MAX_RECURSION_DEPTH);
+        assert_eq!(color, surface + reflected * comps.schlick());
+
+        let grazing = Ray::new(
+            Point::new(0, 0, -3),
+            Vector::new(0.0, -0.1, 1.0).normalize(),
+        );
+        let xs = intersections(&[Intersection::new(101.0f64.sqrt(), floor.as_ref())]);
+        let grazing_comps = xs[0].prepare_computations(&grazing, &xs);
+        assert!(grazing_comps.schlick() > comps.schlick());
+    }
+
+    #[test]
+    fn shadow_factor_counts_the_lights_reaching_the_hit() {
+        let mut w = World::new();
+        let r = Ray::new(Point::origin(), Vector::new(0, -1, 0));
+        let mut floor = Plane::default();
+        floor.set_transform(translation(0, -1, 0));
+        w.add_object(floor);
+        assert!(equal(w.shadow_factor(&r, 0.0, f64::INFINITY), 1.0));
+
+        let mut s = Sphere::default();
+        s.set_transform(&translation(0, 1, 0) * &scaling(0.5, 0.5, 0.5));
+        w.add_object(s);
+        w.add_light(PointLight::new(Point::new(0, 10, 0), Color::white()));
+        w.add_light(PointLight::new(Point::new(10, 10, 0), Color::white()));
+
+        assert!(equal(w.shadow_factor(&r, 0.0, f64::INFINITY), 0.5));
+        let beside = Ray::new(Point::new(5, 0, 0), Vector::new(0, -1, 0));
+        assert!(equal(w.shadow_factor(&beside, 0.0, f64::INFINITY), 1.0));
+        let up = Ray::new(Point::new(5, 0, 0), Vector::new(0, 1, 0));
+        assert!(equal(w.shadow_factor(&up, 0.0, f64::INFINITY), 1.0));
+
+        let mut options = TraceOptions::default();
+        w.color_at_with(&r, 0.0, f64::INFINITY, &mut options);
+        assert_eq!(
+            options.shadow_rays,
+            ShadowRayStats {
+                cast: 2,
+                blocked: 1
+            }
+        );
+    }
+
+    #[test]
+    fn moved_shapes_in_divided_groups_are_hit_at_once() {
+        let mut g = Group::default();
+        let mut ids = vec![];
+        for i in 0..8 {
+            let mut s = Sphere::default();
+            s.set_transform(translation(i as f64 * 3.0, 0.0, 0.0));
+            ids.push(s.id());
+            g.add_child(Box::new(s));
+        }
+        g.divide(2);
+        let mut w = World::new();
+        w.add_object(g);
+
+        let r = Ray::new(Point::new(0, 10, -5), Vector::new(0, 0, 1));
+        assert!(w.intersect(&r).is_empty());
+        assert!(w.update_shape(ids[0], |s| s.set_transform(translation(0, 10, 0))));
+        assert_eq!(w.intersect(&r).len(), 2);
+
+        // moved behind the world's back the boxes hold the old place until refit
+        w.objects_mut()[0].for_each_child_mut(&mut |c| c.set_transform(translation(0, -10, 0)));
+        let below = Ray::new(Point::new(3, -10, -5), Vector::new(0, 0, 1));
+        assert!(w.intersect(&below).is_empty());
+        w.refit();
+        assert_eq!(w.intersect(&below).len(), 2);
+
+        assert!(!w.update_shape(Sphere::default().id(), |_| {}));
+    }
+
+    #[test]
+    fn advance_time_updates_animated_objects() {
+        let mut w = World::default();
+        let mut channel = AnimationChannel::new();
+        channel.add_keyframe(0.0, DecomposedTransform::default(), Easing::Linear);
+        channel.add_keyframe(
+            1.0,
+            DecomposedTransform {
+                translation: Vector::new(0, 4, 0),
+                ..Default::default()
+            },
+            Easing::Linear,
+        );
+        w.objects[0].set_animation(channel);
+
+        w.advance_time(0.5);
+        assert_eq!(w.objects[0].transform(), &translation(0, 2, 0));
+        assert_eq!(w.objects[1].transform(), &scaling(0.5, 0.5, 0.5));
+
+        w.advance_time(1.0);
+        assert_eq!(w.objects[0].transform(), &translation(0, 4, 0));
+    }
+
+    #[test]
+    fn advance_time_moves_animated_children_of_groups() {
+        let mut channel = AnimationChannel::new();
+        channel.add_keyframe(0.0, DecomposedTransform::default(), Easing::Linear);
+        channel.add_keyframe(
+            1.0,
+            DecomposedTransform {
+                translation: Vector::new(0, 4, 0),
+                ..Default::default()
+            },
+            Easing::Linear,
+        );
+        let mut s = Sphere::default();
+        s.set_animation(channel);
+        let mut g = Group::default();
+        g.set_transform(translation(10, 0, 0));
+        g.add(s);
+        let mut w = World::new();
+        w.add_object(g);
+
+        w.advance_time(1.0);
+        let g = w.objects[0].as_any().downcast_ref::<Group>().unwrap();
+        assert_eq!(g.children[0].transform(), &translation(10, 4, 0));
+        assert_eq!(g.get_bounds().get_max(), Point::new(11, 5, 1));
+        let r = Ray::new(Point::new(10, 4, -5), Vector::new(0, 0, 1));
+        assert_eq!(w.intersect(&r).len(), 2);
+    }
 }
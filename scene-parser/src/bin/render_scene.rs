@@ -1,17 +1,165 @@
-use std::{env, path::Path};
+use std::{
+    env,
+    io::{self, Write},
+    path::Path,
+    sync::mpsc::{self, Receiver},
+    thread,
+    time::Instant,
+};
 
 use anyhow::Result;
+use raytracer::{
+    image::dither::Dither,
+    progress::{ProgressMeter, RenderProgress},
+};
 use scene_parser::SceneParser;
 
+// the rows per second estimate covers this many of the latest rows
+const PROGRESS_WINDOW: usize = 32;
+
+// watch mode renders at a quarter of the scene's resolution
+#[cfg(feature = "watch")]
+const WATCH_PREVIEW_DIVISOR: usize = 4;
+
+const USAGE: &str = "usage: render_scene <scene-file> <output-file> [--crop x0,y0,x1,y1] [--camera name] [--dither none|ordered|blue-noise] [--watch] [--quiet]
+       render_scene <scene-file> --dry-run [--camera name]";
+
+#[derive(Default)]
+struct Options {
+    crop: Option<(usize, usize, usize, usize)>,
+    camera: Option<String>,
+    dither: Dither,
+    watch: bool,
+    quiet: bool,
+    // parse the scene and describe it instead of rendering
+    dry_run: bool,
+}
+
 fn main() -> Result<()> {
     let args: Vec<String> = env::args().collect();
-    if args.len() != 3 {
-        println!("usage: render_scene <scene-file> <output-file>");
-    }
+    // a dry run doesn't need an output file
+    let output_filename = args.get(2).filter(|arg| !arg.starts_with("--"));
+    let first_option = if output_filename.is_some() { 3 } else { 2 };
+    let options = if args.len() >= 2 {
+        parse_options(&args[first_option..])
+    } else {
+        None
+    };
+    let options = match options {
+        Some(options) if options.dry_run || output_filename.is_some() => options,
+        _ => {
+            println!("{}", USAGE);
+            return Ok(());
+        }
+    };
     let filename = &args[1];
+    let camera = options.camera.as_deref();
+
+    if options.dry_run {
+        let mut parser = SceneParser::new();
+        parser.load_file(filename)?;
+        println!("{}", parser.dry_run(camera)?);
+        return Ok(());
+    }
+    let output_filename = output_filename.expect("checked above");
+
+    if options.watch {
+        #[cfg(feature = "watch")]
+        return scene_parser::watch::watch(
+            Path::new(filename),
+            Path::new(output_filename),
+            WATCH_PREVIEW_DIVISOR,
+            camera,
+        );
+        #[cfg(not(feature = "watch"))]
+        {
+            println!("render_scene was built without the watch feature");
+            return Ok(());
+        }
+    }
+
     let mut parser = SceneParser::new();
     parser.load_file(filename)?;
-    let output_filename = &args[2];
-    parser.render(&Path::new(output_filename))?;
+    let (tx, rx) = mpsc::channel();
+    parser.progress(tx);
+    parser.dither(options.dither);
+    let quiet = options.quiet;
+    let printer = thread::spawn(move || print_progress(rx, quiet));
+    match options.crop {
+        Some(crop) => parser.render_crop(Path::new(output_filename), crop, camera)?,
+        None => parser.render(Path::new(output_filename), camera)?,
+    }
+    printer.join().expect("progress printer panicked");
     Ok(())
 }
+
+// runs until the render is done, with quiet it only drains the channel
+fn print_progress(rx: Receiver<RenderProgress>, quiet: bool) {
+    let mut meter = ProgressMeter::new(PROGRESS_WINDOW);
+    for progress in rx {
+        if !quiet {
+            meter.record(Instant::now(), progress.rows_done);
+            print!("\rrow {}/{}", progress.rows_done, progress.total_rows);
+            if let (Some(rate), Some(eta)) = (meter.rows_per_sec(), meter.eta(progress.total_rows))
+            {
+                print!(
+                    ", {:.1} rows/s, {} left   ",
+                    rate,
+                    format_duration(eta.as_secs())
+                );
+            }
+            let _ = io::stdout().flush();
+        }
+        if progress.is_done() {
+            if !quiet {
+                println!();
+            }
+            break;
+        }
+    }
+}
+
+fn format_duration(secs: u64) -> String {
+    match secs {
+        0..=59 => format!("{}s", secs),
+        60..=3599 => format!("{}m{:02}s", secs / 60, secs % 60),
+        _ => format!("{}h{:02}m", secs / 3600, secs % 3600 / 60),
+    }
+}
+
+fn parse_options(args: &[String]) -> Option<Options> {
+    let mut options = Options::default();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--crop" => options.crop = Some(parse_crop(args.next()?)?),
+            "--camera" => options.camera = Some(args.next()?.clone()),
+            "--dither" => options.dither = parse_dither(args.next()?)?,
+            "--watch" => options.watch = true,
+            "--quiet" => options.quiet = true,
+            "--dry-run" => options.dry_run = true,
+            _ => return None,
+        }
+    }
+    Some(options)
+}
+
+fn parse_dither(arg: &str) -> Option<Dither> {
+    match arg {
+        "none" => Some(Dither::None),
+        "ordered" => Some(Dither::Ordered),
+        "blue-noise" => Some(Dither::BlueNoise),
+        _ => None,
+    }
+}
+
+fn parse_crop(arg: &str) -> Option<(usize, usize, usize, usize)> {
+    let values = arg
+        .split(',')
+        .map(|v| v.trim().parse::<usize>().ok())
+        .collect::<Option<Vec<_>>>()?;
+    match values.as_slice() {
+        &[x0, y0, x1, y1] if x0 < x1 && y0 < y1 => Some((x0, y0, x1, y1)),
+        _ => None,
+    }
+}
@@ -0,0 +1,208 @@
+use std::{collections::BTreeMap, fmt, mem, sync::Arc};
+
+use anyhow::Result;
+use raytracer::{
+    canvas::PixelStorage,
+    color::Color,
+    geometry::{visitor::walk, Shape},
+    material::Material,
+    matrix::Matrix,
+};
+
+use crate::SceneParser;
+
+// what a parsed scene holds, to check it before a long render
+#[derive(Debug, Default, PartialEq)]
+pub struct SceneDescription {
+    // every shape in the scene by type, groups and csgs included
+    pub shapes: BTreeMap<&'static str, usize>,
+    // materials defined by name, and the different ones the shapes use
+    pub named_materials: usize,
+    pub distinct_materials: usize,
+    pub lights: usize,
+    // name, width and height of every camera
+    pub cameras: Vec<(Option<String>, usize, usize)>,
+    // a rough estimate of the memory the shapes take, and the memory the
+    // image of the described camera takes while rendering, 0 without one
+    pub shape_bytes: usize,
+    pub canvas_bytes: usize,
+}
+
+impl SceneDescription {
+    pub fn shape_count(&self) -> usize {
+        self.shapes.values().sum()
+    }
+}
+
+impl fmt::Display for SceneDescription {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "shapes: {}", self.shape_count())?;
+        for (name, count) in &self.shapes {
+            writeln!(f, "  {}: {}", name, count)?;
+        }
+        writeln!(
+            f,
+            "materials: {} named, {} distinct in use",
+            self.named_materials, self.distinct_materials
+        )?;
+        writeln!(f, "lights: {}", self.lights)?;
+        writeln!(f, "cameras: {}", self.cameras.len())?;
+        for (name, width, height) in &self.cameras {
+            let name = name.as_deref().unwrap_or("(unnamed)");
+            writeln!(f, "  {}: {}x{}", name, width, height)?;
+        }
+        write!(
+            f,
+            "estimated memory: {} for shapes, {} for the image",
+            format_bytes(self.shape_bytes),
+            format_bytes(self.canvas_bytes)
+        )
+    }
+}
+
+fn format_bytes(bytes: usize) -> String {
+    match bytes {
+        0..=1023 => format!("{} B", bytes),
+        1024..=1048575 => format!("{:.1} KiB", bytes as f64 / 1024.0),
+        _ => format!("{:.1} MiB", bytes as f64 / (1024.0 * 1024.0)),
+    }
+}
+
+fn pixel_bytes(storage: PixelStorage) -> usize {
+    match storage {
+        PixelStorage::F64 => mem::size_of::<Color>(),
+        PixelStorage::F32 => mem::size_of::<[f32; 3]>(),
+    }
+}
+
+impl SceneParser {
+    // counts what has been parsed so far, the image is the one camera_name
+    // picks like it does for render
+    pub fn describe(&self, camera_name: Option<&str>) -> SceneDescription {
+        let scene = &self.scene;
+        let mut description = SceneDescription {
+            named_materials: scene.materials.len(),
+            lights: scene.lights.len(),
+            cameras: scene
+                .cameras
+                .iter()
+                .map(|(name, c)| (name.clone(), c.hsize(), c.vsize()))
+                .collect(),
+            ..Default::default()
+        };
+        if let Ok(idx) = self.camera_index(camera_name) {
+            let c = &scene.cameras[idx].1;
+            description.canvas_bytes =
+                c.hsize() * c.vsize() * pixel_bytes(c.render_opts.get_pixel_storage());
+        }
+
+        let mut materials: Vec<*const Material> = vec![];
+        for shape in &scene.shapes {
+            walk(
+                shape.as_ref(),
+                &mut |shape: &dyn Shape, _: &Matrix, _: usize| {
+                    *description.shapes.entry(shape.type_name()).or_insert(0) += 1;
+                    description.shape_bytes +=
+                        mem::size_of_val(shape) + mem::size_of::<Box<dyn Shape>>();
+                    materials.push(Arc::as_ptr(shape.shared_material()));
+                    true
+                },
+            );
+        }
+        materials.sort();
+        materials.dedup();
+        description.distinct_materials = materials.len();
+        description.shape_bytes += materials.len() * mem::size_of::<Material>();
+        description
+    }
+
+    // checks the scene can be rendered with the camera, without rendering it
+    pub fn dry_run(&mut self, camera_name: Option<&str>) -> Result<SceneDescription> {
+        self.camera_mut(camera_name)?;
+        Ok(self.describe(camera_name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCENE: &str = "
+- add: camera
+  width: 40
+  height: 30
+  field-of-view: 1
+  from: [0, 0, -5]
+  to: [0, 0, 0]
+  up: [0, 1, 0]
+- add: light
+  at: [0, 10, 0]
+  intensity: [1, 1, 1]
+- define: red
+  value:
+    color: [1, 0, 0]
+- add: group
+  material: red
+  children:
+    - add: sphere
+    - add: cube
+- add: sphere
+- add: plane
+";
+
+    #[test]
+    fn describe_counts_scene_contents() {
+        let mut p = SceneParser::new();
+        p.parse_str(SCENE).unwrap();
+        let d = p.describe(None);
+
+        let shapes: Vec<_> = d.shapes.iter().map(|(&n, &c)| (n, c)).collect();
+        assert_eq!(
+            shapes,
+            vec![("Cube", 1), ("Group", 1), ("Plane", 1), ("Sphere", 2)]
+        );
+        assert_eq!(d.shape_count(), 5);
+        assert_eq!(d.named_materials, 1);
+        // the group shares red with its children, the others have their own
+        assert_eq!(d.distinct_materials, 3);
+        assert_eq!(d.lights, 1);
+        assert_eq!(d.cameras, vec![(None, 40, 30)]);
+        assert_eq!(d.canvas_bytes, 40 * 30 * mem::size_of::<Color>());
+        assert!(d.shape_bytes > 0);
+        assert!(d.to_string().contains("Sphere: 2"));
+    }
+
+    #[test]
+    fn dry_run_needs_the_camera() {
+        let mut p = SceneParser::new();
+        p.parse_str(SCENE).unwrap();
+        assert!(p.dry_run(Some("missing")).is_err());
+        assert_eq!(p.dry_run(None).unwrap(), p.describe(None));
+        // nothing was moved out of the scene
+        assert_eq!(p.scene().shapes().len(), 3);
+    }
+
+    #[test]
+    fn describe_the_image_of_the_chosen_camera() {
+        let mut p = SceneParser::new();
+        p.parse_str(SCENE).unwrap();
+        p.parse_str(
+            "
+- add: camera
+  name: wide
+  width: 80
+  height: 30
+  field-of-view: 1
+  from: [0, 0, -5]
+  to: [0, 0, 0]
+  up: [0, 1, 0]
+",
+        )
+        .unwrap();
+        let color = mem::size_of::<Color>();
+        assert_eq!(p.describe(None).canvas_bytes, 40 * 30 * color);
+        assert_eq!(p.describe(Some("wide")).canvas_bytes, 80 * 30 * color);
+        assert_eq!(p.dry_run(Some("wide")).unwrap().canvas_bytes, 80 * 30 * color);
+        assert_eq!(p.describe(Some("missing")).canvas_bytes, 0);
+    }
+}
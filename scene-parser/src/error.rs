@@ -10,6 +10,8 @@ pub enum SceneParserError {
     ParseIntError(String),
     #[error("failed to parse `{0}` as f64")]
     ParseFloatError(String),
+    #[error("failed to parse `{0}` as bool")]
+    ParseBoolError(String),
     #[error("failed to parse `{0}` as vec")]
     ParseVecError(String),
     #[error("failed to parse transform")]
@@ -22,4 +24,22 @@ pub enum SceneParserError {
     InvalidDefineElementError,
     #[error("failed to parse pattern")]
     ParsePatternError,
+    #[error("prefab `{0}` adds itself")]
+    RecursivePrefab(String),
+    #[error("unknown tile order `{0}`")]
+    UnknownTileOrder(String),
+    #[error("unknown light type `{0}`")]
+    UnknownLightType(String),
+    #[error("unknown csg operation `{0}`")]
+    UnknownCsgOperation(String),
+    #[error("unknown uv mapping `{0}`")]
+    UnknownUvMapping(String),
+    #[error("unknown render mode `{0}`")]
+    UnknownRenderMode(String),
+    #[error("scene has no camera")]
+    MissingCamera,
+    #[error("no camera named `{0}`")]
+    UnknownCamera(String),
+    #[error("can't read material library `{0}`")]
+    UnreadableMaterialLibrary(String),
 }
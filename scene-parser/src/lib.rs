@@ -1,37 +1,64 @@
-use std::{collections::HashMap, fs, path::Path, vec};
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::{mpsc::Sender, Arc},
+    time::Duration,
+};
 
 use anyhow::Result;
 use error::SceneParserError;
 use lazy_static::lazy_static;
 use raytracer::{
-    camera::Camera,
+    bounding_box::BoundingBox,
+    brdf::BrdfModel,
+    camera::{Camera, RenderBudget, FULL_FRAME_SENSOR_WIDTH},
     color::Color,
+    exposure::AutoExposure,
     geometry::{
-        shape::{Cube, Plane, Sphere},
-        Shape,
+        shape::{Csg, Group, Instance, Operation},
+        RayKind, Shape,
     },
-    image::ExportCanvas,
-    light::PointLight,
-    material::Material,
+    image::{dither::Dither, png::PngExporter, ExportCanvas},
+    light::{Light, PointLight, SpotLight},
+    material::{Cutout, Displacement, Material},
     matrix::Matrix,
-    pattern::{checkers_pattern, stripe_pattern, Pattern},
+    pattern::Pattern,
     point::Point,
-    transform::{self, rotation_y, rotation_z, view_transform},
+    progress::RenderProgress,
+    sky::Sky,
+    tiles::TileOrder,
+    transform::{self, rotation_y, rotation_z, try_view_transform},
     vector::Vector,
-    world::World,
+    world::{RenderMode, World},
 };
 use transform::{rotation_x, scaling, translation};
-use yaml_rust::{yaml, Yaml, YamlLoader};
+use yaml_rust::{Yaml, YamlLoader};
 
+pub mod describe;
 mod error;
+pub mod registry;
+#[cfg(feature = "watch")]
+pub mod watch;
+pub mod writer;
+
+pub use registry::{ParseContext, Registry};
+// factories registered with the parser are handed yaml elements
+pub use yaml_rust::yaml;
 
 lazy_static! {
     static ref ADD_KEY: Yaml = Yaml::String(String::from("add"));
     static ref DEFINE_KEY: Yaml = Yaml::String(String::from("define"));
     static ref EXTEND_KEY: Yaml = Yaml::String(String::from("extend"));
+    static ref NAME_KEY: Yaml = Yaml::String(String::from("name"));
     static ref VALUE_KEY: Yaml = Yaml::String(String::from("value"));
+    static ref CHILDREN_KEY: Yaml = Yaml::String(String::from("children"));
+    static ref OPERATION_KEY: Yaml = Yaml::String(String::from("operation"));
+    static ref LEFT_KEY: Yaml = Yaml::String(String::from("left"));
+    static ref RIGHT_KEY: Yaml = Yaml::String(String::from("right"));
     static ref TRANSFORM_KEY: Yaml = Yaml::String(String::from("transform"));
     static ref MATERIAL_KEY: Yaml = Yaml::String(String::from("material"));
+    static ref TAGS_KEY: Yaml = Yaml::String(String::from("tags"));
     static ref MATERIAL_COLOR_KEY: Yaml = Yaml::String(String::from("color"));
     static ref MATERIAL_PATTERN_KEY: Yaml = Yaml::String(String::from("pattern"));
     static ref MATERIAL_AMBIENT_KEY: Yaml = Yaml::String(String::from("ambient"));
@@ -41,44 +68,159 @@ lazy_static! {
     static ref MATERIAL_REFLECTIVE_KEY: Yaml = Yaml::String(String::from("reflective"));
     static ref MATERIAL_TRANSPARENCY_KEY: Yaml = Yaml::String(String::from("transparency"));
     static ref MATERIAL_REFRACTIVE_INDEX_KEY: Yaml = Yaml::String(String::from("refractive-index"));
+    static ref MATERIAL_FRESNEL_KEY: Yaml = Yaml::String(String::from("fresnel"));
+    static ref MATERIAL_ENVIRONMENT_REFLECTIONS_KEY: Yaml =
+        Yaml::String(String::from("environment-reflections"));
+    static ref MATERIAL_TRANSLUCENCY_KEY: Yaml = Yaml::String(String::from("translucency"));
+    static ref MATERIAL_ROUGHNESS_KEY: Yaml = Yaml::String(String::from("roughness"));
+    static ref MATERIAL_METALLIC_KEY: Yaml = Yaml::String(String::from("metallic"));
+    static ref MATERIAL_BRDF_KEY: Yaml = Yaml::String(String::from("brdf"));
+    static ref MATERIAL_DISPLACEMENT_KEY: Yaml = Yaml::String(String::from("displacement"));
+    static ref MATERIAL_CUTOUT_KEY: Yaml = Yaml::String(String::from("cutout"));
     static ref PATTERN_TYPE_KEY: Yaml = Yaml::String(String::from("type"));
     static ref PATTERN_COLORS_KEY: Yaml = Yaml::String(String::from("colors"));
 }
 
+// the keys of cameras, lights, the sky and factories. one block would go past
+// lazy_static's recursion limit
+lazy_static! {
+    static ref CAMERA_FOCAL_LENGTH_KEY: Yaml = Yaml::String(String::from("focal-length"));
+    static ref CAMERA_SENSOR_WIDTH_KEY: Yaml = Yaml::String(String::from("sensor-width"));
+    static ref CAMERA_FIELD_OF_VIEW_DEG_KEY: Yaml = Yaml::String(String::from("field-of-view-deg"));
+    static ref CAMERA_LENS_SHIFT_KEY: Yaml = Yaml::String(String::from("lens-shift"));
+    static ref CAMERA_RENDER_KEY: Yaml = Yaml::String(String::from("render"));
+    static ref RENDER_THREADS_KEY: Yaml = Yaml::String(String::from("threads"));
+    static ref RENDER_TILE_SIZE_KEY: Yaml = Yaml::String(String::from("tile-size"));
+    static ref RENDER_TILE_ORDER_KEY: Yaml = Yaml::String(String::from("tile-order"));
+    static ref RENDER_TIME_BUDGET_KEY: Yaml = Yaml::String(String::from("time-budget"));
+    static ref RENDER_RAY_BUDGET_KEY: Yaml = Yaml::String(String::from("ray-budget"));
+    static ref RENDER_MODE_KEY: Yaml = Yaml::String(String::from("mode"));
+    static ref RENDER_ENVIRONMENT_REFLECTIONS_KEY: Yaml =
+        Yaml::String(String::from("environment-reflections"));
+    static ref RENDER_AUTO_EXPOSURE_KEY: Yaml = Yaml::String(String::from("auto-exposure"));
+    static ref EXPOSURE_PERCENTILE_KEY: Yaml = Yaml::String(String::from("percentile"));
+    static ref EXPOSURE_TARGET_KEY: Yaml = Yaml::String(String::from("target"));
+    static ref LIGHT_CORNER_KEY: Yaml = Yaml::String(String::from("corner"));
+    static ref LIGHT_SHADOW_FILL_KEY: Yaml = Yaml::String(String::from("shadow-fill"));
+    static ref LIGHT_SHADOW_SOFTNESS_KEY: Yaml = Yaml::String(String::from("shadow-softness"));
+    static ref LIGHT_SHADOW_COLOR_KEY: Yaml = Yaml::String(String::from("shadow-color"));
+    static ref LIGHT_TYPE_KEY: Yaml = Yaml::String(String::from("type"));
+    static ref LIGHT_DIRECTION_KEY: Yaml = Yaml::String(String::from("direction"));
+    static ref LIGHT_TO_KEY: Yaml = Yaml::String(String::from("to"));
+    static ref LIGHT_INNER_ANGLE_KEY: Yaml = Yaml::String(String::from("inner-angle"));
+    static ref SHADOW_KEY: Yaml = Yaml::String(String::from("shadow"));
+    static ref VISIBLE_KEY: Yaml = Yaml::String(String::from("visible"));
+    static ref REFLECTION_VISIBLE_KEY: Yaml = Yaml::String(String::from("reflection-visible"));
+    static ref SKY_AZIMUTH_KEY: Yaml = Yaml::String(String::from("azimuth"));
+    static ref SKY_TURBIDITY_KEY: Yaml = Yaml::String(String::from("turbidity"));
+    static ref SKY_SUN_KEY: Yaml = Yaml::String(String::from("sun"));
+    static ref SKY_INTENSITY_KEY: Yaml = Yaml::String(String::from("intensity"));
+    static ref DISPLACEMENT_FREQUENCY_KEY: Yaml = Yaml::String(String::from("frequency"));
+    static ref THRESHOLD_KEY: Yaml = Yaml::String(String::from("threshold"));
+    static ref PATTERN_EXTENT_KEY: Yaml = Yaml::String(String::from("extent"));
+    static ref EXTENT_X_KEY: Yaml = Yaml::String(String::from("x"));
+    static ref EXTENT_Y_KEY: Yaml = Yaml::String(String::from("y"));
+    static ref EXTENT_Z_KEY: Yaml = Yaml::String(String::from("z"));
+    static ref ANGLE_DEG_KEY: Yaml = Yaml::String(String::from("deg"));
+    static ref ANGLE_RAD_KEY: Yaml = Yaml::String(String::from("rad"));
+    static ref FILE_KEY: Yaml = Yaml::String(String::from("file"));
+    static ref RADII_KEY: Yaml = Yaml::String(String::from("radii"));
+    static ref MAPPING_KEY: Yaml = Yaml::String(String::from("mapping"));
+}
+
+#[derive(Default)]
 pub struct Scene {
-    camera: Option<Camera>,
-    lights: Vec<PointLight>,
+    // cameras in the order they were added, with their optional name
+    cameras: Vec<(Option<String>, Camera)>,
+    lights: Vec<Light>,
+    sky: Option<Sky>,
     materials: HashMap<String, Material>,
     transforms: HashMap<String, Matrix>,
+    constants: HashMap<String, Constant>,
+    // shape subtrees by name
+    prefabs: HashMap<String, Yaml>,
+    // the prefabs built so far, shared by the instances that add them
+    shared_prefabs: HashMap<String, Arc<dyn Shape>>,
     shapes: Vec<Box<dyn Shape>>,
 }
 
-impl Default for Scene {
-    fn default() -> Self {
-        Self {
-            camera: None,
-            lights: vec![],
-            materials: HashMap::new(),
-            transforms: HashMap::new(),
-            shapes: vec![],
-        }
-    }
-}
-
 impl Scene {
     pub fn new() -> Self {
         Self::default()
     }
+
+    pub fn cameras(&self) -> &[(Option<String>, Camera)] {
+        &self.cameras
+    }
+
+    pub fn lights(&self) -> &[Light] {
+        &self.lights
+    }
+
+    pub fn materials(&self) -> &HashMap<String, Material> {
+        &self.materials
+    }
+
+    pub fn transforms(&self) -> &HashMap<String, Matrix> {
+        &self.transforms
+    }
+
+    pub fn constants(&self) -> &HashMap<String, Constant> {
+        &self.constants
+    }
+
+    pub fn prefab_names(&self) -> Vec<&str> {
+        self.prefabs.keys().map(|name| name.as_str()).collect()
+    }
+
+    pub fn shapes(&self) -> &[Box<dyn Shape>] {
+        &self.shapes
+    }
+}
+
+// a number or a color defined once and used by name wherever numbers or colors go
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Constant {
+    Number(f64),
+    Color(Color),
+}
+
+impl Constant {
+    // the value as the yaml it replaces
+    fn to_yaml(self) -> Yaml {
+        let real = |value: f64| Yaml::Real(format!("{:?}", value));
+        match self {
+            Self::Number(value) => real(value),
+            Self::Color(c) => Yaml::Array(vec![real(c.red), real(c.green), real(c.blue)]),
+        }
+    }
+
+    // a number, or three numbers for a color
+    fn from_yaml(el: &Yaml) -> Option<Self> {
+        match el {
+            Yaml::Integer(_) | Yaml::Real(_) => to_f64(el).ok().map(Self::Number),
+            Yaml::Array(items) if items.len() == 3 => to_color(items).ok().map(Self::Color),
+            _ => None,
+        }
+    }
 }
 
 pub struct SceneParser {
     scene: Scene,
+    context: ParseContext,
+    registry: Registry,
+    progress: Option<Sender<RenderProgress>>,
+    dither: Dither,
 }
 
 impl Default for SceneParser {
     fn default() -> Self {
         Self {
             scene: Scene::new(),
+            context: ParseContext::default(),
+            registry: Registry::default(),
+            progress: None,
+            dither: Dither::default(),
         }
     }
 }
@@ -88,12 +230,61 @@ impl SceneParser {
         Self::default()
     }
 
+    // renders report every finished row to sender
+    pub fn progress(&mut self, sender: Sender<RenderProgress>) {
+        self.progress = Some(sender);
+    }
+
+    // how render and render_crop round their output to 8 bits
+    pub fn dither(&mut self, dither: Dither) {
+        self.dither = dither;
+    }
+
+    // lets scenes use shapes and patterns the parser doesn't know about
+    pub fn registry_mut(&mut self) -> &mut Registry {
+        &mut self.registry
+    }
+
+    pub fn register_shape<F>(&mut self, name: &str, factory: F)
+    where
+        F: Fn(&yaml::Hash, &mut ParseContext) -> Result<Box<dyn Shape>> + 'static,
+    {
+        self.registry.register_shape(name, factory);
+    }
+
+    pub fn register_pattern<F>(&mut self, name: &str, factory: F)
+    where
+        F: Fn(&yaml::Hash, &[Color], &mut ParseContext) -> Result<Pattern> + 'static,
+    {
+        self.registry.register_pattern(name, factory);
+    }
+
     pub fn load_file(&mut self, path: &str) -> Result<()> {
         println!("path to scene: {:?}", path);
-        let contents = fs::read_to_string(path).unwrap();
-        let yaml = YamlLoader::load_from_str(&contents)?;
-        let elements = &yaml[0];
-        if let Yaml::Array(array) = elements {
+        let contents = fs::read_to_string(path)?;
+        self.context = ParseContext::for_file(Path::new(path));
+        self.parse_yaml(&contents, path)
+    }
+
+    // what has been parsed so far, before it is moved into a world
+    pub fn scene(&self) -> &Scene {
+        &self.scene
+    }
+
+    // every file the loaded scene was read from, with the models and images
+    // it uses
+    pub fn source_files(&self) -> &[PathBuf] {
+        self.context.source_files()
+    }
+
+    // parses a scene from memory, malformed input gives an error rather than a panic
+    pub fn parse_str(&mut self, contents: &str) -> Result<()> {
+        self.parse_yaml(contents, "<string>")
+    }
+
+    fn parse_yaml(&mut self, contents: &str, source: &str) -> Result<()> {
+        let yaml = YamlLoader::load_from_str(contents)?;
+        if let Some(Yaml::Array(array)) = yaml.first() {
             let define_elements: Vec<&Yaml> = array
                 .iter()
                 .filter(|&element| is_define_element(element))
@@ -111,10 +302,10 @@ impl SceneParser {
             println!("found {} add elements", add_elements.len());
 
             for el in add_elements {
-                self.parse_add_element(el)?;
+                self.parse_add_element(&self.with_constants(el))?;
             }
         } else {
-            return Err(error::SceneParserError::BadInputFile(String::from(path)).into());
+            return Err(error::SceneParserError::BadInputFile(String::from(source)).into());
         }
         Ok(())
     }
@@ -123,10 +314,29 @@ impl SceneParser {
         if let Yaml::Hash(hash) = element {
             if let Some(Yaml::String(kind)) = hash.get(&ADD_KEY) {
                 match kind.as_str() {
-                    "camera" => self.scene.camera = Some(parse_camera(hash)?),
-                    "light" => self.scene.lights.push(parse_light(hash)?),
-                    "sphere" | "plane" | "cube" => {
-                        let shape = self.parse_shape(kind, hash)?;
+                    "camera" => {
+                        let name = match hash.get(&NAME_KEY) {
+                            Some(name_el) => Some(
+                                name_el
+                                    .as_str()
+                                    .ok_or(error::SceneParserError::InvalidAddElementError)?
+                                    .to_string(),
+                            ),
+                            None => None,
+                        };
+                        let camera = parse_camera(hash)?;
+                        self.add_camera(name, camera);
+                    }
+                    "light" => self.scene.lights.extend(parse_light(hash)?),
+                    "sky" => {
+                        let (sky, sun) = parse_sky(hash)?;
+                        if sun {
+                            self.scene.lights.push(sky.sun_light().into());
+                        }
+                        self.scene.sky = Some(sky);
+                    }
+                    kind if self.is_shape(kind) => {
+                        let shape = self.parse_shape(hash, &mut vec![])?;
                         self.scene.shapes.push(shape);
                     }
                     _ => println!("unhandled element: {}", kind),
@@ -141,14 +351,41 @@ impl SceneParser {
         if let Yaml::Hash(hash) = element {
             let name = hash
                 .get(&DEFINE_KEY)
-                .unwrap()
-                .as_str()
+                .and_then(|name| name.as_str())
                 .ok_or(error::SceneParserError::InvalidDefineElementError)?;
             let define_value_el = hash
                 .get(&VALUE_KEY)
                 .ok_or(error::SceneParserError::InvalidDefineElementError)?;
+            let define_value_el = &match define_value_el {
+                // transform lists, whose operations are names
+                Yaml::Array(items) if items.iter().any(|item| item.as_vec().is_some()) => {
+                    substitute_in_transform(&self.scene.constants, define_value_el)
+                }
+                _ => self.with_constants(define_value_el),
+            };
+            if let Some(constant) = Constant::from_yaml(define_value_el) {
+                println!("found defined constant {}", name);
+                self.scene.constants.insert(String::from(name), constant);
+                return Ok(());
+            }
             let extend = hash.get(&EXTEND_KEY);
             match define_value_el {
+                Yaml::Hash(prefab) if prefab.contains_key(&ADD_KEY) => {
+                    println!("found defined prefab {}", name);
+                    self.scene
+                        .prefabs
+                        .insert(String::from(name), define_value_el.clone());
+                }
+                // a list of shapes is a group of them
+                Yaml::Array(items) if !items.is_empty() && items.iter().all(is_add_element) => {
+                    println!("found defined group {}", name);
+                    let mut group = yaml::Hash::new();
+                    group.insert(ADD_KEY.clone(), Yaml::String("group".to_string()));
+                    group.insert(CHILDREN_KEY.clone(), define_value_el.clone());
+                    self.scene
+                        .prefabs
+                        .insert(String::from(name), Yaml::Hash(group));
+                }
                 Yaml::Array(_) => {
                     println!("found defined transform {}", name);
                     let transform = self.parse_transform(define_value_el)?;
@@ -156,9 +393,8 @@ impl SceneParser {
                 }
                 Yaml::Hash(_) => {
                     println!("found defined material {}", name);
-                    if extend.is_some() {
+                    if let Some(extend) = extend {
                         let base_material_name = extend
-                            .unwrap()
                             .as_str()
                             .ok_or(error::SceneParserError::InvalidDefineElementError)?;
                         println!("found material {} extending: {}", name, base_material_name);
@@ -178,22 +414,119 @@ impl SceneParser {
                         self.scene.materials.insert(String::from(name), material);
                     }
                 }
-                _ => unreachable!(),
+                _ => return Err(error::SceneParserError::InvalidDefineElementError.into()),
             }
         }
         Ok(())
     }
 
-    fn parse_shape(&mut self, kind: &str, shape_el: &yaml::Hash) -> Result<Box<dyn Shape>> {
-        let mut shape: Box<dyn Shape> = match kind {
-            "sphere" => Box::new(Sphere::default()),
-            "plane" => Box::new(Plane::default()),
-            "cube" => Box::new(Cube::default()),
-            _ => unreachable!(),
+    // the element with the names of constants swapped for their values, see
+    // substitute_constants
+    fn with_constants(&self, el: &Yaml) -> Yaml {
+        if self.scene.constants.is_empty() {
+            return el.clone();
+        }
+        substitute_constants(&self.scene.constants, el)
+    }
+
+    fn is_shape(&self, kind: &str) -> bool {
+        matches!(kind, "group" | "csg")
+            || self.scene.prefabs.contains_key(kind)
+            || self.registry.has_shape(kind)
+    }
+
+    // the prefab's shapes, built the first time they are added and shared by
+    // every later instance of them
+    fn shared_prefab(&mut self, name: &str, prefab: &yaml::Hash) -> Result<Arc<dyn Shape>> {
+        if let Some(shape) = self.scene.shared_prefabs.get(name) {
+            return Ok(shape.clone());
+        }
+        let shape: Arc<dyn Shape> =
+            Arc::from(self.parse_shape(prefab, &mut vec![name.to_string()])?);
+        self.scene
+            .shared_prefabs
+            .insert(name.to_string(), shape.clone());
+        Ok(shape)
+    }
+
+    // expanding holds the prefabs being built, so one that adds itself is an error
+    // rather than endless recursion. prefabs added straight to the scene are
+    // instances of one shared copy, unless the add changes what's inside it
+    fn parse_shape(
+        &mut self,
+        shape_el: &yaml::Hash,
+        expanding: &mut Vec<String>,
+    ) -> Result<Box<dyn Shape>> {
+        let kind = shape_el
+            .get(&ADD_KEY)
+            .and_then(|kind| kind.as_str())
+            .ok_or(error::SceneParserError::InvalidAddElementError)?;
+        let mut shape = if let Some(prefab) = self.scene.prefabs.get(kind).cloned() {
+            if expanding.iter().any(|name| name == kind) {
+                return Err(error::SceneParserError::RecursivePrefab(kind.to_string()).into());
+            }
+            let prefab = prefab
+                .as_hash()
+                .ok_or(error::SceneParserError::InvalidDefineElementError)?;
+            if expanding.is_empty() && !changes_inside(shape_el) {
+                Box::new(Instance::new(self.shared_prefab(kind, prefab)?))
+            } else {
+                expanding.push(kind.to_string());
+                let shape = self.parse_shape(prefab, expanding)?;
+                expanding.pop();
+                shape
+            }
+        } else if kind == "group" {
+            let mut group = Group::default();
+            if let Some(children_el) = shape_el.get(&CHILDREN_KEY) {
+                let children = children_el
+                    .as_vec()
+                    .ok_or_else(|| SceneParserError::ParseVecError("children".to_string()))?;
+                for child_el in children {
+                    let child_el = child_el
+                        .as_hash()
+                        .ok_or(error::SceneParserError::InvalidAddElementError)?;
+                    group.add_child(self.parse_shape(child_el, expanding)?);
+                }
+            }
+            Box::new(group)
+        } else if kind == "csg" {
+            let operation = match shape_el.get(&OPERATION_KEY).and_then(|op| op.as_str()) {
+                Some("union") => Operation::Union,
+                Some("intersection") => Operation::Intersection,
+                Some("difference") => Operation::Difference,
+                Some(op) => {
+                    return Err(error::SceneParserError::UnknownCsgOperation(op.to_string()).into())
+                }
+                None => {
+                    return Err(error::SceneParserError::MissingRequiredKey(
+                        "operation".to_string(),
+                    )
+                    .into())
+                }
+            };
+            let mut operand = |key: &Yaml, name: &str| -> Result<Box<dyn Shape>> {
+                let operand_el = shape_el
+                    .get(key)
+                    .ok_or_else(|| error::SceneParserError::MissingRequiredKey(name.to_string()))?
+                    .as_hash()
+                    .ok_or(error::SceneParserError::InvalidAddElementError)?;
+                self.parse_shape(operand_el, expanding)
+            };
+            let left = operand(&LEFT_KEY, "left")?;
+            let right = operand(&RIGHT_KEY, "right")?;
+            Box::new(Csg::with_operands(operation, left, right))
+        } else {
+            let factory = self
+                .registry
+                .shape(kind)
+                .ok_or(error::SceneParserError::InvalidAddElementError)?;
+            factory(shape_el, &mut self.context)?
         };
 
         if let Some(transform) = shape_el.get(&TRANSFORM_KEY) {
-            let transform = self.parse_transform(transform)?;
+            // applied on top of any transform the factory gave the shape
+            let transform = &self.parse_transform(transform)? * shape.transform();
             shape.set_transform(transform);
         }
 
@@ -202,12 +535,26 @@ impl SceneParser {
             shape.set_material(material);
         }
 
+        if let Some(tags_el) = shape_el.get(&TAGS_KEY) {
+            let tags = tags_el
+                .as_vec()
+                .ok_or_else(|| SceneParserError::ParseVecError("tags".to_string()))?;
+            for tag in tags {
+                let tag = tag
+                    .as_str()
+                    .ok_or_else(|| SceneParserError::ParseVecError("tags".to_string()))?;
+                shape.add_tag(tag);
+            }
+        }
+
+        parse_visibility(shape_el, shape.as_mut())?;
+
         println!("shape: {:?}", shape);
         Ok(shape)
     }
 
     // change this to return a MaterialBuilder so that it can be used with extends...
-    fn parse_material(&self, material_el: &Yaml) -> Result<Material> {
+    fn parse_material(&mut self, material_el: &Yaml) -> Result<Material> {
         if let Yaml::String(defined_material) = material_el {
             println!("found defined material: {:?}", defined_material);
             let material = self
@@ -227,7 +574,7 @@ impl SceneParser {
                 )?;
             }
             if let Some(pattern_el) = material_def.get(&MATERIAL_PATTERN_KEY) {
-                material.set_pattern(parse_pattern(pattern_el)?);
+                material.set_pattern(parse_pattern(&self.registry, &mut self.context, pattern_el)?);
             }
             if let Some(ambient_el) = material_def.get(&MATERIAL_AMBIENT_KEY) {
                 material.ambient = to_f64(ambient_el)?;
@@ -257,6 +604,42 @@ impl SceneParser {
                 material.refractive_index = to_f64(refractive_index_el)?;
             }
 
+            if let Some(fresnel_el) = material_def.get(&MATERIAL_FRESNEL_KEY) {
+                material.fresnel = fresnel_el
+                    .as_bool()
+                    .ok_or_else(|| SceneParserError::ParseBoolError("fresnel".to_string()))?;
+            }
+
+            if let Some(environment_el) = material_def.get(&MATERIAL_ENVIRONMENT_REFLECTIONS_KEY) {
+                material.environment_reflections = environment_el.as_bool().ok_or_else(|| {
+                    SceneParserError::ParseBoolError("environment-reflections".to_string())
+                })?;
+            }
+
+            if let Some(translucency_el) = material_def.get(&MATERIAL_TRANSLUCENCY_KEY) {
+                material.translucency = to_f64(translucency_el)?;
+            }
+
+            if let Some(roughness_el) = material_def.get(&MATERIAL_ROUGHNESS_KEY) {
+                material.roughness = to_f64(roughness_el)?;
+            }
+
+            if let Some(metallic_el) = material_def.get(&MATERIAL_METALLIC_KEY) {
+                material.metallic = to_f64(metallic_el)?;
+            }
+
+            if let Some(brdf_el) = material_def.get(&MATERIAL_BRDF_KEY) {
+                material.brdf = parse_brdf(brdf_el)?;
+            }
+
+            if let Some(displacement_el) = material_def.get(&MATERIAL_DISPLACEMENT_KEY) {
+                material.displacement = Some(parse_displacement(displacement_el)?);
+            }
+
+            if let Some(cutout_el) = material_def.get(&MATERIAL_CUTOUT_KEY) {
+                material.cutout = Some(parse_cutout(&self.registry, &mut self.context, cutout_el)?);
+            }
+
             println!("material: {:?}", material);
             Ok(material)
         } else {
@@ -264,7 +647,8 @@ impl SceneParser {
         }
     }
 
-    pub fn render(&mut self, output_filename: &Path) -> Result<()> {
+    // moves the parsed lights, shapes and sky into a world, the cameras stay
+    pub fn build_world(&mut self) -> World {
         let mut world = World::new();
         for light in self.scene.lights.drain(0..) {
             world.add_light(light);
@@ -272,18 +656,95 @@ impl SceneParser {
         for shape in self.scene.shapes.drain(0..) {
             world.add_boxed_object(shape);
         }
+        if let Some(sky) = self.scene.sky.take() {
+            world.set_sky(sky);
+        }
+        world
+    }
+
+    // a camera with the same name as an earlier one replaces it
+    fn add_camera(&mut self, name: Option<String>, camera: Camera) {
+        let existing = self
+            .scene
+            .cameras
+            .iter_mut()
+            .find(|(n, _)| name.is_some() && *n == name);
+        match existing {
+            Some((_, c)) => *c = camera,
+            None => self.scene.cameras.push((name, camera)),
+        }
+    }
 
-        let camera = self.scene.camera.as_mut().unwrap();
+    pub fn camera_names(&self) -> Vec<&str> {
+        self.scene
+            .cameras
+            .iter()
+            .filter_map(|(name, _)| name.as_deref())
+            .collect()
+    }
 
-        let canvas = camera.render(&world);
-        let exporter = raytracer::image::png::PngExporter {};
+    // where the named camera is in the scene's cameras, or the first one
+    // without a name
+    fn camera_index(&self, name: Option<&str>) -> Result<usize> {
+        let cameras = &self.scene.cameras;
+        match name {
+            Some(name) => Ok(cameras
+                .iter()
+                .position(|(n, _)| n.as_deref() == Some(name))
+                .ok_or_else(|| error::SceneParserError::UnknownCamera(name.to_string()))?),
+            None if cameras.is_empty() => Err(error::SceneParserError::MissingCamera.into()),
+            None => Ok(0),
+        }
+    }
+
+    fn camera_mut(&mut self, name: Option<&str>) -> Result<&mut Camera> {
+        let idx = self.camera_index(name)?;
+        let camera = &mut self.scene.cameras[idx];
+        if let Some(progress) = &self.progress {
+            camera.1.render_opts.progress(progress.clone());
+        }
+        Ok(&mut camera.1)
+    }
+
+    pub fn render(&mut self, output_filename: &Path, camera_name: Option<&str>) -> Result<()> {
+        // fail before the scene is moved into the world
+        self.camera_mut(camera_name)?;
+        let world = self.build_world();
+        let camera = self.camera_mut(camera_name)?;
+
+        let canvas = camera.render_parallel(&world);
+        let exporter = PngExporter::with_dither(self.dither);
 
         exporter.save(&canvas, output_filename)?;
         println!("scene saved to {}", output_filename.to_string_lossy());
         Ok(())
     }
 
-    fn extend_material(&self, mut material: Material, material_el: &Yaml) -> Result<Material> {
+    // renders only the pixels in x0..x1, y0..y1 into a smaller image
+    pub fn render_crop(
+        &mut self,
+        output_filename: &Path,
+        (x0, y0, x1, y1): (usize, usize, usize, usize),
+        camera_name: Option<&str>,
+    ) -> Result<()> {
+        self.camera_mut(camera_name)?;
+        let world = self.build_world();
+        let camera = self.camera_mut(camera_name)?;
+
+        let (canvas, (x, y)) = camera.render_region(&world, x0, y0, x1, y1);
+        let exporter = PngExporter::with_dither(self.dither);
+
+        exporter.save(&canvas, output_filename)?;
+        println!(
+            "crop at ({}, {}) saved to {}",
+            x,
+            y,
+            output_filename.to_string_lossy()
+        );
+        Ok(())
+    }
+
+    fn extend_material(&mut self, mut material: Material, material_el: &Yaml) -> Result<Material> {
         if let Yaml::Hash(material_def) = material_el {
             if let Some(color_el) = material_def.get(&MATERIAL_COLOR_KEY) {
                 material.color = to_color(
@@ -293,7 +754,7 @@ impl SceneParser {
                 )?;
             }
             if let Some(pattern_el) = material_def.get(&MATERIAL_PATTERN_KEY) {
-                material.set_pattern(parse_pattern(pattern_el)?);
+                material.set_pattern(parse_pattern(&self.registry, &mut self.context, pattern_el)?);
             }
             if let Some(ambient_el) = material_def.get(&MATERIAL_AMBIENT_KEY) {
                 material.ambient = to_f64(ambient_el)?;
@@ -323,6 +784,42 @@ impl SceneParser {
                 material.refractive_index = to_f64(refractive_index_el)?;
             }
 
+            if let Some(fresnel_el) = material_def.get(&MATERIAL_FRESNEL_KEY) {
+                material.fresnel = fresnel_el
+                    .as_bool()
+                    .ok_or_else(|| SceneParserError::ParseBoolError("fresnel".to_string()))?;
+            }
+
+            if let Some(environment_el) = material_def.get(&MATERIAL_ENVIRONMENT_REFLECTIONS_KEY) {
+                material.environment_reflections = environment_el.as_bool().ok_or_else(|| {
+                    SceneParserError::ParseBoolError("environment-reflections".to_string())
+                })?;
+            }
+
+            if let Some(translucency_el) = material_def.get(&MATERIAL_TRANSLUCENCY_KEY) {
+                material.translucency = to_f64(translucency_el)?;
+            }
+
+            if let Some(roughness_el) = material_def.get(&MATERIAL_ROUGHNESS_KEY) {
+                material.roughness = to_f64(roughness_el)?;
+            }
+
+            if let Some(metallic_el) = material_def.get(&MATERIAL_METALLIC_KEY) {
+                material.metallic = to_f64(metallic_el)?;
+            }
+
+            if let Some(brdf_el) = material_def.get(&MATERIAL_BRDF_KEY) {
+                material.brdf = parse_brdf(brdf_el)?;
+            }
+
+            if let Some(displacement_el) = material_def.get(&MATERIAL_DISPLACEMENT_KEY) {
+                material.displacement = Some(parse_displacement(displacement_el)?);
+            }
+
+            if let Some(cutout_el) = material_def.get(&MATERIAL_CUTOUT_KEY) {
+                material.cutout = Some(parse_cutout(&self.registry, &mut self.context, cutout_el)?);
+            }
+
             println!("material: {:?}", material);
             Ok(material)
         } else {
@@ -348,16 +845,32 @@ impl SceneParser {
 
     fn parse_transform_item(&mut self, transform_item_el: &Yaml) -> Result<Matrix> {
         if let Yaml::Array(transform) = transform_item_el {
-            let kind = transform[0]
-                .as_str()
+            let kind = transform
+                .first()
+                .and_then(|kind| kind.as_str())
                 .ok_or(error::SceneParserError::ParseTransformError)?;
+            if let Some(axis) = kind.strip_prefix("rotate-") {
+                let angle_el = transform
+                    .get(1)
+                    .ok_or(error::SceneParserError::ParseTransformError)?;
+                let (axis, angle) = match axis.strip_suffix("-deg") {
+                    Some(axis) => (axis, to_f64(angle_el)?.to_radians()),
+                    None => (axis, to_angle(angle_el)?),
+                };
+                return match axis {
+                    "x" => Ok(rotation_x(angle)),
+                    "y" => Ok(rotation_y(angle)),
+                    "z" => Ok(rotation_z(angle)),
+                    _ => Err(error::SceneParserError::ParseTransformError.into()),
+                };
+            }
+
             let args = to_float_vec(&transform[1..])?;
             match kind {
-                "scale" => Ok(scaling(args[0], args[1], args[2])),
-                "translate" => Ok(translation(args[0], args[1], args[2])),
-                "rotate-x" => Ok(rotation_x(args[0])),
-                "rotate-y" => Ok(rotation_y(args[0])),
-                "rotate-z" => Ok(rotation_z(args[0])),
+                "scale" if args.len() == 3 => Ok(scaling(args[0], args[1], args[2])),
+                "translate" if args.len() == 3 => Ok(translation(args[0], args[1], args[2])),
+                // 16 values, row by row
+                "matrix" if args.len() == 16 => Ok(Matrix::from_slice(4, 4, &args)),
                 _ => Err(error::SceneParserError::ParseTransformError.into()),
             }
         } else if let Yaml::String(defined_transform) = transform_item_el {
@@ -373,6 +886,80 @@ impl SceneParser {
     }
 }
 
+// keys whose string values name things rather than being values
+const NAMING_KEYS: &[&str] = &[
+    "add",
+    "define",
+    "extend",
+    "name",
+    "material",
+    "type",
+    "brdf",
+    "mode",
+    "tile-order",
+    "tags",
+    "file",
+    "mapping",
+    "operation",
+];
+
+// every string naming a constant becomes its value, except for the values of
+// NAMING_KEYS that aren't hashes, and the operations and named transforms of
+// transform lists
+fn substitute_constants(constants: &HashMap<String, Constant>, el: &Yaml) -> Yaml {
+    match el {
+        Yaml::String(name) => constants
+            .get(name)
+            .map_or_else(|| el.clone(), |constant| constant.to_yaml()),
+        Yaml::Array(items) => Yaml::Array(
+            items
+                .iter()
+                .map(|item| substitute_constants(constants, item))
+                .collect(),
+        ),
+        Yaml::Hash(hash) => Yaml::Hash(
+            hash.iter()
+                .map(|(key, value)| {
+                    let value = match (key.as_str(), value) {
+                        (Some(key), value)
+                            if NAMING_KEYS.contains(&key) && value.as_hash().is_none() =>
+                        {
+                            value.clone()
+                        }
+                        (Some("transform"), value) => substitute_in_transform(constants, value),
+                        _ => substitute_constants(constants, value),
+                    };
+                    (key.clone(), value)
+                })
+                .collect(),
+        ),
+        _ => el.clone(),
+    }
+}
+
+// only the arguments of the operations, [rotate-y, quarter] but not [scale, ..]
+// turning into a number when a constant is called scale
+fn substitute_in_transform(constants: &HashMap<String, Constant>, el: &Yaml) -> Yaml {
+    match el {
+        Yaml::Array(items) => Yaml::Array(
+            items
+                .iter()
+                .map(|item| match item {
+                    Yaml::Array(operation) if !operation.is_empty() => {
+                        let mut operation = operation.clone();
+                        for arg in &mut operation[1..] {
+                            *arg = substitute_constants(constants, arg);
+                        }
+                        Yaml::Array(operation)
+                    }
+                    _ => item.clone(),
+                })
+                .collect(),
+        ),
+        _ => el.clone(),
+    }
+}
+
 fn is_add_element(element: &Yaml) -> bool {
     if let Yaml::Hash(hash) = element {
         hash.contains_key(&ADD_KEY)
@@ -391,17 +978,35 @@ fn is_define_element(element: &Yaml) -> bool {
 
 fn parse_camera(camera_el: &yaml::Hash) -> Result<Camera> {
     println!("{:?}", camera_el);
-    let width = get_required_attribute(camera_el, "width".to_string())?
-        .as_i64()
-        .ok_or_else(|| SceneParserError::ParseIntError("width".to_string()))?;
-
-    let height = get_required_attribute(camera_el, "height".to_string())?
-        .as_i64()
-        .ok_or_else(|| SceneParserError::ParseIntError("height".to_string()))?;
-
-    let field_of_view = get_required_attribute(camera_el, "field-of-view".to_string())?
-        .as_f64()
-        .ok_or_else(|| SceneParserError::ParseFloatError("field-of-view".to_string()))?;
+    // a size has to be positive before it becomes a usize, or -1 wraps around
+    let size = |key: &str| -> Result<usize> {
+        match get_required_attribute(camera_el, key.to_string())?.as_i64() {
+            Some(n) if n > 0 => Ok(n as usize),
+            _ => Err(SceneParserError::ParseIntError(key.to_string()).into()),
+        }
+    };
+    let (width, height) = (size("width")?, size("height")?);
+    // a lens in millimeters, on a full frame sensor unless told otherwise, or
+    // the field of view
+    let mut camera = match camera_el.get(&CAMERA_FOCAL_LENGTH_KEY) {
+        Some(focal_el) => {
+            let sensor_width = match camera_el.get(&CAMERA_SENSOR_WIDTH_KEY) {
+                Some(width_el) => to_f64(width_el)?,
+                None => FULL_FRAME_SENSOR_WIDTH,
+            };
+            Camera::try_with_lens(width, height, to_f64(focal_el)?, sensor_width)?
+        }
+        None => {
+            let field_of_view = match camera_el.get(&CAMERA_FIELD_OF_VIEW_DEG_KEY) {
+                Some(fov_el) => to_f64(fov_el)?.to_radians(),
+                None => to_angle(get_required_attribute(
+                    camera_el,
+                    "field-of-view".to_string(),
+                )?)?,
+            };
+            Camera::try_new(width, height, field_of_view)?
+        }
+    };
 
     let from = to_point(
         get_required_attribute(camera_el, "from".to_string())?
@@ -422,14 +1027,113 @@ fn parse_camera(camera_el: &yaml::Hash) -> Result<Camera> {
     )?;
 
     println!("from: {:?}, to: {:?}, up: {:?}", from, to, up);
-    let mut camera = Camera::new(width as usize, height as usize, field_of_view);
-    camera.set_transform(view_transform(from, to, up));
+    camera.set_transform(try_view_transform(from, to, up)?);
+    if let Some(shift_el) = camera_el.get(&CAMERA_LENS_SHIFT_KEY) {
+        match shift_el.as_vec().map(|v| v.as_slice()) {
+            Some([h, v]) => camera.set_lens_shift(to_f64(h)?, to_f64(v)?),
+            _ => return Err(SceneParserError::ParseVecError("lens-shift".to_string()).into()),
+        }
+    }
+    if let Some(render_el) = camera_el.get(&CAMERA_RENDER_KEY) {
+        parse_render_opts(
+            &mut camera,
+            render_el
+                .as_hash()
+                .ok_or_else(|| SceneParserError::MissingRequiredKey("render".to_string()))?,
+        )?;
+    }
 
     println!("camera: {:?}", camera);
     Ok(camera)
 }
 
-fn parse_light(light_el: &yaml::Hash) -> Result<PointLight> {
+// threads: n, tile-size: n, tile-order: scanline | hilbert | spiral,
+// auto-exposure: true | { percentile: p, target: t }
+fn parse_render_opts(camera: &mut Camera, render_el: &yaml::Hash) -> Result<()> {
+    let positive = |key: &Yaml| -> Result<Option<usize>> {
+        match render_el.get(key) {
+            None => Ok(None),
+            Some(el) => match el.as_i64() {
+                Some(n) if n > 0 => Ok(Some(n as usize)),
+                _ => Err(SceneParserError::ParseIntError(key_name(key)).into()),
+            },
+        }
+    };
+    if let Some(threads) = positive(&RENDER_THREADS_KEY)? {
+        camera.render_opts.num_threads(threads);
+    }
+    if let Some(size) = positive(&RENDER_TILE_SIZE_KEY)? {
+        camera.render_opts.tile_size(size);
+    }
+    if let Some(order_el) = render_el.get(&RENDER_TILE_ORDER_KEY) {
+        camera.render_opts.tile_order(match order_el.as_str() {
+            Some("scanline") => TileOrder::Scanline,
+            Some("hilbert") => TileOrder::Hilbert,
+            Some("spiral") => TileOrder::Spiral,
+            _ => return Err(SceneParserError::UnknownTileOrder(format!("{:?}", order_el)).into()),
+        });
+    }
+    // time-budget is in seconds, a ray-budget is checked after it
+    if let Some(time_el) = render_el.get(&RENDER_TIME_BUDGET_KEY) {
+        let seconds = to_f64(time_el)?;
+        if !(seconds >= 0.0 && seconds.is_finite()) {
+            return Err(SceneParserError::ParseFloatError("time-budget".to_string()).into());
+        }
+        camera
+            .render_opts
+            .render_budget(RenderBudget::Time(Duration::from_secs_f64(seconds)));
+    }
+    if let Some(rays) = positive(&RENDER_RAY_BUDGET_KEY)? {
+        camera.render_opts.render_budget(RenderBudget::Rays(rays));
+    }
+    if let Some(mode_el) = render_el.get(&RENDER_MODE_KEY) {
+        camera.render_opts.render_mode(match mode_el.as_str() {
+            Some("full") => RenderMode::Full,
+            Some("clay") => RenderMode::Clay,
+            _ => return Err(SceneParserError::UnknownRenderMode(format!("{:?}", mode_el)).into()),
+        });
+    }
+    if let Some(environment_el) = render_el.get(&RENDER_ENVIRONMENT_REFLECTIONS_KEY) {
+        camera
+            .render_opts
+            .environment_reflections(environment_el.as_bool().ok_or_else(|| {
+                SceneParserError::ParseBoolError("environment-reflections".to_string())
+            })?);
+    }
+    if let Some(exposure_el) = render_el.get(&RENDER_AUTO_EXPOSURE_KEY) {
+        let exposure = match exposure_el {
+            Yaml::Boolean(false) => None,
+            Yaml::Boolean(true) => Some(AutoExposure::default()),
+            Yaml::Hash(exposure) => {
+                let default = AutoExposure::default();
+                let value = |key: &Yaml, default: f64| match exposure.get(key) {
+                    Some(el) => to_f64(el),
+                    None => Ok(default),
+                };
+                let percentile = value(&EXPOSURE_PERCENTILE_KEY, default.percentile())?;
+                let target = value(&EXPOSURE_TARGET_KEY, default.target())?;
+                if !(0.0..=1.0).contains(&percentile) || target <= 0.0 {
+                    return Err(
+                        SceneParserError::ParseFloatError("auto-exposure".to_string()).into(),
+                    );
+                }
+                Some(AutoExposure::new(percentile, target))
+            }
+            _ => return Err(SceneParserError::ParseBoolError("auto-exposure".to_string()).into()),
+        };
+        if let Some(exposure) = exposure {
+            camera.render_opts.auto_exposure(exposure);
+        }
+    }
+    Ok(())
+}
+
+// a point light, or an area light approximated by a grid of point lights.
+// either can be a spotlight
+fn parse_light(light_el: &yaml::Hash) -> Result<Vec<Light>> {
+    if light_el.contains_key(&LIGHT_CORNER_KEY) {
+        return parse_area_light(light_el);
+    }
     let at = to_point(
         get_required_attribute(light_el, "at".to_string())?
             .as_vec()
@@ -440,24 +1144,239 @@ fn parse_light(light_el: &yaml::Hash) -> Result<PointLight> {
             .as_vec()
             .ok_or_else(|| SceneParserError::ParseVecError("from".to_string()))?,
     )?;
-    let light = PointLight::new(at, intensity);
+    let mut light = PointLight::new(at, intensity);
+    parse_light_shadows(light_el, &mut light)?;
+    let light = parse_light_type(light_el, light)?;
     println!("light: {:?}", light);
-    Ok(light)
+    Ok(vec![light])
+}
+
+// one light at the center of every cell, splitting the intensity between them.
+// jitter is accepted but ignored, the grid is always regular
+fn parse_area_light(light_el: &yaml::Hash) -> Result<Vec<Light>> {
+    let vec_attribute = |key: &str| -> Result<&Vec<Yaml>> {
+        get_required_attribute(light_el, key.to_string())?
+            .as_vec()
+            .ok_or_else(|| SceneParserError::ParseVecError(key.to_string()).into())
+    };
+    let steps_attribute = |key: &str| -> Result<usize> {
+        match get_required_attribute(light_el, key.to_string())?.as_i64() {
+            Some(steps) if steps > 0 => Ok(steps as usize),
+            _ => Err(SceneParserError::ParseIntError(key.to_string()).into()),
+        }
+    };
+    let corner = to_point(vec_attribute("corner")?)?;
+    let uvec = to_vector(vec_attribute("uvec")?)?;
+    let vvec = to_vector(vec_attribute("vvec")?)?;
+    let usteps = steps_attribute("usteps")?;
+    let vsteps = steps_attribute("vsteps")?;
+    let intensity = to_color(vec_attribute("intensity")?)? * (1.0 / (usteps * vsteps) as f64);
+
+    let mut lights = Vec::with_capacity(usteps * vsteps);
+    for v in 0..vsteps {
+        for u in 0..usteps {
+            let at = corner
+                + uvec * ((u as f64 + 0.5) / usteps as f64)
+                + vvec * ((v as f64 + 0.5) / vsteps as f64);
+            let mut light = PointLight::new(at, intensity);
+            parse_light_shadows(light_el, &mut light)?;
+            lights.push(parse_light_type(light_el, light)?);
+        }
+    }
+    println!("area light: {} lights", lights.len());
+    Ok(lights)
+}
+
+// shadow: false keeps the shape from casting shadows, visible: false hides it
+// from the camera and reflection-visible: false from reflections and
+// refractions. shapes keep their visibility for the keys left out
+fn parse_visibility(shape_el: &yaml::Hash, shape: &mut dyn Shape) -> Result<()> {
+    let flag = |key: &Yaml| -> Result<Option<bool>> {
+        match shape_el.get(key) {
+            Some(el) => match el.as_bool() {
+                Some(flag) => Ok(Some(flag)),
+                None => Err(SceneParserError::ParseBoolError(key_name(key)).into()),
+            },
+            None => Ok(None),
+        }
+    };
+    // groups and csgs hand the flags that are set down to their shapes, which
+    // keep the others
+    for (key, kind) in [
+        (&*SHADOW_KEY, RayKind::Shadow),
+        (&*VISIBLE_KEY, RayKind::Camera),
+        (&*REFLECTION_VISIBLE_KEY, RayKind::Secondary),
+    ] {
+        if let Some(visible) = flag(key)? {
+            shape.set_visible_to(kind, visible);
+        }
+    }
+    Ok(())
+}
+
+// whether an add element sets things that groups hand down to their shapes.
+// an instance doesn't hand them down the same way, so such adds of a prefab
+// get a copy of it
+fn changes_inside(shape_el: &yaml::Hash) -> bool {
+    shape_el.contains_key(&MATERIAL_KEY)
+        || shape_el.contains_key(&TAGS_KEY)
+        || [&*SHADOW_KEY, &*VISIBLE_KEY, &*REFLECTION_VISIBLE_KEY]
+            .iter()
+            .any(|key| shape_el.contains_key(key))
+}
+
+// shadow-fill, shadow-softness and shadow-color, all optional
+fn parse_light_shadows(light_el: &yaml::Hash, light: &mut PointLight) -> Result<()> {
+    if let Some(fill_el) = light_el.get(&LIGHT_SHADOW_FILL_KEY) {
+        light.set_shadow_fill(to_f64(fill_el)?);
+    }
+    if let Some(softness_el) = light_el.get(&LIGHT_SHADOW_SOFTNESS_KEY) {
+        light.set_shadow_softness(to_f64(softness_el)?);
+    }
+    if let Some(color_el) = light_el.get(&LIGHT_SHADOW_COLOR_KEY) {
+        light.set_shadow_color(to_color(
+            color_el
+                .as_vec()
+                .ok_or_else(|| SceneParserError::ParseVecError("shadow-color".to_string()))?,
+        )?);
+    }
+    Ok(())
+}
+
+// type: point | spot, spotlights shine along direction, or towards to, and
+// fade from inner-angle to outer-angle off it. inner-angle defaults to
+// outer-angle, a hard edge
+fn parse_light_type(light_el: &yaml::Hash, light: PointLight) -> Result<Light> {
+    let type_el = match light_el.get(&LIGHT_TYPE_KEY) {
+        Some(type_el) => type_el,
+        None => return Ok(light.into()),
+    };
+    match type_el.as_str() {
+        Some("point") => return Ok(light.into()),
+        Some("spot") => {}
+        _ => return Err(SceneParserError::UnknownLightType(format!("{:?}", type_el)).into()),
+    }
+    let vec_attribute = |key: &Yaml| -> Result<Option<&Vec<Yaml>>> {
+        match light_el.get(key) {
+            Some(el) => {
+                Ok(Some(el.as_vec().ok_or_else(|| {
+                    SceneParserError::ParseVecError(key_name(key))
+                })?))
+            }
+            None => Ok(None),
+        }
+    };
+    let direction = match (
+        vec_attribute(&LIGHT_DIRECTION_KEY)?,
+        vec_attribute(&LIGHT_TO_KEY)?,
+    ) {
+        (Some(direction), _) => to_vector(direction)?,
+        (None, Some(to)) => to_point(to)? - light.position(),
+        (None, None) => {
+            return Err(SceneParserError::MissingRequiredKey("direction".to_string()).into())
+        }
+    };
+    let outer = to_angle(get_required_attribute(light_el, "outer-angle".to_string())?)?;
+    let inner = match light_el.get(&LIGHT_INNER_ANGLE_KEY) {
+        Some(inner_el) => to_angle(inner_el)?,
+        None => outer,
+    };
+    Ok(SpotLight::new(light, direction, inner, outer).into())
+}
+
+fn parse_sky(sky_el: &yaml::Hash) -> Result<(Sky, bool)> {
+    let elevation = to_f64(get_required_attribute(sky_el, "elevation".to_string())?)?;
+    let azimuth = match sky_el.get(&SKY_AZIMUTH_KEY) {
+        Some(azimuth_el) => to_f64(azimuth_el)?,
+        None => 0.0,
+    };
+    let turbidity = match sky_el.get(&SKY_TURBIDITY_KEY) {
+        Some(turbidity_el) => to_f64(turbidity_el)?,
+        None => 2.5,
+    };
+    let sun = match sky_el.get(&SKY_SUN_KEY) {
+        Some(sun_el) => sun_el
+            .as_bool()
+            .ok_or_else(|| SceneParserError::ParseBoolError("sun".to_string()))?,
+        None => true,
+    };
+
+    let mut sky = Sky::new(elevation, azimuth, turbidity);
+    if let Some(intensity_el) = sky_el.get(&SKY_INTENSITY_KEY) {
+        sky.intensity(to_f64(intensity_el)?);
+    }
+    Ok((sky, sun))
+}
+
+fn parse_brdf(brdf_el: &Yaml) -> Result<BrdfModel> {
+    match brdf_el.as_str() {
+        Some("phong") => Ok(BrdfModel::Phong),
+        Some("blinn-phong") => Ok(BrdfModel::BlinnPhong),
+        Some("oren-nayar") => Ok(BrdfModel::OrenNayar),
+        Some("cook-torrance") => Ok(BrdfModel::CookTorrance),
+        _ => Err(error::SceneParserError::ParseMaterialError.into()),
+    }
+}
+
+// { amplitude: a, frequency: f }, frequency defaults to 1
+fn parse_displacement(displacement_el: &Yaml) -> Result<Displacement> {
+    let displacement = displacement_el
+        .as_hash()
+        .ok_or(error::SceneParserError::ParseMaterialError)?;
+    let amplitude = to_f64(get_required_attribute(
+        displacement,
+        "amplitude".to_string(),
+    )?)?;
+    let frequency = match displacement.get(&DISPLACEMENT_FREQUENCY_KEY) {
+        Some(frequency_el) => to_f64(frequency_el)?,
+        None => 1.0,
+    };
+    Ok(Displacement {
+        amplitude,
+        frequency,
+    })
 }
 
-fn parse_pattern(pattern_el: &Yaml) -> Result<Pattern> {
+// { pattern: { ... }, threshold: t }, threshold defaults to 0.5
+fn parse_cutout(
+    registry: &Registry,
+    context: &mut ParseContext,
+    cutout_el: &Yaml,
+) -> Result<Cutout> {
+    let cutout = cutout_el
+        .as_hash()
+        .ok_or(error::SceneParserError::ParseMaterialError)?;
+    let pattern = parse_pattern(
+        registry,
+        context,
+        get_required_attribute(cutout, "pattern".to_string())?,
+    )?;
+    let threshold = match cutout.get(&THRESHOLD_KEY) {
+        Some(threshold_el) => to_f64(threshold_el)?,
+        None => 0.5,
+    };
+    Ok(Cutout::new(pattern, threshold))
+}
+
+fn parse_pattern(
+    registry: &Registry,
+    context: &mut ParseContext,
+    pattern_el: &Yaml,
+) -> Result<Pattern> {
     if let Yaml::Hash(pattern_def) = pattern_el {
         let kind = pattern_def
             .get(&PATTERN_TYPE_KEY)
             .ok_or(error::SceneParserError::ParsePatternError)?
             .as_str()
             .ok_or(error::SceneParserError::ParsePatternError)?;
-        let colors_el = pattern_def
-            .get(&PATTERN_COLORS_KEY)
-            .ok_or_else(|| anyhow::Error::from(error::SceneParserError::ParsePatternError))?;
-        let color_defs = colors_el
-            .as_vec()
-            .ok_or(error::SceneParserError::ParsePatternError)?;
+        // patterns like images don't list any colors
+        let color_defs = match pattern_def.get(&PATTERN_COLORS_KEY) {
+            Some(colors_el) => colors_el
+                .as_vec()
+                .ok_or(error::SceneParserError::ParsePatternError)?
+                .clone(),
+            None => vec![],
+        };
 
         let colors = color_defs
             .iter()
@@ -471,17 +1390,52 @@ fn parse_pattern(pattern_el: &Yaml) -> Result<Pattern> {
             .map(|&color_vec| to_color(color_vec))
             .collect::<Result<Vec<_>>>()?;
 
-        let pattern = match kind {
-            "stripes" => stripe_pattern(colors[0], colors[1]),
-            "checkers" => checkers_pattern(colors[0], colors[1]),
-            _ => Pattern::default(),
+        let mut pattern = match registry.pattern(kind) {
+            Some(factory) => factory(pattern_def, &colors, context)?,
+            None => {
+                println!("unhandled pattern: {}", kind);
+                Pattern::default()
+            }
         };
+        if let Some(extent_el) = pattern_def.get(&PATTERN_EXTENT_KEY) {
+            pattern.set_extent(parse_pattern_extent(extent_el)?);
+        }
         Ok(pattern)
     } else {
         Err(error::SceneParserError::ParsePatternError.into())
     }
 }
 
+// { x: [min, max], z: [min, max] }, axes left out repeat forever
+fn parse_pattern_extent(extent_el: &Yaml) -> Result<BoundingBox> {
+    let extent_def = extent_el
+        .as_hash()
+        .ok_or(error::SceneParserError::ParsePatternError)?;
+    let axis = |key: &Yaml| -> Result<(f64, f64)> {
+        match extent_def.get(key) {
+            Some(Yaml::Array(range)) if range.len() == 2 => {
+                Ok((to_f64(&range[0])?, to_f64(&range[1])?))
+            }
+            Some(_) => Err(error::SceneParserError::ParsePatternError.into()),
+            None => Ok((f64::NEG_INFINITY, f64::INFINITY)),
+        }
+    };
+    let ((x0, x1), (y0, y1), (z0, z1)) = (
+        axis(&EXTENT_X_KEY)?,
+        axis(&EXTENT_Y_KEY)?,
+        axis(&EXTENT_Z_KEY)?,
+    );
+    Ok(BoundingBox::new(
+        Point::new(x0, y0, z0),
+        Point::new(x1, y1, z1),
+    ))
+}
+
+// the string a key constant holds, for error messages
+fn key_name(key: &Yaml) -> String {
+    key.as_str().unwrap_or_default().to_string()
+}
+
 fn get_required_attribute(hash: &yaml::Hash, key: String) -> Result<&Yaml> {
     Ok(hash
         .get(&Yaml::String(key.clone()))
@@ -499,6 +1453,20 @@ fn to_f64(f: &Yaml) -> Result<f64> {
     }
 }
 
+// angles are radians, unless given as { deg: value }
+fn to_angle(angle_el: &Yaml) -> Result<f64> {
+    if let Yaml::Hash(hash) = angle_el {
+        if let Some(deg_el) = hash.get(&ANGLE_DEG_KEY) {
+            return Ok(to_f64(deg_el)?.to_radians());
+        }
+        if let Some(rad_el) = hash.get(&ANGLE_RAD_KEY) {
+            return to_f64(rad_el);
+        }
+        return Err(error::SceneParserError::ParseFloatError(String::from("angle")).into());
+    }
+    to_f64(angle_el)
+}
+
 fn to_float_vec(v: &[Yaml]) -> Result<Vec<f64>> {
     let res = v.iter().map(to_f64).collect::<Result<Vec<_>>>();
     res
@@ -533,6 +1501,13 @@ fn to_color(v: &[Yaml]) -> Result<Color> {
 
 #[cfg(test)]
 mod tests {
+    use std::f64::consts::PI;
+
+    use raytracer::{
+        camera::CameraError, canvas::Canvas, geometry::shape::Sphere, ray::Ray,
+        transform::ViewError,
+    };
+
     use super::*;
     #[test]
     fn test_load_file() {
@@ -541,7 +1516,7 @@ mod tests {
         let res = p.load_file(file);
         println!("res: {:?}", res);
         assert!(res.is_ok());
-        assert!(p.scene.camera.is_some());
+        assert_eq!(p.scene.cameras.len(), 1);
         assert_eq!(p.scene.lights.len(), 1);
         assert_eq!(p.scene.shapes.len(), 13);
         assert_eq!(p.scene.materials.len(), 1);
@@ -558,6 +1533,917 @@ mod tests {
         assert!(res.is_ok());
     }
 
+    #[test]
+    fn test_parse_render_block() {
+        let camera =
+            "add: camera\nwidth: 10\nheight: 10\nfield-of-view: 1\nfrom: [0, 0, -5]\nto: [0, 0, 0]\nup: [0, 1, 0]\n";
+        let parse = |render: &str| {
+            let el = &YamlLoader::load_from_str(&format!("{}{}", camera, render)).unwrap()[0];
+            parse_camera(el.as_hash().unwrap())
+        };
+        let c = parse("render: { threads: 3, tile-size: 16, tile-order: spiral }").unwrap();
+        assert_eq!(c.render_opts.threads(), 3);
+        assert_eq!(c.render_opts.tiles(), Some((16, TileOrder::Spiral)));
+        assert_eq!(parse("").unwrap().render_opts.tiles(), None);
+        assert!(!parse("").unwrap().render_opts.get_environment_reflections());
+        let c = parse("render: { environment-reflections: true }").unwrap();
+        assert!(c.render_opts.get_environment_reflections());
+        assert!(parse("render: { environment-reflections: 1 }").is_err());
+        assert_eq!(
+            parse("").unwrap().render_opts.get_render_mode(),
+            RenderMode::Full
+        );
+        let c = parse("render: { mode: clay }").unwrap();
+        assert_eq!(c.render_opts.get_render_mode(), RenderMode::Clay);
+        assert!(parse("render: { mode: wireframe }").is_err());
+
+        let c = parse("render: { auto-exposure: true }").unwrap();
+        assert_eq!(
+            c.render_opts.get_auto_exposure(),
+            Some(AutoExposure::default())
+        );
+        let c = parse("render: { auto-exposure: { percentile: 0.9 } }").unwrap();
+        assert_eq!(
+            c.render_opts.get_auto_exposure(),
+            Some(AutoExposure::new(0.9, 0.18))
+        );
+        assert!(parse("render: { auto-exposure: { percentile: 2 } }").is_err());
+
+        let c = parse("render: { time-budget: 2.5 }").unwrap();
+        assert_eq!(
+            c.render_opts.get_render_budget(),
+            Some(RenderBudget::Time(Duration::from_millis(2500)))
+        );
+        let c = parse("render: { ray-budget: 1000 }").unwrap();
+        assert_eq!(
+            c.render_opts.get_render_budget(),
+            Some(RenderBudget::Rays(1000))
+        );
+        assert_eq!(parse("").unwrap().render_opts.get_render_budget(), None);
+        assert!(parse("render: { time-budget: -1 }").is_err());
+        assert!(parse("render: { ray-budget: 0 }").is_err());
+
+        assert!(parse("render: { tile-order: zigzag }").is_err());
+        assert!(parse("render: { threads: 0 }").is_err());
+        assert!(parse("render: 4").is_err());
+    }
+
+    #[test]
+    fn test_parse_area_light() {
+        let el = &YamlLoader::load_from_str(
+            "add: light\ncorner: [-1, 2, 4]\nuvec: [2, 0, 0]\nvvec: [0, 2, 0]\nusteps: 2\nvsteps: 2\njitter: true\nintensity: [1, 1, 1]",
+        )
+        .unwrap()[0];
+        let lights = parse_light(el.as_hash().unwrap()).unwrap();
+        assert_eq!(lights.len(), 4);
+        assert_eq!(lights[0].position(), Point::new(-0.5, 2.5, 4.0));
+        assert_eq!(lights[3].position(), Point::new(0.5, 3.5, 4.0));
+        assert_eq!(lights[0].intensity(), Color::new(0.25, 0.25, 0.25));
+
+        let el = &YamlLoader::load_from_str(
+            "add: light\ncorner: [0, 0, 0]\nuvec: [1, 0, 0]\nvvec: [0, 1, 0]\nusteps: 0\nvsteps: 1\nintensity: [1, 1, 1]",
+        )
+        .unwrap()[0];
+        assert!(parse_light(el.as_hash().unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_parse_light_shadows() {
+        let el = &YamlLoader::load_from_str(
+            "add: light\nat: [0, 10, 0]\nintensity: [1, 1, 1]\nshadow-softness: 0.5\nshadow-color: [0.2, 0.1, 0.3]",
+        )
+        .unwrap()[0];
+        let lights = parse_light(el.as_hash().unwrap()).unwrap();
+        assert_eq!(lights[0].shadow_softness(), 0.5);
+        assert_eq!(lights[0].shadow_color(), Some(Color::new(0.2, 0.1, 0.3)));
+        assert_eq!(lights[0].shadow_fill(), None);
+
+        let el = &YamlLoader::load_from_str(
+            "add: light\nat: [0, 10, 0]\nintensity: [1, 1, 1]\nshadow-color: 0.5",
+        )
+        .unwrap()[0];
+        assert!(parse_light(el.as_hash().unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_parse_visibility_flags() {
+        let mut p = SceneParser::new();
+        p.parse_str(
+            "
+- add: plane
+  shadow: false
+- add: sphere
+  visible: false
+  reflection-visible: false
+- add: group
+  shadow: false
+  children:
+    - add: sphere
+      visible: false
+    - add: cube
+",
+        )
+        .unwrap();
+        let shapes = p.scene().shapes();
+        let floor = shapes[0].visibility();
+        assert!(!floor.shadow && floor.camera && floor.secondary);
+        let hidden = shapes[1].visibility();
+        assert!(hidden.shadow && !hidden.camera && !hidden.secondary);
+        // the group's flags are added to those of its children
+        let mut children = vec![];
+        shapes[2].for_each_child(&mut |child| children.push(child.visibility()));
+        assert!(children.iter().all(|child| !child.shadow && child.secondary));
+        assert!(!children[0].camera && children[1].camera);
+
+        assert!(p.parse_str("- add: sphere\n  shadow: no shadow").is_err());
+    }
+
+    #[test]
+    fn test_parse_csg() {
+        let mut p = SceneParser::new();
+        p.parse_str(
+            "
+- add: csg
+  operation: difference
+  left:
+    add: csg
+    operation: intersection
+    left:
+      add: sphere
+      transform:
+        - [scale, 1.3, 1.3, 1.3]
+    right:
+      add: cube
+  right:
+    add: sphere
+    transform:
+      - [scale, 0.5, 0.5, 0.5]
+  transform:
+    - [translate, 0, 0, 1]
+",
+        )
+        .unwrap();
+        let shape = &p.scene().shapes()[0];
+        let csg = shape.as_any().downcast_ref::<Csg>().unwrap();
+        assert_eq!(csg.operation(), &Operation::Difference);
+        let inner = csg.left.as_any().downcast_ref::<Csg>().unwrap();
+        assert_eq!(inner.operation(), &Operation::Intersection);
+
+        let r = Ray::new(Point::new(0, 0, -5), Vector::new(0, 0, 1));
+        let ts: Vec<f64> = shape.intersect(&r).iter().map(|i| i.t()).collect();
+        assert_eq!(ts, vec![5.0, 5.5, 6.5, 7.0]);
+
+        for el in &[
+            "- add: csg\n  left: { add: sphere }\n  right: { add: cube }",
+            "- add: csg\n  operation: xor\n  left: { add: sphere }\n  right: { add: cube }",
+            "- add: csg\n  operation: union\n  left: { add: sphere }",
+            "- add: csg\n  operation: union\n  left: sphere\n  right: { add: cube }",
+        ] {
+            assert!(SceneParser::new().parse_str(el).is_err(), "{}", el);
+        }
+    }
+
+    #[test]
+    fn test_parse_csg_visibility() {
+        let mut p = SceneParser::new();
+        p.parse_str(
+            "
+- add: light
+  at: [0, 10, 0]
+  intensity: [1, 1, 1]
+- add: csg
+  operation: union
+  shadow: false
+  left:
+    add: sphere
+    visible: false
+  right:
+    add: cube
+",
+        )
+        .unwrap();
+        let csg = p.scene().shapes()[0].as_any().downcast_ref::<Csg>().unwrap();
+        let (left, right) = (csg.left.visibility(), csg.right.visibility());
+        assert!(!left.shadow && !left.camera && left.secondary);
+        assert!(!right.shadow && right.camera);
+
+        let world = p.build_world();
+        assert!(!world.is_shadowed(Point::new(0, -5, 0), &world.lights()[0]));
+    }
+
+    #[test]
+    fn test_parse_obj() {
+        fn leaf_colors(shape: &dyn Shape, colors: &mut Vec<Color>) {
+            if shape.as_any().is::<Group>() {
+                shape.for_each_child(&mut |child| leaf_colors(child, colors));
+            } else {
+                colors.push(shape.material().color);
+            }
+        }
+        let file = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../raytracer/src/obj_parser/test_data/materials.obj"
+        );
+
+        let mut p = SceneParser::new();
+        p.parse_str(&format!(
+            "
+- add: obj
+  file: {:?}
+  threshold: 2
+  transform:
+    - [translate, 0, 0, 5]
+- add: obj
+  file: {:?}
+  material:
+    color: [0, 1, 0]
+",
+            file, file
+        ))
+        .unwrap();
+        let shapes = p.scene().shapes();
+
+        let mut colors = vec![];
+        leaf_colors(shapes[0].as_ref(), &mut colors);
+        assert_eq!(colors.len(), 6);
+        let red = colors
+            .iter()
+            .filter(|&&c| c == Color::new(1.0, 0.0, 0.0))
+            .count();
+        assert_eq!(red, 3);
+        assert!(colors.contains(&Color::new(0.0, 0.0, 1.0)));
+        assert_eq!(shapes[0].get_bounds().get_min().z, 5.0);
+
+        // the element's material replaces those of the mtllib
+        let mut colors = vec![];
+        leaf_colors(shapes[1].as_ref(), &mut colors);
+        assert!(colors.iter().all(|&c| c == Color::new(0.0, 1.0, 0.0)));
+
+        for el in &[
+            "- add: obj",
+            "- add: obj\n  file: no/such/model.obj",
+            "- add: obj\n  file: model.obj\n  threshold: 0",
+        ] {
+            assert!(SceneParser::new().parse_str(el).is_err(), "{}", el);
+        }
+    }
+
+    #[test]
+    fn test_parse_spotlight() {
+        let parse = |extra: &str| {
+            let el = &YamlLoader::load_from_str(&format!(
+                "add: light\nat: [0, 10, 0]\nintensity: [1, 1, 1]\n{}",
+                extra
+            ))
+            .unwrap()[0];
+            parse_light(el.as_hash().unwrap())
+        };
+        let lights =
+            parse("type: spot\nto: [0, 0, 0]\ninner-angle: { deg: 20 }\nouter-angle: 0.5").unwrap();
+        let Light::Spot(spot) = &lights[0] else {
+            panic!("not a spotlight: {:?}", lights[0]);
+        };
+        assert_eq!(spot.direction(), Vector::new(0, -1, 0));
+        assert!((spot.inner() - 20f64.to_radians()).abs() < 1e-9);
+        assert_eq!(spot.outer(), 0.5);
+
+        let lights = parse("type: spot\ndirection: [1, 0, 0]\nouter-angle: 0.5").unwrap();
+        assert!(matches!(&lights[0], Light::Spot(spot) if spot.inner() == 0.5));
+        assert!(matches!(parse("type: point").unwrap()[0], Light::Point(_)));
+        assert!(parse("type: spot\nouter-angle: 0.5").is_err());
+        assert!(parse("type: spot\ndirection: [1, 0, 0]").is_err());
+        assert!(parse("type: laser").is_err());
+    }
+
+    #[test]
+    fn test_parse_sky() {
+        let el = &YamlLoader::load_from_str(
+            "add: sky\nelevation: 0.5\nazimuth: 1\nturbidity: 3.0\nsun: false",
+        )
+        .unwrap()[0];
+        let (sky, sun) = parse_sky(el.as_hash().unwrap()).unwrap();
+        assert_eq!(sky, Sky::new(0.5, 1.0, 3.0));
+        assert!(!sun);
+
+        let el = &YamlLoader::load_from_str("add: sky").unwrap()[0];
+        assert!(parse_sky(el.as_hash().unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_add_sky_with_sun() {
+        let el = &YamlLoader::load_from_str("add: sky\nelevation: 0.5").unwrap()[0];
+        let mut p = SceneParser::new();
+        p.parse_add_element(el).unwrap();
+        assert!(p.scene.sky.is_some());
+        assert_eq!(p.scene.lights.len(), 1);
+    }
+
+    #[test]
+    fn test_parse_matrix_transform() {
+        let mut p = SceneParser::new();
+        let el = &YamlLoader::load_from_str(
+            "[[matrix, 1, 0, 0, 1, 0, 2, 0, 2, 0, 0, 3, 3, 0, 0, 0, 1], [translate, 1, 0, 0]]",
+        )
+        .unwrap()[0];
+        let transform = p.parse_transform(el).unwrap();
+        assert_eq!(transform, &translation(2, 2, 3) * &scaling(1, 2, 3));
+
+        let el = &YamlLoader::load_from_str("[[matrix, 1, 0, 0]]").unwrap()[0];
+        assert!(p.parse_transform(el).is_err());
+    }
+
+    #[test]
+    fn test_parse_rotation_in_degrees() {
+        let mut p = SceneParser::new();
+        let expected = rotation_y(PI / 2.0);
+        for transform in &[
+            "[[rotate-y, 1.5707963267948966]]",
+            "[[rotate-y, { deg: 90 }]]",
+            "[[rotate-y, { rad: 1.5707963267948966 }]]",
+            "[[rotate-y-deg, 90]]",
+        ] {
+            let el = &YamlLoader::load_from_str(transform).unwrap()[0];
+            assert_eq!(p.parse_transform(el).unwrap(), expected);
+        }
+
+        let el = &YamlLoader::load_from_str("[[rotate-w, 1]]").unwrap()[0];
+        assert!(p.parse_transform(el).is_err());
+    }
+
+    #[test]
+    fn test_parse_camera_fov_in_degrees() {
+        let camera =
+            "add: camera\nwidth: 10\nheight: 10\nfrom: [0, 0, -5]\nto: [0, 0, 0]\nup: [0, 1, 0]\n";
+        for fov in &["field-of-view: { deg: 60 }", "field-of-view-deg: 60"] {
+            let el = &YamlLoader::load_from_str(&format!("{}{}", camera, fov)).unwrap()[0];
+            let c = parse_camera(el.as_hash().unwrap()).unwrap();
+            assert!((c.field_of_view() - PI / 3.0).abs() < 1e-9);
+        }
+
+        let el = &YamlLoader::load_from_str(&format!("{}field-of-view: 1", camera)).unwrap()[0];
+        let c = parse_camera(el.as_hash().unwrap()).unwrap();
+        assert_eq!(c.field_of_view(), 1.0);
+        assert_eq!(c.lens_shift(), (0.0, 0.0));
+    }
+
+    #[test]
+    fn test_parse_degenerate_camera() {
+        let parse = |camera: &str| {
+            let el = &YamlLoader::load_from_str(camera).unwrap()[0];
+            parse_camera(el.as_hash().unwrap())
+        };
+        let view = "from: [0, 0, -5]\nto: [0, 0, 0]\nup: [0, 1, 0]\n";
+        for camera in &[
+            format!("width: 10\nheight: 10\nfield-of-view: 0\n{}", view),
+            format!("width: 10\nheight: 10\nfocal-length: 0\n{}", view),
+            "width: 10\nheight: 10\nfield-of-view: 1\nfrom: [0, 0, 0]\nto: [0, 0, 0]\nup: [0, 1, 0]".to_string(),
+            "width: 10\nheight: 10\nfield-of-view: 1\nfrom: [0, 0, -5]\nto: [0, 0, 0]\nup: [0, 0, 1]".to_string(),
+        ] {
+            let err = parse(camera).unwrap_err();
+            assert!(
+                err.downcast_ref::<CameraError>().is_some()
+                    || err.downcast_ref::<ViewError>().is_some(),
+                "{}",
+                camera
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_camera_size() {
+        let parse = |size: &str| {
+            let camera = format!(
+                "{}\nfield-of-view: 1\nfrom: [0, 0, -5]\nto: [0, 0, 0]\nup: [0, 1, 0]\n",
+                size
+            );
+            let el = &YamlLoader::load_from_str(&camera).unwrap()[0];
+            parse_camera(el.as_hash().unwrap())
+        };
+        for (size, key) in &[
+            ("width: -1\nheight: 10", "width"),
+            ("width: 10\nheight: -1", "height"),
+            ("width: 0\nheight: 10", "width"),
+            ("width: 10\nheight: ten", "height"),
+        ] {
+            match parse(size).unwrap_err().downcast_ref::<SceneParserError>() {
+                Some(SceneParserError::ParseIntError(k)) => assert_eq!(k, key),
+                e => panic!("{}: {:?}", size, e),
+            }
+        }
+        let c = parse("width: 30\nheight: 20").unwrap();
+        assert_eq!((c.hsize(), c.vsize()), (30, 20));
+    }
+
+    #[test]
+    fn test_parse_camera_lens() {
+        let camera =
+            "add: camera\nwidth: 30\nheight: 20\nfrom: [0, 0, -5]\nto: [0, 0, 0]\nup: [0, 1, 0]\n";
+        let parse = |lens: &str| {
+            let el = &YamlLoader::load_from_str(&format!("{}{}", camera, lens)).unwrap()[0];
+            parse_camera(el.as_hash().unwrap())
+        };
+        let c = parse("focal-length: 50").unwrap();
+        assert!((c.field_of_view() - 2.0 * (18.0f64 / 50.0).atan()).abs() < 1e-9);
+        let c = parse("focal-length: 50\nsensor-width: 24\nfield-of-view: 1").unwrap();
+        assert!((c.focal_length(24.0) - 50.0).abs() < 1e-9);
+        assert!(parse("focal-length: long").is_err());
+        assert!(parse("sensor-width: 24").is_err());
+    }
+
+    #[test]
+    fn test_parse_camera_lens_shift() {
+        let camera = "add: camera\nwidth: 10\nheight: 10\nfield-of-view: 1\nfrom: [0, 0, -5]\nto: [0, 0, 0]\nup: [0, 1, 0]\n";
+        let el =
+            &YamlLoader::load_from_str(&format!("{}lens-shift: [0, 0.25]", camera)).unwrap()[0];
+        let c = parse_camera(el.as_hash().unwrap()).unwrap();
+        assert_eq!(c.lens_shift(), (0.0, 0.25));
+
+        let el =
+            &YamlLoader::load_from_str(&format!("{}lens-shift: [1, 2, 3]", camera)).unwrap()[0];
+        assert!(parse_camera(el.as_hash().unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_parse_pattern_extent() {
+        let registry = Registry::default();
+        let el = &YamlLoader::load_from_str(
+            "{ type: checkers, colors: [[1, 1, 1], [0, 0, 0]], extent: { x: [0, 8], z: [-4, 4.5] } }",
+        )
+        .unwrap()[0];
+        let pattern = parse_pattern(&registry, &mut ParseContext::default(), el).unwrap();
+        let extent = pattern.extent().unwrap();
+        assert_eq!(extent.get_min().x, 0.0);
+        assert_eq!(extent.get_min().y, f64::NEG_INFINITY);
+        assert_eq!(extent.get_max().z, 4.5);
+
+        for extent in &["{ x: [0] }", "{ x: 3 }", "[0, 8]"] {
+            let el = &YamlLoader::load_from_str(&format!(
+                "{{ type: stripes, colors: [[1, 1, 1], [0, 0, 0]], extent: {} }}",
+                extent
+            ))
+            .unwrap()[0];
+            assert!(parse_pattern(&registry, &mut ParseContext::default(), el).is_err(), "{}", extent);
+        }
+    }
+
+    #[test]
+    fn test_parse_image_pattern() {
+        let mut image = Canvas::new(2, 1);
+        image.set_pixel(1, 0, Color::new(1.0, 0.0, 0.0));
+        let path = std::env::temp_dir().join("test_parse_image_pattern.png");
+        image.save(&path).unwrap();
+
+        let registry = Registry::default();
+        // named relative to a scene file in the same directory
+        let mut context = ParseContext::for_file(&std::env::temp_dir().join("scene.yml"));
+        let el = &YamlLoader::load_from_str(
+            "{ type: image, file: test_parse_image_pattern.png, mapping: planar }",
+        )
+        .unwrap()[0];
+        let pattern = parse_pattern(&registry, &mut context, el);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(context.source_files()[1], path);
+        let s = Sphere::default();
+        let pattern = pattern.unwrap();
+        assert_eq!(
+            pattern.color_at_shape(&s, Point::new(0.9, 0.0, 0.0)),
+            Color::new(1.0, 0.0, 0.0)
+        );
+        assert_eq!(
+            pattern.color_at_shape(&s, Point::new(0.1, 0.0, 0.0)),
+            Color::black()
+        );
+
+        for def in &[
+            "{ type: image }",
+            "{ type: image, file: no/such/image.png }",
+            "{ type: image, file: x.png, mapping: wobbly }",
+        ] {
+            let el = &YamlLoader::load_from_str(def).unwrap()[0];
+            assert!(parse_pattern(&registry, &mut ParseContext::default(), el).is_err(), "{}", def);
+        }
+    }
+
+    #[test]
+    fn test_named_constants() {
+        let mut p = SceneParser::new();
+        p.parse_str(
+            "
+- define: red
+  value: [1, 0, 0]
+- define: small
+  value: 0.25
+- define: quarter
+  value: small
+- define: scale
+  value: 2
+- define: shiny
+  value: { color: red, reflective: quarter }
+- define: move
+  value: [[translate, small, 0, 0]]
+- add: sphere
+  material: shiny
+  transform: [move, [scale, scale, scale, scale]]
+- add: light
+  at: [0, 10, small]
+  intensity: red
+",
+        )
+        .unwrap();
+        let red = Color::new(1.0, 0.0, 0.0);
+        let constants = p.scene.constants();
+        assert_eq!(constants["red"], Constant::Color(red));
+        assert_eq!(constants["quarter"], Constant::Number(0.25));
+        assert_eq!(constants["scale"], Constant::Number(2.0));
+        assert_eq!(p.scene.materials["shiny"].color, red);
+        assert_eq!(p.scene.materials["shiny"].reflective, 0.25);
+        assert_eq!(
+            p.scene.shapes[0].transform(),
+            &(&scaling(2, 2, 2) * &translation(0.25, 0.0, 0.0))
+        );
+        assert_eq!(p.scene.lights[0].position(), Point::new(0.0, 10.0, 0.25));
+        assert_eq!(p.scene.lights[0].intensity(), red);
+
+        let mut p = SceneParser::new();
+        assert!(p
+            .parse_str(
+                "- define: small\n  value: 0.25\n- add: light\n  at: [0, 0, 0]\n  intensity: small"
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn test_parse_str() {
+        let mut p = SceneParser::new();
+        p.parse_str(
+            "- add: camera\n  width: 10\n  height: 10\n  field-of-view: 1\n  from: [0, 0, -5]\n  to: [0, 0, 0]\n  up: [0, 1, 0]\n- add: sphere",
+        )
+        .unwrap();
+        assert_eq!(p.scene.cameras.len(), 1);
+        assert_eq!(p.scene.shapes.len(), 1);
+    }
+
+    #[test]
+    fn test_malformed_input_is_an_error() {
+        for input in &[
+            "",
+            "add: sphere",
+            "- define: 3\n  value: {}",
+            "- define: m\n  value: true",
+            "- define: m",
+            "- add: sphere\n  transform: [[]]",
+            "- add: sphere\n  transform: [[scale, 1]]",
+            "- add: sphere\n  transform: [[translate]]",
+            "- add: sphere\n  transform: [[rotate-x]]",
+            "- add: sphere\n  material: { pattern: { type: stripes, colors: [[1, 0, 0]] } }",
+            "- add: camera\n  width: 10",
+            "- [unclosed",
+        ] {
+            let mut p = SceneParser::new();
+            assert!(p.parse_str(input).is_err(), "{:?} should not parse", input);
+        }
+    }
+
+    #[test]
+    fn test_named_cameras() {
+        let camera = |name: &str, width: usize| {
+            format!(
+                "- add: camera\n  {}width: {}\n  height: 10\n  field-of-view: 1\n  from: [0, 0, -5]\n  to: [0, 0, 0]\n  up: [0, 1, 0]\n",
+                name, width
+            )
+        };
+        let scene = [
+            camera("", 10),
+            camera("name: wide\n  ", 20),
+            camera("name: detail\n  ", 30),
+            camera("name: wide\n  ", 40),
+        ]
+        .concat();
+        let mut p = SceneParser::new();
+        p.parse_str(&scene).unwrap();
+
+        assert_eq!(p.camera_names(), vec!["wide", "detail"]);
+        assert_eq!(p.camera_mut(None).unwrap().hsize(), 10);
+        assert_eq!(p.camera_mut(Some("wide")).unwrap().hsize(), 40);
+        assert_eq!(p.camera_mut(Some("detail")).unwrap().hsize(), 30);
+        assert!(p.camera_mut(Some("missing")).is_err());
+    }
+
+    #[test]
+    fn test_registered_shape_and_pattern() {
+        use raytracer::{geometry::shape::Sphere, pattern::stripe_pattern};
+
+        let mut p = SceneParser::new();
+        p.register_shape("ball", |el, _| {
+            let radius = to_f64(get_required_attribute(el, "radius".to_string())?)?;
+            let mut ball = Sphere::default();
+            ball.set_transform(scaling(radius, radius, radius));
+            Ok(Box::new(ball))
+        });
+        p.register_pattern("solid", |_, colors, _| {
+            let color = *colors
+                .first()
+                .ok_or(error::SceneParserError::ParsePatternError)?;
+            Ok(stripe_pattern(color, color))
+        });
+        p.parse_str(
+            "- add: ball\n  radius: 2\n  transform: [[translate, 1, 0, 0]]\n  material: { pattern: { type: solid, colors: [[1, 0, 0]] } }",
+        )
+        .unwrap();
+
+        let shape = &p.scene.shapes[0];
+        assert_eq!(
+            *shape.transform(),
+            &translation(1, 0, 0) * &scaling(2, 2, 2)
+        );
+        let red = Color::new(1.0, 0.0, 0.0);
+        let mut material = Material::default();
+        material.set_pattern(stripe_pattern(red, red));
+        assert_eq!(*shape.material(), material);
+
+        assert!(p.parse_str("- add: ball").is_err());
+    }
+
+    #[test]
+    fn test_parse_tags() {
+        let mut p = SceneParser::new();
+        p.parse_str(
+            "- add: group\n  tags: [glass, hero]\n  children:\n    - add: sphere\n- add: cube",
+        )
+        .unwrap();
+        let group = &p.scene.shapes[0];
+        assert_eq!(group.tags(), ["glass", "hero"]);
+        group.for_each_child(&mut |child| assert!(child.has_tag("hero")));
+        assert!(p.scene.shapes[1].tags().is_empty());
+
+        assert!(p.parse_str("- add: cube\n  tags: glass").is_err());
+        assert!(p.parse_str("- add: cube\n  tags: [1]").is_err());
+    }
+
+    #[test]
+    fn test_parse_ellipsoid() {
+        use raytracer::geometry::shape::Ellipsoid;
+
+        let mut p = SceneParser::new();
+        p.parse_str("- add: ellipsoid\n  radii: [2, 1, 1]\n- add: ellipsoid")
+            .unwrap();
+        let radii = |idx: usize| {
+            p.scene.shapes[idx]
+                .as_any()
+                .downcast_ref::<Ellipsoid>()
+                .unwrap()
+                .radii()
+        };
+        assert_eq!(radii(0), Vector::new(2, 1, 1));
+        assert_eq!(radii(1), Vector::new(1, 1, 1));
+        assert!(p.parse_str("- add: ellipsoid\n  radii: [2, 1]").is_err());
+    }
+
+    #[test]
+    fn test_prefabs() {
+        use raytracer::geometry::visitor::for_each_leaf;
+
+        let mut p = SceneParser::new();
+        p.parse_str(
+            "
+- define: red
+  value:
+    color: [1, 0, 0]
+- define: column
+  value:
+    add: group
+    children:
+      - add: cube
+        transform: [[scale, 1, 0.1, 1]]
+      - add: sphere
+        transform: [[translate, 0, 2, 0]]
+- define: colonnade
+  value:
+    add: group
+    children:
+      - add: column
+      - add: column
+        transform: [[translate, 4, 0, 0]]
+- add: column
+  transform: [[translate, 0, 0, 10]]
+  material: red
+- add: colonnade
+",
+        )
+        .unwrap();
+
+        let mut names = p.scene.prefab_names();
+        names.sort_unstable();
+        assert_eq!(names, vec!["colonnade", "column"]);
+        assert_eq!(p.scene.materials.len(), 1);
+        assert_eq!(p.scene.shapes.len(), 2);
+
+        let mut leaves = vec![];
+        for shape in &p.scene.shapes {
+            for_each_leaf(shape.as_ref(), |leaf, transform| {
+                leaves.push((transform * Point::new(0, 1, 0), leaf.material().color))
+            });
+        }
+        let red = Color::new(1.0, 0.0, 0.0);
+        let white = Material::default().color;
+        assert_eq!(
+            leaves,
+            vec![
+                (Point::new(0.0, 0.1, 10.0), red),
+                (Point::new(0, 3, 10), red),
+                (Point::new(0.0, 0.1, 0.0), white),
+                (Point::new(0, 3, 0), white),
+                (Point::new(4.0, 0.1, 0.0), white),
+                (Point::new(4, 3, 0), white),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_define_group_as_list_of_shapes() {
+        use raytracer::geometry::visitor::for_each_leaf;
+
+        let mut scene = String::from(
+            "
+- define: tree
+  value:
+    - add: cube
+      transform: [[scale, 0.1, 1, 0.1]]
+    - add: sphere
+      transform: [[translate, 0, 2, 0]]
+      material:
+        color: [0, 1, 0]
+",
+        );
+        for i in 0..50 {
+            scene.push_str(&format!(
+                "- add: tree\n  transform: [[translate, {}, 0, {}]]\n",
+                i % 10,
+                i / 10
+            ));
+        }
+        let mut p = SceneParser::new();
+        p.parse_str(&scene).unwrap();
+
+        assert_eq!(p.scene.prefab_names(), vec!["tree"]);
+        assert_eq!(p.scene.shapes.len(), 50);
+        // every add is an instance of one shared tree
+        let tree = |idx: usize| {
+            p.scene.shapes[idx]
+                .as_any()
+                .downcast_ref::<Instance>()
+                .unwrap()
+                .shape()
+        };
+        assert!(tree(0).as_any().is::<Group>());
+        assert!(Arc::ptr_eq(tree(0), tree(49)));
+        assert_eq!(Arc::strong_count(tree(0)), 51);
+        let mut crowns = vec![];
+        for shape in &p.scene.shapes {
+            for_each_leaf(shape.as_ref(), |leaf, transform| {
+                if leaf.as_any().is::<Sphere>() {
+                    crowns.push(transform * Point::origin());
+                }
+            });
+        }
+        assert_eq!(crowns.len(), 50);
+        assert_eq!(crowns[0], Point::new(0, 2, 0));
+        assert_eq!(crowns[49], Point::new(9, 2, 4));
+    }
+
+    #[test]
+    fn test_recursive_prefab_is_an_error() {
+        let mut p = SceneParser::new();
+        let res = p.parse_str(
+            "- define: loop\n  value:\n    add: group\n    children: [{ add: loop }]\n- add: loop",
+        );
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn test_render_without_camera() {
+        let mut p = SceneParser::new();
+        p.parse_str("- add: sphere").unwrap();
+        assert!(p.render(Path::new("unused.png"), None).is_err());
+    }
+
+    #[test]
+    fn test_source_files() {
+        let file = "./examples/cover.yml";
+        let mut p = SceneParser::new();
+        p.load_file(file).unwrap();
+        assert_eq!(p.source_files(), &[PathBuf::from(file)]);
+    }
+
+    #[test]
+    fn test_obj_next_to_scene_file() {
+        let data = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../raytracer/src/obj_parser/test_data"
+        );
+        let dir = std::env::temp_dir().join("test_obj_next_to_scene_file");
+        std::fs::create_dir_all(&dir).unwrap();
+        for file in &["materials.obj", "materials.mtl"] {
+            std::fs::copy(Path::new(data).join(file), dir.join(file)).unwrap();
+        }
+        let scene = dir.join("scene.yml");
+        std::fs::write(&scene, "- add: obj\n  file: materials.obj\n").unwrap();
+
+        let mut p = SceneParser::new();
+        let loaded = p.load_file(scene.to_str().unwrap());
+        // a material library the model names has to be there too
+        std::fs::remove_file(dir.join("materials.mtl")).unwrap();
+        let without_library = SceneParser::new().load_file(scene.to_str().unwrap());
+        std::fs::remove_dir_all(&dir).unwrap();
+        loaded.unwrap();
+        assert!(without_library.is_err());
+        assert_eq!(p.scene().shapes().len(), 1);
+        assert_eq!(
+            p.source_files(),
+            &[
+                scene.clone(),
+                dir.join("materials.obj"),
+                dir.join("materials.mtl")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_load_missing_file() {
+        let mut p = SceneParser::new();
+        assert!(p.load_file("./examples/does-not-exist.yml").is_err());
+    }
+
+    #[test]
+    fn test_parse_brdf() {
+        let mut p = SceneParser::new();
+        let el = &YamlLoader::load_from_str("brdf: oren-nayar\nroughness: 0.3").unwrap()[0];
+        let material = p.parse_material(el).unwrap();
+        assert_eq!(material.brdf, BrdfModel::OrenNayar);
+        assert_eq!(material.roughness, 0.3);
+
+        let el = &YamlLoader::load_from_str("brdf: cook-torrance\nmetallic: 1").unwrap()[0];
+        let material = p.parse_material(el).unwrap();
+        assert_eq!(material.brdf, BrdfModel::CookTorrance);
+        assert_eq!(material.metallic, 1.0);
+
+        let el = &YamlLoader::load_from_str("brdf: unknown").unwrap()[0];
+        assert!(p.parse_material(el).is_err());
+    }
+
+    #[test]
+    fn test_parse_environment_reflections() {
+        let mut p = SceneParser::new();
+        let el = &YamlLoader::load_from_str("reflective: 0.9\nenvironment-reflections: true")
+            .unwrap()[0];
+        assert!(p.parse_material(el).unwrap().environment_reflections);
+
+        let el = &YamlLoader::load_from_str("environment-reflections: maybe").unwrap()[0];
+        assert!(p.parse_material(el).is_err());
+    }
+
+    #[test]
+    fn test_parse_displacement() {
+        let mut p = SceneParser::new();
+        let el = &YamlLoader::load_from_str("displacement: { amplitude: 0.2, frequency: 8 }")
+            .unwrap()[0];
+        let material = p.parse_material(el).unwrap();
+        assert_eq!(
+            material.displacement,
+            Some(Displacement {
+                amplitude: 0.2,
+                frequency: 8.0
+            })
+        );
+
+        let el = &YamlLoader::load_from_str("displacement: { frequency: 8 }").unwrap()[0];
+        assert!(p.parse_material(el).is_err());
+    }
+
+    #[test]
+    fn test_parse_cutout() {
+        let mut p = SceneParser::new();
+        let el = &YamlLoader::load_from_str(
+            "cutout: { pattern: { type: stripes, colors: [[1, 1, 1], [0, 0, 0]] }, threshold: 0.2 }",
+        )
+        .unwrap()[0];
+        let cutout = p.parse_material(el).unwrap().cutout.unwrap();
+        assert_eq!(cutout.threshold, 0.2);
+        let s = Sphere::default();
+        assert!(!cutout.removes(&s, Point::new(0.5, 0.0, 0.0), None));
+        assert!(cutout.removes(&s, Point::new(1.5, 0.0, 0.0), None));
+
+        let el = &YamlLoader::load_from_str(
+            "cutout: { pattern: { type: stripes, colors: [[1, 1, 1], [0, 0, 0]] } }",
+        )
+        .unwrap()[0];
+        assert_eq!(p.parse_material(el).unwrap().cutout.unwrap().threshold, 0.5);
+        for def in &["cutout: { threshold: 0.5 }", "cutout: 0.5"] {
+            let el = &YamlLoader::load_from_str(def).unwrap()[0];
+            assert!(p.parse_material(el).is_err(), "{}", def);
+        }
+    }
+
     #[test]
     fn test_is_add_element() {
         let add_element = &YamlLoader::load_from_str("add: plane").unwrap()[0];
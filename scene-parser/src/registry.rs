@@ -0,0 +1,212 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Result;
+use raytracer::{
+    color::Color,
+    geometry::{
+        shape::{Cube, Ellipsoid, Plane, Sphere},
+        uv::UvMapping,
+        Shape,
+    },
+    image::load_canvas,
+    obj_parser::parse_obj_file,
+    pattern::{
+        checkers_pattern, filtered_checkers_pattern, image_pattern, stripe_pattern, Pattern,
+    },
+};
+use yaml_rust::{yaml, Yaml};
+
+use crate::{
+    error::SceneParserError, to_vector, FILE_KEY, MAPPING_KEY, RADII_KEY, THRESHOLD_KEY,
+};
+
+// builds a shape from its add element. the parser applies the element's
+// transform on top of the shape's own, and sets its material
+pub type ShapeFactory = Box<dyn Fn(&yaml::Hash, &mut ParseContext) -> Result<Box<dyn Shape>>>;
+// builds a pattern from its definition and the colors listed in it
+pub type PatternFactory =
+    Box<dyn Fn(&yaml::Hash, &[Color], &mut ParseContext) -> Result<Pattern>>;
+
+// what factories learn about the scene being parsed: where the files it names
+// are found, and the files it was read from so far
+#[derive(Debug, Default)]
+pub struct ParseContext {
+    // the scene file's directory, none for scenes parsed from memory
+    dir: Option<PathBuf>,
+    source_files: Vec<PathBuf>,
+}
+
+impl ParseContext {
+    // the context of the scene file at path
+    pub fn for_file(path: &Path) -> Self {
+        Self {
+            dir: path.parent().map(Path::to_path_buf),
+            source_files: vec![path.to_path_buf()],
+        }
+    }
+
+    // a file named in the scene, relative paths start at the scene file
+    pub fn resolve(&self, file: &str) -> PathBuf {
+        match &self.dir {
+            Some(dir) => dir.join(file),
+            None => PathBuf::from(file),
+        }
+    }
+
+    // a file the scene depends on, watch mode renders again when it changes
+    pub fn add_source_file(&mut self, path: PathBuf) {
+        self.source_files.push(path);
+    }
+
+    pub fn source_files(&self) -> &[PathBuf] {
+        &self.source_files
+    }
+}
+
+// the shape and pattern kinds a scene can use, by the name they go by in the yaml
+pub struct Registry {
+    shapes: HashMap<String, ShapeFactory>,
+    patterns: HashMap<String, PatternFactory>,
+}
+
+impl Default for Registry {
+    fn default() -> Self {
+        let mut registry = Self {
+            shapes: HashMap::new(),
+            patterns: HashMap::new(),
+        };
+        registry.register_shape("sphere", |_, _| Ok(Box::new(Sphere::default())));
+        registry.register_shape("plane", |_, _| Ok(Box::new(Plane::default())));
+        registry.register_shape("cube", |_, _| Ok(Box::new(Cube::default())));
+        registry.register_shape("ellipsoid", |el, _| {
+            let radii = match el.get(&RADII_KEY) {
+                Some(radii_el) => to_vector(
+                    radii_el
+                        .as_vec()
+                        .ok_or_else(|| SceneParserError::ParseVecError("radii".to_string()))?,
+                )?,
+                None => return Ok(Box::new(Ellipsoid::default())),
+            };
+            Ok(Box::new(Ellipsoid::new(radii.x, radii.y, radii.z)))
+        });
+        // { add: obj, file: path, threshold: n }, the faces keep the materials of
+        // the model's mtllib unless the element gives one for the whole mesh.
+        // threshold is the most faces a leaf of the divided mesh holds, without
+        // it only big models are divided
+        registry.register_shape("obj", |el, context| {
+            let file = el
+                .get(&FILE_KEY)
+                .and_then(|file_el| file_el.as_str())
+                .ok_or_else(|| SceneParserError::MissingRequiredKey("file".to_string()))?;
+            let threshold = match el.get(&THRESHOLD_KEY) {
+                Some(threshold_el) => Some(
+                    threshold_el
+                        .as_i64()
+                        .filter(|&threshold| threshold > 0)
+                        .ok_or_else(|| SceneParserError::ParseIntError("threshold".to_string()))?
+                        as usize,
+                ),
+                None => None,
+            };
+            let path = context.resolve(file);
+            let mut parser = parse_obj_file(&path)?;
+            context.add_source_file(path);
+            for library in parser.library_files() {
+                context.add_source_file(library.clone());
+            }
+            if let Some(library) = parser.unreadable_libraries().first() {
+                return Err(
+                    SceneParserError::UnreadableMaterialLibrary(library.display().to_string())
+                        .into(),
+                );
+            }
+            if let Some(threshold) = threshold {
+                parser.set_max_leaf(threshold);
+            }
+            let materials = parser.library_materials();
+            Ok(Box::new(parser.as_group_with_materials(&materials)))
+        });
+        registry.register_pattern("stripes", |_, colors, _| {
+            let (a, b) = two_colors(colors)?;
+            Ok(stripe_pattern(a, b))
+        });
+        registry.register_pattern("checkers", |_, colors, _| {
+            let (a, b) = two_colors(colors)?;
+            Ok(checkers_pattern(a, b))
+        });
+        registry.register_pattern("filtered-checkers", |_, colors, _| {
+            let (a, b) = two_colors(colors)?;
+            Ok(filtered_checkers_pattern(a, b))
+        });
+        // { type: image, file: path, mapping: shape | spherical | planar | cylindrical | cubic }
+        registry.register_pattern("image", |def, _, context| {
+            let file = def
+                .get(&FILE_KEY)
+                .and_then(|file_el| file_el.as_str())
+                .ok_or_else(|| SceneParserError::MissingRequiredKey("file".to_string()))?;
+            let mapping = match def.get(&MAPPING_KEY) {
+                Some(mapping_el) => parse_uv_mapping(mapping_el)?,
+                None => UvMapping::default(),
+            };
+            let path = context.resolve(file);
+            let image = load_canvas(&path)?;
+            context.add_source_file(path);
+            Ok(image_pattern(image, mapping))
+        });
+        registry
+    }
+}
+
+impl Registry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // registering a name again replaces the earlier factory
+    pub fn register_shape<F>(&mut self, name: &str, factory: F)
+    where
+        F: Fn(&yaml::Hash, &mut ParseContext) -> Result<Box<dyn Shape>> + 'static,
+    {
+        self.shapes.insert(name.to_string(), Box::new(factory));
+    }
+
+    pub fn register_pattern<F>(&mut self, name: &str, factory: F)
+    where
+        F: Fn(&yaml::Hash, &[Color], &mut ParseContext) -> Result<Pattern> + 'static,
+    {
+        self.patterns.insert(name.to_string(), Box::new(factory));
+    }
+
+    pub fn has_shape(&self, name: &str) -> bool {
+        self.shapes.contains_key(name)
+    }
+
+    pub(crate) fn shape(&self, name: &str) -> Option<&ShapeFactory> {
+        self.shapes.get(name)
+    }
+
+    pub(crate) fn pattern(&self, name: &str) -> Option<&PatternFactory> {
+        self.patterns.get(name)
+    }
+}
+
+fn parse_uv_mapping(mapping_el: &Yaml) -> Result<UvMapping> {
+    match mapping_el.as_str() {
+        Some("shape") => Ok(UvMapping::Shape),
+        Some("spherical") => Ok(UvMapping::Spherical),
+        Some("planar") => Ok(UvMapping::Planar),
+        Some("cylindrical") => Ok(UvMapping::Cylindrical),
+        Some("cubic") => Ok(UvMapping::Cubic),
+        _ => Err(SceneParserError::UnknownUvMapping(format!("{:?}", mapping_el)).into()),
+    }
+}
+
+fn two_colors(colors: &[Color]) -> Result<(Color, Color)> {
+    match colors {
+        [a, b, ..] => Ok((*a, *b)),
+        _ => Err(SceneParserError::ParsePatternError.into()),
+    }
+}
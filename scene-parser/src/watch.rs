@@ -0,0 +1,97 @@
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver},
+    time::Duration,
+};
+
+use anyhow::Result;
+use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
+use raytracer::{camera::Camera, image::png::PngExporter, image::ExportCanvas};
+
+use crate::SceneParser;
+
+// changes arriving this close together are handled with a single render
+const DEBOUNCE: Duration = Duration::from_millis(100);
+
+// renders the scene at 1/divisor of its resolution, then again every time the
+// scene file or a file it references changes. only returns on a watcher error.
+pub fn watch(
+    scene_path: &Path,
+    output_filename: &Path,
+    divisor: usize,
+    camera_name: Option<&str>,
+) -> Result<()> {
+    let (tx, rx) = mpsc::channel();
+    let mut watcher: RecommendedWatcher = notify::recommended_watcher(tx)?;
+    let mut watched_dirs = HashSet::new();
+
+    loop {
+        let mut parser = SceneParser::new();
+        let files = match parser.load_file(&scene_path.to_string_lossy()) {
+            Ok(()) => {
+                if let Err(e) = render_preview(&mut parser, output_filename, divisor, camera_name) {
+                    println!("render failed: {}", e);
+                }
+                parser.source_files().to_vec()
+            }
+            Err(e) => {
+                println!("failed to load scene: {}", e);
+                vec![scene_path.to_path_buf()]
+            }
+        };
+
+        // editors often save by replacing the file, so watch the directories
+        let files: HashSet<PathBuf> = files.iter().map(|f| absolute(f)).collect();
+        for file in &files {
+            if let Some(dir) = file.parent() {
+                if watched_dirs.insert(dir.to_path_buf()) {
+                    watcher.watch(dir, RecursiveMode::NonRecursive)?;
+                }
+            }
+        }
+
+        println!("watching {} files for changes", files.len());
+        wait_for_change(&rx, &files)?;
+    }
+}
+
+fn wait_for_change(rx: &Receiver<notify::Result<Event>>, files: &HashSet<PathBuf>) -> Result<()> {
+    loop {
+        let event = rx.recv()??;
+        if event.paths.iter().any(|p| files.contains(&absolute(p))) {
+            while rx.recv_timeout(DEBOUNCE).is_ok() {}
+            return Ok(());
+        }
+    }
+}
+
+fn render_preview(
+    parser: &mut SceneParser,
+    output_filename: &Path,
+    divisor: usize,
+    camera_name: Option<&str>,
+) -> Result<()> {
+    parser.camera_mut(camera_name)?;
+    let world = parser.build_world();
+    let camera = parser.camera_mut(camera_name)?;
+
+    let divisor = divisor.max(1);
+    let mut preview = Camera::new(
+        (camera.hsize() / divisor).max(1),
+        (camera.vsize() / divisor).max(1),
+        camera.field_of_view(),
+    );
+    preview.set_transform(camera.transform().clone());
+    preview.set_clipping(camera.near(), camera.far());
+
+    let canvas = preview.render(&world);
+    PngExporter::default().save(&canvas, output_filename)?;
+    println!("preview saved to {}", output_filename.to_string_lossy());
+    Ok(())
+}
+
+fn absolute(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
@@ -0,0 +1,360 @@
+use std::{fs, path::Path};
+
+use anyhow::Result;
+use raytracer::{
+    brdf::BrdfModel,
+    camera::Camera,
+    color::Color,
+    geometry::{
+        shape::{Cube, Ellipsoid, Plane, Sphere},
+        Shape,
+    },
+    light::Light,
+    material::Material,
+    matrix::Matrix,
+    point::Point,
+    sky::Sky,
+    transform::decompose,
+    vector::{cross, Vector},
+    world::World,
+};
+use yaml_rust::{yaml, Yaml, YamlEmitter};
+
+// writes the world and camera in the format read by SceneParser. only the shapes
+// the parser knows about are written, and material patterns are left out.
+pub fn scene_to_yaml(world: &World, camera: &Camera) -> Result<String> {
+    let mut elements = vec![camera_to_yaml(camera)];
+    elements.extend(world.lights().iter().map(light_to_yaml));
+    if let Some(sky) = world.sky() {
+        elements.push(sky_to_yaml(sky));
+    }
+    for object in world.objects() {
+        match shape_to_yaml(object.as_ref()) {
+            Some(el) => elements.push(el),
+            None => println!("skipping unsupported shape: {:?}", object),
+        }
+    }
+
+    let mut out = String::new();
+    YamlEmitter::new(&mut out).dump(&Yaml::Array(elements))?;
+    out.push('\n');
+    Ok(out)
+}
+
+pub fn save_scene(world: &World, camera: &Camera, path: &Path) -> Result<()> {
+    fs::write(path, scene_to_yaml(world, camera)?)?;
+    println!("scene written to {}", path.to_string_lossy());
+    Ok(())
+}
+
+fn camera_to_yaml(camera: &Camera) -> Yaml {
+    let m = camera.transform();
+    let from = &m.inverse() * Point::origin();
+    let left = Vector::new(m[(0, 0)], m[(0, 1)], m[(0, 2)]);
+    let forward = -Vector::new(m[(2, 0)], m[(2, 1)], m[(2, 2)]);
+    // view_transform only keeps the part of up perpendicular to forward, whose
+    // length shows up as the length of left. rebuild an up with the same effect.
+    let sin = left.magnitude().min(1.0);
+    let up = cross(left, forward) + forward * (1.0 - sin * sin).sqrt();
+
+    let mut hash = yaml::Hash::new();
+    insert(&mut hash, "add", string("camera"));
+    insert(&mut hash, "width", Yaml::Integer(camera.hsize() as i64));
+    insert(&mut hash, "height", Yaml::Integer(camera.vsize() as i64));
+    insert(&mut hash, "field-of-view", real(camera.field_of_view()));
+    insert(&mut hash, "from", triple(from.x, from.y, from.z));
+    let to = from + forward;
+    insert(&mut hash, "to", triple(to.x, to.y, to.z));
+    insert(&mut hash, "up", triple(up.x, up.y, up.z));
+    let (h, v) = camera.lens_shift();
+    if h != 0.0 || v != 0.0 {
+        insert(&mut hash, "lens-shift", Yaml::Array(vec![real(h), real(v)]));
+    }
+    Yaml::Hash(hash)
+}
+
+fn light_to_yaml(light: &Light) -> Yaml {
+    let at = light.position();
+    let mut hash = yaml::Hash::new();
+    insert(&mut hash, "add", string("light"));
+    insert(&mut hash, "at", triple(at.x, at.y, at.z));
+    insert(&mut hash, "intensity", color(light.intensity()));
+    if let Some(fill) = light.shadow_fill() {
+        insert(&mut hash, "shadow-fill", real(fill));
+    }
+    if light.shadow_softness() > 0.0 {
+        insert(&mut hash, "shadow-softness", real(light.shadow_softness()));
+    }
+    if let Some(shadow) = light.shadow_color() {
+        insert(&mut hash, "shadow-color", color(shadow));
+    }
+    if let Light::Spot(spot) = light {
+        let d = spot.direction();
+        insert(&mut hash, "type", string("spot"));
+        insert(&mut hash, "direction", triple(d.x, d.y, d.z));
+        insert(&mut hash, "inner-angle", real(spot.inner()));
+        insert(&mut hash, "outer-angle", real(spot.outer()));
+    }
+    Yaml::Hash(hash)
+}
+
+fn sky_to_yaml(sky: &Sky) -> Yaml {
+    let mut hash = yaml::Hash::new();
+    insert(&mut hash, "add", string("sky"));
+    insert(&mut hash, "elevation", real(sky.sun_elevation()));
+    insert(&mut hash, "azimuth", real(sky.sun_azimuth()));
+    insert(&mut hash, "turbidity", real(sky.turbidity()));
+    // the sun is already in the world's lights
+    insert(&mut hash, "sun", Yaml::Boolean(false));
+    Yaml::Hash(hash)
+}
+
+fn shape_to_yaml(shape: &dyn Shape) -> Option<Yaml> {
+    let any = shape.as_any();
+    let kind = if any.is::<Sphere>() {
+        "sphere"
+    } else if any.is::<Plane>() {
+        "plane"
+    } else if any.is::<Cube>() {
+        "cube"
+    } else if any.is::<Ellipsoid>() {
+        "ellipsoid"
+    } else {
+        return None;
+    };
+
+    let mut hash = yaml::Hash::new();
+    insert(&mut hash, "add", string(kind));
+    if let Some(ellipsoid) = any.downcast_ref::<Ellipsoid>() {
+        let radii = ellipsoid.radii();
+        insert(&mut hash, "radii", triple(radii.x, radii.y, radii.z));
+    }
+    insert(&mut hash, "transform", transform_to_yaml(shape.transform()));
+    insert(&mut hash, "material", material_to_yaml(shape.material()));
+    if !shape.tags().is_empty() {
+        let tags = shape.tags().iter().map(|tag| string(tag)).collect();
+        insert(&mut hash, "tags", Yaml::Array(tags));
+    }
+    let visibility = shape.visibility();
+    for (key, visible) in [
+        ("shadow", visibility.shadow),
+        ("visible", visibility.camera),
+        ("reflection-visible", visibility.secondary),
+    ] {
+        if !visible {
+            insert(&mut hash, key, Yaml::Boolean(false));
+        }
+    }
+    Some(Yaml::Hash(hash))
+}
+
+// the parser applies transforms in list order, so this is decompose's
+// translation * rotation_z * rotation_y * rotation_x * scaling read backwards.
+// anything decompose can't represent is written as the full matrix.
+fn transform_to_yaml(transform: &Matrix) -> Yaml {
+    let d = decompose(transform);
+    if d.to_matrix() != *transform {
+        let mut values = vec![];
+        for row in 0..4 {
+            for column in 0..4 {
+                values.push(transform[(row, column)]);
+            }
+        }
+        return Yaml::Array(vec![transform_item("matrix", &values)]);
+    }
+    let (t, r, s) = (d.translation, d.rotation, d.scale);
+
+    let mut items = vec![];
+    if (s.x, s.y, s.z) != (1.0, 1.0, 1.0) {
+        items.push(transform_item("scale", &[s.x, s.y, s.z]));
+    }
+    for (kind, angle) in [("rotate-x", r.x), ("rotate-y", r.y), ("rotate-z", r.z)] {
+        if angle != 0.0 {
+            items.push(transform_item(kind, &[angle]));
+        }
+    }
+    if (t.x, t.y, t.z) != (0.0, 0.0, 0.0) {
+        items.push(transform_item("translate", &[t.x, t.y, t.z]));
+    }
+    Yaml::Array(items)
+}
+
+fn material_to_yaml(material: &Material) -> Yaml {
+    let mut hash = yaml::Hash::new();
+    insert(&mut hash, "color", color(material.color));
+    insert(&mut hash, "ambient", real(material.ambient));
+    insert(&mut hash, "diffuse", real(material.diffuse));
+    insert(&mut hash, "specular", real(material.specular));
+    insert(&mut hash, "shininess", real(material.shininess));
+    insert(&mut hash, "reflective", real(material.reflective));
+    insert(&mut hash, "transparency", real(material.transparency));
+    insert(
+        &mut hash,
+        "refractive-index",
+        real(material.refractive_index),
+    );
+    insert(&mut hash, "fresnel", Yaml::Boolean(material.fresnel));
+    insert(
+        &mut hash,
+        "environment-reflections",
+        Yaml::Boolean(material.environment_reflections),
+    );
+    insert(&mut hash, "translucency", real(material.translucency));
+    insert(&mut hash, "roughness", real(material.roughness));
+    insert(&mut hash, "metallic", real(material.metallic));
+    let brdf = match material.brdf {
+        BrdfModel::Phong => "phong",
+        BrdfModel::BlinnPhong => "blinn-phong",
+        BrdfModel::OrenNayar => "oren-nayar",
+        BrdfModel::CookTorrance => "cook-torrance",
+    };
+    insert(&mut hash, "brdf", string(brdf));
+    if let Some(displacement) = &material.displacement {
+        let mut displacement_hash = yaml::Hash::new();
+        insert(
+            &mut displacement_hash,
+            "amplitude",
+            real(displacement.amplitude),
+        );
+        insert(
+            &mut displacement_hash,
+            "frequency",
+            real(displacement.frequency),
+        );
+        insert(&mut hash, "displacement", Yaml::Hash(displacement_hash));
+    }
+    Yaml::Hash(hash)
+}
+
+fn insert(hash: &mut yaml::Hash, key: &str, value: Yaml) {
+    hash.insert(string(key), value);
+}
+
+fn string(s: &str) -> Yaml {
+    Yaml::String(s.to_string())
+}
+
+// debug formatting keeps the decimal point, so the value reads back as a float
+fn real(value: f64) -> Yaml {
+    Yaml::Real(format!("{:?}", value))
+}
+
+fn triple(x: f64, y: f64, z: f64) -> Yaml {
+    Yaml::Array(vec![real(x), real(y), real(z)])
+}
+
+fn color(c: Color) -> Yaml {
+    triple(c.red, c.green, c.blue)
+}
+
+fn transform_item(kind: &str, args: &[f64]) -> Yaml {
+    let mut item = vec![string(kind)];
+    item.extend(args.iter().map(|&a| real(a)));
+    Yaml::Array(item)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, f64::consts::PI};
+
+    use raytracer::{
+        geometry::Visibility,
+        light::{PointLight, SpotLight},
+        material::Displacement,
+        transform::{rotation_y, scaling, shearing, translation, view_transform},
+    };
+
+    use crate::SceneParser;
+
+    use super::*;
+
+    fn round_trip(world: &World, camera: &Camera, name: &str) -> SceneParser {
+        let path = env::temp_dir().join(name);
+        save_scene(world, camera, &path).unwrap();
+        let mut p = SceneParser::new();
+        p.load_file(path.to_str().unwrap()).unwrap();
+        fs::remove_file(path).unwrap();
+        p
+    }
+
+    #[test]
+    fn write_and_reload_scene() {
+        let mut world = World::new();
+        let mut light = PointLight::new(Point::new(-10, 10, -10), Color::new(1.0, 0.9, 0.8));
+        light.set_shadow_fill(0.25);
+        light.set_shadow_softness(0.5);
+        light.set_shadow_color(Color::new(0.1, 0.2, 0.3));
+        world.add_light(light);
+        world.add_light(SpotLight::new(
+            PointLight::new(Point::new(0, 5, 0), Color::white()),
+            Vector::new(0.0, -1.0, 0.5),
+            0.25,
+            0.5,
+        ));
+        let mut s = Sphere::default();
+        s.set_transform(&(&translation(1, 2, 3) * &rotation_y(PI / 3.0)) * &scaling(1.0, 2.0, 0.5));
+        s.material_mut().color = Color::new(0.2, 0.4, 0.6);
+        s.material_mut().reflective = 0.3;
+        s.material_mut().environment_reflections = true;
+        s.add_tag("hero");
+        s.material_mut().brdf = BrdfModel::OrenNayar;
+        world.add_object(s);
+        let mut c = Cube::default();
+        c.set_transform(shearing(1, 0, 0, 0, 0, 0));
+        c.material_mut().displacement = Some(Displacement {
+            amplitude: 0.1,
+            frequency: 4.0,
+        });
+        world.add_object(c);
+        let mut floor = Plane::default();
+        floor.no_shadow();
+        floor.set_visibility(Visibility {
+            secondary: false,
+            ..floor.visibility()
+        });
+        world.add_object(floor);
+        let mut e = Ellipsoid::new(2.0, 1.0, 0.5);
+        e.set_transform(translation(0, 1, 0));
+        world.add_object(e);
+
+        let mut camera = Camera::new(40, 30, PI / 3.0);
+        camera.set_transform(view_transform(
+            Point::new(0.0, 1.5, -5.0),
+            Point::new(0, 1, 0),
+            Vector::new(0, 1, 0),
+        ));
+        camera.set_lens_shift(0.0, 0.125);
+
+        let p = round_trip(&world, &camera, "write_and_reload_scene.yml");
+
+        let loaded_camera = &p.scene.cameras[0].1;
+        assert_eq!(loaded_camera.resolution(), (40, 30));
+        assert_eq!(loaded_camera.lens_shift(), (0.0, 0.125));
+        assert_eq!(loaded_camera.transform(), camera.transform());
+        assert_eq!(p.scene.lights, world.lights());
+        assert_eq!(p.scene.shapes.len(), 4);
+        assert!(p.scene.shapes[3].equals(world.objects()[3].as_ref()));
+        for (loaded, original) in p.scene.shapes.iter().zip(world.objects()) {
+            assert_eq!(loaded.transform(), original.transform());
+            assert_eq!(loaded.material(), original.material());
+            assert_eq!(loaded.tags(), original.tags());
+            assert_eq!(loaded.visibility(), original.visibility());
+        }
+    }
+
+    #[test]
+    fn write_sky_without_sun() {
+        let mut world = World::new();
+        let sky = Sky::new(0.4, 1.0, 3.0);
+        world.add_light(sky.sun_light());
+        world.set_sky(sky.clone());
+
+        let p = round_trip(
+            &world,
+            &Camera::new(10, 10, PI / 2.0),
+            "write_sky_without_sun.yml",
+        );
+        assert_eq!(p.scene.sky, Some(sky));
+        assert_eq!(p.scene.lights.len(), 1);
+    }
+}
@@ -0,0 +1,48 @@
+use raytracer::{
+    geometry::{shape::Cube, Visibility},
+    point::Point,
+};
+use scene_parser::SceneParser;
+
+// the area light scene lights its floor through a glowing cube that has
+// shadow: false
+fn load_area_light() -> SceneParser {
+    let mut p = SceneParser::new();
+    p.load_file(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/examples/area-light.yml"
+    ))
+    .unwrap();
+    p
+}
+
+#[test]
+fn area_light_scene_parses() {
+    let p = load_area_light();
+    let scene = p.scene();
+
+    assert_eq!(scene.lights().len(), 100);
+    assert_eq!(scene.shapes().len(), 4);
+    let shadowless: Vec<_> = scene.shapes().iter().filter(|s| !s.has_shadow()).collect();
+    assert_eq!(shadowless.len(), 1);
+    assert!(shadowless[0].as_any().is::<Cube>());
+    assert!(shadowless[0].visibility().camera);
+}
+
+#[test]
+fn shadowless_light_cube_lets_the_light_through() {
+    let mut p = load_area_light();
+    let mut world = p.build_world();
+    let floor = Point::new(-1.5, 0.01, 1.0);
+    assert!(world
+        .lights()
+        .iter()
+        .all(|light| !world.is_shadowed(floor, light)));
+
+    // casting shadows, the cube around the lights blocks them all
+    world.objects_mut()[0].set_visibility(Visibility::default());
+    assert!(world
+        .lights()
+        .iter()
+        .all(|light| world.is_shadowed(floor, light)));
+}
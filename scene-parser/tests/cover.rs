@@ -0,0 +1,69 @@
+use raytracer::{
+    color::Color,
+    geometry::shape::{Cube, Plane, Sphere},
+};
+use scene_parser::SceneParser;
+
+// the book's cover scene is the conformance scene for the yaml format
+fn load_cover() -> SceneParser {
+    let mut p = SceneParser::new();
+    p.load_file(concat!(env!("CARGO_MANIFEST_DIR"), "/examples/cover.yml"))
+        .unwrap();
+    p
+}
+
+#[test]
+fn cover_scene_parses() {
+    let p = load_cover();
+    let scene = p.scene();
+
+    assert_eq!(scene.cameras().len(), 1);
+    assert_eq!(scene.cameras()[0].1.hsize(), 4000);
+    assert_eq!(scene.lights().len(), 2);
+
+    let mut materials: Vec<&str> = scene.materials().keys().map(|k| k.as_str()).collect();
+    materials.sort_unstable();
+    assert_eq!(
+        materials,
+        vec![
+            "blue-material",
+            "purple-material",
+            "red-material",
+            "white-material"
+        ]
+    );
+    assert_eq!(scene.transforms().len(), 4);
+
+    let count = |f: &dyn Fn(&dyn std::any::Any) -> bool| {
+        scene.shapes().iter().filter(|s| f(s.as_any())).count()
+    };
+    assert_eq!(scene.shapes().len(), 19);
+    assert_eq!(count(&|s| s.is::<Plane>()), 1);
+    assert_eq!(count(&|s| s.is::<Sphere>()), 1);
+    assert_eq!(count(&|s| s.is::<Cube>()), 17);
+}
+
+#[test]
+fn cover_materials_extend_white() {
+    let p = load_cover();
+    let materials = p.scene().materials();
+    let white = &materials["white-material"];
+    let blue = &materials["blue-material"];
+
+    assert_eq!(blue.color, Color::new(0.537, 0.831, 0.914));
+    assert_eq!(blue.diffuse, white.diffuse);
+    assert_eq!(blue.ambient, white.ambient);
+    assert_eq!(blue.reflective, white.reflective);
+}
+
+#[test]
+fn cover_world_builds() {
+    let mut p = load_cover();
+    let world = p.build_world();
+
+    assert_eq!(world.len(), 19);
+    assert_eq!(world.lights().len(), 2);
+    // the shapes were moved into the world, the camera stays with the parser
+    assert!(p.scene().shapes().is_empty());
+    assert_eq!(p.scene().cameras().len(), 1);
+}